@@ -0,0 +1,181 @@
+//! Alert settings and notification delivery.
+//!
+//! The alert manager of the server evaluates the rules and publishes what is firing here
+//! for the panel, this module stores the settings in `alerts.json`, what is firing in
+//! `alerts_firing.json` and knows how to reach every kind of [`NotificationChannel`].
+
+use std::{collections::HashMap, time::Duration};
+
+use chrono::{DateTime, Utc};
+use lettre::{
+    message::header::ContentType, transport::smtp::authentication::Credentials, AsyncSmtpTransport,
+    AsyncTransport, Message, Tokio1Executor,
+};
+use once_cell::sync::Lazy;
+
+use crate::common::{
+    get_home_path, AlertSettings, ChannelKind, FiringAlert, NotificationChannel, SmtpSettings,
+    WebhookFormat,
+};
+
+/// Deliveries taking longer than this count as failed
+const DELIVERY_TIMEOUT: Duration = Duration::from_secs(20);
+
+/// Serializes read-modify-write of the settings file
+static SETTINGS_LOCK: Lazy<tokio::sync::Mutex<()>> = Lazy::new(Default::default);
+
+/// Alerts firing as of the alert manager's last evaluation
+static FIRING: Lazy<std::sync::Mutex<Vec<FiringAlert>>> = Lazy::new(Default::default);
+
+pub async fn load_settings() -> AlertSettings {
+    let Ok(data) = tokio::fs::read(get_home_path().join("alerts.json")).await else {
+        return AlertSettings::default();
+    };
+    match serde_json::from_slice(&data) {
+        Ok(settings) => settings,
+        Err(err) => {
+            tracing::warn!("Cannot parse alert settings {err:?}");
+            AlertSettings::default()
+        }
+    }
+}
+
+/// Applies `f` to the stored settings, nothing is saved when it fails
+pub async fn update_settings(
+    f: impl FnOnce(&mut AlertSettings) -> Result<(), String>,
+) -> anyhow::Result<()> {
+    let _lock = SETTINGS_LOCK.lock().await;
+    let mut settings = load_settings().await;
+    f(&mut settings).map_err(anyhow::Error::msg)?;
+    tokio::fs::write(
+        get_home_path().join("alerts.json"),
+        serde_json::to_vec(&settings)?,
+    )
+    .await?;
+    Ok(())
+}
+
+/// Alerts that fired when the server last evaluated them, keyed by rule and subject.
+/// Loaded at startup so alerts still firing after a restart are not notified again.
+pub async fn load_firing() -> HashMap<String, FiringAlert> {
+    let Ok(data) = tokio::fs::read(get_home_path().join("alerts_firing.json")).await else {
+        return HashMap::new();
+    };
+    match serde_json::from_slice(&data) {
+        Ok(firing) => firing,
+        Err(err) => {
+            tracing::warn!("Cannot parse firing alerts {err:?}");
+            HashMap::new()
+        }
+    }
+}
+
+pub async fn save_firing(firing: &HashMap<String, FiringAlert>) -> anyhow::Result<()> {
+    tokio::fs::write(
+        get_home_path().join("alerts_firing.json"),
+        serde_json::to_vec(firing)?,
+    )
+    .await?;
+    Ok(())
+}
+
+pub fn set_firing(alerts: Vec<FiringAlert>) {
+    *FIRING.lock().expect("firing alerts lock poisoned") = alerts;
+}
+
+pub fn firing() -> Vec<FiringAlert> {
+    FIRING.lock().expect("firing alerts lock poisoned").clone()
+}
+
+/// A change of an alert, sent once when it starts firing and once when it resolves
+pub struct Notification {
+    pub summary: String,
+    pub details: String,
+    pub resolved: bool,
+    pub ts: DateTime<Utc>,
+}
+
+impl Notification {
+    fn title(&self) -> String {
+        if self.resolved {
+            format!("[Resolved] {}", self.summary)
+        } else {
+            format!("[Firing] {}", self.summary)
+        }
+    }
+}
+
+pub async fn notify(
+    channel: &NotificationChannel,
+    smtp: Option<&SmtpSettings>,
+    notification: &Notification,
+) -> anyhow::Result<()> {
+    let delivery = async {
+        match &channel.kind {
+            ChannelKind::Webhook { url, format } => send_webhook(url, *format, notification).await,
+            ChannelKind::Email { to } => {
+                let smtp = smtp.ok_or(anyhow::anyhow!("No SMTP server configured"))?;
+                send_email(smtp, to, notification).await
+            }
+        }
+    };
+    tokio::time::timeout(DELIVERY_TIMEOUT, delivery)
+        .await
+        .map_err(|_| anyhow::anyhow!("Timed out notifying {}", channel.kind))?
+}
+
+async fn send_webhook(
+    url: &str,
+    format: WebhookFormat,
+    notification: &Notification,
+) -> anyhow::Result<()> {
+    let text = format!("{}\n{}", notification.title(), notification.details);
+    let body = match format {
+        WebhookFormat::Generic => serde_json::json!({
+            "status": if notification.resolved { "resolved" } else { "firing" },
+            "summary": notification.summary,
+            "details": notification.details,
+            "ts": notification.ts,
+        }),
+        WebhookFormat::Slack => serde_json::json!({ "text": text }),
+        WebhookFormat::Discord => serde_json::json!({ "content": text }),
+    };
+    let response = reqwest::Client::new().post(url).json(&body).send().await?;
+    if !response.status().is_success() {
+        anyhow::bail!("{url} answered {}", response.status());
+    }
+    Ok(())
+}
+
+async fn send_email(
+    smtp: &SmtpSettings,
+    to: &str,
+    notification: &Notification,
+) -> anyhow::Result<()> {
+    let message = Message::builder()
+        .from(smtp.from.parse()?)
+        .to(to.parse()?)
+        .subject(notification.title())
+        .header(ContentType::TEXT_PLAIN)
+        .body(format!(
+            "{}\n\n{}\n\n{}",
+            notification.summary,
+            notification.details,
+            notification.ts.to_rfc2822()
+        ))?;
+
+    let mut transport = if smtp.starttls {
+        AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&smtp.host)?
+    } else {
+        AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&smtp.host)
+    }
+    .port(smtp.port);
+    if !smtp.username.is_empty() {
+        transport = transport.credentials(Credentials::new(
+            smtp.username.clone(),
+            smtp.password.clone(),
+        ));
+    }
+    transport.build().send(message).await?;
+    Ok(())
+}
@@ -5,8 +5,11 @@ use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
 use crate::common::{
-    Container, DomainStatusFields, EnvironmentVar, ExposedPort, PortForward, Project, ProjectType,
-    SupportContainer, Token,
+    AlertSettings, AuditQuery, AuditSearch, ConcurrencyPolicy, Container, ContainerRuntime,
+    Dependency, DomainStatusFields, DomainUptime, EnvironmentVar, ExposedPort, FiringAlert,
+    HealthCheck, ImageSource, JobRun, LogQuery, LogSearch, MetricPoint, MetricsRange, PortForward,
+    Project, ProjectType, ResourceLimits, RestartPolicy, SupportContainer, SupportPort, Token,
+    Volume, WebhookDelivery, WebhookSubscription,
 };
 
 #[server(InspectContainer)]
@@ -24,6 +27,12 @@ pub async fn inspect_container(
         support_containers: _,
         tokens: _,
         exposed_ports: _,
+        image_source: _,
+        deploy_history: _,
+        hooks: _,
+        jobs: _,
+        log_retention: _,
+        log_sinks: _,
     } = &project.project_type
     {
         if let crate::common::ContainerStatus::Running(container) = &container.status {
@@ -39,7 +48,7 @@ pub async fn inspect_container(
 
 #[server(PauseContainer)]
 pub async fn pause_container(id: Uuid) -> Result<(), ServerFnError> {
-    user()?;
+    let user = user()?;
 
     let context = project_context()?;
     let project = context
@@ -51,6 +60,12 @@ pub async fn pause_container(id: Uuid) -> Result<(), ServerFnError> {
         support_containers: _,
         tokens: _,
         exposed_ports: _,
+        image_source: _,
+        deploy_history: _,
+        hooks: _,
+        jobs: _,
+        log_retention: _,
+        log_sinks: _,
     } = &project.project_type
     {
         if let crate::common::ContainerStatus::Running(container) = &container.status {
@@ -58,6 +73,15 @@ pub async fn pause_container(id: Uuid) -> Result<(), ServerFnError> {
                 .pause()
                 .await
                 .map_err(|e| ServerFnError::new(format!("Cannot pause container {e:#?}")))?;
+            audit_project(
+                &user,
+                &context,
+                "container.pause",
+                id,
+                Some(project.as_ref()),
+                None,
+            )
+            .await;
             Ok(())
         } else {
             Err(ServerFnError::new("container not running"))
@@ -69,7 +93,7 @@ pub async fn pause_container(id: Uuid) -> Result<(), ServerFnError> {
 
 #[server(ResumeContainer)]
 pub async fn resume_container(id: Uuid) -> Result<(), ServerFnError> {
-    user()?;
+    let user = user()?;
     let context = project_context()?;
     let project = context
         .get_project(id)
@@ -80,6 +104,12 @@ pub async fn resume_container(id: Uuid) -> Result<(), ServerFnError> {
         support_containers: _,
         tokens: _,
         exposed_ports: _,
+        image_source: _,
+        deploy_history: _,
+        hooks: _,
+        jobs: _,
+        log_retention: _,
+        log_sinks: _,
     } = &project.project_type
     {
         if let crate::common::ContainerStatus::Running(container) = &container.status {
@@ -87,6 +117,15 @@ pub async fn resume_container(id: Uuid) -> Result<(), ServerFnError> {
                 .unpause()
                 .await
                 .map_err(|e| ServerFnError::new(format!("Cannot resume container {e:?}")))?;
+            audit_project(
+                &user,
+                &context,
+                "container.resume",
+                id,
+                Some(project.as_ref()),
+                None,
+            )
+            .await;
             Ok(())
         } else {
             Err(ServerFnError::new("container not running"))
@@ -98,7 +137,7 @@ pub async fn resume_container(id: Uuid) -> Result<(), ServerFnError> {
 
 #[server(StopContainer)]
 pub async fn stop_container(id: Uuid) -> Result<(), ServerFnError> {
-    user()?;
+    let user = user()?;
 
     let context = project_context()?;
     let project = context
@@ -110,25 +149,42 @@ pub async fn stop_container(id: Uuid) -> Result<(), ServerFnError> {
         support_containers: _,
         tokens: _,
         exposed_ports: _,
+        image_source: _,
+        deploy_history: _,
+        hooks: _,
+        jobs: _,
+        log_retention: _,
+        log_sinks: _,
     } = &project.project_type
     {
         if let crate::common::ContainerStatus::Running(container) = &container.status {
+            // Mark first so the container manager doesn't treat the exit as a crash
+            set_stopped_by_user(id, true).await?;
             container
                 .stop(&docker_api::opts::ContainerStopOpts::builder().build())
                 .await
                 .map_err(|e| ServerFnError::new(format!("Cannot stop container {e:?}")))?;
-            Ok(())
         } else {
-            Err(ServerFnError::new("container not running"))
+            return Err(ServerFnError::new("container not running"));
         }
     } else {
-        Err(ServerFnError::new("project doesnt have container"))
+        return Err(ServerFnError::new("project doesnt have container"));
     }
+    audit_project(
+        &user,
+        &context,
+        "container.stop",
+        id,
+        Some(project.as_ref()),
+        None,
+    )
+    .await;
+    Ok(())
 }
 
 #[server(StartContainer)]
 pub async fn start_container(id: Uuid) -> Result<(), ServerFnError> {
-    user()?;
+    let user = user()?;
 
     let context = project_context()?;
     let project = context
@@ -140,6 +196,12 @@ pub async fn start_container(id: Uuid) -> Result<(), ServerFnError> {
         support_containers: _,
         tokens: _,
         exposed_ports: _,
+        image_source: _,
+        deploy_history: _,
+        hooks: _,
+        jobs: _,
+        log_retention: _,
+        log_sinks: _,
     } = &project.project_type
     {
         if let crate::common::ContainerStatus::Running(container) = &container.status {
@@ -147,27 +209,131 @@ pub async fn start_container(id: Uuid) -> Result<(), ServerFnError> {
                 .start()
                 .await
                 .map_err(|e| ServerFnError::new(format!("Cannot start container {e:?}")))?;
-            Ok(())
         } else {
-            Err(ServerFnError::new("container not running"))
+            return Err(ServerFnError::new("container not running"));
         }
     } else {
-        Err(ServerFnError::new("project doesnt have container"))
+        return Err(ServerFnError::new("project doesnt have container"));
+    }
+    set_stopped_by_user(id, false).await?;
+    audit_project(
+        &user,
+        &context,
+        "container.start",
+        id,
+        Some(project.as_ref()),
+        None,
+    )
+    .await;
+    Ok(())
+}
+
+/// Containers stopped from the panel are left alone by the container manager
+/// instead of being treated as crashed.
+#[cfg(feature = "ssr")]
+async fn set_stopped_by_user(id: Uuid, stopped: bool) -> Result<(), ServerFnError> {
+    let mut context = project_context()?;
+    let project = context
+        .get_project(id)
+        .await
+        .ok_or(ServerFnError::new("Not project with given id"))?;
+    let mut project = project.as_ref().clone();
+    if let ProjectType::Container {
+        primary_container: container,
+        ..
+    } = &mut project.project_type
+    {
+        container.runtime.stopped_by_user = stopped;
+        if stopped {
+            container.runtime.next_retry_at = None;
+        } else {
+            container.runtime.record_start();
+        }
     }
+    context
+        .update_project(id, Arc::new(project))
+        .await
+        .map_err(ServerFnError::new)?;
+    Ok(())
+}
+
+#[server(GetContainerRuntime)]
+pub async fn get_container_runtime(id: Uuid) -> Result<ContainerRuntime, ServerFnError> {
+    user()?;
+    let context = project_context()?;
+    let project = context
+        .get_project(id)
+        .await
+        .ok_or(ServerFnError::new("Not project with given id"))?;
+    let container = project
+        .project_type
+        .try_get_primary()
+        .ok_or(ServerFnError::new("project doesnt have container"))?;
+    Ok(container.runtime.clone())
 }
 
 #[server(AddProject)]
 pub async fn add_project(name: String) -> Result<Project, ServerFnError> {
-    user()?;
+    let user = user()?;
 
     let mut project_context = project_context()?;
     let project = crate::common::add_port_forward_project(&name, 3000, &mut project_context)
         .await
         .map_err(|e| ServerFnError::new(e))?;
+    audit_project(
+        &user,
+        &project_context,
+        "project.create",
+        project.id,
+        None,
+        None,
+    )
+    .await;
 
     Ok(project.as_ref().clone())
 }
 
+#[server(ImportCompose)]
+pub async fn import_compose(
+    name: String,
+    compose: String,
+    primary: Option<String>,
+) -> Result<crate::compose::ComposeImport, ServerFnError> {
+    let user = user()?;
+
+    let (project_type, primary, warnings) =
+        crate::compose::compose_to_project(&compose, primary.as_deref())
+            .map_err(ServerFnError::new)?;
+
+    let mut project_context = project_context()?;
+    let id = Uuid::new_v4();
+    let project = Arc::new(Project {
+        id,
+        name,
+        project_type,
+        status_page: None,
+    });
+    project_context
+        .update_project(id, project)
+        .await
+        .map_err(ServerFnError::new)?;
+    audit_project(
+        &user,
+        &project_context,
+        "project.import_compose",
+        id,
+        None,
+        None,
+    )
+    .await;
+
+    Ok(crate::compose::ComposeImport {
+        project_id: id,
+        primary,
+        warnings,
+    })
+}
+
 #[server(GetProjects)]
 pub async fn get_projects() -> Result<Vec<Project>, ServerFnError> {
     user()?;
@@ -176,11 +342,30 @@ pub async fn get_projects() -> Result<Vec<Project>, ServerFnError> {
 
     let projects = projects
         .iter()
-        .map(|e| e.as_ref().clone())
+        .map(|e| without_secrets(e))
         .collect::<Vec<_>>();
     Ok(projects)
 }
 
+/// The project as sent to the browser, the registry password is blanked like the SMTP
+/// password of the alert settings. [`set_image_source`] keeps the stored one for a blank
+#[cfg(feature = "ssr")]
+fn without_secrets(project: &Project) -> Project {
+    let mut project = project.clone();
+    if let ProjectType::Container {
+        image_source:
+            Some(ImageSource {
+                password: Some(password),
+                ..
+            }),
+        ..
+    } = &mut project.project_type
+    {
+        password.clear();
+    }
+    project
+}
+
 #[server(GetProject)]
 pub async fn get_project(id: Uuid) -> Result<Project, ServerFnError> {
     println!("Fetching project with id: {}", id);
@@ -189,7 +374,7 @@ pub async fn get_project(id: Uuid) -> Result<Project, ServerFnError> {
     println!("Fetched project with id: {}, lock", id);
     if let Some(project) = context.get_project(id).await {
         println!("Fetched project with id: {}", id);
-        Ok(project.as_ref().clone())
+        Ok(without_secrets(&project))
     } else {
         println!("No project with id: {}", id);
         use http::StatusCode;
@@ -221,7 +406,7 @@ pub async fn get_project_domains(
 #[server(AddProjectDomain)]
 pub async fn add_project_domain(id: Uuid, domain: String) -> Result<(), ServerFnError> {
     println!("Adding domain {} to project {}", domain, id);
-    user()?;
+    let user = user()?;
 
     let mut project_context = project_context()?;
 
@@ -231,9 +416,18 @@ pub async fn add_project_domain(id: Uuid, domain: String) -> Result<(), ServerFn
         .ok_or(ServerFnError::new("Not project with given id"))?;
 
     project_context
-        .add_project_domain(project, domain)
+        .add_project_domain(project.clone(), domain.clone())
         .await
         .map_err(ServerFnError::new)?;
+    audit_project(
+        &user,
+        &project_context,
+        "domain.add",
+        id,
+        Some(project.as_ref()),
+        Some(domain),
+    )
+    .await;
     Ok(())
 }
 
@@ -242,6 +436,21 @@ pub struct SupportContainerFields {
     pub name: String,
     pub image: String,
     pub env_vars: Option<HashMap<String, EnvironmentVar>>,
+    pub volumes: Option<HashMap<String, Volume>>,
+    pub command: Option<String>,
+    pub entrypoint: Option<String>,
+    pub ports: Option<HashMap<String, SupportPort>>,
+    pub restart_policy: Option<RestartPolicy>,
+    pub healthcheck: Option<HealthCheck>,
+    pub limits: Option<ResourceLimits>,
+    pub depends_on: Option<HashMap<String, Dependency>>,
+}
+
+/// Collects indexed form fields like `env_vars[3][key]` back into a list in form order
+fn form_list<T>(fields: Option<HashMap<String, T>>) -> Vec<T> {
+    let mut fields = fields.unwrap_or_default().into_iter().collect::<Vec<_>>();
+    fields.sort_by_key(|(index, _)| index.parse::<usize>().unwrap_or(usize::MAX));
+    fields.into_iter().map(|(_, field)| field).collect()
 }
 
 #[server(SetSupportContainers)]
@@ -249,55 +458,137 @@ pub async fn set_support_containers(
     id: Uuid,
     support_containers: Option<HashMap<String, SupportContainerFields>>,
 ) -> Result<(), ServerFnError> {
-    user()?;
+    let user = user()?;
 
     let mut project_context = project_context()?;
 
-    let mut project = project_context
+    let before = project_context
         .get_project(id)
         .await
-        .ok_or(ServerFnError::new("Not project with given id"))?
-        .as_ref()
-        .clone();
+        .ok_or(ServerFnError::new("Not project with given id"))?;
+    let mut project = before.as_ref().clone();
 
-    if let ProjectType::Container {
+    let ProjectType::Container {
         support_containers: support_c,
         ..
     } = &mut project.project_type
-    {
-        *support_c = HashMap::new();
-        if let Some(support_containers_new) = support_containers {
-            for container in support_containers_new.into_values() {
-                support_c.insert(
-                    container.name,
-                    SupportContainer {
-                        image: container.image,
-                        container: Container {
-                            env_vars: container
-                                .env_vars
-                                .map(|ev| ev.into_values().collect::<Vec<_>>().into())
-                                .unwrap_or_default(),
-                            volumes: vec![].into(),
-                            status: crate::common::ContainerStatus::None,
-                        },
-                    },
-                );
+    else {
+        return Err(ServerFnError::new("project not container"));
+    };
+    // Submitting the form without any service removes all of them
+    let support_containers_new = support_containers.unwrap_or_default();
+
+    let mut previous = std::mem::take(support_c);
+    // Docker containers that have to go, either removed or with a changed config
+    let mut stale = vec![];
+    for fields in support_containers_new.into_values() {
+        let healthcheck = fields.healthcheck.filter(|h| !h.command.trim().is_empty());
+        let runtime = ContainerRuntime::for_config(
+            healthcheck.as_ref(),
+            previous.get(&fields.name).map(|old| &old.container.runtime),
+        );
+        let new_container = SupportContainer {
+            image: fields.image,
+            command: fields.command.filter(|c| !c.trim().is_empty()),
+            entrypoint: fields.entrypoint.filter(|e| !e.trim().is_empty()),
+            ports: form_list(fields.ports).into(),
+            container: Container {
+                env_vars: form_list(fields.env_vars).into(),
+                volumes: form_list(fields.volumes)
+                    .into_iter()
+                    .filter(|v| !v.name.is_empty() && !v.container_path.is_empty())
+                    .collect::<Vec<_>>()
+                    .into(),
+                restart_policy: fields.restart_policy.unwrap_or_default(),
+                healthcheck,
+                limits: fields.limits.unwrap_or_default(),
+                depends_on: form_list(fields.depends_on),
+                status: crate::common::ContainerStatus::None,
+                runtime,
+            },
+        };
+        // Unchanged containers keep running, only changed ones are recreated
+        let container = match previous.remove(&fields.name) {
+            Some(old) if old == new_container => old,
+            Some(_) => {
+                stale.push(fields.name.clone());
+                // Keeps the container manager away until the old container is gone
+                let mut new_container = new_container;
+                new_container.container.status = crate::common::ContainerStatus::Creating;
+                new_container
             }
+            None => new_container,
+        };
+        support_c.insert(fields.name, container);
+    }
+    if let ProjectType::Container {
+        primary_container,
+        support_containers,
+        ..
+    } = &project.project_type
+    {
+        crate::common::validate_dependencies(primary_container, support_containers)
+            .map_err(ServerFnError::new)?;
+    }
+    let recreate = stale.clone();
+    stale.extend(previous.into_keys());
 
-            project_context
-                .update_project(project.id, Arc::new(project))
-                .await
-                .map_err(ServerFnError::new)?;
+    project_context
+        .update_project(project.id, Arc::new(project))
+        .await
+        .map_err(ServerFnError::new)?;
+
+    let docker = crate::common::get_docker();
+    for name in stale {
+        let docker_name = crate::common::ContainerSlot::Support(name).docker_name(id);
+        if let Err(err) = docker
+            .containers()
+            .get(&docker_name)
+            .remove(
+                &docker_api::opts::ContainerRemoveOpts::builder()
+                    .force(true)
+                    .build(),
+            )
+            .await
+        {
+            tracing::warn!("Cannot remove support container {docker_name} {err:?}");
         }
-    } else {
-        return Err(ServerFnError::new("project not container"));
     }
+
+    if !recreate.is_empty() {
+        let mut project = project_context
+            .get_project(id)
+            .await
+            .ok_or(ServerFnError::new("Not project with given id"))?
+            .as_ref()
+            .clone();
+        for name in recreate {
+            let slot = crate::common::ContainerSlot::Support(name);
+            if let Some(container) = project.project_type.get_container_mut(&slot) {
+                container.status = crate::common::ContainerStatus::None;
+            }
+        }
+        project_context
+            .update_project(id, Arc::new(project))
+            .await
+            .map_err(ServerFnError::new)?;
+    }
+
+    audit_project(
+        &user,
+        &project_context,
+        "project.support_containers.update",
+        id,
+        Some(before.as_ref()),
+        None,
+    )
+    .await;
     Ok(())
 }
 
 #[server(UpdateProjectPort)]
 pub async fn update_project_port(id: Uuid, port: u16) -> Result<(), ServerFnError> {
-    user()?;
+    let user = user()?;
 
     let mut project_context = project_context()?;
 
@@ -320,6 +611,15 @@ pub async fn update_project_port(id: Uuid, port: u16) -> Result<(), ServerFnErro
         .await
         .map_err(ServerFnError::new)?;
 
+    audit_project(
+        &user,
+        &project_context,
+        "project.port.update",
+        id,
+        Some(project.as_ref()),
+        None,
+    )
+    .await;
     Ok(())
 }
 
@@ -329,9 +629,13 @@ pub async fn update_project_image(
     exposed_ports: Option<HashMap<String, ExposedPort>>,
     env_vars: Option<HashMap<String, EnvironmentVar>>,
     volumes: Option<HashMap<String, crate::common::Volume>>,
+    restart_policy: Option<RestartPolicy>,
+    healthcheck: Option<HealthCheck>,
+    limits: Option<ResourceLimits>,
+    depends_on: Option<HashMap<String, Dependency>>,
     // tokens: Option<HashMap<String, Token>>,
 ) -> Result<(), ServerFnError> {
-    user()?;
+    let user = user()?;
 
     let mut project_context = project_context()?;
 
@@ -340,18 +644,49 @@ pub async fn update_project_image(
         .await
         .ok_or(ServerFnError::new("Not project with given id"))?;
 
-    let tokens = if let ProjectType::Container {
-        primary_container: _,
-        support_containers: _,
-        tokens,
-        exposed_ports: _,
-    } = &project.project_type
-    {
-        Some(tokens.clone())
-    } else {
-        None
-    };
+    let (tokens, image_source, deploy_history, hooks, jobs, log_retention, log_sinks) =
+        if let ProjectType::Container {
+            primary_container: _,
+            support_containers: _,
+            tokens,
+            exposed_ports: _,
+            image_source,
+            deploy_history,
+            hooks,
+            jobs,
+            log_retention,
+            log_sinks,
+        } = &project.project_type
+        {
+            (
+                Some(tokens.clone()),
+                image_source.clone(),
+                deploy_history.clone(),
+                hooks.clone(),
+                jobs.clone(),
+                log_retention.clone(),
+                log_sinks.clone(),
+            )
+        } else {
+            (
+                None,
+                None,
+                Default::default(),
+                Default::default(),
+                Default::default(),
+                Default::default(),
+                Default::default(),
+            )
+        };
 
+    let healthcheck = healthcheck.filter(|h| !h.command.trim().is_empty());
+    let runtime = ContainerRuntime::for_config(
+        healthcheck.as_ref(),
+        project
+            .project_type
+            .get_container(&crate::common::ContainerSlot::Primary)
+            .map(|previous| &previous.runtime),
+    );
     let new_project = Project {
         project_type: ProjectType::Container {
             support_containers: if let ProjectType::Container {
@@ -359,6 +694,12 @@ pub async fn update_project_image(
                 support_containers,
                 tokens: _,
                 exposed_ports: _,
+                image_source: _,
+                deploy_history: _,
+                hooks: _,
+                jobs: _,
+                log_retention: _,
+                log_sinks: _,
             } = &project.project_type
             {
                 support_containers.clone()
@@ -376,6 +717,12 @@ pub async fn update_project_image(
                 })
                 .unwrap_or_default(),
             tokens: tokens.unwrap_or_default(),
+            image_source,
+            deploy_history,
+            hooks,
+            jobs,
+            log_retention,
+            log_sinks,
             primary_container: Container {
                 env_vars: env_vars
                     .map(|ev| ev.into_values().collect::<Vec<_>>().into())
@@ -383,24 +730,1096 @@ pub async fn update_project_image(
                 volumes: volumes
                     .map(|v| v.into_values().collect::<Vec<_>>().into())
                     .unwrap_or_default(),
+                restart_policy: restart_policy.unwrap_or_default(),
+                healthcheck,
+                limits: limits.unwrap_or_default(),
+                depends_on: form_list(depends_on),
 
                 status: crate::common::ContainerStatus::None,
+                runtime,
             },
         },
         ..project.as_ref().clone()
     };
 
+    if let ProjectType::Container {
+        primary_container,
+        support_containers,
+        ..
+    } = &new_project.project_type
+    {
+        crate::common::validate_dependencies(primary_container, support_containers)
+            .map_err(ServerFnError::new)?;
+    }
+
     project_context
         .update_project(id, Arc::new(new_project))
         .await
         .map_err(ServerFnError::new)?;
 
+    audit_project(
+        &user,
+        &project_context,
+        "project.image.update",
+        id,
+        Some(project.as_ref()),
+        None,
+    )
+    .await;
+    Ok(())
+}
+
+#[server(SetImageSource)]
+pub async fn set_image_source(
+    id: Uuid,
+    reference: String,
+    username: Option<String>,
+    password: Option<String>,
+    auto_deploy: Option<bool>,
+    poll_interval_secs: Option<u64>,
+) -> Result<(), ServerFnError> {
+    let user = user()?;
+
+    let mut project_context = project_context()?;
+    let before = project_context
+        .get_project(id)
+        .await
+        .ok_or(ServerFnError::new("Not project with given id"))?;
+    let mut project = before.as_ref().clone();
+
+    let ProjectType::Container { image_source, .. } = &mut project.project_type else {
+        return Err(ServerFnError::new("project not container"));
+    };
+    let reference = reference.trim().to_string();
+    if reference.is_empty() {
+        // Back to uploading images with push_image
+        *image_source = None;
+    } else {
+        let username = username.filter(|u| !u.is_empty());
+        let mut source = match image_source.take() {
+            Some(old) if old.reference == reference => old,
+            _ => ImageSource::new(reference),
+        };
+        // An empty password field keeps the stored one for the same user
+        let password = match password.filter(|p| !p.is_empty()) {
+            Some(password) => Some(password),
+            None if source.username == username => source.password.take(),
+            None => None,
+        };
+        source.username = username;
+        source.password = password;
+        source.auto_deploy = auto_deploy.unwrap_or_default();
+        source.poll_interval_secs = poll_interval_secs
+            .unwrap_or(source.poll_interval_secs)
+            .max(30);
+        *image_source = Some(source);
+    }
+
+    project_context
+        .update_project(id, Arc::new(project))
+        .await
+        .map_err(ServerFnError::new)?;
+    audit_project(
+        &user,
+        &project_context,
+        "project.image_source.update",
+        id,
+        Some(before.as_ref()),
+        None,
+    )
+    .await;
+    Ok(())
+}
+
+/// Pulls the project's image source and redeploys the primary container with it,
+/// returns the deployed image digest
+#[server(DeployImage)]
+pub async fn deploy_image(id: Uuid) -> Result<String, ServerFnError> {
+    let user = user()?;
+
+    let mut project_context = project_context()?;
+    let before = project_context.get_project(id).await;
+    let digest = crate::deploy::deploy_image(&mut project_context, id, true)
+        .await
+        .map_err(ServerFnError::new)?;
+    audit_project(
+        &user,
+        &project_context,
+        "deploy.start",
+        id,
+        before.as_deref(),
+        digest.clone(),
+    )
+    .await;
+    Ok(digest.unwrap_or_default())
+}
+
+#[server(RollbackDeployment)]
+pub async fn rollback_deployment(id: Uuid, deployment_id: Uuid) -> Result<(), ServerFnError> {
+    let user = user()?;
+
+    let mut project_context = project_context()?;
+    let before = project_context.get_project(id).await;
+    crate::deploy::rollback(&mut project_context, id, deployment_id)
+        .await
+        .map_err(ServerFnError::new)?;
+    audit_project(
+        &user,
+        &project_context,
+        "deploy.rollback",
+        id,
+        before.as_deref(),
+        Some(format!("To deployment {deployment_id}")),
+    )
+    .await;
+    Ok(())
+}
+
+#[server(SetRetainedDeployments)]
+pub async fn set_retained_deployments(id: Uuid, retain: usize) -> Result<(), ServerFnError> {
+    let user = user()?;
+
+    let mut project_context = project_context()?;
+    let before = project_context.get_project(id).await;
+    crate::deploy::set_retained(&mut project_context, id, retain)
+        .await
+        .map_err(ServerFnError::new)?;
+    audit_project(
+        &user,
+        &project_context,
+        "deploy.retention.update",
+        id,
+        before.as_deref(),
+        Some(format!("Keep {retain} deployments")),
+    )
+    .await;
+    Ok(())
+}
+
+#[server(SetDeployHooks)]
+pub async fn set_deploy_hooks(
+    id: Uuid,
+    pre_deploy: String,
+    post_deploy: String,
+    timeout_secs: u64,
+) -> Result<(), ServerFnError> {
+    let user = user()?;
+
+    let mut project_context = project_context()?;
+    let before = project_context
+        .get_project(id)
+        .await
+        .ok_or(ServerFnError::new("Not project with given id"))?;
+    let mut project = before.as_ref().clone();
+    let ProjectType::Container { hooks, .. } = &mut project.project_type else {
+        return Err(ServerFnError::new("project doesnt have container"));
+    };
+    let command = |command: String| Some(command.trim().to_string()).filter(|c| !c.is_empty());
+    hooks.pre_deploy = command(pre_deploy);
+    hooks.post_deploy = command(post_deploy);
+    hooks.timeout_secs = timeout_secs.max(1);
+    project_context
+        .update_project(id, Arc::new(project))
+        .await
+        .map_err(ServerFnError::new)?;
+    audit_project(
+        &user,
+        &project_context,
+        "project.deploy_hooks.update",
+        id,
+        Some(before.as_ref()),
+        None,
+    )
+    .await;
+    Ok(())
+}
+
+#[server(SetJob)]
+pub async fn set_job(
+    id: Uuid,
+    name: String,
+    schedule: String,
+    command: String,
+    image: String,
+    concurrency: ConcurrencyPolicy,
+    enabled: Option<bool>,
+) -> Result<(), ServerFnError> {
+    use crate::common::ScheduledJob;
+    use crate::jobs::{parse_schedule, validate_job_name};
+    let user = user()?;
+
+    let name = name.trim().to_string();
+    validate_job_name(&name).map_err(ServerFnError::new)?;
+    let schedule = schedule.trim().to_string();
+    parse_schedule(&schedule).map_err(ServerFnError::new)?;
+    let command = command.trim().to_string();
+    if command.is_empty() {
+        return Err(ServerFnError::new("Command is required"));
+    }
+
+    let mut project_context = project_context()?;
+    let before = project_context
+        .get_project(id)
+        .await
+        .ok_or(ServerFnError::new("Not project with given id"))?;
+    let mut project = before.as_ref().clone();
+    let ProjectType::Container { jobs, .. } = &mut project.project_type else {
+        return Err(ServerFnError::new("project doesnt have container"));
+    };
+    let job_name = name.clone();
+    jobs.insert(
+        name,
+        ScheduledJob {
+            schedule,
+            command,
+            image: Some(image.trim().to_string()).filter(|image| !image.is_empty()),
+            concurrency,
+            enabled: enabled.unwrap_or_default(),
+        },
+    );
+    project_context
+        .update_project(id, Arc::new(project))
+        .await
+        .map_err(ServerFnError::new)?;
+    audit_project(
+        &user,
+        &project_context,
+        "job.set",
+        id,
+        Some(before.as_ref()),
+        Some(job_name),
+    )
+    .await;
+    Ok(())
+}
+
+#[server(DeleteJob)]
+pub async fn delete_job(id: Uuid, name: String) -> Result<(), ServerFnError> {
+    let user = user()?;
+
+    let mut project_context = project_context()?;
+    let before = project_context
+        .get_project(id)
+        .await
+        .ok_or(ServerFnError::new("Not project with given id"))?;
+    let mut project = before.as_ref().clone();
+    let ProjectType::Container { jobs, .. } = &mut project.project_type else {
+        return Err(ServerFnError::new("project doesnt have container"));
+    };
+    if jobs.remove(&name).is_none() {
+        return Err(ServerFnError::new("No job with given name"));
+    }
+    project_context
+        .update_project(id, Arc::new(project))
+        .await
+        .map_err(ServerFnError::new)?;
+    crate::jobs::remove_runs(id, &name).await;
+    audit_project(
+        &user,
+        &project_context,
+        "job.delete",
+        id,
+        Some(before.as_ref()),
+        Some(name),
+    )
+    .await;
+    Ok(())
+}
+
+#[server(RunJobNow)]
+pub async fn run_job_now(id: Uuid, name: String) -> Result<(), ServerFnError> {
+    let user = user()?;
+
+    let project_context = project_context()?;
+    let project = project_context
+        .get_project(id)
+        .await
+        .ok_or(ServerFnError::new("Not project with given id"))?;
+    let ProjectType::Container { jobs, .. } = &project.project_type else {
+        return Err(ServerFnError::new("project doesnt have container"));
+    };
+    if !jobs.contains_key(&name) {
+        return Err(ServerFnError::new("No job with given name"));
+    }
+    crate::jobs::request_run(id, &name);
+    audit_project(
+        &user,
+        &project_context,
+        "job.run",
+        id,
+        Some(project.as_ref()),
+        Some(name),
+    )
+    .await;
+    Ok(())
+}
+
+#[server(GetJobRuns)]
+pub async fn get_job_runs(id: Uuid, name: String) -> Result<Vec<JobRun>, ServerFnError> {
+    user()?;
+    Ok(crate::jobs::load_runs(id, &name).await)
+}
+
+#[server(SearchLogs)]
+pub async fn search_logs(id: Uuid, query: LogQuery) -> Result<LogSearch, ServerFnError> {
+    user()?;
+    crate::log_store::search(id, &query)
+        .await
+        .map_err(ServerFnError::new)
+}
+
+/// Stored metrics of a container of the project, or of the host without a project
+#[server(GetMetrics)]
+pub async fn get_metrics(
+    project_id: Option<Uuid>,
+    container: Option<String>,
+    range: MetricsRange,
+) -> Result<Vec<MetricPoint>, ServerFnError> {
+    use crate::common::ContainerSlot;
+    use crate::metrics_store::{query, MetricsSeries};
+    user()?;
+
+    let series = match project_id {
+        Some(project_id) => MetricsSeries::Container(
+            project_id,
+            match container {
+                Some(name) => ContainerSlot::Support(name),
+                None => ContainerSlot::Primary,
+            },
+        ),
+        None => MetricsSeries::Host,
+    };
+    Ok(query(&series, range).await)
+}
+
+#[server(SetLogRetention)]
+pub async fn set_log_retention(
+    id: Uuid,
+    max_age_days: u32,
+    max_size_mb: u64,
+) -> Result<(), ServerFnError> {
+    let user = user()?;
+
+    let mut project_context = project_context()?;
+    let before = project_context
+        .get_project(id)
+        .await
+        .ok_or(ServerFnError::new("Not project with given id"))?;
+    let mut project = before.as_ref().clone();
+    let ProjectType::Container { log_retention, .. } = &mut project.project_type else {
+        return Err(ServerFnError::new("project doesnt have container"));
+    };
+    log_retention.max_age_days = max_age_days.max(1);
+    log_retention.max_size_mb = max_size_mb.clamp(1, crate::common::LogRetention::MAX_SIZE_MB);
+    project_context
+        .update_project(id, Arc::new(project))
+        .await
+        .map_err(ServerFnError::new)?;
+    audit_project(
+        &user,
+        &project_context,
+        "project.log_retention.update",
+        id,
+        Some(before.as_ref()),
+        None,
+    )
+    .await;
+    Ok(())
+}
+
+#[server(AddLogSink)]
+pub async fn add_log_sink(
+    id: Uuid,
+    kind: String,
+    target: String,
+    authorization: Option<String>,
+    max_size_mb: Option<u64>,
+    max_files: Option<u32>,
+    containers: Option<bool>,
+    gateway: Option<bool>,
+) -> Result<(), ServerFnError> {
+    use crate::common::{LogSink, LogSinkKind, SyslogTransport};
+    let user = user()?;
+
+    let target = target.trim().to_string();
+    if target.is_empty() {
+        return Err(ServerFnError::new("Destination is required"));
+    }
+    let syslog = |transport| {
+        if !target.contains(':') {
+            return Err(ServerFnError::new("Syslog address needs to be host:port"));
+        }
+        Ok(LogSinkKind::Syslog {
+            address: target.clone(),
+            transport,
+        })
+    };
+    let kind = match kind.as_str() {
+        "syslog_udp" => syslog(SyslogTransport::Udp)?,
+        "syslog_tcp" => syslog(SyslogTransport::Tcp)?,
+        "http" => {
+            if !target.starts_with("http://") && !target.starts_with("https://") {
+                return Err(ServerFnError::new(
+                    "URL needs to start with http:// or https://",
+                ));
+            }
+            LogSinkKind::Http {
+                url: target,
+                authorization: authorization
+                    .map(|authorization| authorization.trim().to_string())
+                    .filter(|authorization| !authorization.is_empty()),
+            }
+        }
+        "file" => LogSinkKind::File {
+            path: target,
+            max_size_mb: max_size_mb.unwrap_or(100).max(1),
+            max_files: max_files.unwrap_or(5).max(1),
+        },
+        _ => return Err(ServerFnError::new("Unknown sink kind")),
+    };
+    let containers = containers.unwrap_or_default();
+    let gateway = gateway.unwrap_or_default();
+    if !containers && !gateway {
+        return Err(ServerFnError::new(
+            "Select container or gateway logs to forward",
+        ));
+    }
+
+    let mut project_context = project_context()?;
+    let before = project_context
+        .get_project(id)
+        .await
+        .ok_or(ServerFnError::new("Not project with given id"))?;
+    let mut project = before.as_ref().clone();
+    let ProjectType::Container { log_sinks, .. } = &mut project.project_type else {
+        return Err(ServerFnError::new("project doesnt have container"));
+    };
+    log_sinks.push(LogSink {
+        id: Uuid::new_v4(),
+        kind,
+        containers,
+        gateway,
+        enabled: true,
+    });
+    project_context
+        .update_project(id, Arc::new(project))
+        .await
+        .map_err(ServerFnError::new)?;
+    audit_project(
+        &user,
+        &project_context,
+        "log_sink.add",
+        id,
+        Some(before.as_ref()),
+        None,
+    )
+    .await;
+    Ok(())
+}
+
+#[server(SetLogSinkEnabled)]
+pub async fn set_log_sink_enabled(
+    id: Uuid,
+    sink_id: Uuid,
+    enabled: bool,
+) -> Result<(), ServerFnError> {
+    let user = user()?;
+
+    let mut project_context = project_context()?;
+    let before = project_context
+        .get_project(id)
+        .await
+        .ok_or(ServerFnError::new("Not project with given id"))?;
+    let mut project = before.as_ref().clone();
+    let ProjectType::Container { log_sinks, .. } = &mut project.project_type else {
+        return Err(ServerFnError::new("project doesnt have container"));
+    };
+    let sink = log_sinks
+        .iter_mut()
+        .find(|sink| sink.id == sink_id)
+        .ok_or(ServerFnError::new("No log sink with given id"))?;
+    sink.enabled = enabled;
+    project_context
+        .update_project(id, Arc::new(project))
+        .await
+        .map_err(ServerFnError::new)?;
+    audit_project(
+        &user,
+        &project_context,
+        "log_sink.update",
+        id,
+        Some(before.as_ref()),
+        Some(format!(
+            "{} {sink_id}",
+            if enabled { "Enabled" } else { "Disabled" }
+        )),
+    )
+    .await;
+    Ok(())
+}
+
+#[server(DeleteLogSink)]
+pub async fn delete_log_sink(id: Uuid, sink_id: Uuid) -> Result<(), ServerFnError> {
+    let user = user()?;
+
+    let mut project_context = project_context()?;
+    let before = project_context
+        .get_project(id)
+        .await
+        .ok_or(ServerFnError::new("Not project with given id"))?;
+    let mut project = before.as_ref().clone();
+    let ProjectType::Container { log_sinks, .. } = &mut project.project_type else {
+        return Err(ServerFnError::new("project doesnt have container"));
+    };
+    let count = log_sinks.len();
+    log_sinks.retain(|sink| sink.id != sink_id);
+    if log_sinks.len() == count {
+        return Err(ServerFnError::new("No log sink with given id"));
+    }
+    project_context
+        .update_project(id, Arc::new(project))
+        .await
+        .map_err(ServerFnError::new)?;
+    audit_project(
+        &user,
+        &project_context,
+        "log_sink.delete",
+        id,
+        Some(before.as_ref()),
+        None,
+    )
+    .await;
+    Ok(())
+}
+
+/// Delivers a single line to the sink right away, bypassing the forwarder's buffer
+#[server(TestLogSink)]
+pub async fn test_log_sink(id: Uuid, sink_id: Uuid) -> Result<(), ServerFnError> {
+    use crate::common::LogStream;
+    use crate::log_sinks::{deliver, SinkRecord};
+    user()?;
+
+    let project = project_context()?
+        .get_project(id)
+        .await
+        .ok_or(ServerFnError::new("Not project with given id"))?;
+    let ProjectType::Container { log_sinks, .. } = &project.project_type else {
+        return Err(ServerFnError::new("project doesnt have container"));
+    };
+    let sink = log_sinks
+        .iter()
+        .find(|sink| sink.id == sink_id)
+        .ok_or(ServerFnError::new("No log sink with given id"))?;
+    let record = SinkRecord {
+        source: "selfcloud".to_string(),
+        stream: LogStream::Stdout,
+        ts: chrono::Utc::now(),
+        text: format!("Test message from SelfCloud for {}", project.name),
+    };
+    deliver(&sink.kind, &project.name, &[record])
+        .await
+        .map_err(|err| ServerFnError::new(format!("{err:#}")))?;
+    Ok(())
+}
+
+/// Alert settings with the SMTP password left out
+#[server(GetAlertSettings)]
+pub async fn get_alert_settings() -> Result<AlertSettings, ServerFnError> {
+    user()?;
+    let mut settings = crate::alerts::load_settings().await;
+    if let Some(smtp) = &mut settings.smtp {
+        smtp.password = String::new();
+    }
+    Ok(settings)
+}
+
+#[server(GetFiringAlerts)]
+pub async fn get_firing_alerts() -> Result<Vec<FiringAlert>, ServerFnError> {
+    user()?;
+    Ok(crate::alerts::firing())
+}
+
+#[server(AddAlertRule)]
+pub async fn add_alert_rule(
+    condition: String,
+    percent: Option<f64>,
+    minutes: Option<u32>,
+    project_id: String,
+) -> Result<(), ServerFnError> {
+    use crate::common::{AlertCondition, AlertRule};
+    let user = user()?;
+
+    let percent = || match percent {
+        Some(percent) if percent > 0.0 && percent <= 100.0 => Ok(percent),
+        _ => Err(ServerFnError::new(
+            "Threshold needs to be between 0 and 100",
+        )),
+    };
+    let condition = match condition.as_str() {
+        "container_crashed" => AlertCondition::ContainerCrashed,
+        "cpu_above" => AlertCondition::CpuAbove {
+            percent: percent()?,
+            // Stored metrics at full resolution only go back an hour
+            minutes: minutes.unwrap_or(5).clamp(1, 60),
+        },
+        "host_disk_above" => AlertCondition::HostDiskAbove {
+            percent: percent()?,
+        },
+        "certificate_failed" => AlertCondition::CertificateFailed,
+        _ => return Err(ServerFnError::new("Unknown alert condition")),
+    };
+    let project_id = match project_id.trim() {
+        "" => None,
+        project_id => Some(Uuid::parse_str(project_id).map_err(ServerFnError::new)?),
+    };
+
+    let detail = format!("{condition:?}");
+    crate::alerts::update_settings(|settings| {
+        settings.rules.push(AlertRule {
+            id: Uuid::new_v4(),
+            condition,
+            project_id,
+            enabled: true,
+        });
+        Ok(())
+    })
+    .await
+    .map_err(ServerFnError::new)?;
+    audit(&user, "alert.rule.add", Some(detail)).await;
+    Ok(())
+}
+
+#[server(SetAlertRuleEnabled)]
+pub async fn set_alert_rule_enabled(rule_id: Uuid, enabled: bool) -> Result<(), ServerFnError> {
+    let user = user()?;
+    crate::alerts::update_settings(|settings| {
+        let rule = settings
+            .rules
+            .iter_mut()
+            .find(|rule| rule.id == rule_id)
+            .ok_or("No alert rule with given id")?;
+        rule.enabled = enabled;
+        Ok(())
+    })
+    .await
+    .map_err(ServerFnError::new)?;
+    audit(
+        &user,
+        "alert.rule.update",
+        Some(format!(
+            "{} {rule_id}",
+            if enabled { "Enabled" } else { "Disabled" }
+        )),
+    )
+    .await;
+    Ok(())
+}
+
+#[server(DeleteAlertRule)]
+pub async fn delete_alert_rule(rule_id: Uuid) -> Result<(), ServerFnError> {
+    let user = user()?;
+    crate::alerts::update_settings(|settings| {
+        let count = settings.rules.len();
+        settings.rules.retain(|rule| rule.id != rule_id);
+        if settings.rules.len() == count {
+            return Err("No alert rule with given id".to_string());
+        }
+        Ok(())
+    })
+    .await
+    .map_err(ServerFnError::new)?;
+    audit(&user, "alert.rule.delete", Some(rule_id.to_string())).await;
+    Ok(())
+}
+
+#[server(AddNotificationChannel)]
+pub async fn add_notification_channel(kind: String, target: String) -> Result<(), ServerFnError> {
+    use crate::common::{ChannelKind, NotificationChannel, WebhookFormat};
+    let user = user()?;
+
+    let target = target.trim().to_string();
+    let webhook = |format| {
+        if !target.starts_with("http://") && !target.starts_with("https://") {
+            return Err(ServerFnError::new(
+                "URL needs to start with http:// or https://",
+            ));
+        }
+        Ok(ChannelKind::Webhook {
+            url: target.clone(),
+            format,
+        })
+    };
+    let kind = match kind.as_str() {
+        "generic" => webhook(WebhookFormat::Generic)?,
+        "slack" => webhook(WebhookFormat::Slack)?,
+        "discord" => webhook(WebhookFormat::Discord)?,
+        "email" => {
+            if !target.contains('@') {
+                return Err(ServerFnError::new("Not an email address"));
+            }
+            ChannelKind::Email { to: target }
+        }
+        _ => return Err(ServerFnError::new("Unknown channel kind")),
+    };
+
+    let detail = match &kind {
+        ChannelKind::Webhook { url, format } => format!("{format:?} webhook to {}", url_host(url)),
+        ChannelKind::Email { to } => format!("Email to {to}"),
+    };
+    crate::alerts::update_settings(|settings| {
+        if matches!(kind, ChannelKind::Email { .. }) && settings.smtp.is_none() {
+            return Err("Configure an SMTP server first".to_string());
+        }
+        settings.channels.push(NotificationChannel {
+            id: Uuid::new_v4(),
+            kind,
+            enabled: true,
+        });
+        Ok(())
+    })
+    .await
+    .map_err(ServerFnError::new)?;
+    audit(&user, "alert.channel.add", Some(detail)).await;
+    Ok(())
+}
+
+#[server(DeleteNotificationChannel)]
+pub async fn delete_notification_channel(channel_id: Uuid) -> Result<(), ServerFnError> {
+    let user = user()?;
+    crate::alerts::update_settings(|settings| {
+        let count = settings.channels.len();
+        settings.channels.retain(|channel| channel.id != channel_id);
+        if settings.channels.len() == count {
+            return Err("No notification channel with given id".to_string());
+        }
+        Ok(())
+    })
+    .await
+    .map_err(ServerFnError::new)?;
+    audit(&user, "alert.channel.delete", Some(channel_id.to_string())).await;
+    Ok(())
+}
+
+#[server(TestNotificationChannel)]
+pub async fn test_notification_channel(channel_id: Uuid) -> Result<(), ServerFnError> {
+    use crate::alerts::{load_settings, notify, Notification};
+    user()?;
+
+    let settings = load_settings().await;
+    let channel = settings
+        .channels
+        .iter()
+        .find(|channel| channel.id == channel_id)
+        .ok_or(ServerFnError::new("No notification channel with given id"))?;
+    let notification = Notification {
+        summary: "Test notification from SelfCloud".to_string(),
+        details: "Alerts will be delivered to this channel".to_string(),
+        resolved: false,
+        ts: chrono::Utc::now(),
+    };
+    notify(channel, settings.smtp.as_ref(), &notification)
+        .await
+        .map_err(|err| ServerFnError::new(format!("{err:#}")))
+}
+
+/// Saves the SMTP server used by email channels, an empty host removes it and an empty
+/// password keeps the stored one
+#[server(SetSmtpSettings)]
+pub async fn set_smtp_settings(
+    host: String,
+    port: u16,
+    username: String,
+    password: String,
+    from: String,
+    starttls: Option<bool>,
+) -> Result<(), ServerFnError> {
+    use crate::common::SmtpSettings;
+    let user = user()?;
+
+    let host = host.trim().to_string();
+    let detail = Some(host.clone()).filter(|host| !host.is_empty());
+    crate::alerts::update_settings(|settings| {
+        if host.is_empty() {
+            settings.smtp = None;
+            return Ok(());
+        }
+        if !from.contains('@') {
+            return Err("Sender needs to be an email address".to_string());
+        }
+        let password = match (&settings.smtp, password.is_empty()) {
+            (Some(smtp), true) => smtp.password.clone(),
+            _ => password,
+        };
+        settings.smtp = Some(SmtpSettings {
+            host,
+            port,
+            username: username.trim().to_string(),
+            password,
+            from: from.trim().to_string(),
+            starttls: starttls.unwrap_or_default(),
+        });
+        Ok(())
+    })
+    .await
+    .map_err(ServerFnError::new)?;
+    audit(&user, "alert.smtp.update", detail).await;
+    Ok(())
+}
+
+/// Global subscriptions for `None`, otherwise the ones of the project
+#[server(GetWebhooks)]
+pub async fn get_webhooks(
+    project_id: Option<Uuid>,
+) -> Result<Vec<WebhookSubscription>, ServerFnError> {
+    user()?;
+    Ok(crate::webhooks::load_subscriptions()
+        .await
+        .into_iter()
+        .filter(|subscription| subscription.project_id == project_id)
+        .collect())
+}
+
+/// Subscribes `url` to the checked events, or to every event when none is checked. An
+/// empty secret is generated
+#[server(AddWebhook)]
+pub async fn add_webhook(
+    project_id: String,
+    url: String,
+    secret: String,
+    events: Option<HashMap<String, String>>,
+) -> Result<(), ServerFnError> {
+    use crate::common::WebhookEventKind;
+    let user = user()?;
+
+    let url = url.trim().to_string();
+    if !url.starts_with("http://") && !url.starts_with("https://") {
+        return Err(ServerFnError::new(
+            "URL needs to start with http:// or https://",
+        ));
+    }
+    let project_id = match project_id.trim() {
+        "" => None,
+        project_id => Some(Uuid::parse_str(project_id).map_err(ServerFnError::new)?),
+    };
+    let events = form_list(events)
+        .iter()
+        .map(|name| {
+            WebhookEventKind::from_name(name)
+                .ok_or(ServerFnError::new(format!("Unknown event {name}")))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let secret = match secret.trim() {
+        "" => crate::utils::random_ascii_string(32),
+        secret => secret.to_string(),
+    };
+
+    let detail = format!("To {}", url_host(&url));
+    crate::webhooks::update_subscriptions(|subscriptions| {
+        subscriptions.push(WebhookSubscription {
+            id: Uuid::new_v4(),
+            url,
+            secret,
+            events,
+            project_id,
+            enabled: true,
+        });
+        Ok(())
+    })
+    .await
+    .map_err(ServerFnError::new)?;
+    audit(&user, "webhook.add", Some(detail)).await;
+    Ok(())
+}
+
+#[server(SetWebhookEnabled)]
+pub async fn set_webhook_enabled(webhook_id: Uuid, enabled: bool) -> Result<(), ServerFnError> {
+    let user = user()?;
+    crate::webhooks::update_subscriptions(|subscriptions| {
+        let subscription = subscriptions
+            .iter_mut()
+            .find(|subscription| subscription.id == webhook_id)
+            .ok_or("No webhook with given id")?;
+        subscription.enabled = enabled;
+        Ok(())
+    })
+    .await
+    .map_err(ServerFnError::new)?;
+    audit(
+        &user,
+        "webhook.update",
+        Some(format!(
+            "{} {webhook_id}",
+            if enabled { "Enabled" } else { "Disabled" }
+        )),
+    )
+    .await;
+    Ok(())
+}
+
+#[server(DeleteWebhook)]
+pub async fn delete_webhook(webhook_id: Uuid) -> Result<(), ServerFnError> {
+    let user = user()?;
+    crate::webhooks::update_subscriptions(|subscriptions| {
+        subscriptions.retain(|subscription| subscription.id != webhook_id);
+        Ok(())
+    })
+    .await
+    .map_err(ServerFnError::new)?;
+    audit(&user, "webhook.delete", Some(webhook_id.to_string())).await;
+    Ok(())
+}
+
+/// Sends a signed `ping` event right away, it is logged like any other delivery
+#[server(TestWebhook)]
+pub async fn test_webhook(webhook_id: Uuid) -> Result<(), ServerFnError> {
+    use crate::common::WebhookEventKind;
+    use crate::webhooks::{deliver, load_subscriptions, log_delivery, payload, WebhookEvent};
+    user()?;
+
+    let subscription = load_subscriptions()
+        .await
+        .into_iter()
+        .find(|subscription| subscription.id == webhook_id)
+        .ok_or(ServerFnError::new("No webhook with given id"))?;
+    let project_name = match subscription.project_id {
+        Some(project_id) => project_context()?
+            .get_project(project_id)
+            .await
+            .map(|project| project.name.clone()),
+        None => None,
+    };
+    let event = WebhookEvent::new(
+        WebhookEventKind::Ping,
+        subscription.project_id,
+        serde_json::json!({ "message": "Test delivery from SelfCloud" }),
+    );
+    let body = payload(&event, project_name);
+    let delivery = deliver(&reqwest::Client::new(), &subscription, &event, &body, 1).await;
+    log_delivery(&delivery).await.map_err(ServerFnError::new)?;
+    match delivery.error {
+        Some(error) => Err(ServerFnError::new(error)),
+        None => Ok(()),
+    }
+}
+
+#[server(GetWebhookDeliveries)]
+pub async fn get_webhook_deliveries(
+    webhook_id: Uuid,
+) -> Result<Vec<WebhookDelivery>, ServerFnError> {
+    user()?;
+    Ok(crate::webhooks::deliveries(webhook_id, 50).await)
+}
+
+/// Entries the audit log page shows at most
+#[cfg(feature = "ssr")]
+const AUDIT_PAGE_SIZE: usize = 500;
+
+/// Newest audit log entries matching the query, the export has all of them
+#[server(GetAuditLog)]
+pub async fn get_audit_log(query: AuditQuery) -> Result<AuditSearch, ServerFnError> {
+    user()?;
+    let mut entries = crate::audit::search(&query)
+        .await
+        .map_err(ServerFnError::new)?;
+    let truncated = entries.len() > AUDIT_PAGE_SIZE;
+    entries.truncate(AUDIT_PAGE_SIZE);
+    Ok(AuditSearch { entries, truncated })
+}
+
+#[server(GetProjectUptime)]
+pub async fn get_project_uptime(id: Uuid) -> Result<Vec<DomainUptime>, ServerFnError> {
+    user()?;
+    Ok(crate::uptime::project_uptime(id))
+}
+
+/// Serves the public status page of the project on `domain`, an empty domain turns it off
+#[server(SetStatusPage)]
+pub async fn set_status_page(id: Uuid, domain: String, title: String) -> Result<(), ServerFnError> {
+    use crate::common::StatusPage;
+    use unicase::UniCase;
+
+    let user = user()?;
+    let mut project_context = project_context()?;
+    let project = project_context
+        .get_project(id)
+        .await
+        .ok_or(ServerFnError::new("Not project with given id"))?;
+
+    let domain = domain.trim().to_ascii_lowercase();
+    let status_page = if domain.is_empty() {
+        None
+    } else {
+        let name = UniCase::new(domain.clone());
+        if let Some(status) = project_context.get_domain(&name).await {
+            if status.project.upgrade().is_some_and(|owner| owner.id != id) {
+                return Err(ServerFnError::new("Domain belongs to another project"));
+            }
+        }
+        let current = project
+            .status_page
+            .as_ref()
+            .is_some_and(|page| page.domain == name);
+        let routed = !current
+            && match &project.project_type {
+                ProjectType::PortForward(_) => project_context
+                    .get_project_domains(id)
+                    .await
+                    .contains_key(&name),
+                ProjectType::Container { exposed_ports, .. } => exposed_ports.iter().any(|port| {
+                    port.domains
+                        .iter()
+                        .any(|port_domain| port_domain.name == name)
+                }),
+            };
+        if routed {
+            return Err(ServerFnError::new(
+                "Domain already serves the project, assign a separate one",
+            ));
+        }
+        let title = title.trim();
+        Some(StatusPage {
+            domain: name,
+            title: if title.is_empty() {
+                project.name.clone()
+            } else {
+                title.to_string()
+            },
+        })
+    };
+
+    let mut new_project = project.as_ref().clone();
+    new_project.status_page = status_page;
+    let new_project = Arc::new(new_project);
+    project_context
+        .update_project(id, new_project.clone())
+        .await
+        .map_err(ServerFnError::new)?;
+    if !domain.is_empty()
+        && project_context
+            .get_domain(&UniCase::new(domain.clone()))
+            .await
+            .is_none()
+    {
+        project_context
+            .add_project_domain(new_project, domain)
+            .await
+            .map_err(ServerFnError::new)?;
+    }
+    audit_project(
+        &user,
+        &project_context,
+        "status_page.update",
+        id,
+        Some(project.as_ref()),
+        None,
+    )
+    .await;
     Ok(())
 }
 
 #[server(DeleteProject)]
 pub async fn delete_project(id: Uuid) -> Result<(), ServerFnError> {
-    user()?;
+    let user = user()?;
 
     let mut project_context = project_context()?;
 
@@ -414,6 +1833,12 @@ pub async fn delete_project(id: Uuid) -> Result<(), ServerFnError> {
         support_containers,
         tokens,
         exposed_ports,
+        image_source: _,
+        deploy_history: _,
+        hooks: _,
+        jobs: _,
+        log_retention: _,
+        log_sinks: _,
     } = &project.project_type
     {
         if let Some(container) = container.status.as_running() {
@@ -431,6 +1856,19 @@ pub async fn delete_project(id: Uuid) -> Result<(), ServerFnError> {
         .remove_project(id)
         .await
         .map_err(ServerFnError::new)?;
+    crate::log_store::remove_project_logs(id).await;
+    crate::metrics_store::remove_project_metrics(id).await;
+    crate::uptime::remove_project_uptime(id).await;
+    crate::registry_store::remove_project_registry(id).await;
+    audit_project(
+        &user,
+        &project_context,
+        "project.delete",
+        id,
+        Some(project.as_ref()),
+        None,
+    )
+    .await;
     Ok(())
 }
 
@@ -448,7 +1886,7 @@ pub async fn update_project_name_token(
     project_name: String,
     tokens: Option<HashMap<String, Token>>,
 ) -> Result<(), ServerFnError> {
-    user()?;
+    let user = user()?;
 
     let mut project_context = project_context()?;
 
@@ -471,6 +1909,12 @@ pub async fn update_project_name_token(
             support_containers,
             tokens: old_tokens,
             exposed_ports,
+            image_source,
+            deploy_history,
+            hooks,
+            jobs,
+            log_retention,
+            log_sinks,
         } => {
             // container.tokens = tokens.unwrap_or_default();
             ProjectType::Container {
@@ -478,6 +1922,12 @@ pub async fn update_project_name_token(
                 support_containers,
                 tokens: tokens.unwrap_or_default(),
                 exposed_ports,
+                image_source,
+                deploy_history,
+                hooks,
+                jobs,
+                log_retention,
+                log_sinks,
             }
         }
     };
@@ -485,11 +1935,21 @@ pub async fn update_project_name_token(
         name: project_name,
         id,
         project_type: new_project_type,
+        status_page: project.status_page.clone(),
     };
     project_context
         .update_project(id, Arc::new(new_project))
         .await
         .map_err(ServerFnError::new)?;
+    audit_project(
+        &user,
+        &project_context,
+        "project.update",
+        id,
+        Some(project.as_ref()),
+        None,
+    )
+    .await;
     Ok(())
 }
 
@@ -518,3 +1978,31 @@ pub fn project_context() -> Result<crate::context::ProjectContext, ServerFnError
 
     Ok(context)
 }
+
+/// Records a change of the project made by the user
+#[cfg(feature = "ssr")]
+async fn audit_project(
+    user: &crate::auth::User,
+    context: &crate::context::ProjectContext,
+    action: &str,
+    id: Uuid,
+    before: Option<&Project>,
+    detail: Option<String>,
+) {
+    crate::audit::record_change(user.into(), action, context, id, before, detail).await
+}
+
+/// Records a change that does not belong to a project
+#[cfg(feature = "ssr")]
+pub(crate) async fn audit(user: &crate::auth::User, action: &str, detail: Option<String>) {
+    crate::audit::record(user.into(), action, None, Default::default(), detail).await
+}
+
+/// Host of a URL, the rest of it may carry secrets
+#[cfg(feature = "ssr")]
+fn url_host(url: &str) -> String {
+    reqwest::Url::parse(url)
+        .ok()
+        .and_then(|url| url.host_str().map(str::to_string))
+        .unwrap_or_default()
+}
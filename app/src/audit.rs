@@ -0,0 +1,266 @@
+//! Append-only log of who changed what and when.
+//!
+//! Every audited action is one JSON line in `audit/audit.jsonl`, lines are never rewritten
+//! or removed. Changes of a project are stored as a JSON patch between its configuration
+//! before and after, with project tokens, environment variable values and other secrets
+//! replaced by fingerprints so the log can be exported without leaking them.
+
+use chrono::Utc;
+use once_cell::sync::Lazy;
+use sha2::{Digest, Sha256};
+use tokio::io::AsyncWriteExt;
+use uuid::Uuid;
+
+use crate::auth::User;
+use crate::common::{get_home_path, AuditActor, AuditEntry, AuditQuery, Project};
+use crate::context::ProjectContext;
+
+/// Keys whose string values are replaced by a fingerprint
+const SECRET_KEYS: [&str; 4] = ["token", "secret", "password", "authorization"];
+
+/// Serializes appends, so concurrent entries never interleave
+static APPEND_LOCK: Lazy<tokio::sync::Mutex<()>> = Lazy::new(Default::default);
+
+impl From<&User> for AuditActor {
+    fn from(user: &User) -> Self {
+        AuditActor::User {
+            id: user.id,
+            name: user.name.clone(),
+            email: user.email.clone(),
+        }
+    }
+}
+
+fn audit_path() -> std::path::PathBuf {
+    get_home_path().join("audit").join("audit.jsonl")
+}
+
+/// Tells values apart without revealing them
+fn fingerprint(secret: &str) -> String {
+    let digest = hex::encode(Sha256::digest(secret.as_bytes()));
+    format!("redacted:{}", &digest[..12])
+}
+
+fn redact(value: &mut serde_json::Value) {
+    match value {
+        serde_json::Value::Object(object) => {
+            // Project tokens are keyed by their value
+            if let Some(serde_json::Value::Object(tokens)) = object.get_mut("tokens") {
+                *tokens = std::mem::take(tokens)
+                    .into_iter()
+                    .map(|(token, value)| (fingerprint(&token), value))
+                    .collect();
+            }
+            // Environment variables are key value pairs, every value may be a secret
+            if let Some(serde_json::Value::Array(env_vars)) = object.get_mut("env_vars") {
+                for env_var in env_vars.iter_mut() {
+                    if let Some(serde_json::Value::String(val)) = env_var.get_mut("val") {
+                        *val = fingerprint(val);
+                    }
+                }
+            }
+            for (key, value) in object.iter_mut() {
+                match value {
+                    serde_json::Value::String(secret) if SECRET_KEYS.contains(&key.as_str()) => {
+                        *secret = fingerprint(secret);
+                    }
+                    value => redact(value),
+                }
+            }
+        }
+        serde_json::Value::Array(values) => values.iter_mut().for_each(redact),
+        _ => {}
+    }
+}
+
+fn snapshot(project: Option<&Project>) -> serde_json::Value {
+    let Some(project) = project else {
+        return serde_json::Value::Null;
+    };
+    let mut value = crate::webhooks::configuration(project);
+    redact(&mut value);
+    value
+}
+
+/// Patch from `before` to `after`, `None` when the project did not exist
+pub fn changes(before: Option<&Project>, after: Option<&Project>) -> json_patch::Patch {
+    json_patch::diff(&snapshot(before), &snapshot(after))
+}
+
+/// Appends an entry, failures are logged but never fail the audited action
+pub async fn record(
+    actor: AuditActor,
+    action: &str,
+    project: Option<&Project>,
+    changes: json_patch::Patch,
+    detail: Option<String>,
+) {
+    let entry = AuditEntry {
+        id: Uuid::new_v4(),
+        ts: Utc::now(),
+        actor,
+        action: action.to_string(),
+        project_id: project.map(|project| project.id),
+        project_name: project.map(|project| project.name.clone()),
+        changes,
+        detail,
+    };
+    if let Err(err) = append(&entry).await {
+        tracing::error!("Cannot write audit entry {entry:?} {err:?}");
+    }
+}
+
+/// Records a change of project `id`, diffing `before` against its current state
+pub async fn record_change(
+    actor: AuditActor,
+    action: &str,
+    context: &ProjectContext,
+    id: Uuid,
+    before: Option<&Project>,
+    detail: Option<String>,
+) {
+    let after = context.get_project(id).await;
+    record(
+        actor,
+        action,
+        after.as_deref().or(before),
+        changes(before, after.as_deref()),
+        detail,
+    )
+    .await
+}
+
+/// Runs an interactive session between `<action>.open` and `<action>.close` entries,
+/// the closing one notes how long it lasted
+pub async fn record_session<F: std::future::Future>(
+    actor: AuditActor,
+    action: &str,
+    project: Option<&Project>,
+    detail: Option<String>,
+    session: F,
+) -> F::Output {
+    let opened = Utc::now();
+    record(
+        actor.clone(),
+        &format!("{action}.open"),
+        project,
+        Default::default(),
+        detail.clone(),
+    )
+    .await;
+    let output = session.await;
+    let seconds = (Utc::now() - opened).num_seconds();
+    let duration = format!("Lasted {}m {}s", seconds / 60, seconds % 60);
+    record(
+        actor,
+        &format!("{action}.close"),
+        project,
+        Default::default(),
+        Some(match detail {
+            Some(detail) => format!("{detail}, {duration}"),
+            None => duration,
+        }),
+    )
+    .await;
+    output
+}
+
+async fn append(entry: &AuditEntry) -> anyhow::Result<()> {
+    let path = audit_path();
+    if let Some(parent) = path.parent() {
+        tokio::fs::create_dir_all(parent).await?;
+    }
+    let mut data = serde_json::to_vec(entry)?;
+    data.push(b'\n');
+    let _lock = APPEND_LOCK.lock().await;
+    let mut file = tokio::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .await?;
+    file.write_all(&data).await?;
+    file.flush().await?;
+    Ok(())
+}
+
+/// Entries matching the query, newest first
+pub async fn search(query: &AuditQuery) -> anyhow::Result<Vec<AuditEntry>> {
+    let since = query.since_time().map_err(anyhow::Error::msg)?;
+    let until = query.until_time().map_err(anyhow::Error::msg)?;
+    let actor = query.actor.trim().to_lowercase();
+    let action = query.action.trim();
+
+    let data = match tokio::fs::read_to_string(audit_path()).await {
+        Ok(data) => data,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(vec![]),
+        Err(err) => return Err(err.into()),
+    };
+    let mut entries = data
+        .lines()
+        .filter_map(|line| serde_json::from_str::<AuditEntry>(line).ok())
+        .filter(|entry| {
+            since.is_none_or(|since| entry.ts >= since)
+                && until.is_none_or(|until| entry.ts <= until)
+                && query
+                    .project_id
+                    .is_none_or(|project_id| entry.project_id == Some(project_id))
+                && entry.action.starts_with(action)
+                && (actor.is_empty() || entry.actor.to_string().to_lowercase().contains(&actor))
+        })
+        .collect::<Vec<_>>();
+    entries.reverse();
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::ProjectFields;
+
+    fn project(env_vars: serde_json::Value, support_env_vars: serde_json::Value) -> Project {
+        serde_json::from_value::<ProjectFields>(serde_json::json!({
+            "id": Uuid::nil(),
+            "name": "app",
+            "project_type": { "Container": {
+                "primary_container": { "env_vars": env_vars },
+                "support_containers": {
+                    "db": { "image": "postgres", "container": { "env_vars": support_env_vars } },
+                },
+                "tokens": {},
+                "exposed_ports": [],
+            } },
+        }))
+        .unwrap()
+        .into()
+    }
+
+    #[test]
+    fn changes_redact_env_var_values() {
+        let before = project(
+            serde_json::json!([{ "key": "DB_PASSWORD", "val": "old-db-secret" }]),
+            serde_json::json!([]),
+        );
+        let after = project(
+            serde_json::json!([
+                { "key": "DB_PASSWORD", "val": "new-db-secret" },
+                { "key": "PUBLIC_URL", "val": "https://example.com" },
+            ]),
+            serde_json::json!([{ "key": "POSTGRES_PASSWORD", "val": "postgres-secret" }]),
+        );
+
+        let patch = serde_json::to_string(&changes(Some(&before), Some(&after))).unwrap();
+        for plain in [
+            "old-db-secret",
+            "new-db-secret",
+            "https://example.com",
+            "postgres-secret",
+        ] {
+            assert!(!patch.contains(plain), "{plain} in {patch}");
+        }
+        assert!(patch.contains(&fingerprint("new-db-secret")));
+        assert!(patch.contains("POSTGRES_PASSWORD"));
+
+        let created = serde_json::to_string(&changes(None, Some(&before))).unwrap();
+        assert!(!created.contains("old-db-secret"), "{created}");
+    }
+}
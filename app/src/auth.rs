@@ -50,6 +50,7 @@ pub type AuthorizedUsers = HashMap<String, UserWithPass>;
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 pub struct UserWithPass {
     pub user: User,
+    /// Argon2 PHC string, or the plain password in files written before hashing
     pub pass: String,
 }
 
@@ -67,37 +68,100 @@ pub async fn login(email: String, password: String) -> Result<(), ServerFnError>
     use http::header::HeaderValue;
     use leptos_axum::ResponseOptions;
 
-    let users = use_context::<AuthorizedUsers>().ok_or(ServerFnError::new(anyhow::anyhow!(
-        "authorized user not found"
-    )))?;
-    // use tower_cookies::Cookies; // Removed
-
-    // let cookies =
-    //    use_context::<Cookies>().ok_or(ServerFnError::new(anyhow::anyhow!("no cookies")))?;
-
-    let user = users.get(&email);
-    if let Some(user) = user {
-        if user.pass == password {
-            let cookie = get_encrypted_user_cookie(&user.user).unwrap();
-            // cookies.add(cookie);
-            let response_options = use_context::<ResponseOptions>()
-                .ok_or(ServerFnError::new(anyhow::anyhow!("No ResponseOptions")))?;
-            response_options.append_header(
-                http::header::SET_COOKIE,
-                HeaderValue::from_str(&cookie.to_string())?,
-            );
-
-            info!("Login successful");
-            leptos_axum::redirect("/dashboard");
-            Ok(())
-        } else {
-            info!("User password mismatch {}!={}", user.pass, password);
-            Err(ServerFnError::new("UnAuthorized"))
-        }
-    } else {
-        info!("User not found with email {email}");
-        Err(ServerFnError::new("UnAuthorized"))
+    let user = crate::users::authenticate(&email, &password)
+        .await
+        .map_err(|err| {
+            tracing::error!("Cannot authenticate {err:?}");
+            ServerFnError::new("UnAuthorized")
+        })?;
+    let Some(user) = user else {
+        info!("Login failed for {email}");
+        return Err(ServerFnError::new("UnAuthorized"));
+    };
+
+    let cookie = get_encrypted_user_cookie(&user).map_err(ServerFnError::new)?;
+    let response_options = use_context::<ResponseOptions>()
+        .ok_or(ServerFnError::new(anyhow::anyhow!("No ResponseOptions")))?;
+    response_options.append_header(
+        http::header::SET_COOKIE,
+        HeaderValue::from_str(&cookie.to_string())?,
+    );
+
+    info!("Login successful");
+    leptos_axum::redirect("/dashboard");
+    Ok(())
+}
+
+#[server(GetUsers)]
+pub async fn get_users() -> Result<Vec<User>, ServerFnError> {
+    crate::api::user()?;
+    let mut users = crate::users::users()
+        .map_err(ServerFnError::new)?
+        .into_values()
+        .map(|user| user.user)
+        .collect::<Vec<_>>();
+    users.sort_by(|a, b| a.email.cmp(&b.email));
+    Ok(users)
+}
+
+#[server(AddUser)]
+pub async fn add_user(name: String, email: String, password: String) -> Result<(), ServerFnError> {
+    let user = crate::api::user()?;
+    let (name, email) = (name.trim().to_string(), email.trim().to_string());
+    if name.is_empty() || !email.contains('@') {
+        return Err(ServerFnError::new("Name and a valid email are required"));
     }
+    crate::users::add_user(name, email.clone(), password)
+        .await
+        .map_err(ServerFnError::new)?;
+    crate::api::audit(&user, "user.add", Some(email)).await;
+    Ok(())
+}
+
+#[server(RemoveUser)]
+pub async fn remove_user(email: String) -> Result<(), ServerFnError> {
+    let user = crate::api::user()?;
+    if user.email == email {
+        return Err(ServerFnError::new("You cannot remove yourself"));
+    }
+    crate::users::update_users(|users| {
+        users.remove(&email).ok_or("No user with this email")?;
+        Ok(())
+    })
+    .await
+    .map_err(ServerFnError::new)?;
+    crate::api::audit(&user, "user.remove", Some(email)).await;
+    Ok(())
+}
+
+/// Sets a new password for another user
+#[server(ResetPassword)]
+pub async fn reset_password(email: String, password: String) -> Result<(), ServerFnError> {
+    let user = crate::api::user()?;
+    crate::users::set_password(&email, password)
+        .await
+        .map_err(ServerFnError::new)?;
+    crate::api::audit(&user, "user.password.reset", Some(email)).await;
+    Ok(())
+}
+
+#[server(ChangePassword)]
+pub async fn change_password(
+    current_password: String,
+    new_password: String,
+) -> Result<(), ServerFnError> {
+    let user = crate::api::user()?;
+    let authenticated = crate::users::authenticate(&user.email, &current_password)
+        .await
+        .map_err(ServerFnError::new)?;
+    if authenticated.is_none() {
+        return Err(ServerFnError::new("Current password is wrong"));
+    }
+    crate::users::set_password(&user.email, new_password)
+        .await
+        .map_err(ServerFnError::new)?;
+    crate::api::audit(&user, "user.password.change", None).await;
+    Ok(())
 }
 
 #[cfg(feature = "ssr")]
@@ -163,6 +227,9 @@ pub mod server {
             .map_err(|_e| anyhow!("Invalid decrypt"))?;
 
         let decoded_user = bincode::deserialize::<User>(&ciphertext)?;
+        if !crate::users::is_current(&decoded_user) {
+            return Err(anyhow!("User no longer exists"));
+        }
 
         Ok(decoded_user)
     }
@@ -13,6 +13,7 @@ pub struct Project {
     pub name: String,
 
     pub project_type: ProjectType,
+    pub status_page: Option<StatusPage>,
 }
 
 #[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
@@ -24,6 +25,22 @@ pub enum ProjectType {
 
         tokens: HashMap<String, Token>,
         exposed_ports: smallvec::SmallVec<[ExposedPort; 4]>,
+        /// Registry image deployed as the primary container instead of an uploaded one
+        #[serde(default)]
+        image_source: Option<ImageSource>,
+        /// Images the primary container was deployed with, kept for rollbacks
+        #[serde(default)]
+        deploy_history: DeployHistory,
+        #[serde(default)]
+        hooks: DeployHooks,
+        /// Scheduled one-off commands by name
+        #[serde(default)]
+        jobs: HashMap<String, ScheduledJob>,
+        #[serde(default)]
+        log_retention: LogRetention,
+        /// Where container output and gateway access logs are forwarded to
+        #[serde(default)]
+        log_sinks: Vec<LogSink>,
     },
 }
 
@@ -31,6 +48,85 @@ pub enum ProjectType {
 pub struct SupportContainer {
     pub image: String,
     pub container: Container,
+    /// Overrides the image's `CMD`, split like a shell would
+    #[serde(default)]
+    pub command: Option<String>,
+    /// Overrides the image's `ENTRYPOINT`, split like a shell would
+    #[serde(default)]
+    pub entrypoint: Option<String>,
+    /// Ports published on the host, support containers are otherwise
+    /// only reachable from the project network
+    #[serde(default)]
+    pub ports: smallvec::SmallVec<[SupportPort; 2]>,
+}
+
+impl SupportContainer {
+    pub fn new(image: String) -> Self {
+        Self {
+            image,
+            container: Container {
+                env_vars: vec![].into(),
+                volumes: vec![].into(),
+                restart_policy: Default::default(),
+                healthcheck: None,
+                limits: Default::default(),
+                depends_on: vec![],
+                #[cfg(feature = "ssr")]
+                status: ContainerStatus::None,
+                #[cfg(feature = "ssr")]
+                runtime: Default::default(),
+            },
+            command: None,
+            entrypoint: None,
+            ports: vec![].into(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct SupportPort {
+    pub port: u16,
+    pub host_port: u16,
+}
+
+/// Splits a command line into arguments, honouring single and double quotes
+/// and backslash escapes like a POSIX shell would.
+pub fn split_command(command: &str) -> Vec<String> {
+    let mut args = vec![];
+    let mut current = String::new();
+    let mut in_arg = false;
+    let mut quote: Option<char> = None;
+    let mut chars = command.chars();
+    while let Some(c) = chars.next() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some('"'), '\\') | (None, '\\') => {
+                if let Some(next) = chars.next() {
+                    current.push(next);
+                }
+                in_arg = true;
+            }
+            (Some(_), c) => current.push(c),
+            (None, '\'' | '"') => {
+                quote = Some(c);
+                in_arg = true;
+            }
+            (None, c) if c.is_whitespace() => {
+                if in_arg {
+                    args.push(std::mem::take(&mut current));
+                    in_arg = false;
+                }
+            }
+            (None, c) => {
+                current.push(c);
+                in_arg = true;
+            }
+        }
+    }
+    if in_arg {
+        args.push(current);
+    }
+    args
 }
 
 #[derive(Serialize, Clone, Debug)]
@@ -114,9 +210,106 @@ impl ProjectType {
                 support_containers: _,
                 tokens: _,
                 exposed_ports: _,
+                image_source: _,
+                deploy_history: _,
+                hooks: _,
+                jobs: _,
+                log_retention: _,
+                log_sinks: _,
             } => Some(primary_container),
         }
     }
+
+    pub fn get_container(&self, slot: &ContainerSlot) -> Option<&Container> {
+        match &self {
+            ProjectType::PortForward(_) => None,
+            ProjectType::Container {
+                primary_container,
+                support_containers,
+                ..
+            } => match slot {
+                ContainerSlot::Primary => Some(primary_container),
+                ContainerSlot::Support(name) => support_containers.get(name).map(|s| &s.container),
+            },
+        }
+    }
+
+    pub fn get_container_mut(&mut self, slot: &ContainerSlot) -> Option<&mut Container> {
+        match self {
+            ProjectType::PortForward(_) => None,
+            ProjectType::Container {
+                primary_container,
+                support_containers,
+                ..
+            } => match slot {
+                ContainerSlot::Primary => Some(primary_container),
+                ContainerSlot::Support(name) => {
+                    support_containers.get_mut(name).map(|s| &mut s.container)
+                }
+            },
+        }
+    }
+
+    /// The primary container followed by the support containers, empty for port forwards
+    pub fn containers(&self) -> Vec<(ContainerSlot, &Container)> {
+        match &self {
+            ProjectType::PortForward(_) => vec![],
+            ProjectType::Container {
+                primary_container,
+                support_containers,
+                ..
+            } => std::iter::once((ContainerSlot::Primary, primary_container))
+                .chain(
+                    support_containers
+                        .iter()
+                        .map(|(name, s)| (ContainerSlot::Support(name.clone()), &s.container)),
+                )
+                .collect(),
+        }
+    }
+}
+
+/// Identifies one of the containers managed for a project.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Hash)]
+pub enum ContainerSlot {
+    Primary,
+    Support(String),
+}
+
+impl ContainerSlot {
+    /// Name of the docker container backing this slot
+    pub fn docker_name(&self, project_id: Uuid) -> String {
+        match self {
+            ContainerSlot::Primary => format!("selfcloud_container_{}_latest", project_id),
+            ContainerSlot::Support(name) => {
+                format!("selfcloud_supportcontainer_{}_{}", project_id, name)
+            }
+        }
+    }
+
+    /// Reverse of [`ContainerSlot::docker_name`], `None` for containers not managed by us
+    pub fn from_docker_name(name: &str) -> Option<(Uuid, ContainerSlot)> {
+        let name = name.trim_start_matches('/');
+        if let Some(rest) = name.strip_prefix("selfcloud_container_") {
+            let id = rest.strip_suffix("_latest")?;
+            return Some((Uuid::parse_str(id).ok()?, ContainerSlot::Primary));
+        }
+        let rest = name.strip_prefix("selfcloud_supportcontainer_")?;
+        let (id, support_name) = rest.split_once('_')?;
+        Some((
+            Uuid::parse_str(id).ok()?,
+            ContainerSlot::Support(support_name.to_string()),
+        ))
+    }
+}
+
+impl std::fmt::Display for ContainerSlot {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ContainerSlot::Primary => write!(f, "primary"),
+            ContainerSlot::Support(name) => write!(f, "{name}"),
+        }
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
@@ -131,14 +324,329 @@ pub struct Volume {
     pub container_path: String,
 }
 
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum RestartPolicyKind {
+    #[default]
+    No,
+    Always,
+    UnlessStopped,
+    OnFailure,
+}
+
+impl RestartPolicyKind {
+    pub const ALL: [RestartPolicyKind; 4] = [
+        RestartPolicyKind::No,
+        RestartPolicyKind::Always,
+        RestartPolicyKind::UnlessStopped,
+        RestartPolicyKind::OnFailure,
+    ];
+
+    /// Name used by docker for `HostConfig.RestartPolicy`
+    pub fn docker_name(&self) -> &'static str {
+        match self {
+            RestartPolicyKind::No => "no",
+            RestartPolicyKind::Always => "always",
+            RestartPolicyKind::UnlessStopped => "unless-stopped",
+            RestartPolicyKind::OnFailure => "on-failure",
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+pub struct RestartPolicy {
+    pub kind: RestartPolicyKind,
+    /// Only used by docker for [`RestartPolicyKind::OnFailure`]
+    #[serde(default)]
+    pub max_retries: u32,
+}
+
+/// Command run inside the container to decide whether it is healthy,
+/// executed with `sh -c` like docker's `CMD-SHELL` healthchecks.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct HealthCheck {
+    pub command: String,
+    #[serde(default = "HealthCheck::default_interval")]
+    pub interval_secs: u64,
+    #[serde(default = "HealthCheck::default_timeout")]
+    pub timeout_secs: u64,
+    #[serde(default = "HealthCheck::default_retries")]
+    pub retries: u32,
+    #[serde(default)]
+    pub start_period_secs: u64,
+}
+
+impl HealthCheck {
+    fn default_interval() -> u64 {
+        30
+    }
+
+    fn default_timeout() -> u64 {
+        10
+    }
+
+    fn default_retries() -> u32 {
+        3
+    }
+}
+
+impl Default for HealthCheck {
+    fn default() -> Self {
+        Self {
+            command: String::new(),
+            interval_secs: Self::default_interval(),
+            timeout_secs: Self::default_timeout(),
+            retries: Self::default_retries(),
+            start_period_secs: 0,
+        }
+    }
+}
+
+/// Resource limits applied to a container, unset limits are unlimited
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+pub struct ResourceLimits {
+    /// Hard memory limit in MiB
+    #[serde(default)]
+    pub memory_mb: Option<u64>,
+    /// Relative CPU weight against other containers, docker's default is 1024
+    #[serde(default)]
+    pub cpu_shares: Option<u32>,
+    /// Number of CPUs the container may use, e.g. `0.5`
+    #[serde(default)]
+    pub cpus: Option<f64>,
+    #[serde(default)]
+    pub pids_limit: Option<i64>,
+    /// Restart the container after the kernel killed it for running out of memory,
+    /// otherwise it stays stopped until started by hand
+    #[serde(default)]
+    pub restart_on_oom: bool,
+}
+
+impl ResourceLimits {
+    pub fn memory_bytes(&self) -> Option<u64> {
+        self.memory_mb.map(|mb| mb * 1024 * 1024)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+pub enum HealthStatus {
+    #[default]
+    NoCheck,
+    Starting,
+    Healthy,
+    Unhealthy,
+}
+
+/// Readiness a container waits for before a dependent container is started
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum DependencyCondition {
+    /// The container is running
+    #[default]
+    Started,
+    /// A TCP connection to [`Dependency::port`] succeeds
+    TcpPort,
+    /// The container's healthcheck passes
+    Healthy,
+    /// The container ran to completion with exit code 0, e.g. migrations
+    CompletedSuccessfully,
+}
+
+impl DependencyCondition {
+    pub const ALL: [DependencyCondition; 4] = [
+        DependencyCondition::Started,
+        DependencyCondition::TcpPort,
+        DependencyCondition::Healthy,
+        DependencyCondition::CompletedSuccessfully,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DependencyCondition::Started => "started",
+            DependencyCondition::TcpPort => "tcp_port",
+            DependencyCondition::Healthy => "healthy",
+            DependencyCondition::CompletedSuccessfully => "completed_successfully",
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Dependency {
+    /// Name of the support container depended upon
+    pub name: String,
+    #[serde(default)]
+    pub condition: DependencyCondition,
+    /// Port checked by [`DependencyCondition::TcpPort`]
+    #[serde(default)]
+    pub port: Option<u16>,
+    #[serde(default = "Dependency::default_timeout")]
+    pub timeout_secs: u64,
+}
+
+impl Dependency {
+    fn default_timeout() -> u64 {
+        120
+    }
+}
+
+/// Orders support containers so every container comes after the ones it depends on,
+/// fails on unknown dependencies and cycles.
+pub fn start_order(
+    support_containers: &HashMap<String, SupportContainer>,
+) -> Result<Vec<String>, String> {
+    let mut names = support_containers.keys().cloned().collect::<Vec<_>>();
+    names.sort();
+
+    let mut order = Vec::with_capacity(names.len());
+    let mut remaining = names;
+    while !remaining.is_empty() {
+        let (ready, blocked): (Vec<_>, Vec<_>) = remaining.into_iter().partition(|name| {
+            support_containers[name]
+                .container
+                .depends_on
+                .iter()
+                .all(|dep| order.contains(&dep.name))
+        });
+        if ready.is_empty() {
+            for name in blocked.iter() {
+                for dep in support_containers[name].container.depends_on.iter() {
+                    if !support_containers.contains_key(&dep.name) {
+                        return Err(format!("{name} depends on unknown container {}", dep.name));
+                    }
+                }
+            }
+            return Err(format!("Dependency cycle between {}", blocked.join(", ")));
+        }
+        order.extend(ready);
+        remaining = blocked;
+    }
+    Ok(order)
+}
+
+/// Checks the dependencies of every container in the project can be satisfied
+pub fn validate_dependencies(
+    primary: &Container,
+    support_containers: &HashMap<String, SupportContainer>,
+) -> Result<(), String> {
+    start_order(support_containers)?;
+    for dep in primary.depends_on.iter() {
+        if !support_containers.contains_key(&dep.name) {
+            return Err(format!("Depends on unknown container {}", dep.name));
+        }
+    }
+    let all_dependencies = primary.depends_on.iter().chain(
+        support_containers
+            .values()
+            .flat_map(|s| s.container.depends_on.iter()),
+    );
+    for dep in all_dependencies {
+        if dep.condition == DependencyCondition::TcpPort && dep.port.is_none() {
+            return Err(format!("{} needs a port to wait for", dep.name));
+        }
+    }
+    Ok(())
+}
+
+/// Number of consecutive failures after which a container is considered crash looping
+pub const CRASH_LOOP_THRESHOLD: u32 = 5;
+
+/// Supervision state the container manager keeps for a running container,
+/// it is not persisted and resets whenever the container config changes.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+pub struct ContainerRuntime {
+    pub restart_count: u32,
+    pub consecutive_failures: u32,
+    pub started_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub next_retry_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub last_failure: Option<String>,
+    pub health: HealthStatus,
+    pub failing_checks: u32,
+    pub stopped_by_user: bool,
+    /// Set when the last exit was the kernel's OOM killer
+    pub oom_killed: bool,
+    /// Set when a one-off container other containers wait on exited successfully
+    pub completed: bool,
+}
+
+impl ContainerRuntime {
+    const BACKOFF_BASE_SECS: i64 = 5;
+    const BACKOFF_MAX_SECS: i64 = 300;
+
+    /// Supervision state for a freshly configured container. A container that was
+    /// already probed keeps its last health until the next probe reports
+    pub fn for_config(healthcheck: Option<&HealthCheck>, previous: Option<&Self>) -> Self {
+        let health = match (healthcheck, previous.map(|runtime| runtime.health)) {
+            (None, _) => HealthStatus::NoCheck,
+            (Some(_), None | Some(HealthStatus::NoCheck)) => HealthStatus::Starting,
+            (Some(_), Some(health)) => health,
+        };
+        ContainerRuntime {
+            health,
+            ..Default::default()
+        }
+    }
+
+    #[must_use]
+    pub fn is_crash_looping(&self) -> bool {
+        self.consecutive_failures >= CRASH_LOOP_THRESHOLD
+    }
+
+    /// Delay before the next restart attempt, doubling with every consecutive failure
+    pub fn backoff(&self) -> chrono::Duration {
+        let exponent = self.consecutive_failures.saturating_sub(1).min(16);
+        let secs = (Self::BACKOFF_BASE_SECS << exponent).min(Self::BACKOFF_MAX_SECS);
+        chrono::Duration::seconds(secs)
+    }
+
+    pub fn record_failure(&mut self, reason: impl Into<String>) {
+        let now = chrono::Utc::now();
+        self.restart_count += 1;
+        self.consecutive_failures += 1;
+        self.failing_checks = 0;
+        self.last_failure = Some(reason.into());
+        self.next_retry_at = Some(now + self.backoff());
+    }
+
+    pub fn record_start(&mut self) {
+        self.started_at = Some(chrono::Utc::now());
+        self.next_retry_at = None;
+        self.failing_checks = 0;
+        self.oom_killed = false;
+        self.completed = false;
+        if self.health != HealthStatus::NoCheck {
+            self.health = HealthStatus::Starting;
+        }
+    }
+
+    /// Returns `true` once the backoff of the last failure has elapsed
+    pub fn can_retry(&self) -> bool {
+        !self.stopped_by_user
+            && self
+                .next_retry_at
+                .map(|at| at <= chrono::Utc::now())
+                .unwrap_or(true)
+    }
+}
+
 #[derive(Serialize, Clone, Debug)]
 pub struct Container {
     pub env_vars: smallvec::SmallVec<[EnvironmentVar; 4]>,
     #[serde(default)]
     pub volumes: smallvec::SmallVec<[Volume; 2]>,
+    #[serde(default)]
+    pub restart_policy: RestartPolicy,
+    #[serde(default)]
+    pub healthcheck: Option<HealthCheck>,
+    #[serde(default)]
+    pub limits: ResourceLimits,
+    #[serde(default)]
+    pub depends_on: Vec<Dependency>,
     #[cfg(feature = "ssr")]
     #[serde(skip)]
     pub status: ContainerStatus,
+    #[cfg(feature = "ssr")]
+    #[serde(skip)]
+    pub runtime: ContainerRuntime,
 }
 
 #[derive(Serialize, Clone, PartialEq, Deserialize, Debug)]
@@ -148,6 +656,528 @@ pub struct Token {
     pub description: String,
 }
 
+/// Registry reference the primary image is pulled from, e.g. `ghcr.io/org/app:tag`
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct ImageSource {
+    pub reference: String,
+    #[serde(default)]
+    pub username: Option<String>,
+    #[serde(default)]
+    pub password: Option<String>,
+    /// Pull periodically and redeploy when the image behind the reference changes
+    #[serde(default)]
+    pub auto_deploy: bool,
+    #[serde(default = "ImageSource::default_poll_interval")]
+    pub poll_interval_secs: u64,
+    /// Digest of the image that is currently deployed
+    #[serde(default)]
+    pub deployed_digest: Option<String>,
+    #[serde(default)]
+    pub deployed_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+impl ImageSource {
+    pub fn new(reference: String) -> Self {
+        Self {
+            reference,
+            username: None,
+            password: None,
+            auto_deploy: false,
+            poll_interval_secs: Self::default_poll_interval(),
+            deployed_digest: None,
+            deployed_at: None,
+        }
+    }
+
+    fn default_poll_interval() -> u64 {
+        300
+    }
+
+    /// Splits the reference into the image and the tag docker pulls,
+    /// references pinned by digest are passed through whole
+    pub fn image_and_tag(&self) -> (&str, Option<&str>) {
+        split_image_reference(&self.reference)
+    }
+
+    /// Registry host used for credentials, docker hub when the reference has none
+    pub fn registry(&self) -> &str {
+        let (image, _) = self.image_and_tag();
+        match image.split_once('/') {
+            Some((host, _)) if host.contains(['.', ':']) || host == "localhost" => host,
+            _ => "docker.io",
+        }
+    }
+}
+
+/// Splits an image reference into the image and the tag docker pulls,
+/// references pinned by digest are passed through whole
+pub fn split_image_reference(reference: &str) -> (&str, Option<&str>) {
+    let reference = reference.trim();
+    if reference.contains('@') {
+        return (reference, None);
+    }
+    let name_start = reference.rfind('/').map(|i| i + 1).unwrap_or_default();
+    match reference[name_start..].rfind(':') {
+        Some(i) => (
+            &reference[..name_start + i],
+            Some(&reference[name_start + i + 1..]),
+        ),
+        None => (reference, Some("latest")),
+    }
+}
+
+/// Deployments of the primary image, oldest first. Each one keeps its image alive under
+/// `selfcloud_image_<id>:<deployment id>` until it falls out of the retained count
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct DeployHistory {
+    #[serde(default = "DeployHistory::default_retain")]
+    pub retain: usize,
+    #[serde(default)]
+    pub deployments: Vec<Deployment>,
+    /// Deployment currently tagged `latest`
+    #[serde(default)]
+    pub active: Option<Uuid>,
+    /// Set by a rollback, automatic deploys leave the project alone until the next deploy
+    #[serde(default)]
+    pub pinned: bool,
+}
+
+impl Default for DeployHistory {
+    fn default() -> Self {
+        Self {
+            retain: Self::default_retain(),
+            deployments: vec![],
+            active: None,
+            pinned: false,
+        }
+    }
+}
+
+impl DeployHistory {
+    fn default_retain() -> usize {
+        5
+    }
+
+    pub fn active_deployment(&self) -> Option<&Deployment> {
+        self.deployments
+            .iter()
+            .find(|deployment| Some(deployment.id) == self.active)
+    }
+
+    /// Whether [`Self::advance`] would change the active deployment
+    pub fn would_advance(&self, status: &DeployStatus) -> bool {
+        self.active_deployment()
+            .is_some_and(|deployment| !deployment.status.is_done() && &deployment.status != status)
+    }
+
+    /// Moves the active deployment on to `status`, a deployment that already turned
+    /// healthy or failed keeps its outcome
+    pub fn advance(&mut self, status: DeployStatus) {
+        if !self.would_advance(&status) {
+            return;
+        }
+        if let Some(deployment) = self
+            .deployments
+            .iter_mut()
+            .find(|deployment| Some(deployment.id) == self.active)
+        {
+            deployment.status = status;
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct Deployment {
+    pub id: Uuid,
+    pub image_id: String,
+    pub deployed_at: chrono::DateTime<chrono::Utc>,
+    /// What deployed it, e.g. the description of the token used to push
+    pub source: String,
+    #[serde(default)]
+    pub git_sha: Option<String>,
+    #[serde(default)]
+    pub status: DeployStatus,
+    #[serde(default)]
+    pub hook_runs: Vec<HookRun>,
+}
+
+/// Where a deployment is, from the image being loaded to the container passing its healthcheck
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum DeployStatus {
+    #[default]
+    ImageLoaded,
+    ContainerCreated,
+    Healthy,
+    Failed(String),
+}
+
+impl DeployStatus {
+    pub fn is_done(&self) -> bool {
+        matches!(self, Self::Healthy | Self::Failed(_))
+    }
+}
+
+impl std::fmt::Display for DeployStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::ImageLoaded => write!(f, "Image loaded"),
+            Self::ContainerCreated => write!(f, "Container created"),
+            Self::Healthy => write!(f, "Healthy"),
+            Self::Failed(err) => write!(f, "Failed: {err}"),
+        }
+    }
+}
+
+impl Deployment {
+    /// Tag the deployment's image is retained under
+    pub fn tag(&self) -> String {
+        self.id.simple().to_string()
+    }
+
+    /// Whether the hook still has to succeed for this deployment
+    pub fn hook_pending(&self, kind: HookKind) -> bool {
+        !self
+            .hook_runs
+            .iter()
+            .any(|run| run.kind == kind && run.succeeded())
+    }
+}
+
+/// Commands run in a one-off container from the new image, on the project network
+/// with the primary container's env and volumes
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct DeployHooks {
+    /// Runs before the new image replaces the running container, e.g. `./migrate`.
+    /// A failure aborts the deploy and keeps the old container
+    #[serde(default)]
+    pub pre_deploy: Option<String>,
+    /// Runs once the new container is up, failures are only recorded
+    #[serde(default)]
+    pub post_deploy: Option<String>,
+    #[serde(default = "DeployHooks::default_timeout")]
+    pub timeout_secs: u64,
+}
+
+impl Default for DeployHooks {
+    fn default() -> Self {
+        Self {
+            pre_deploy: None,
+            post_deploy: None,
+            timeout_secs: Self::default_timeout(),
+        }
+    }
+}
+
+impl DeployHooks {
+    fn default_timeout() -> u64 {
+        600
+    }
+
+    pub fn command(&self, kind: HookKind) -> Option<&str> {
+        match kind {
+            HookKind::PreDeploy => self.pre_deploy.as_deref(),
+            HookKind::PostDeploy => self.post_deploy.as_deref(),
+        }
+        .filter(|command| !command.trim().is_empty())
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum HookKind {
+    PreDeploy,
+    PostDeploy,
+}
+
+impl std::fmt::Display for HookKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            HookKind::PreDeploy => write!(f, "pre-deploy"),
+            HookKind::PostDeploy => write!(f, "post-deploy"),
+        }
+    }
+}
+
+/// A command run on a cron schedule in a one-off container on the project network
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct ScheduledJob {
+    /// Cron expression, `min hour day month weekday` with an optional leading seconds field
+    pub schedule: String,
+    pub command: String,
+    /// Image to run, the primary image when `None`
+    #[serde(default)]
+    pub image: Option<String>,
+    #[serde(default)]
+    pub concurrency: ConcurrencyPolicy,
+    #[serde(default = "ScheduledJob::default_enabled")]
+    pub enabled: bool,
+}
+
+impl ScheduledJob {
+    fn default_enabled() -> bool {
+        true
+    }
+}
+
+/// What happens when a job is due while its previous run is still going
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum ConcurrencyPolicy {
+    /// Skip the new run
+    #[default]
+    Forbid,
+    /// Kill the running one and start the new run
+    Replace,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct JobRun {
+    pub id: Uuid,
+    pub started_at: chrono::DateTime<chrono::Utc>,
+    /// `None` while running
+    pub finished_at: Option<chrono::DateTime<chrono::Utc>>,
+    /// `None` when it timed out, was replaced or could not be started
+    pub exit_code: Option<i64>,
+    /// Tail of the combined stdout and stderr, or why the run did not finish
+    pub logs: String,
+    /// Started with "run now" instead of by the schedule
+    pub manual: bool,
+}
+
+/// How much persisted container output a project keeps, whichever limit is hit first
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct LogRetention {
+    pub max_age_days: u32,
+    /// Across all containers of the project
+    pub max_size_mb: u64,
+}
+
+impl Default for LogRetention {
+    fn default() -> Self {
+        Self {
+            max_age_days: 14,
+            max_size_mb: 256,
+        }
+    }
+}
+
+impl LogRetention {
+    /// 1 TiB, far beyond what a single project should keep
+    pub const MAX_SIZE_MB: u64 = 1024 * 1024;
+}
+
+/// A destination logs of a project are forwarded to
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct LogSink {
+    pub id: Uuid,
+    pub kind: LogSinkKind,
+    /// Forward the output of the project's containers
+    pub containers: bool,
+    /// Forward access logs of the project's domains
+    pub gateway: bool,
+    pub enabled: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum LogSinkKind {
+    /// RFC5424 messages, octet counted over TCP
+    Syslog {
+        address: String,
+        transport: SyslogTransport,
+    },
+    /// JSON pushed in the format of Loki's `/loki/api/v1/push`
+    Http {
+        url: String,
+        /// Sent as the `Authorization` header
+        #[serde(default)]
+        authorization: Option<String>,
+    },
+    /// Appended lines, rotated to `<path>.1` .. `<path>.<max_files>`
+    File {
+        path: String,
+        max_size_mb: u64,
+        max_files: u32,
+    },
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum SyslogTransport {
+    Udp,
+    Tcp,
+}
+
+impl std::fmt::Display for LogSinkKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LogSinkKind::Syslog {
+                address,
+                transport: SyslogTransport::Udp,
+            } => write!(f, "syslog udp://{address}"),
+            LogSinkKind::Syslog {
+                address,
+                transport: SyslogTransport::Tcp,
+            } => write!(f, "syslog tcp://{address}"),
+            LogSinkKind::Http { url, .. } => write!(f, "HTTP {url}"),
+            LogSinkKind::File { path, .. } => write!(f, "File {path}"),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum LogStream {
+    Stdout,
+    Stderr,
+}
+
+/// A line of container output as persisted by the log collector
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct LogLine {
+    pub ts: chrono::DateTime<chrono::Utc>,
+    pub stream: LogStream,
+    pub text: String,
+}
+
+/// A search hit, with the short id of the container it came from so lines of
+/// different deployments can be told apart
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct LogMatch {
+    pub version: String,
+    pub line: LogLine,
+}
+
+/// Filters for persisted logs, also the query string of the download endpoint.
+/// Times are `datetime-local` input values in UTC.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug, Default)]
+pub struct LogQuery {
+    /// Support container name, the primary container when `None`
+    #[serde(default)]
+    pub container: Option<String>,
+    /// Case insensitive substring, empty matches everything
+    #[serde(default)]
+    pub text: String,
+    #[serde(default)]
+    pub since: Option<String>,
+    #[serde(default)]
+    pub until: Option<String>,
+}
+
+impl LogQuery {
+    pub fn since_time(&self) -> Result<Option<chrono::DateTime<chrono::Utc>>, String> {
+        parse_time_input(self.since.as_deref())
+    }
+
+    pub fn until_time(&self) -> Result<Option<chrono::DateTime<chrono::Utc>>, String> {
+        parse_time_input(self.until.as_deref())
+    }
+
+    pub fn slot(&self) -> ContainerSlot {
+        match self.container.as_deref().filter(|name| !name.is_empty()) {
+            Some(name) => ContainerSlot::Support(name.to_string()),
+            None => ContainerSlot::Primary,
+        }
+    }
+}
+
+/// Query params of the live logs websocket. Times are `datetime-local` input values in
+/// UTC like in [`LogQuery`].
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct LogsParams {
+    /// Lines of history to replay before following, all of it when `None`
+    #[serde(default)]
+    pub tail: Option<u64>,
+    #[serde(default)]
+    pub since: Option<String>,
+    /// Only replays history up to this time instead of following
+    #[serde(default)]
+    pub until: Option<String>,
+    /// Prefix every line with docker's timestamp
+    #[serde(default)]
+    pub timestamps: bool,
+    #[serde(default = "LogsParams::default_stream")]
+    pub stdout: bool,
+    #[serde(default = "LogsParams::default_stream")]
+    pub stderr: bool,
+}
+
+impl Default for LogsParams {
+    fn default() -> Self {
+        Self {
+            tail: None,
+            since: None,
+            until: None,
+            timestamps: false,
+            stdout: true,
+            stderr: true,
+        }
+    }
+}
+
+impl LogsParams {
+    fn default_stream() -> bool {
+        true
+    }
+
+    pub fn since_time(&self) -> Result<Option<chrono::DateTime<chrono::Utc>>, String> {
+        parse_time_input(self.since.as_deref())
+    }
+
+    pub fn until_time(&self) -> Result<Option<chrono::DateTime<chrono::Utc>>, String> {
+        parse_time_input(self.until.as_deref())
+    }
+}
+
+fn parse_time_input(value: Option<&str>) -> Result<Option<chrono::DateTime<chrono::Utc>>, String> {
+    let Some(value) = value.map(str::trim).filter(|value| !value.is_empty()) else {
+        return Ok(None);
+    };
+    ["%Y-%m-%dT%H:%M", "%Y-%m-%dT%H:%M:%S"]
+        .iter()
+        .find_map(|format| chrono::NaiveDateTime::parse_from_str(value, format).ok())
+        .map(|time| Some(time.and_utc()))
+        .or_else(|| {
+            chrono::DateTime::parse_from_rfc3339(value)
+                .ok()
+                .map(|time| Some(time.to_utc()))
+        })
+        .ok_or(format!("Invalid time {value}"))
+}
+
+/// Result of a log search, oldest line first
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug, Default)]
+pub struct LogSearch {
+    pub matches: Vec<LogMatch>,
+    /// More lines matched than were returned, only the newest are kept
+    pub truncated: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct HookRun {
+    pub kind: HookKind,
+    pub command: String,
+    pub started_at: chrono::DateTime<chrono::Utc>,
+    pub finished_at: chrono::DateTime<chrono::Utc>,
+    /// `None` when the hook timed out or could not be started
+    pub exit_code: Option<i64>,
+    /// Tail of the combined stdout and stderr, or why the hook could not run
+    pub logs: String,
+}
+
+impl HookRun {
+    pub fn succeeded(&self) -> bool {
+        self.exit_code == Some(0)
+    }
+
+    pub fn outcome(&self) -> String {
+        match self.exit_code {
+            Some(0) => "succeeded".to_string(),
+            Some(code) => format!("exited with code {code}"),
+            None => "did not finish".to_string(),
+        }
+    }
+}
+
 #[derive(Clone, Debug)]
 #[cfg(feature = "ssr")]
 pub enum ContainerStatus {
@@ -182,6 +1212,14 @@ impl ContainerStatus {
     pub fn is_running(&self) -> bool {
         matches!(self, Self::Running(..))
     }
+
+    /// Returns `true` if the container status is [`Failed`].
+    ///
+    /// [`Failed`]: ContainerStatus::Failed
+    #[must_use]
+    pub fn is_failed(&self) -> bool {
+        matches!(self, Self::Failed)
+    }
 }
 
 #[derive(Serialize, Clone, Debug)]
@@ -247,9 +1285,22 @@ impl<'de> Deserialize<'de> for ExposedPort {
     }
 }
 
+#[cfg(feature = "ssr")]
+impl Container {
+    /// Whether the container manager should bring a failed container back up
+    pub fn should_retry(&self) -> bool {
+        self.runtime.can_retry() && (!self.runtime.oom_killed || self.limits.restart_on_oom)
+    }
+}
+
 impl PartialEq for Container {
     fn eq(&self, other: &Self) -> bool {
-        self.env_vars == other.env_vars && self.volumes == other.volumes
+        self.env_vars == other.env_vars
+            && self.volumes == other.volumes
+            && self.restart_policy == other.restart_policy
+            && self.healthcheck == other.healthcheck
+            && self.limits == other.limits
+            && self.depends_on == other.depends_on
     }
 }
 
@@ -263,6 +1314,14 @@ impl<'de> Deserialize<'de> for Container {
             pub env_vars: smallvec::SmallVec<[EnvironmentVar; 4]>,
             #[serde(default)]
             pub volumes: smallvec::SmallVec<[Volume; 2]>,
+            #[serde(default)]
+            pub restart_policy: RestartPolicy,
+            #[serde(default)]
+            pub healthcheck: Option<HealthCheck>,
+            #[serde(default)]
+            pub limits: ResourceLimits,
+            #[serde(default)]
+            pub depends_on: Vec<Dependency>,
         }
 
         let d = TmpContainer::deserialize(deserializer)?;
@@ -274,17 +1333,27 @@ impl<'de> Deserialize<'de> for Container {
                 // tokens: d.tokens,
                 env_vars: d.env_vars,
                 volumes: d.volumes,
+                restart_policy: d.restart_policy,
+                healthcheck: d.healthcheck,
+                limits: d.limits,
+                depends_on: d.depends_on,
             })
         }
 
         #[cfg(feature = "ssr")]
         {
+            let runtime = ContainerRuntime::for_config(d.healthcheck.as_ref(), None);
             Ok(Container {
                 // exposed_ports: d.exposed_ports,
                 status: ContainerStatus::None,
+                runtime,
                 // tokens: d.tokens,
                 env_vars: d.env_vars,
                 volumes: d.volumes,
+                restart_policy: d.restart_policy,
+                healthcheck: d.healthcheck,
+                limits: d.limits,
+                depends_on: d.depends_on,
             })
         }
     }
@@ -297,6 +1366,7 @@ impl Project {
             id: fields.id,
             project_type: fields.project_type,
             name: fields.name,
+            status_page: fields.status_page,
         }
     }
 }
@@ -306,6 +1376,8 @@ pub struct ProjectFields {
     pub id: Uuid,
     pub name: String,
     pub project_type: ProjectType,
+    #[serde(default)]
+    pub status_page: Option<StatusPage>,
 }
 
 impl From<Project> for ProjectFields {
@@ -314,6 +1386,7 @@ impl From<Project> for ProjectFields {
             id: val.id,
             project_type: val.project_type,
             name: val.name,
+            status_page: val.status_page,
         }
     }
 }
@@ -324,6 +1397,7 @@ impl From<ProjectFields> for Project {
             id: value.id,
             name: value.name,
             project_type: value.project_type,
+            status_page: value.status_page,
         }
     }
 }
@@ -488,6 +1562,7 @@ pub async fn add_port_forward_project(
         id,
         name: name.to_string(),
         project_type: ProjectType::PortForward(PortForward::new(port)),
+        status_page: None,
     });
     context.update_project(id, project.clone()).await?;
     Ok(project)
@@ -501,6 +1576,20 @@ pub fn get_home_path() -> std::path::PathBuf {
     PathBuf::from(home)
 }
 
+/// Home of the tests that store files. `SELF_CLOUD_HOME` is process wide, so the tests
+/// share it and keep apart by the project ids they use.
+#[cfg(all(test, feature = "ssr"))]
+pub fn test_home() -> std::path::PathBuf {
+    static HOME: std::sync::OnceLock<std::path::PathBuf> = std::sync::OnceLock::new();
+    HOME.get_or_init(|| {
+        let home = std::env::temp_dir().join(format!("selfcloud-test-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&home).unwrap();
+        std::env::set_var("SELF_CLOUD_HOME", &home);
+        home
+    })
+    .clone()
+}
+
 #[cfg(feature = "ssr")]
 pub fn get_docker() -> docker_api::Docker {
     let sock = std::env::var("DOCKER_SOCK").expect("DOCKER_SOCK var not set");
@@ -534,6 +1623,71 @@ impl AsRef<[u8]> for TtyChunk {
     }
 }
 
+/// Progress of a server side image build, streamed over the build websocket
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum BuildEvent {
+    Started { id: Uuid },
+    Log(String),
+    Finished { image: String },
+    Failed(String),
+}
+
+impl BuildEvent {
+    pub fn is_done(&self) -> bool {
+        matches!(self, Self::Finished { .. } | Self::Failed(_))
+    }
+}
+
+/// Change of the state held by the project context, streamed over the project events websocket
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum ProjectEvent {
+    ProjectCreated {
+        project_id: Uuid,
+    },
+    /// Anything of the project changed, refetch it for the details
+    ProjectUpdated {
+        project_id: Uuid,
+    },
+    ProjectRemoved {
+        project_id: Uuid,
+    },
+    ContainerStatusChanged {
+        project_id: Uuid,
+        container: ContainerSlot,
+        running: bool,
+        health: HealthStatus,
+    },
+    DomainUpdated {
+        project_id: Option<Uuid>,
+        domain: String,
+        status: DomainStatusFields,
+    },
+    /// The subscriber fell behind and missed events, everything shown should be refetched
+    Lagged,
+}
+
+impl ProjectEvent {
+    /// The project the event is about, `None` for [`ProjectEvent::Lagged`]
+    pub fn project_id(&self) -> Option<Uuid> {
+        match self {
+            Self::ProjectCreated { project_id }
+            | Self::ProjectUpdated { project_id }
+            | Self::ProjectRemoved { project_id }
+            | Self::ContainerStatusChanged { project_id, .. } => Some(*project_id),
+            Self::DomainUpdated { project_id, .. } => *project_id,
+            Self::Lagged => None,
+        }
+    }
+
+    /// Whether state of `project_id` may be stale after this event
+    pub fn concerns(&self, project_id: Uuid) -> bool {
+        match self {
+            Self::Lagged => true,
+            event => event.project_id() == Some(project_id),
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize)]
 pub struct AttachParams {
     pub command: String,
@@ -550,6 +1704,444 @@ pub struct UpdateStatus {
     pub update_available: bool,
 }
 
+/// One downsampled sample of a container or the host. Rates are per second, averaged over
+/// the sample's interval
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct MetricPoint {
+    pub ts: chrono::DateTime<chrono::Utc>,
+    pub cpu_percent: f64,
+    pub memory_bytes: f64,
+    pub net_rx_bytes: f64,
+    pub net_tx_bytes: f64,
+    /// Only collected for containers
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub block_read_bytes: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub block_write_bytes: Option<f64>,
+}
+
+/// Time ranges stored metrics can be shown for, each backed by its own resolution
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash, Debug, Default)]
+pub enum MetricsRange {
+    #[default]
+    Hour,
+    Day,
+    Week,
+}
+
+impl MetricsRange {
+    pub const ALL: [MetricsRange; 3] = [MetricsRange::Hour, MetricsRange::Day, MetricsRange::Week];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            MetricsRange::Hour => "1h",
+            MetricsRange::Day => "24h",
+            MetricsRange::Week => "7d",
+        }
+    }
+
+    pub fn duration(&self) -> chrono::Duration {
+        match self {
+            MetricsRange::Hour => chrono::Duration::hours(1),
+            MetricsRange::Day => chrono::Duration::hours(24),
+            MetricsRange::Week => chrono::Duration::days(7),
+        }
+    }
+
+    /// Seconds between the stored points of the range
+    pub fn resolution_secs(&self) -> i64 {
+        match self {
+            MetricsRange::Hour => 10,
+            MetricsRange::Day => 60,
+            MetricsRange::Week => 600,
+        }
+    }
+}
+
+/// Outcome of one probe of a project domain by the uptime monitor
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct UptimeCheck {
+    pub ts: chrono::DateTime<chrono::Utc>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<u16>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub latency_ms: Option<u64>,
+    /// Whether the certificate verified, `None` for domains without a certificate yet
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tls_valid: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tls_expires_in_days: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl UptimeCheck {
+    /// Answers below 500 count as up, redirects and client errors included
+    pub fn is_up(&self) -> bool {
+        self.error.is_none()
+            && self.tls_valid != Some(false)
+            && self.status.is_some_and(|status| status < 500)
+    }
+
+    /// Why the check is down
+    pub fn reason(&self) -> String {
+        match (&self.error, self.status) {
+            (Some(error), _) => error.clone(),
+            (None, _) if self.tls_valid == Some(false) => "Invalid certificate".to_string(),
+            (None, Some(status)) => format!("HTTP {status}"),
+            (None, None) => "No answer".to_string(),
+        }
+    }
+}
+
+/// A run of failed checks of a domain, still open while `ended` is `None`
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct UptimeIncident {
+    pub started: chrono::DateTime<chrono::Utc>,
+    pub ended: Option<chrono::DateTime<chrono::Utc>>,
+    pub reason: String,
+}
+
+/// Uptime of one domain over the checks kept by the uptime monitor
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct DomainUptime {
+    pub domain: String,
+    pub last: Option<UptimeCheck>,
+    /// Percentages of checks that were up, `None` without checks in the window
+    pub uptime_day: Option<f64>,
+    pub uptime_week: Option<f64>,
+    pub uptime_month: Option<f64>,
+    /// Average latency of the successful checks of the last day
+    pub latency_ms_day: Option<u64>,
+    /// Newest first
+    pub incidents: Vec<UptimeIncident>,
+}
+
+/// Public page with the uptime of the project's domains, served by the gateway on `domain`
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct StatusPage {
+    #[serde(with = "unicase_serde::unicase")]
+    pub domain: UniCase<String>,
+    pub title: String,
+}
+
+/// Project lifecycle events webhooks can subscribe to
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum WebhookEventKind {
+    #[serde(rename = "project.created")]
+    ProjectCreated,
+    #[serde(rename = "project.updated")]
+    ProjectUpdated,
+    #[serde(rename = "project.deleted")]
+    ProjectDeleted,
+    #[serde(rename = "image.pushed")]
+    ImagePushed,
+    #[serde(rename = "deploy.started")]
+    DeployStarted,
+    #[serde(rename = "deploy.succeeded")]
+    DeploySucceeded,
+    #[serde(rename = "deploy.failed")]
+    DeployFailed,
+    #[serde(rename = "container.started")]
+    ContainerStarted,
+    #[serde(rename = "container.stopped")]
+    ContainerStopped,
+    #[serde(rename = "container.crashed")]
+    ContainerCrashed,
+    #[serde(rename = "certificate.issued")]
+    CertificateIssued,
+    #[serde(rename = "certificate.renewed")]
+    CertificateRenewed,
+    #[serde(rename = "certificate.failed")]
+    CertificateFailed,
+    /// Only sent by the test button
+    #[serde(rename = "ping")]
+    Ping,
+}
+
+impl WebhookEventKind {
+    pub const ALL: [WebhookEventKind; 13] = [
+        WebhookEventKind::ProjectCreated,
+        WebhookEventKind::ProjectUpdated,
+        WebhookEventKind::ProjectDeleted,
+        WebhookEventKind::ImagePushed,
+        WebhookEventKind::DeployStarted,
+        WebhookEventKind::DeploySucceeded,
+        WebhookEventKind::DeployFailed,
+        WebhookEventKind::ContainerStarted,
+        WebhookEventKind::ContainerStopped,
+        WebhookEventKind::ContainerCrashed,
+        WebhookEventKind::CertificateIssued,
+        WebhookEventKind::CertificateRenewed,
+        WebhookEventKind::CertificateFailed,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WebhookEventKind::ProjectCreated => "project.created",
+            WebhookEventKind::ProjectUpdated => "project.updated",
+            WebhookEventKind::ProjectDeleted => "project.deleted",
+            WebhookEventKind::ImagePushed => "image.pushed",
+            WebhookEventKind::DeployStarted => "deploy.started",
+            WebhookEventKind::DeploySucceeded => "deploy.succeeded",
+            WebhookEventKind::DeployFailed => "deploy.failed",
+            WebhookEventKind::ContainerStarted => "container.started",
+            WebhookEventKind::ContainerStopped => "container.stopped",
+            WebhookEventKind::ContainerCrashed => "container.crashed",
+            WebhookEventKind::CertificateIssued => "certificate.issued",
+            WebhookEventKind::CertificateRenewed => "certificate.renewed",
+            WebhookEventKind::CertificateFailed => "certificate.failed",
+            WebhookEventKind::Ping => "ping",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|kind| kind.as_str() == name)
+    }
+}
+
+impl std::fmt::Display for WebhookEventKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Endpoint receiving signed lifecycle events of one project or of all of them
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct WebhookSubscription {
+    pub id: Uuid,
+    pub url: String,
+    /// Key of the `X-SelfCloud-Signature` HMAC
+    pub secret: String,
+    /// Subscribed events, every event when empty
+    #[serde(default)]
+    pub events: Vec<WebhookEventKind>,
+    #[serde(default)]
+    pub project_id: Option<Uuid>,
+    pub enabled: bool,
+}
+
+impl WebhookSubscription {
+    pub fn wants(&self, kind: WebhookEventKind, project_id: Option<Uuid>) -> bool {
+        self.enabled
+            && (self.events.is_empty() || self.events.contains(&kind))
+            && self
+                .project_id
+                .is_none_or(|subscribed| Some(subscribed) == project_id)
+    }
+}
+
+/// One attempt at delivering an event to a webhook
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct WebhookDelivery {
+    /// Shared by the attempts of the same event
+    pub event_id: Uuid,
+    pub subscription_id: Uuid,
+    pub event: WebhookEventKind,
+    pub ts: chrono::DateTime<chrono::Utc>,
+    pub attempt: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<u16>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// When the next attempt is due, `None` once delivered or given up on
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next_attempt_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+impl WebhookDelivery {
+    pub fn is_success(&self) -> bool {
+        self.error.is_none()
+            && self
+                .status
+                .is_some_and(|status| (200..300).contains(&status))
+    }
+}
+
+/// Who made an audited change
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub enum AuditActor {
+    User {
+        id: Uuid,
+        name: String,
+        email: String,
+    },
+    /// A project token, e.g. CI pushing an image
+    Token {
+        project_id: Uuid,
+        description: String,
+    },
+}
+
+impl std::fmt::Display for AuditActor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AuditActor::User { name, email, .. } => write!(f, "{name} <{email}>"),
+            AuditActor::Token { description, .. } => write!(f, "token {description}"),
+        }
+    }
+}
+
+/// One line of the append-only audit log
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct AuditEntry {
+    pub id: Uuid,
+    pub ts: chrono::DateTime<chrono::Utc>,
+    pub actor: AuditActor,
+    /// Dotted name of what was done, e.g. `project.image.update`
+    pub action: String,
+    #[serde(default)]
+    pub project_id: Option<Uuid>,
+    /// Name of the project at the time, it may have been renamed or removed since
+    #[serde(default)]
+    pub project_name: Option<String>,
+    /// JSON patch from the stored project before the change to after it, secrets redacted
+    #[serde(default)]
+    pub changes: json_patch::Patch,
+    #[serde(default)]
+    pub detail: Option<String>,
+}
+
+/// Filters of the audit log, also the query string of the export endpoint.
+/// Times are `datetime-local` input values in UTC.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug, Default)]
+pub struct AuditQuery {
+    /// Case insensitive substring of the actor, empty matches everything
+    #[serde(default)]
+    pub actor: String,
+    /// Prefix of the action, empty matches everything
+    #[serde(default)]
+    pub action: String,
+    #[serde(default)]
+    pub project_id: Option<Uuid>,
+    #[serde(default)]
+    pub since: Option<String>,
+    #[serde(default)]
+    pub until: Option<String>,
+}
+
+impl AuditQuery {
+    pub fn since_time(&self) -> Result<Option<chrono::DateTime<chrono::Utc>>, String> {
+        parse_time_input(self.since.as_deref())
+    }
+
+    pub fn until_time(&self) -> Result<Option<chrono::DateTime<chrono::Utc>>, String> {
+        parse_time_input(self.until.as_deref())
+    }
+}
+
+/// Result of an audit log search, newest entry first
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug, Default)]
+pub struct AuditSearch {
+    pub entries: Vec<AuditEntry>,
+    /// More entries matched than were returned, only the newest are kept
+    pub truncated: bool,
+}
+
+/// Alert rules and where their notifications go, kept in `alerts.json`
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct AlertSettings {
+    pub rules: Vec<AlertRule>,
+    pub channels: Vec<NotificationChannel>,
+    #[serde(default)]
+    pub smtp: Option<SmtpSettings>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct AlertRule {
+    pub id: Uuid,
+    pub condition: AlertCondition,
+    /// Project the rule watches, every project when unset. Ignored by host rules
+    pub project_id: Option<Uuid>,
+    pub enabled: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum AlertCondition {
+    /// A container exited or failed to start and has not been stable since
+    ContainerCrashed,
+    /// Every sample of a container over the last `minutes` is above `percent`
+    CpuAbove { percent: f64, minutes: u32 },
+    /// A host disk is fuller than `percent`
+    HostDiskAbove { percent: f64 },
+    /// Ordering or renewing a certificate of a domain failed
+    CertificateFailed,
+}
+
+impl std::fmt::Display for AlertCondition {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AlertCondition::ContainerCrashed => write!(f, "Container crashed"),
+            AlertCondition::CpuAbove { percent, minutes } => {
+                write!(f, "CPU above {percent}% for {minutes} minutes")
+            }
+            AlertCondition::HostDiskAbove { percent } => write!(f, "Host disk above {percent}%"),
+            AlertCondition::CertificateFailed => write!(f, "Certificate renewal failed"),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct NotificationChannel {
+    pub id: Uuid,
+    pub kind: ChannelKind,
+    pub enabled: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ChannelKind {
+    Webhook {
+        url: String,
+        format: WebhookFormat,
+    },
+    /// Sent through the SMTP server of the alert settings
+    Email {
+        to: String,
+    },
+}
+
+impl std::fmt::Display for ChannelKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ChannelKind::Webhook { url, format } => write!(f, "{format:?} webhook {url}"),
+            ChannelKind::Email { to } => write!(f, "Email to {to}"),
+        }
+    }
+}
+
+/// Body of webhook notifications
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WebhookFormat {
+    /// `{"status", "summary", "details", "ts"}`
+    Generic,
+    /// Slack incoming webhook `{"text"}`
+    Slack,
+    /// Discord webhook `{"content"}`
+    Discord,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct SmtpSettings {
+    pub host: String,
+    pub port: u16,
+    pub username: String,
+    pub password: String,
+    pub from: String,
+    /// Upgrade the connection with STARTTLS, plain SMTP otherwise
+    pub starttls: bool,
+}
+
+/// An alert whose condition currently holds
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct FiringAlert {
+    pub rule_id: Uuid,
+    pub summary: String,
+    pub details: String,
+    pub since: chrono::DateTime<chrono::Utc>,
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct SystemStats {
     pub cpu_usage: f32,
@@ -586,3 +2178,88 @@ pub struct FileInfo {
     pub size: u64,
     pub modified: Option<u64>,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn support(depends_on: &[&str]) -> SupportContainer {
+        let depends_on = depends_on
+            .iter()
+            .map(|name| serde_json::json!({ "name": name }))
+            .collect::<Vec<_>>();
+        serde_json::from_value(serde_json::json!({
+            "image": "busybox",
+            "container": { "env_vars": [], "depends_on": depends_on },
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn split_command_on_whitespace() {
+        assert_eq!(
+            split_command("  npm run   start "),
+            vec!["npm", "run", "start"]
+        );
+        assert!(split_command("   ").is_empty());
+    }
+
+    #[test]
+    fn split_command_with_quotes() {
+        assert_eq!(
+            split_command(r#"sh -c 'echo "hi there"' "a b"c"#),
+            vec!["sh", "-c", r#"echo "hi there""#, "a bc"]
+        );
+        assert_eq!(split_command(r#"echo '' """#), vec!["echo", "", ""]);
+    }
+
+    #[test]
+    fn split_command_with_escapes() {
+        assert_eq!(
+            split_command(r#"echo a\ b "c\"d" 'e\f'"#),
+            vec!["echo", "a b", r#"c"d"#, r"e\f"]
+        );
+    }
+
+    #[test]
+    fn start_order_after_dependencies() {
+        let support_containers = HashMap::from([
+            ("app".to_string(), support(&["cache", "db"])),
+            ("cache".to_string(), support(&[])),
+            ("db".to_string(), support(&[])),
+            ("worker".to_string(), support(&["app"])),
+        ]);
+        assert_eq!(
+            start_order(&support_containers).unwrap(),
+            vec!["cache", "db", "app", "worker"]
+        );
+    }
+
+    #[test]
+    fn start_order_detects_cycles() {
+        let support_containers = HashMap::from([
+            ("a".to_string(), support(&["b"])),
+            ("b".to_string(), support(&["a"])),
+            ("c".to_string(), support(&[])),
+        ]);
+        assert_eq!(
+            start_order(&support_containers).unwrap_err(),
+            "Dependency cycle between a, b"
+        );
+
+        let support_containers = HashMap::from([("a".to_string(), support(&["a"]))]);
+        assert_eq!(
+            start_order(&support_containers).unwrap_err(),
+            "Dependency cycle between a"
+        );
+    }
+
+    #[test]
+    fn start_order_rejects_unknown_dependencies() {
+        let support_containers = HashMap::from([("a".to_string(), support(&["missing"]))]);
+        assert_eq!(
+            start_order(&support_containers).unwrap_err(),
+            "a depends on unknown container missing"
+        );
+    }
+}
@@ -0,0 +1,299 @@
+use leptos::prelude::*;
+
+use crate::common::{
+    Dependency, DependencyCondition, HealthCheck, ResourceLimits, RestartPolicy, RestartPolicyKind,
+};
+
+/// Form fields for a [`RestartPolicy`], `name` is the field prefix used in the form
+/// e.g. `restart_policy` or `support_containers[db][restart_policy]`
+#[component]
+pub fn RestartPolicyFields(name: String, policy: RestartPolicy) -> impl IntoView {
+    view! {
+        <div class="text-md">"Restart Policy"</div>
+        <div class="flex gap-4 flex-wrap p-2">
+            <div class="flex flex-col">
+                <label class="text-sm dark:text-white/50">"Policy"</label>
+                <select
+                    name=format!("{name}[kind]")
+                    class="p-2 bg-white border rounded-md dark:bg-white/10 dark:border-white/5"
+                >
+                    {RestartPolicyKind::ALL
+                        .into_iter()
+                        .map(|kind| {
+                            view! {
+                                <option value=kind.docker_name() selected=kind == policy.kind>
+                                    {kind.docker_name()}
+                                </option>
+                            }
+                        })
+                        .collect::<Vec<_>>()}
+                </select>
+            </div>
+            <div class="flex flex-col">
+                <label class="text-sm dark:text-white/50">"Max Retries (on-failure)"</label>
+                <input
+                    type="number"
+                    min="0"
+                    name=format!("{name}[max_retries]")
+                    prop:value=policy.max_retries
+                    class="border p-2 rounded-md dark:bg-white/10 dark:border-white/5"
+                />
+            </div>
+        </div>
+    }
+}
+
+/// Form fields for an optional [`HealthCheck`], leaving the command empty disables it
+#[component]
+pub fn HealthCheckFields(name: String, healthcheck: Option<HealthCheck>) -> impl IntoView {
+    let check = healthcheck.unwrap_or_default();
+    view! {
+        <div class="text-md">"Health Check"</div>
+        <div class="flex flex-col gap-2 p-2">
+            <div class="flex flex-col">
+                <label class="text-sm dark:text-white/50">"Command"</label>
+                <input
+                    type="text"
+                    name=format!("{name}[command]")
+                    prop:value=check.command
+                    placeholder="e.g. curl -f http://localhost:8080/health"
+                    class="border p-2 rounded-md dark:bg-white/10 dark:border-white/5"
+                />
+            </div>
+            <div class="flex gap-4 flex-wrap">
+                <div class="flex flex-col">
+                    <label class="text-sm dark:text-white/50">"Interval (s)"</label>
+                    <input
+                        type="number"
+                        min="1"
+                        name=format!("{name}[interval_secs]")
+                        prop:value=check.interval_secs
+                        class="border p-2 rounded-md dark:bg-white/10 dark:border-white/5"
+                    />
+                </div>
+                <div class="flex flex-col">
+                    <label class="text-sm dark:text-white/50">"Timeout (s)"</label>
+                    <input
+                        type="number"
+                        min="1"
+                        name=format!("{name}[timeout_secs]")
+                        prop:value=check.timeout_secs
+                        class="border p-2 rounded-md dark:bg-white/10 dark:border-white/5"
+                    />
+                </div>
+                <div class="flex flex-col">
+                    <label class="text-sm dark:text-white/50">"Retries"</label>
+                    <input
+                        type="number"
+                        min="1"
+                        name=format!("{name}[retries]")
+                        prop:value=check.retries
+                        class="border p-2 rounded-md dark:bg-white/10 dark:border-white/5"
+                    />
+                </div>
+                <div class="flex flex-col">
+                    <label class="text-sm dark:text-white/50">"Start Period (s)"</label>
+                    <input
+                        type="number"
+                        min="0"
+                        name=format!("{name}[start_period_secs]")
+                        prop:value=check.start_period_secs
+                        class="border p-2 rounded-md dark:bg-white/10 dark:border-white/5"
+                    />
+                </div>
+            </div>
+        </div>
+    }
+}
+
+/// Form fields for [`ResourceLimits`], empty fields leave the resource unlimited
+#[component]
+pub fn ResourceLimitsFields(name: String, limits: ResourceLimits) -> impl IntoView {
+    view! {
+        <div class="text-md">"Resource Limits"</div>
+        <div class="flex flex-col gap-2 p-2">
+            <div class="flex gap-4 flex-wrap">
+                <div class="flex flex-col">
+                    <label class="text-sm dark:text-white/50">"Memory (MiB)"</label>
+                    <input
+                        type="number"
+                        min="6"
+                        name=format!("{name}[memory_mb]")
+                        prop:value=limits.memory_mb.map(|v| v.to_string()).unwrap_or_default()
+                        placeholder="Unlimited"
+                        class="border p-2 rounded-md dark:bg-white/10 dark:border-white/5"
+                    />
+                </div>
+                <div class="flex flex-col">
+                    <label class="text-sm dark:text-white/50">"CPUs"</label>
+                    <input
+                        type="number"
+                        min="0.01"
+                        step="0.01"
+                        name=format!("{name}[cpus]")
+                        prop:value=limits.cpus.map(|v| v.to_string()).unwrap_or_default()
+                        placeholder="Unlimited"
+                        class="border p-2 rounded-md dark:bg-white/10 dark:border-white/5"
+                    />
+                </div>
+                <div class="flex flex-col">
+                    <label class="text-sm dark:text-white/50">"CPU Shares"</label>
+                    <input
+                        type="number"
+                        min="2"
+                        name=format!("{name}[cpu_shares]")
+                        prop:value=limits.cpu_shares.map(|v| v.to_string()).unwrap_or_default()
+                        placeholder="1024"
+                        class="border p-2 rounded-md dark:bg-white/10 dark:border-white/5"
+                    />
+                </div>
+                <div class="flex flex-col">
+                    <label class="text-sm dark:text-white/50">"Max Processes"</label>
+                    <input
+                        type="number"
+                        min="1"
+                        name=format!("{name}[pids_limit]")
+                        prop:value=limits.pids_limit.map(|v| v.to_string()).unwrap_or_default()
+                        placeholder="Unlimited"
+                        class="border p-2 rounded-md dark:bg-white/10 dark:border-white/5"
+                    />
+                </div>
+            </div>
+            <label class="flex gap-2 items-center text-sm">
+                <input
+                    type="checkbox"
+                    name=format!("{name}[restart_on_oom]")
+                    value="true"
+                    prop:checked=limits.restart_on_oom
+                />
+                "Restart when killed for running out of memory"
+            </label>
+        </div>
+    }
+}
+
+/// Editable list of [`Dependency`] entries, `options` are the support containers
+/// that can be depended upon.
+#[component]
+pub fn DependsOnFields(
+    name: String,
+    depends_on: Vec<Dependency>,
+    options: Vec<String>,
+) -> impl IntoView {
+    let name = StoredValue::new(name);
+    let options = StoredValue::new(options);
+    let (dependencies, set_dependencies) = signal({
+        let mut list = vec![];
+        for dep in depends_on.into_iter() {
+            list.push((list.len(), dep));
+        }
+        list
+    });
+
+    view! {
+        <div class="text-md">"Depends On"</div>
+        <div>
+            <For
+                each=move || dependencies.get().into_iter()
+                key=|d| d.0
+                children=move |(index, dep)| {
+                    view! {
+                        <div class="flex gap-4 flex-wrap p-2 border dark:border-white/20 m-2 rounded">
+                            <div class="flex flex-col">
+                                <label class="text-sm dark:text-white/50">"Container"</label>
+                                <select
+                                    name=format!("{}[{index}][name]", name.get_value())
+                                    class="p-2 bg-white border rounded-md dark:bg-white/10 dark:border-white/5"
+                                >
+                                    {options
+                                        .get_value()
+                                        .into_iter()
+                                        .map(|option| {
+                                            let selected = option == dep.name;
+                                            view! {
+                                                <option value=option.clone() selected=selected>
+                                                    {option.clone()}
+                                                </option>
+                                            }
+                                        })
+                                        .collect::<Vec<_>>()}
+                                </select>
+                            </div>
+                            <div class="flex flex-col">
+                                <label class="text-sm dark:text-white/50">"Wait Until"</label>
+                                <select
+                                    name=format!("{}[{index}][condition]", name.get_value())
+                                    class="p-2 bg-white border rounded-md dark:bg-white/10 dark:border-white/5"
+                                >
+                                    {DependencyCondition::ALL
+                                        .into_iter()
+                                        .map(|condition| {
+                                            view! {
+                                                <option
+                                                    value=condition.as_str()
+                                                    selected=condition == dep.condition
+                                                >
+                                                    {condition.as_str()}
+                                                </option>
+                                            }
+                                        })
+                                        .collect::<Vec<_>>()}
+                                </select>
+                            </div>
+                            <div class="flex flex-col">
+                                <label class="text-sm dark:text-white/50">"Port (tcp_port)"</label>
+                                <input
+                                    type="number"
+                                    name=format!("{}[{index}][port]", name.get_value())
+                                    prop:value=dep.port.map(|p| p.to_string()).unwrap_or_default()
+                                    class="border p-2 rounded-md dark:bg-white/10 dark:border-white/5"
+                                />
+                            </div>
+                            <div class="flex flex-col">
+                                <label class="text-sm dark:text-white/50">"Timeout (s)"</label>
+                                <input
+                                    type="number"
+                                    min="1"
+                                    name=format!("{}[{index}][timeout_secs]", name.get_value())
+                                    prop:value=dep.timeout_secs
+                                    class="border p-2 rounded-md dark:bg-white/10 dark:border-white/5"
+                                />
+                            </div>
+                            <button
+                                type="button"
+                                class="p-2 rounded bg-red-700 px-6 text-white mt-5"
+                                on:click=move |_| {
+                                    let mut deps = dependencies.get_untracked();
+                                    deps.retain(|(i, _)| *i != index);
+                                    set_dependencies.set(deps);
+                                }
+                            >
+                                "Remove"
+                            </button>
+                        </div>
+                    }
+                }
+            />
+            <button
+                type="button"
+                class="p-2 rounded border bg-white/90 px-6 text-black disabled:opacity-50"
+                disabled=move || options.with_value(|o| o.is_empty())
+                on:click=move |_| {
+                    let mut deps = dependencies.get_untracked();
+                    deps.push((
+                        deps.last().map(|d| d.0).unwrap_or_default() + 1,
+                        Dependency {
+                            name: options.with_value(|o| o.first().cloned().unwrap_or_default()),
+                            condition: DependencyCondition::Started,
+                            port: None,
+                            timeout_secs: 120,
+                        },
+                    ));
+                    set_dependencies.set(deps);
+                }
+            >
+                "Add Dependency"
+            </button>
+        </div>
+    }
+}
@@ -0,0 +1,152 @@
+use leptos::prelude::*;
+use uuid::Uuid;
+
+use crate::api::get_metrics;
+use crate::common::{MetricPoint, MetricsRange};
+use crate::components::apex_chart::{ApexChart, ChartSeries};
+
+/// Stored metrics of a project container, or of the host without a project
+#[component]
+pub fn MetricsHistory(project_id: Option<Uuid>, container: Option<String>) -> impl IntoView {
+    let (range, set_range) = signal(MetricsRange::Hour);
+    let (refresh, set_refresh) = signal(());
+    let metrics = Resource::new(
+        move || (range.get(), refresh.get()),
+        move |(range, _)| {
+            let container = container.clone();
+            async move { get_metrics(project_id, container, range).await }
+        },
+    );
+    let points = Memo::new(move |_| {
+        metrics
+            .get()
+            .and_then(|metrics| metrics.ok())
+            .unwrap_or_default()
+    });
+
+    let series = move |name: &'static str, value: fn(&MetricPoint) -> Option<f64>| ChartSeries {
+        name: name.to_string(),
+        data: points.with(|points| {
+            points
+                .iter()
+                .filter_map(|point| Some((point.ts.timestamp_millis(), value(point)?)))
+                .collect()
+        }),
+    };
+    let cpu_series =
+        Signal::derive(move || vec![series("CPU Usage %", |point| Some(point.cpu_percent))]);
+    let memory_series = Signal::derive(move || {
+        vec![series("Memory (MB)", |point| {
+            Some(point.memory_bytes / 1024.0 / 1024.0)
+        })]
+    });
+    let network_series = Signal::derive(move || {
+        vec![
+            series("Rx (KB/s)", |point| Some(point.net_rx_bytes / 1024.0)),
+            series("Tx (KB/s)", |point| Some(point.net_tx_bytes / 1024.0)),
+        ]
+    });
+    let block_series = Signal::derive(move || {
+        vec![
+            series("Read (KB/s)", |point| {
+                point.block_read_bytes.map(|bytes| bytes / 1024.0)
+            }),
+            series("Write (KB/s)", |point| {
+                point.block_write_bytes.map(|bytes| bytes / 1024.0)
+            }),
+        ]
+    });
+    let has_block_io = Memo::new(move |_| {
+        points.with(|points| points.iter().any(|point| point.block_read_bytes.is_some()))
+    });
+
+    let options = serde_json::json!({
+        "chart": {
+            "type": "area",
+            "animations": { "enabled": false },
+            "toolbar": { "show": false },
+            "zoom": { "enabled": false }
+        },
+        "dataLabels": { "enabled": false },
+        "stroke": { "curve": "smooth", "width": 1 },
+        "xaxis": {
+            "type": "datetime",
+            "labels": { "datetimeUTC": false }
+        },
+        "yaxis": { "decimalsInFloat": 1 },
+        "tooltip": { "x": { "format": "dd MMM HH:mm" } },
+        "theme": { "mode": "dark" }
+    });
+    let card_class = "bg-white dark:bg-gray-900 p-4 rounded-xl shadow-sm border border-gray-200 dark:border-gray-800";
+
+    view! {
+        <div class="px-4 pt-4 flex gap-2 items-center">
+            <h3 class="text-lg font-bold dark:text-white grow">"History"</h3>
+            {MetricsRange::ALL
+                .into_iter()
+                .map(|option| {
+                    view! {
+                        <button
+                            type="button"
+                            class="px-3 py-1 rounded border text-sm"
+                            class=("bg-slate-800", move || range.get() == option)
+                            class=("text-white", move || range.get() == option)
+                            on:click=move |_| set_range.set(option)
+                        >
+                            {option.label()}
+                        </button>
+                    }
+                })
+                .collect_view()}
+            <button
+                type="button"
+                class="px-3 py-1 rounded border text-sm"
+                on:click=move |_| set_refresh.set(())
+            >
+                "Refresh"
+            </button>
+        </div>
+        <Show when=move || metrics.get().is_some_and(|metrics| metrics.is_err())>
+            <div class="px-4 text-sm text-red-500">
+                {move || {
+                    metrics
+                        .get()
+                        .and_then(|metrics| metrics.err())
+                        .map(|err| err.to_string())
+                }}
+            </div>
+        </Show>
+        <div class="p-4 grid grid-cols-1 md:grid-cols-2 gap-4">
+            <div class=card_class>
+                <h3 class="text-sm font-bold mb-2 dark:text-white">"CPU Usage"</h3>
+                <ApexChart series=cpu_series options=Signal::from(options.clone()) height="250"/>
+            </div>
+            <div class=card_class>
+                <h3 class="text-sm font-bold mb-2 dark:text-white">"Memory"</h3>
+                <ApexChart
+                    series=memory_series
+                    options=Signal::from(options.clone())
+                    height="250"
+                />
+            </div>
+            <div class=card_class>
+                <h3 class="text-sm font-bold mb-2 dark:text-white">"Network"</h3>
+                <ApexChart
+                    series=network_series
+                    options=Signal::from(options.clone())
+                    height="250"
+                />
+            </div>
+            <Show when=move || has_block_io.get()>
+                <div class=card_class>
+                    <h3 class="text-sm font-bold mb-2 dark:text-white">"Block IO"</h3>
+                    <ApexChart
+                        series=block_series
+                        options=Signal::from(options.clone())
+                        height="250"
+                    />
+                </div>
+            </Show>
+        </div>
+    }
+}
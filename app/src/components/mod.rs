@@ -1,6 +1,9 @@
 pub mod apex_chart;
+pub mod container_options;
 pub mod file_browser;
 pub mod input_field;
+pub mod metrics_history;
 pub mod nav_bar;
 pub mod terminal;
 pub mod toaster;
+pub mod webhooks;
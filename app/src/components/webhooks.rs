@@ -0,0 +1,270 @@
+use leptos::prelude::*;
+use uuid::Uuid;
+
+use crate::api::{
+    get_webhook_deliveries, get_webhooks, AddWebhook, DeleteWebhook, SetWebhookEnabled, TestWebhook,
+};
+use crate::common::{WebhookEventKind, WebhookSubscription};
+use crate::components::toaster::{ToastVariant, ToasterContext};
+
+/// Webhook subscriptions of a project, or the global ones without a project
+#[component]
+pub fn Webhooks(project_id: Option<Uuid>) -> impl IntoView {
+    let add_action = ServerAction::<AddWebhook>::new();
+    let enable_action = ServerAction::<SetWebhookEnabled>::new();
+    let delete_action = ServerAction::<DeleteWebhook>::new();
+    let test_action = ServerAction::<TestWebhook>::new();
+    let toast_context = expect_context::<ToasterContext>();
+
+    let webhooks = Resource::new(
+        move || {
+            (
+                add_action.version().get(),
+                enable_action.version().get(),
+                delete_action.version().get(),
+            )
+        },
+        move |_| get_webhooks(project_id),
+    );
+
+    let toast_outcome = |value: RwSignal<Option<Result<(), ServerFnError>>>,
+                         success: Option<&'static str>| {
+        let toast_context = toast_context.clone();
+        Effect::new(move |_| match value.get() {
+            Some(Ok(_)) => {
+                if let Some(success) = success {
+                    toast_context.toast(success, ToastVariant::Success);
+                }
+            }
+            Some(Err(err)) => toast_context.toast(err.to_string(), ToastVariant::Error),
+            None => {}
+        });
+    };
+    toast_outcome(add_action.value(), Some("Webhook Added"));
+    toast_outcome(enable_action.value(), None);
+    toast_outcome(delete_action.value(), Some("Webhook Deleted"));
+    toast_outcome(test_action.value(), Some("Test Event Delivered"));
+
+    view! {
+        <div class="text-xl">"Webhooks"</div>
+        <div class="text-sm dark:text-white/50">
+            {if project_id.is_some() {
+                "Events of this project are posted to these URLs"
+            } else {
+                "Events of every project are posted to these URLs"
+            }}
+            ", signed with "
+            <span class="font-mono">"X-SelfCloud-Signature: sha256=HMAC(secret, timestamp.body)"</span>
+            " where the timestamp is sent as "
+            <span class="font-mono">"X-SelfCloud-Timestamp"</span>
+            ". Failed deliveries are retried for about two and a half hours"
+        </div>
+        <div class="flex flex-col gap-1 p-2">
+            <Transition>
+                {move || {
+                    webhooks
+                        .get()
+                        .and_then(|webhooks| webhooks.ok())
+                        .map(|webhooks| {
+                            webhooks
+                                .into_iter()
+                                .map(|webhook| {
+                                    view! {
+                                        <WebhookRow
+                                            webhook
+                                            enable_action
+                                            delete_action
+                                            test_action
+                                        />
+                                    }
+                                })
+                                .collect_view()
+                        })
+                }}
+            </Transition>
+        </div>
+        <ActionForm action=add_action>
+            <input
+                type="hidden"
+                name="project_id"
+                value=project_id.map(|id| id.to_string()).unwrap_or_default()
+            />
+            <div class="flex flex-col gap-2 p-2">
+                <div class="flex gap-2 flex-wrap">
+                    <div class="flex flex-col grow">
+                        <label class="text-sm dark:text-white/50">"URL"</label>
+                        <input
+                            type="url"
+                            name="url"
+                            required
+                            placeholder="https://example.com/hooks/selfcloud"
+                            class="border p-2 rounded-md dark:bg-white/10 dark:border-white/5"
+                        />
+                    </div>
+                    <div class="flex flex-col">
+                        <label class="text-sm dark:text-white/50">"Secret"</label>
+                        <input
+                            type="text"
+                            name="secret"
+                            placeholder="Generated when empty"
+                            class="border p-2 rounded-md dark:bg-white/10 dark:border-white/5"
+                        />
+                    </div>
+                </div>
+                <div class="text-sm dark:text-white/50">"Events, every event when none is checked"</div>
+                <div class="flex gap-x-4 gap-y-1 flex-wrap text-sm">
+                    {WebhookEventKind::ALL
+                        .into_iter()
+                        .enumerate()
+                        .map(|(index, kind)| {
+                            view! {
+                                <label class="flex gap-1 items-center font-mono">
+                                    <input
+                                        type="checkbox"
+                                        name=format!("events[{index}]")
+                                        value=kind.as_str()
+                                    />
+                                    {kind.as_str()}
+                                </label>
+                            }
+                        })
+                        .collect_view()}
+                </div>
+                <input
+                    type="submit"
+                    value="Add Webhook"
+                    class="cursor-pointer self-start border p-2 px-10 rounded bg-slate-800 text-white dark:border-none dark:bg-white/90 dark:text-black"
+                />
+            </div>
+        </ActionForm>
+    }
+}
+
+#[component]
+fn WebhookRow(
+    webhook: WebhookSubscription,
+    enable_action: ServerAction<SetWebhookEnabled>,
+    delete_action: ServerAction<DeleteWebhook>,
+    test_action: ServerAction<TestWebhook>,
+) -> impl IntoView {
+    let webhook_id = webhook.id;
+    let enabled = webhook.enabled;
+    let (show_deliveries, set_show_deliveries) = signal(false);
+    let deliveries = Resource::new(
+        move || (show_deliveries.get(), test_action.version().get()),
+        move |(show, _)| async move {
+            if show {
+                get_webhook_deliveries(webhook_id).await
+            } else {
+                Ok(vec![])
+            }
+        },
+    );
+    let events = if webhook.events.is_empty() {
+        "All events".to_string()
+    } else {
+        webhook
+            .events
+            .iter()
+            .map(|event| event.as_str())
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+
+    view! {
+        <div class="flex flex-col gap-2 text-sm p-2 rounded border dark:border-white/10">
+            <div class="flex gap-4 items-center">
+                <div class="flex flex-col grow min-w-0">
+                    <div class="font-mono truncate">
+                        {webhook.url} {(!enabled).then(|| " (disabled)")}
+                    </div>
+                    <div class="dark:text-white/50">{events}</div>
+                    <div class="dark:text-white/50 font-mono break-all">
+                        "Secret " {webhook.secret}
+                    </div>
+                </div>
+                <button
+                    type="button"
+                    class="p-2 px-4 rounded border"
+                    on:click=move |_| set_show_deliveries.update(|show| *show = !*show)
+                >
+                    "Deliveries"
+                </button>
+                <button
+                    type="button"
+                    class="p-2 px-4 rounded border disabled:opacity-50"
+                    disabled=move || test_action.pending().get()
+                    on:click=move |_| {
+                        test_action.dispatch(TestWebhook { webhook_id });
+                    }
+                >
+                    "Send Test"
+                </button>
+                <button
+                    type="button"
+                    class="p-2 px-4 rounded border"
+                    on:click=move |_| {
+                        enable_action
+                            .dispatch(SetWebhookEnabled {
+                                webhook_id,
+                                enabled: !enabled,
+                            });
+                    }
+                >
+                    {if enabled { "Disable" } else { "Enable" }}
+                </button>
+                <button
+                    type="button"
+                    class="p-2 px-4 rounded border border-red-600 text-red-600"
+                    on:click=move |_| {
+                        delete_action.dispatch(DeleteWebhook { webhook_id });
+                    }
+                >
+                    "Delete"
+                </button>
+            </div>
+            <Show when=move || show_deliveries.get()>
+                <Transition>
+                    {move || {
+                        deliveries
+                            .get()
+                            .and_then(|deliveries| deliveries.ok())
+                            .map(|deliveries| {
+                                if deliveries.is_empty() {
+                                    return view! { <div class="dark:text-white/50">"No deliveries yet"</div> }
+                                        .into_any();
+                                }
+                                deliveries
+                                    .into_iter()
+                                    .map(|delivery| {
+                                        let success = delivery.is_success();
+                                        view! {
+                                            <div
+                                                class="font-mono text-xs"
+                                                class=("text-red-600", !success)
+                                                class=("dark:text-red-400", !success)
+                                            >
+                                                {delivery.ts.format("%Y-%m-%d %H:%M:%S").to_string()}
+                                                " " {delivery.event.as_str()} " #" {delivery.attempt} " "
+                                                {delivery
+                                                    .status
+                                                    .map(|status| status.to_string())
+                                                    .unwrap_or("-".to_string())} " "
+                                                {delivery.error.unwrap_or_default()}
+                                                {delivery
+                                                    .next_attempt_at
+                                                    .map(|next| {
+                                                        format!(", retrying at {}", next.format("%H:%M:%S"))
+                                                    })}
+                                            </div>
+                                        }
+                                    })
+                                    .collect_view()
+                                    .into_any()
+                            })
+                    }}
+                </Transition>
+            </Show>
+        </div>
+    }
+}
@@ -0,0 +1,903 @@
+//! Maps a `docker-compose.yml` onto a container project.
+//!
+//! One service becomes the primary container, every other service a support container.
+//! Keys that have no equivalent are skipped and reported back as warnings.
+
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ComposeImport {
+    pub project_id: Uuid,
+    pub primary: String,
+    pub warnings: Vec<String>,
+}
+
+#[cfg(feature = "ssr")]
+pub use parse::*;
+
+#[cfg(feature = "ssr")]
+mod parse {
+    use std::collections::{BTreeMap, HashMap};
+
+    use serde::Deserialize;
+    use serde_yaml::Value;
+
+    use crate::common::{
+        Container, ContainerStatus, Dependency, DependencyCondition, EnvironmentVar, ExposedPort,
+        HealthCheck, ImageSource, ProjectType, ResourceLimits, RestartPolicy, RestartPolicyKind,
+        SupportContainer, SupportPort, Volume,
+    };
+
+    #[derive(Deserialize)]
+    struct ComposeFile {
+        #[serde(default)]
+        services: BTreeMap<String, Service>,
+        #[serde(flatten)]
+        other: BTreeMap<String, Value>,
+    }
+
+    #[derive(Deserialize)]
+    struct Service {
+        image: Option<String>,
+        build: Option<Value>,
+        command: Option<StringOrList>,
+        entrypoint: Option<StringOrList>,
+        #[serde(default)]
+        environment: Option<Environment>,
+        #[serde(default)]
+        ports: Vec<Value>,
+        #[serde(default)]
+        volumes: Vec<Value>,
+        depends_on: Option<DependsOn>,
+        healthcheck: Option<ComposeHealthCheck>,
+        restart: Option<String>,
+        mem_limit: Option<Value>,
+        cpus: Option<Value>,
+        cpu_shares: Option<u32>,
+        pids_limit: Option<i64>,
+        deploy: Option<Deploy>,
+        #[serde(flatten)]
+        other: BTreeMap<String, Value>,
+    }
+
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum StringOrList {
+        String(String),
+        List(Vec<String>),
+    }
+
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Environment {
+        Map(BTreeMap<String, Value>),
+        List(Vec<String>),
+    }
+
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum DependsOn {
+        List(Vec<String>),
+        Map(BTreeMap<String, DependsOnEntry>),
+    }
+
+    #[derive(Deserialize)]
+    struct DependsOnEntry {
+        condition: Option<String>,
+    }
+
+    #[derive(Deserialize)]
+    struct ComposeHealthCheck {
+        test: Option<StringOrList>,
+        interval: Option<String>,
+        timeout: Option<String>,
+        retries: Option<u32>,
+        start_period: Option<String>,
+        #[serde(default)]
+        disable: bool,
+    }
+
+    #[derive(Deserialize)]
+    struct Deploy {
+        resources: Option<Resources>,
+        #[serde(flatten)]
+        other: BTreeMap<String, Value>,
+    }
+
+    #[derive(Deserialize)]
+    struct Resources {
+        limits: Option<ResourceValues>,
+    }
+
+    #[derive(Deserialize)]
+    struct ResourceValues {
+        cpus: Option<Value>,
+        memory: Option<Value>,
+        pids: Option<i64>,
+    }
+
+    /// Parses `compose` and builds the project type, `primary` picks the primary service,
+    /// defaulting to the first service that builds an image or publishes ports.
+    pub fn compose_to_project(
+        compose: &str,
+        primary: Option<&str>,
+    ) -> Result<(ProjectType, String, Vec<String>), String> {
+        let file: ComposeFile =
+            serde_yaml::from_str(compose).map_err(|e| format!("Invalid compose file: {e}"))?;
+        if file.services.is_empty() {
+            return Err("Compose file has no services".to_string());
+        }
+
+        let mut warnings = vec![];
+        for key in file.other.keys() {
+            if !matches!(key.as_str(), "version" | "name" | "volumes") {
+                warnings.push(format!("Top level `{key}` is not supported"));
+            }
+        }
+
+        let primary = match primary.filter(|p| !p.is_empty()) {
+            Some(primary) => {
+                if !file.services.contains_key(primary) {
+                    return Err(format!("No service named {primary}"));
+                }
+                primary.to_string()
+            }
+            None => file
+                .services
+                .iter()
+                .find(|(_, s)| s.build.is_some())
+                .or_else(|| file.services.iter().find(|(_, s)| !s.ports.is_empty()))
+                .or_else(|| file.services.iter().next())
+                .map(|(name, _)| name.clone())
+                .unwrap_or_default(),
+        };
+
+        let mut primary_container = None;
+        let mut image_source = None;
+        let mut exposed_ports = smallvec::SmallVec::new();
+        let mut support_containers = HashMap::new();
+
+        for (name, service) in file.services.into_iter() {
+            let mut warn = |msg: String| warnings.push(format!("{name}: {msg}"));
+            for key in service.other.keys() {
+                warn(format!("`{key}` is not supported"));
+            }
+
+            let container = Container {
+                env_vars: environment(service.environment, &mut warn).into(),
+                volumes: volumes(&service.volumes, &mut warn).into(),
+                restart_policy: restart_policy(service.restart.as_deref(), &mut warn),
+                healthcheck: service.healthcheck.and_then(|h| healthcheck(h, &mut warn)),
+                limits: limits(
+                    service.mem_limit,
+                    service.cpus,
+                    service.cpu_shares,
+                    service.pids_limit,
+                    service.deploy,
+                    &mut warn,
+                ),
+                depends_on: depends_on(service.depends_on, &primary, &mut warn),
+                status: ContainerStatus::None,
+                runtime: Default::default(),
+            };
+
+            if name == primary {
+                match service.image {
+                    Some(image) => {
+                        if service.build.is_some() {
+                            warn("`build` is ignored, the image is pulled".to_string());
+                        }
+                        image_source = Some(ImageSource::new(image));
+                    }
+                    None => {
+                        warn("`build` is not supported, push the image to the project".to_string())
+                    }
+                }
+                if service.command.is_some() || service.entrypoint.is_some() {
+                    warn(
+                        "command and entrypoint are only supported on support services".to_string(),
+                    );
+                }
+                for port in service.ports.iter() {
+                    match parse_port(port) {
+                        Some((port, host_port)) => exposed_ports.push(ExposedPort {
+                            port,
+                            host_port,
+                            domains: Default::default(),
+                            peer: None,
+                        }),
+                        None => warn(format!("cannot parse port {}", value_to_string(port))),
+                    }
+                }
+                primary_container = Some(container);
+                continue;
+            }
+
+            let Some(image) = service.image else {
+                warn("skipped, support services need an `image`".to_string());
+                continue;
+            };
+            if service.build.is_some() {
+                warn("`build` is ignored, the image is pulled".to_string());
+            }
+            let mut ports = smallvec::SmallVec::new();
+            for port in service.ports.iter() {
+                match parse_port(port) {
+                    Some((port, Some(host_port))) => ports.push(SupportPort { port, host_port }),
+                    Some((port, None)) => warn(format!(
+                        "port {port} has no host port, it is only reachable inside the project"
+                    )),
+                    None => warn(format!("cannot parse port {}", value_to_string(port))),
+                }
+            }
+            support_containers.insert(
+                name,
+                SupportContainer {
+                    image,
+                    container,
+                    command: service.command.map(command_line),
+                    entrypoint: service.entrypoint.map(command_line),
+                    ports,
+                },
+            );
+        }
+
+        let primary_container = primary_container.ok_or(format!("No service named {primary}"))?;
+        crate::common::validate_dependencies(&primary_container, &support_containers)?;
+
+        Ok((
+            ProjectType::Container {
+                primary_container,
+                support_containers,
+                tokens: HashMap::new(),
+                exposed_ports,
+                image_source,
+                deploy_history: Default::default(),
+                hooks: Default::default(),
+                jobs: HashMap::new(),
+                log_retention: Default::default(),
+                log_sinks: vec![],
+            },
+            primary,
+            warnings,
+        ))
+    }
+
+    fn value_to_string(value: &Value) -> String {
+        match value {
+            Value::String(s) => s.clone(),
+            Value::Number(n) => n.to_string(),
+            Value::Bool(b) => b.to_string(),
+            Value::Null => String::new(),
+            other => serde_yaml::to_string(other)
+                .unwrap_or_default()
+                .trim()
+                .to_string(),
+        }
+    }
+
+    fn command_line(command: StringOrList) -> String {
+        match command {
+            StringOrList::String(command) => command,
+            StringOrList::List(args) => args
+                .iter()
+                .map(|arg| {
+                    if arg.is_empty()
+                        || arg.contains(|c: char| c.is_whitespace() || c == '\'' || c == '"')
+                    {
+                        format!("'{}'", arg.replace('\'', r"'\''"))
+                    } else {
+                        arg.clone()
+                    }
+                })
+                .collect::<Vec<_>>()
+                .join(" "),
+        }
+    }
+
+    fn environment(
+        environment: Option<Environment>,
+        warn: &mut impl FnMut(String),
+    ) -> Vec<EnvironmentVar> {
+        match environment {
+            None => vec![],
+            Some(Environment::Map(map)) => map
+                .into_iter()
+                .map(|(key, val)| EnvironmentVar {
+                    key,
+                    val: value_to_string(&val),
+                })
+                .collect(),
+            Some(Environment::List(list)) => list
+                .into_iter()
+                .filter_map(|entry| match entry.split_once('=') {
+                    Some((key, val)) => Some(EnvironmentVar {
+                        key: key.to_string(),
+                        val: val.to_string(),
+                    }),
+                    None => {
+                        warn(format!("environment `{entry}` has no value, skipped"));
+                        None
+                    }
+                })
+                .collect(),
+        }
+    }
+
+    fn volumes(volumes: &[Value], warn: &mut impl FnMut(String)) -> Vec<Volume> {
+        let mut result = vec![];
+        for volume in volumes {
+            let (source, target) = match volume {
+                Value::String(spec) => {
+                    let mut parts = spec.split(':');
+                    match (parts.next(), parts.next()) {
+                        (Some(source), Some(target)) => {
+                            if parts.next().is_some() {
+                                warn(format!("volume options of `{spec}` are ignored"));
+                            }
+                            (source.to_string(), target.to_string())
+                        }
+                        _ => {
+                            warn(format!("anonymous volume `{spec}` is not supported"));
+                            continue;
+                        }
+                    }
+                }
+                Value::Mapping(map) => {
+                    let get = |key: &str| map.get(key).map(value_to_string).unwrap_or_default();
+                    if get("type") != "volume" {
+                        warn(format!("volume of type `{}` is not supported", get("type")));
+                        continue;
+                    }
+                    (get("source"), get("target"))
+                }
+                other => {
+                    warn(format!("cannot parse volume {}", value_to_string(other)));
+                    continue;
+                }
+            };
+            if source.starts_with('.') || source.starts_with('/') || source.starts_with('~') {
+                warn(format!(
+                    "bind mount `{source}` is not supported, use a named volume"
+                ));
+                continue;
+            }
+            result.push(Volume {
+                name: source,
+                container_path: target,
+            });
+        }
+        result
+    }
+
+    /// Returns the container port and the host port it is published on, if any
+    fn parse_port(port: &Value) -> Option<(u16, Option<u16>)> {
+        match port {
+            Value::Number(n) => Some((n.as_u64()?.try_into().ok()?, None)),
+            Value::String(spec) => {
+                let spec = spec.strip_suffix("/tcp").unwrap_or(spec);
+                let parts = spec.rsplitn(3, ':').collect::<Vec<_>>();
+                let container = parts.first()?.parse().ok()?;
+                let host = match parts.get(1) {
+                    Some(host) => Some(host.parse().ok()?),
+                    None => None,
+                };
+                Some((container, host))
+            }
+            Value::Mapping(map) => {
+                let target = map.get("target")?.as_u64()?.try_into().ok()?;
+                let published = map
+                    .get("published")
+                    .map(value_to_string)
+                    .and_then(|p| p.parse().ok());
+                Some((target, published))
+            }
+            _ => None,
+        }
+    }
+
+    fn restart_policy(restart: Option<&str>, warn: &mut impl FnMut(String)) -> RestartPolicy {
+        let Some(restart) = restart else {
+            return RestartPolicy::default();
+        };
+        let (kind, max_retries) = restart.split_once(':').unwrap_or((restart, "0"));
+        let kind = match kind {
+            "no" => RestartPolicyKind::No,
+            "always" => RestartPolicyKind::Always,
+            "unless-stopped" => RestartPolicyKind::UnlessStopped,
+            "on-failure" => RestartPolicyKind::OnFailure,
+            other => {
+                warn(format!("unknown restart policy `{other}`"));
+                RestartPolicyKind::No
+            }
+        };
+        RestartPolicy {
+            kind,
+            max_retries: max_retries.parse().unwrap_or_default(),
+        }
+    }
+
+    fn healthcheck(
+        check: ComposeHealthCheck,
+        warn: &mut impl FnMut(String),
+    ) -> Option<HealthCheck> {
+        if check.disable {
+            return None;
+        }
+        let command = match check.test? {
+            StringOrList::String(command) => command,
+            StringOrList::List(test) => match test.split_first() {
+                Some((kind, rest)) if kind == "CMD-SHELL" => rest.join(" "),
+                Some((kind, rest)) if kind == "CMD" => {
+                    command_line(StringOrList::List(rest.to_vec()))
+                }
+                Some((kind, _)) if kind == "NONE" => return None,
+                _ => {
+                    warn("cannot parse healthcheck test".to_string());
+                    return None;
+                }
+            },
+        };
+        let defaults = HealthCheck::default();
+        let mut secs = |value: Option<String>, default: u64| {
+            value
+                .map(|v| match parse_duration_secs(&v) {
+                    Some(secs) => secs,
+                    None => {
+                        warn(format!("cannot parse duration `{v}`"));
+                        default
+                    }
+                })
+                .unwrap_or(default)
+        };
+        Some(HealthCheck {
+            command,
+            interval_secs: secs(check.interval, defaults.interval_secs),
+            timeout_secs: secs(check.timeout, defaults.timeout_secs),
+            start_period_secs: secs(check.start_period, defaults.start_period_secs),
+            retries: check.retries.unwrap_or(defaults.retries),
+        })
+    }
+
+    /// Parses compose durations like `1m30s` or `500ms`, rounding up to whole seconds
+    fn parse_duration_secs(duration: &str) -> Option<u64> {
+        let mut millis = 0u64;
+        let mut number = String::new();
+        let mut chars = duration.trim().chars().peekable();
+        while let Some(c) = chars.next() {
+            if c.is_ascii_digit() || c == '.' {
+                number.push(c);
+                continue;
+            }
+            let mut unit = c.to_string();
+            while let Some(next) = chars.peek().filter(|c| c.is_ascii_alphabetic()) {
+                unit.push(*next);
+                chars.next();
+            }
+            let value = number.parse::<f64>().ok()?;
+            number.clear();
+            let factor = match unit.as_str() {
+                "ms" => 1.0,
+                "s" => 1_000.0,
+                "m" => 60_000.0,
+                "h" => 3_600_000.0,
+                _ => return None,
+            };
+            let part = value * factor;
+            if part >= u64::MAX as f64 {
+                return None;
+            }
+            millis = millis.checked_add(part as u64)?;
+        }
+        if !number.is_empty() {
+            let secs = number.parse::<u64>().ok()?;
+            millis = millis.checked_add(secs.checked_mul(1_000)?)?;
+        }
+        Some(millis.div_ceil(1_000))
+    }
+
+    /// Parses memory like `512m` or `1g` into MiB
+    fn parse_memory_mb(memory: &Value) -> Option<u64> {
+        let memory = value_to_string(memory).to_lowercase();
+        let memory = memory.trim_end_matches('b');
+        let (number, factor) = match memory.chars().last()? {
+            'k' => (&memory[..memory.len() - 1], 1.0 / 1024.0),
+            'm' => (&memory[..memory.len() - 1], 1.0),
+            'g' => (&memory[..memory.len() - 1], 1024.0),
+            _ => (memory, 1.0 / 1024.0 / 1024.0),
+        };
+        let mb = (number.trim().parse::<f64>().ok()? * factor).ceil() as u64;
+        Some(mb.max(1))
+    }
+
+    fn limits(
+        mem_limit: Option<Value>,
+        cpus: Option<Value>,
+        cpu_shares: Option<u32>,
+        pids_limit: Option<i64>,
+        deploy: Option<Deploy>,
+        warn: &mut impl FnMut(String),
+    ) -> ResourceLimits {
+        let mut mem_limit = mem_limit;
+        let mut cpus = cpus;
+        let mut pids_limit = pids_limit;
+        if let Some(deploy) = deploy {
+            for key in deploy.other.keys() {
+                warn(format!("`deploy.{key}` is not supported"));
+            }
+            if let Some(limits) = deploy.resources.and_then(|r| r.limits) {
+                mem_limit = limits.memory.or(mem_limit);
+                cpus = limits.cpus.or(cpus);
+                pids_limit = limits.pids.or(pids_limit);
+            }
+        }
+        ResourceLimits {
+            memory_mb: mem_limit.and_then(|m| {
+                let mb = parse_memory_mb(&m);
+                if mb.is_none() {
+                    warn(format!("cannot parse memory limit {}", value_to_string(&m)));
+                }
+                mb
+            }),
+            cpus: cpus.and_then(|c| value_to_string(&c).parse().ok()),
+            cpu_shares,
+            pids_limit,
+            restart_on_oom: false,
+        }
+    }
+
+    fn depends_on(
+        depends_on: Option<DependsOn>,
+        primary: &str,
+        warn: &mut impl FnMut(String),
+    ) -> Vec<Dependency> {
+        let entries = match depends_on {
+            None => vec![],
+            Some(DependsOn::List(names)) => names.into_iter().map(|n| (n, None)).collect(),
+            Some(DependsOn::Map(map)) => map
+                .into_iter()
+                .map(|(name, entry)| (name, entry.condition))
+                .collect(),
+        };
+        entries
+            .into_iter()
+            .filter_map(|(name, condition)| {
+                if name == primary {
+                    warn(format!(
+                        "depending on the primary service {name} is not supported"
+                    ));
+                    return None;
+                }
+                let condition = match condition.as_deref() {
+                    None | Some("service_started") => DependencyCondition::Started,
+                    Some("service_healthy") => DependencyCondition::Healthy,
+                    Some("service_completed_successfully") => {
+                        DependencyCondition::CompletedSuccessfully
+                    }
+                    Some(other) => {
+                        warn(format!("unknown depends_on condition `{other}`"));
+                        DependencyCondition::Started
+                    }
+                };
+                Some(Dependency {
+                    name,
+                    condition,
+                    port: None,
+                    timeout_secs: 120,
+                })
+            })
+            .collect()
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        fn container(
+            project_type: &ProjectType,
+        ) -> (&Container, &HashMap<String, SupportContainer>) {
+            match project_type {
+                ProjectType::Container {
+                    primary_container,
+                    support_containers,
+                    ..
+                } => (primary_container, support_containers),
+                ProjectType::PortForward(_) => panic!("not a container project"),
+            }
+        }
+
+        #[test]
+        fn services_split_into_primary_and_support() {
+            let compose = r#"
+version: "3.8"
+networks:
+  default: {}
+services:
+  db:
+    image: postgres:16
+    environment:
+      POSTGRES_PASSWORD: secret
+      POSTGRES_PORT: 5432
+    volumes:
+      - pgdata:/var/lib/postgresql/data
+  web:
+    build: .
+    image: ghcr.io/acme/web:latest
+    ports:
+      - "8080:80"
+    depends_on:
+      - db
+  worker:
+    image: ghcr.io/acme/web:latest
+    command: ["celery", "-A", "app worker"]
+    environment:
+      - QUEUE=default
+      - BROKEN
+  tools:
+    entrypoint: sh
+"#;
+            let (project_type, primary, warnings) = compose_to_project(compose, None).unwrap();
+            assert_eq!(primary, "web");
+            let (primary_container, support_containers) = container(&project_type);
+
+            assert_eq!(primary_container.depends_on.len(), 1);
+            assert_eq!(primary_container.depends_on[0].name, "db");
+            let ProjectType::Container {
+                exposed_ports,
+                image_source,
+                ..
+            } = &project_type
+            else {
+                unreachable!()
+            };
+            assert_eq!(exposed_ports.len(), 1);
+            assert_eq!(exposed_ports[0].port, 80);
+            assert_eq!(exposed_ports[0].host_port, Some(8080));
+            assert_eq!(
+                image_source
+                    .as_ref()
+                    .map(|source| source.reference.as_str()),
+                Some("ghcr.io/acme/web:latest")
+            );
+
+            let mut names = support_containers.keys().collect::<Vec<_>>();
+            names.sort();
+            assert_eq!(names, vec!["db", "worker"]);
+            let db = &support_containers["db"];
+            assert_eq!(db.image, "postgres:16");
+            assert_eq!(
+                db.container.env_vars.to_vec(),
+                vec![
+                    EnvironmentVar {
+                        key: "POSTGRES_PASSWORD".to_string(),
+                        val: "secret".to_string(),
+                    },
+                    EnvironmentVar {
+                        key: "POSTGRES_PORT".to_string(),
+                        val: "5432".to_string(),
+                    },
+                ]
+            );
+            assert_eq!(db.container.volumes[0].name, "pgdata");
+            assert_eq!(
+                db.container.volumes[0].container_path,
+                "/var/lib/postgresql/data"
+            );
+            let worker = &support_containers["worker"];
+            assert_eq!(worker.command.as_deref(), Some("celery -A 'app worker'"));
+            assert_eq!(worker.container.env_vars.len(), 1);
+
+            assert!(warnings.contains(&"Top level `networks` is not supported".to_string()));
+            assert!(warnings.contains(&"web: `build` is ignored, the image is pulled".to_string()));
+            assert!(warnings
+                .contains(&"worker: environment `BROKEN` has no value, skipped".to_string()));
+            assert!(
+                warnings.contains(&"tools: skipped, support services need an `image`".to_string())
+            );
+        }
+
+        #[test]
+        fn primary_can_be_picked() {
+            let compose = "services:\n  a:\n    image: a\n  b:\n    image: b\n";
+            let (_, primary, _) = compose_to_project(compose, Some("b")).unwrap();
+            assert_eq!(primary, "b");
+            assert_eq!(
+                compose_to_project(compose, Some("c")).unwrap_err(),
+                "No service named c"
+            );
+            assert_eq!(
+                compose_to_project("services: {}\n", None).unwrap_err(),
+                "Compose file has no services"
+            );
+        }
+
+        #[test]
+        fn depends_on_conditions() {
+            let compose = r#"
+services:
+  web:
+    image: web
+    ports: [80]
+  db:
+    image: postgres
+  migrate:
+    image: web
+    depends_on:
+      db:
+        condition: service_healthy
+  api:
+    image: api
+    depends_on:
+      web:
+        condition: service_started
+      migrate:
+        condition: service_completed_successfully
+      db:
+        condition: service_something
+"#;
+            let (project_type, primary, warnings) = compose_to_project(compose, None).unwrap();
+            assert_eq!(primary, "web");
+            let (_, support_containers) = container(&project_type);
+
+            let migrate = &support_containers["migrate"].container.depends_on;
+            assert_eq!(migrate.len(), 1);
+            assert_eq!(migrate[0].condition, DependencyCondition::Healthy);
+
+            let api = &support_containers["api"].container.depends_on;
+            assert_eq!(api.len(), 2);
+            assert_eq!(api[0].name, "db");
+            assert_eq!(api[0].condition, DependencyCondition::Started);
+            assert_eq!(api[1].name, "migrate");
+            assert_eq!(api[1].condition, DependencyCondition::CompletedSuccessfully);
+
+            assert!(warnings.contains(
+                &"api: depending on the primary service web is not supported".to_string()
+            ));
+            assert!(warnings
+                .contains(&"api: unknown depends_on condition `service_something`".to_string()));
+        }
+
+        #[test]
+        fn dependency_cycles_are_rejected() {
+            let compose = r#"
+services:
+  web:
+    image: web
+  a:
+    image: a
+    depends_on: [b]
+  b:
+    image: b
+    depends_on: [a]
+"#;
+            assert_eq!(
+                compose_to_project(compose, Some("web")).unwrap_err(),
+                "Dependency cycle between a, b"
+            );
+        }
+
+        #[test]
+        fn healthchecks() {
+            let mut warnings = vec![];
+            let mut warn = |msg: String| warnings.push(msg);
+            let parse = |yaml: &str| serde_yaml::from_str::<ComposeHealthCheck>(yaml).unwrap();
+
+            let check = parse(
+                r#"
+test: ["CMD", "curl", "-f", "http://localhost/health check"]
+interval: 1m30s
+timeout: 500ms
+retries: 5
+start_period: 10s
+"#,
+            );
+            let check = healthcheck(check, &mut warn).unwrap();
+            assert_eq!(
+                check,
+                HealthCheck {
+                    command: "curl -f 'http://localhost/health check'".to_string(),
+                    interval_secs: 90,
+                    timeout_secs: 1,
+                    retries: 5,
+                    start_period_secs: 10,
+                }
+            );
+
+            let check = healthcheck(
+                parse("test: [\"CMD-SHELL\", \"pg_isready -U postgres\"]\ninterval: soon\n"),
+                &mut warn,
+            )
+            .unwrap();
+            let defaults = HealthCheck::default();
+            assert_eq!(check.command, "pg_isready -U postgres");
+            assert_eq!(check.interval_secs, defaults.interval_secs);
+            assert_eq!(check.retries, defaults.retries);
+
+            assert!(healthcheck(parse("test: [\"NONE\"]\n"), &mut warn).is_none());
+            assert!(healthcheck(
+                parse("test: [\"CMD\", \"true\"]\ndisable: true\n"),
+                &mut warn
+            )
+            .is_none());
+            assert_eq!(warnings, vec!["cannot parse duration `soon`".to_string()]);
+        }
+
+        #[test]
+        fn durations() {
+            assert_eq!(parse_duration_secs("30s"), Some(30));
+            assert_eq!(parse_duration_secs("1m30s"), Some(90));
+            assert_eq!(parse_duration_secs("1h"), Some(3_600));
+            assert_eq!(parse_duration_secs("500ms"), Some(1));
+            assert_eq!(parse_duration_secs("1.5s"), Some(2));
+            assert_eq!(parse_duration_secs(" 45 "), Some(45));
+            assert_eq!(parse_duration_secs("0s"), Some(0));
+            assert_eq!(parse_duration_secs("10d"), None);
+            assert_eq!(parse_duration_secs("s"), None);
+        }
+
+        #[test]
+        fn durations_that_overflow() {
+            assert_eq!(parse_duration_secs("18446744073709551615"), None);
+            assert_eq!(parse_duration_secs("99999999999999999999h"), None);
+            assert_eq!(parse_duration_secs("4000000000000h4000000000000h"), None);
+        }
+
+        #[test]
+        fn ports() {
+            let parse = |yaml: &str| parse_port(&serde_yaml::from_str::<Value>(yaml).unwrap());
+            assert_eq!(parse("80"), Some((80, None)));
+            assert_eq!(parse("\"8080:80\""), Some((80, Some(8080))));
+            assert_eq!(parse("\"127.0.0.1:8080:80/tcp\""), Some((80, Some(8080))));
+            assert_eq!(parse("\"3000\""), Some((3000, None)));
+            assert_eq!(
+                parse("{target: 80, published: \"8080\"}"),
+                Some((80, Some(8080)))
+            );
+            assert_eq!(parse("{target: 80}"), Some((80, None)));
+            assert_eq!(parse("70000"), None);
+            assert_eq!(parse("\"web:80\""), None);
+            assert_eq!(parse("[80]"), None);
+        }
+
+        #[test]
+        fn volume_specs() {
+            let mut warnings = vec![];
+            let mut warn = |msg: String| warnings.push(msg);
+            let specs = serde_yaml::from_str::<Vec<Value>>(
+                r#"
+- data:/var/lib/data
+- cache:/cache:ro
+- ./src:/app
+- /var/run/docker.sock:/var/run/docker.sock
+- /tmp
+- {type: volume, source: logs, target: /logs}
+- {type: bind, source: ., target: /app}
+"#,
+            )
+            .unwrap();
+            let parsed = volumes(&specs, &mut warn)
+                .into_iter()
+                .map(|volume| (volume.name, volume.container_path))
+                .collect::<Vec<_>>();
+            assert_eq!(
+                parsed,
+                vec![
+                    ("data".to_string(), "/var/lib/data".to_string()),
+                    ("cache".to_string(), "/cache".to_string()),
+                    ("logs".to_string(), "/logs".to_string()),
+                ]
+            );
+            assert_eq!(
+                warnings,
+                vec![
+                    "volume options of `cache:/cache:ro` are ignored",
+                    "bind mount `./src` is not supported, use a named volume",
+                    "bind mount `/var/run/docker.sock` is not supported, use a named volume",
+                    "anonymous volume `/tmp` is not supported",
+                    "volume of type `bind` is not supported",
+                ]
+            );
+        }
+    }
+}
@@ -4,14 +4,19 @@ use unicase::UniCase;
 use uuid::Uuid;
 
 use crate::common::{
-    get_home_path, DomainSerialize, DomainStatus, Project, ProjectConfig, SSLProvisioning, SSlData,
+    get_home_path, DomainSerialize, DomainStatus, Project, ProjectConfig, ProjectEvent,
+    SSLProvisioning, SSlData, WebhookEventKind,
 };
 
+/// Events beyond this many not yet received by a subscriber make it lag
+const EVENT_BUFFER: usize = 256;
+
 #[derive(Clone)]
 pub struct ProjectContext {
     projects: Arc<tokio::sync::RwLock<std::collections::HashMap<Uuid, std::sync::Arc<Project>>>>,
     domains:
         Arc<tokio::sync::RwLock<std::collections::HashMap<unicase::UniCase<String>, DomainStatus>>>,
+    events: tokio::sync::broadcast::Sender<ProjectEvent>,
 }
 
 impl ProjectContext {
@@ -19,9 +24,57 @@ impl ProjectContext {
         Self {
             projects: Arc::new(tokio::sync::RwLock::const_new(HashMap::new())),
             domains: Arc::new(tokio::sync::RwLock::const_new(HashMap::new())),
+            events: tokio::sync::broadcast::channel(EVENT_BUFFER).0,
+        }
+    }
+
+    /// Receives every change made through the context from now on
+    pub fn subscribe(&self) -> tokio::sync::broadcast::Receiver<ProjectEvent> {
+        self.events.subscribe()
+    }
+
+    fn publish(&self, event: ProjectEvent) {
+        // Fails only when nobody is subscribed
+        let _ = self.events.send(event);
+    }
+
+    fn publish_project_changes(&self, old: Option<&Project>, new: &Project) {
+        let project_id = new.id;
+        let Some(old) = old else {
+            self.publish(ProjectEvent::ProjectCreated { project_id });
+            return;
+        };
+        if serde_json::to_value(old).ok() != serde_json::to_value(new).ok() {
+            self.publish(ProjectEvent::ProjectUpdated { project_id });
+        }
+        let old_containers = old.project_type.containers();
+        for (slot, container) in new.project_type.containers() {
+            let changed = old_containers
+                .iter()
+                .find(|(old_slot, _)| *old_slot == slot)
+                .is_none_or(|(_, previous)| {
+                    previous.status.is_running() != container.status.is_running()
+                        || previous.runtime != container.runtime
+                });
+            if changed {
+                self.publish(ProjectEvent::ContainerStatusChanged {
+                    project_id,
+                    container: slot,
+                    running: container.status.is_running(),
+                    health: container.runtime.health,
+                });
+            }
         }
     }
 
+    fn publish_domain(&self, domain: &UniCase<String>, status: &DomainStatus) {
+        self.publish(ProjectEvent::DomainUpdated {
+            project_id: status.project.upgrade().map(|project| project.id),
+            domain: domain.to_lowercase(),
+            status: status.clone().into(),
+        });
+    }
+
     pub async fn load_from_config(&mut self) -> anyhow::Result<()> {
         let path = get_home_path().join("projects.json");
         tracing::info!("Loading path {path:?}");
@@ -109,7 +162,9 @@ impl ProjectContext {
                 }
             }
             let mut projects = self.projects.write().await;
-            projects.insert(id, new_project);
+            let old_project = projects.insert(id, new_project.clone());
+            crate::webhooks::emit_project_changes(old_project.as_deref(), &new_project);
+            self.publish_project_changes(old_project.as_deref(), &new_project);
         }
         self.save_to_config().await?;
         Ok(())
@@ -118,7 +173,14 @@ impl ProjectContext {
     pub async fn remove_project(&mut self, id: Uuid) -> anyhow::Result<()> {
         {
             let mut projects = self.projects.write().await;
-            projects.remove(&id);
+            if let Some(project) = projects.remove(&id) {
+                crate::webhooks::emit(
+                    WebhookEventKind::ProjectDeleted,
+                    Some(id),
+                    serde_json::json!({ "name": project.name }),
+                );
+                self.publish(ProjectEvent::ProjectRemoved { project_id: id });
+            }
         }
         self.save_to_config().await?;
         Ok(())
@@ -131,7 +193,11 @@ impl ProjectContext {
     ) -> anyhow::Result<()> {
         {
             let mut domains = self.domains.write().await;
-            Self::add_project_domain_self(project, domain, &mut domains).await?;
+            let domain = UniCase::from(domain.to_ascii_lowercase());
+            Self::add_project_domain_self(project, domain.to_string(), &mut domains).await?;
+            if let Some(status) = domains.get(&domain) {
+                self.publish_domain(&domain, status);
+            }
         }
         self.save_to_config().await?;
         Ok(())
@@ -231,6 +297,7 @@ impl ProjectContext {
 
     pub async fn update_domain(&mut self, domain: UniCase<String>, status: DomainStatus) {
         let mut domains = self.domains.write().await;
+        self.publish_domain(&domain, &status);
         domains.insert(domain, status);
     }
 }
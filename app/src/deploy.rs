@@ -0,0 +1,452 @@
+//! Pulls a project's [`ImageSource`] and deploys it as the primary container.
+//!
+//! The pulled image is tagged `selfcloud_image_<id>:latest`, the same tag `push_image`
+//! uploads to, so the container manager recreates the primary container like after an upload.
+//! Every deployment is recorded in the project's [`DeployHistory`] so it can be rolled back to.
+
+use std::{collections::HashSet, sync::Arc};
+
+use anyhow::bail;
+use docker_api::{
+    models::ImageBuildChunk,
+    opts::{PullOpts, RegistryAuth, TagOpts},
+};
+use futures::StreamExt;
+use once_cell::sync::Lazy;
+use uuid::Uuid;
+
+use crate::{
+    common::{
+        get_docker, ContainerStatus, DeployHistory, DeployStatus, Deployment, ImageSource,
+        ProjectType,
+    },
+    context::ProjectContext,
+};
+
+/// Projects with a pull in flight, a manual deploy and the poller must not race
+static DEPLOYING: Lazy<std::sync::Mutex<HashSet<Uuid>>> = Lazy::new(Default::default);
+
+struct DeployGuard(Uuid);
+
+impl DeployGuard {
+    fn acquire(project_id: Uuid) -> anyhow::Result<Self> {
+        let mut deploying = DEPLOYING.lock().expect("deploy lock poisoned");
+        if !deploying.insert(project_id) {
+            bail!("A deploy is already running for this project");
+        }
+        Ok(Self(project_id))
+    }
+}
+
+impl Drop for DeployGuard {
+    fn drop(&mut self) {
+        if let Ok(mut deploying) = DEPLOYING.lock() {
+            deploying.remove(&self.0);
+        }
+    }
+}
+
+pub fn image_tag(project_id: Uuid) -> String {
+    format!("selfcloud_image_{project_id}")
+}
+
+/// Whether [`deploy_image`] goes on with the image pulled as `digest`, or with pulling when
+/// it is `None`. `latest` may point at a rolled back image, so the pull is compared with
+/// the digest the source deployed last.
+fn wants_deploy(
+    history: &DeployHistory,
+    source: &ImageSource,
+    digest: Option<&str>,
+    force: bool,
+) -> bool {
+    if force {
+        return true;
+    }
+    if history.pinned {
+        return false;
+    }
+    digest.is_none_or(|digest| source.deployed_digest.as_deref() != Some(digest))
+}
+
+/// Pulls the project's image source and redeploys the primary container.
+///
+/// Unless `force` is set nothing is redeployed when the pulled image is the one already
+/// deployed or the project was rolled back. Returns the digest of the deployed image,
+/// `None` when nothing was deployed.
+pub async fn deploy_image(
+    context: &mut ProjectContext,
+    project_id: Uuid,
+    force: bool,
+) -> anyhow::Result<Option<String>> {
+    let _guard = DeployGuard::acquire(project_id)?;
+
+    let project = context
+        .get_project(project_id)
+        .await
+        .ok_or(anyhow::anyhow!("Project {project_id} not found"))?;
+    let ProjectType::Container {
+        image_source: Some(source),
+        deploy_history,
+        ..
+    } = &project.project_type
+    else {
+        bail!("Project has no image source");
+    };
+    if !wants_deploy(deploy_history, source, None, force) {
+        tracing::debug!("{} is rolled back, not polling its image", project.name);
+        return Ok(None);
+    }
+
+    pull_image(source).await?;
+
+    let docker = get_docker();
+    let pulled = docker
+        .images()
+        .get(source.reference.trim())
+        .inspect()
+        .await?;
+    let pulled_id = pulled.id.unwrap_or_default();
+    let digest = pulled
+        .repo_digests
+        .and_then(|digests| digests.into_iter().next())
+        .unwrap_or(pulled_id);
+
+    if !wants_deploy(deploy_history, source, Some(&digest), force) {
+        tracing::debug!("Image of {} is unchanged", project.name);
+        return Ok(None);
+    }
+
+    tracing::info!(
+        "Deploying {} as {digest} for {}",
+        source.reference,
+        project.name
+    );
+    record_deployment(
+        context,
+        project_id,
+        source.reference.trim(),
+        format!("Registry {}", source.reference.trim()),
+        None,
+    )
+    .await?;
+
+    // Re-read, recording the deployment updated the project
+    let project = context
+        .get_project(project_id)
+        .await
+        .ok_or(anyhow::anyhow!("Project {project_id} not found"))?;
+    let mut project = project.as_ref().clone();
+    if let ProjectType::Container {
+        image_source: Some(source),
+        ..
+    } = &mut project.project_type
+    {
+        source.deployed_digest = Some(digest.clone());
+        source.deployed_at = Some(chrono::Utc::now());
+    }
+    context
+        .update_project(project_id, Arc::new(project))
+        .await?;
+
+    Ok(Some(digest))
+}
+
+/// Records `image` as a new deployment of the primary container and tags it `latest`.
+///
+/// Deployments beyond the retained count are dropped along with their tags and pushed
+/// layers, except the active one.
+pub async fn record_deployment(
+    context: &mut ProjectContext,
+    project_id: Uuid,
+    image: &str,
+    source: String,
+    git_sha: Option<String>,
+) -> anyhow::Result<Deployment> {
+    let docker = get_docker();
+    let image_id = docker
+        .images()
+        .get(image)
+        .inspect()
+        .await?
+        .id
+        .unwrap_or_else(|| image.to_string());
+    let deployment = Deployment {
+        id: Uuid::new_v4(),
+        image_id,
+        deployed_at: chrono::Utc::now(),
+        source,
+        git_sha: git_sha.filter(|sha| !sha.trim().is_empty()),
+        status: DeployStatus::ImageLoaded,
+        hook_runs: vec![],
+    };
+    for tag in [deployment.tag().as_str(), "latest"] {
+        docker
+            .images()
+            .get(image)
+            .tag(
+                &TagOpts::builder()
+                    .repo(image_tag(project_id))
+                    .tag(tag)
+                    .build(),
+            )
+            .await?;
+    }
+
+    let project = context
+        .get_project(project_id)
+        .await
+        .ok_or(anyhow::anyhow!("Project {project_id} not found"))?;
+    let mut project = project.as_ref().clone();
+    let ProjectType::Container {
+        primary_container,
+        deploy_history,
+        ..
+    } = &mut project.project_type
+    else {
+        bail!("Project not of type container");
+    };
+    deploy_history.deployments.push(deployment.clone());
+    deploy_history.active = Some(deployment.id);
+    deploy_history.pinned = false;
+    let expired = expire_deployments(deploy_history);
+    let retained = deploy_history.deployments.clone();
+    primary_container.status = ContainerStatus::None;
+    context
+        .update_project(project_id, Arc::new(project))
+        .await?;
+
+    remove_expired(project_id, &expired, &retained).await;
+    Ok(deployment)
+}
+
+/// Points `latest` back at a retained deployment and redeploys it
+pub async fn rollback(
+    context: &mut ProjectContext,
+    project_id: Uuid,
+    deployment_id: Uuid,
+) -> anyhow::Result<()> {
+    let _guard = DeployGuard::acquire(project_id)?;
+
+    let project = context
+        .get_project(project_id)
+        .await
+        .ok_or(anyhow::anyhow!("Project {project_id} not found"))?;
+    let mut project = project.as_ref().clone();
+    let ProjectType::Container {
+        primary_container,
+        deploy_history,
+        ..
+    } = &mut project.project_type
+    else {
+        bail!("Project not of type container");
+    };
+    let deployment = deploy_history
+        .deployments
+        .iter_mut()
+        .find(|deployment| deployment.id == deployment_id)
+        .ok_or(anyhow::anyhow!("Deployment is no longer retained"))?;
+    // It is rolled out again, report the new attempt
+    deployment.status = DeployStatus::ImageLoaded;
+
+    get_docker()
+        .images()
+        .get(format!("{}:{}", image_tag(project_id), deployment.tag()))
+        .tag(
+            &TagOpts::builder()
+                .repo(image_tag(project_id))
+                .tag("latest")
+                .build(),
+        )
+        .await?;
+    tracing::info!(
+        "Rolled {} back to {} from {}",
+        project.name,
+        deployment.image_id,
+        deployment.deployed_at
+    );
+    deploy_history.active = Some(deployment_id);
+    deploy_history.pinned = true;
+    primary_container.status = ContainerStatus::None;
+    context
+        .update_project(project_id, Arc::new(project))
+        .await?;
+    Ok(())
+}
+
+/// Changes how many deployments are retained, dropping the ones beyond it
+pub async fn set_retained(
+    context: &mut ProjectContext,
+    project_id: Uuid,
+    retain: usize,
+) -> anyhow::Result<()> {
+    let project = context
+        .get_project(project_id)
+        .await
+        .ok_or(anyhow::anyhow!("Project {project_id} not found"))?;
+    let mut project = project.as_ref().clone();
+    let ProjectType::Container { deploy_history, .. } = &mut project.project_type else {
+        bail!("Project not of type container");
+    };
+    deploy_history.retain = retain.max(1);
+    let expired = expire_deployments(deploy_history);
+    let retained = deploy_history.deployments.clone();
+    context
+        .update_project(project_id, Arc::new(project))
+        .await?;
+
+    remove_expired(project_id, &expired, &retained).await;
+    Ok(())
+}
+
+/// Drops the oldest deployments beyond the retained count, never the active one
+fn expire_deployments(history: &mut DeployHistory) -> Vec<Deployment> {
+    let mut expired = vec![];
+    let mut excess = history.deployments.len().saturating_sub(history.retain);
+    history.deployments.retain(|deployment| {
+        if excess == 0 || Some(deployment.id) == history.active {
+            return true;
+        }
+        excess -= 1;
+        expired.push(deployment.clone());
+        false
+    });
+    expired
+}
+
+/// Removes the images and registry data only expired deployments used
+async fn remove_expired(project_id: Uuid, expired: &[Deployment], retained: &[Deployment]) {
+    if expired.is_empty() {
+        return;
+    }
+    let docker = get_docker();
+    for deployment in expired {
+        let tag = format!("{}:{}", image_tag(project_id), deployment.tag());
+        // Only untags when the image is still referenced elsewhere, e.g. by `latest`
+        if let Err(err) = docker.images().get(tag.as_str()).delete().await {
+            tracing::warn!("Cannot remove expired image {tag} {err:?}");
+        }
+    }
+    if let Err(err) = crate::registry_store::prune(project_id, retained).await {
+        tracing::warn!("Cannot prune registry of {project_id} {err:?}");
+    }
+}
+
+async fn pull_image(source: &ImageSource) -> anyhow::Result<()> {
+    let (image, tag) = source.image_and_tag();
+    let mut opts = PullOpts::builder().image(image);
+    if let Some(tag) = tag {
+        opts = opts.tag(tag);
+    }
+    if let Some(username) = source.username.as_ref().filter(|u| !u.is_empty()) {
+        opts = opts.auth(
+            RegistryAuth::builder()
+                .username(username)
+                .password(source.password.clone().unwrap_or_default())
+                .server_address(source.registry())
+                .build(),
+        );
+    }
+
+    let docker = get_docker();
+    let images = docker.images();
+    let mut stream = images.pull(&opts.build());
+    while let Some(chunk) = stream.next().await {
+        if let ImageBuildChunk::Error { error, .. } = chunk? {
+            bail!("Pulling {} failed: {error}", source.reference);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DEPLOYED: &str = "ghcr.io/org/app@sha256:aaaa";
+    const PUSHED: &str = "ghcr.io/org/app@sha256:bbbb";
+
+    fn source(deployed_digest: Option<&str>) -> ImageSource {
+        let mut source = ImageSource::new("ghcr.io/org/app:main".to_string());
+        source.auto_deploy = true;
+        source.deployed_digest = deployed_digest.map(String::from);
+        source
+    }
+
+    #[test]
+    fn polls_deploy_a_new_digest() {
+        let history = DeployHistory::default();
+        let source = source(Some(DEPLOYED));
+        assert!(wants_deploy(&history, &source, None, false));
+        assert!(!wants_deploy(&history, &source, Some(DEPLOYED), false));
+        assert!(wants_deploy(&history, &source, Some(PUSHED), false));
+    }
+
+    #[test]
+    fn first_poll_deploys() {
+        let history = DeployHistory::default();
+        assert!(wants_deploy(&history, &source(None), Some(DEPLOYED), false));
+    }
+
+    #[test]
+    fn rollbacks_pin_polls_but_not_manual_deploys() {
+        let history = DeployHistory {
+            pinned: true,
+            ..Default::default()
+        };
+        let source = source(Some(DEPLOYED));
+        assert!(!wants_deploy(&history, &source, None, false));
+        assert!(!wants_deploy(&history, &source, Some(PUSHED), false));
+        assert!(wants_deploy(&history, &source, None, true));
+        assert!(wants_deploy(&history, &source, Some(DEPLOYED), true));
+    }
+
+    fn deployment(image_id: &str) -> Deployment {
+        Deployment {
+            id: Uuid::new_v4(),
+            image_id: image_id.to_string(),
+            deployed_at: chrono::Utc::now(),
+            source: "test".to_string(),
+            git_sha: None,
+            status: Default::default(),
+            hook_runs: vec![],
+        }
+    }
+
+    fn history(retain: usize, count: usize) -> DeployHistory {
+        DeployHistory {
+            retain,
+            deployments: (0..count)
+                .map(|n| deployment(&format!("sha256:{n}")))
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    fn image_ids(deployments: &[Deployment]) -> Vec<&str> {
+        deployments
+            .iter()
+            .map(|deployment| deployment.image_id.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn oldest_deployments_expire_beyond_retain() {
+        let mut history = history(2, 4);
+        history.active = Some(history.deployments[3].id);
+        let expired = expire_deployments(&mut history);
+        assert_eq!(image_ids(&expired), ["sha256:0", "sha256:1"]);
+        assert_eq!(image_ids(&history.deployments), ["sha256:2", "sha256:3"]);
+
+        assert!(expire_deployments(&mut history).is_empty());
+    }
+
+    #[test]
+    fn active_deployment_never_expires() {
+        // Rolled back to the oldest deployment
+        let mut history = history(2, 4);
+        history.active = Some(history.deployments[0].id);
+        let expired = expire_deployments(&mut history);
+        assert_eq!(image_ids(&expired), ["sha256:1", "sha256:2"]);
+        assert_eq!(image_ids(&history.deployments), ["sha256:0", "sha256:3"]);
+    }
+}
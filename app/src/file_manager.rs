@@ -2,7 +2,7 @@ use crate::common::FileInfo;
 use leptos::prelude::*;
 
 #[cfg(feature = "ssr")]
-use crate::api::user;
+use crate::api::{audit, user};
 
 #[server(ListFiles, "/api/files")]
 pub async fn list_files(path: String) -> Result<Vec<FileInfo>, ServerFnError> {
@@ -46,19 +46,21 @@ pub async fn read_file(path: String) -> Result<String, ServerFnError> {
 
 #[server(WriteFile, "/api/files/write")]
 pub async fn write_file(path: String, content: String) -> Result<(), ServerFnError> {
-    user()?;
+    let user = user()?;
     std::fs::write(&path, content).map_err(|e| ServerFnError::new(e.to_string()))?;
+    audit(&user, "file.write", Some(path)).await;
     Ok(())
 }
 
 #[server(DeleteFile, "/api/files/delete")]
 pub async fn delete_file(path: String) -> Result<(), ServerFnError> {
-    user()?;
+    let user = user()?;
     let meta = std::fs::metadata(&path).map_err(|e| ServerFnError::new(e.to_string()))?;
     if meta.is_dir() {
         std::fs::remove_dir_all(&path).map_err(|e| ServerFnError::new(e.to_string()))?;
     } else {
         std::fs::remove_file(&path).map_err(|e| ServerFnError::new(e.to_string()))?;
     }
+    audit(&user, "file.delete", Some(path)).await;
     Ok(())
 }
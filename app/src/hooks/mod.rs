@@ -1,2 +1,4 @@
+pub mod use_project_events;
 pub mod use_socket;
+pub use use_project_events::*;
 pub use use_socket::*;
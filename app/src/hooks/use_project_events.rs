@@ -0,0 +1,26 @@
+use leptos::prelude::*;
+
+use crate::common::ProjectEvent;
+use crate::hooks::use_socket::{use_socket, WsMessage};
+
+/// Latest change of the project context, pushed by the server as it happens.
+///
+/// Every event notifies, even when it equals the previous one, so it can be
+/// used directly to refetch resources.
+pub fn use_project_events() -> ReadSignal<Option<ProjectEvent>> {
+    let socket = use_socket("/events/projects/ws");
+    let message = socket.message;
+    let (event, set_event) = signal(None::<ProjectEvent>);
+
+    Effect::new(move |_| {
+        message.with(|msg| {
+            if let Some(WsMessage::Binary(bytes)) = msg {
+                if let Ok(project_event) = bincode::deserialize::<ProjectEvent>(bytes) {
+                    set_event.set(Some(project_event));
+                }
+            }
+        });
+    });
+
+    event
+}
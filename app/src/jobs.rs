@@ -0,0 +1,173 @@
+//! Scheduled jobs of container projects.
+//!
+//! The container manager runs the jobs, this module keeps their run history on disk under
+//! `jobs/<project id>/<job>.json` and the "run now" requests made from the panel.
+
+use std::{collections::HashSet, path::PathBuf, str::FromStr};
+
+use chrono::{DateTime, Utc};
+use once_cell::sync::Lazy;
+use uuid::Uuid;
+
+use crate::common::{get_home_path, JobRun};
+
+/// Runs kept per job, older ones are dropped
+const KEPT_RUNS: usize = 20;
+
+/// Jobs to run on the next container manager tick regardless of their schedule
+static RUN_REQUESTS: Lazy<std::sync::Mutex<HashSet<(Uuid, String)>>> = Lazy::new(Default::default);
+
+/// Serializes read-modify-write of the run files
+static RUNS_LOCK: Lazy<tokio::sync::Mutex<()>> = Lazy::new(Default::default);
+
+/// Parses a cron expression, five field expressions get a leading `0` seconds field
+pub fn parse_schedule(expression: &str) -> Result<cron::Schedule, String> {
+    let expression = expression.trim();
+    let expression = if expression.split_whitespace().count() == 5 {
+        format!("0 {expression}")
+    } else {
+        expression.to_string()
+    };
+    cron::Schedule::from_str(&expression).map_err(|err| format!("Invalid schedule: {err}"))
+}
+
+pub fn next_run(expression: &str, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+    parse_schedule(expression).ok()?.after(&after).next()
+}
+
+/// Job names end up in file and container names
+pub fn validate_job_name(name: &str) -> Result<(), String> {
+    if name.is_empty()
+        || !name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        return Err("Job names may only contain letters, digits, '-' and '_'".to_string());
+    }
+    Ok(())
+}
+
+pub fn request_run(project_id: Uuid, job: &str) {
+    let mut requests = RUN_REQUESTS.lock().expect("run requests lock poisoned");
+    requests.insert((project_id, job.to_string()));
+}
+
+pub fn take_run_requests() -> Vec<(Uuid, String)> {
+    let mut requests = RUN_REQUESTS.lock().expect("run requests lock poisoned");
+    requests.drain().collect()
+}
+
+fn runs_path(project_id: Uuid, job: &str) -> PathBuf {
+    get_home_path()
+        .join("jobs")
+        .join(project_id.to_string())
+        .join(format!("{job}.json"))
+}
+
+/// Runs of the job, newest first
+pub async fn load_runs(project_id: Uuid, job: &str) -> Vec<JobRun> {
+    let Ok(data) = tokio::fs::read(runs_path(project_id, job)).await else {
+        return vec![];
+    };
+    serde_json::from_slice(&data).unwrap_or_default()
+}
+
+async fn update_runs(
+    project_id: Uuid,
+    job: &str,
+    f: impl FnOnce(&mut Vec<JobRun>),
+) -> anyhow::Result<()> {
+    let _lock = RUNS_LOCK.lock().await;
+    let mut runs = load_runs(project_id, job).await;
+    f(&mut runs);
+    runs.truncate(KEPT_RUNS);
+
+    let path = runs_path(project_id, job);
+    if let Some(parent) = path.parent() {
+        tokio::fs::create_dir_all(parent).await?;
+    }
+    tokio::fs::write(path, serde_json::to_vec(&runs)?).await?;
+    Ok(())
+}
+
+/// Records a run as started
+pub async fn start_run(project_id: Uuid, job: &str, id: Uuid, manual: bool) -> anyhow::Result<()> {
+    let run = JobRun {
+        id,
+        started_at: Utc::now(),
+        finished_at: None,
+        exit_code: None,
+        logs: String::new(),
+        manual,
+    };
+    update_runs(project_id, job, |runs| runs.insert(0, run)).await
+}
+
+pub async fn finish_run(
+    project_id: Uuid,
+    job: &str,
+    run_id: Uuid,
+    exit_code: Option<i64>,
+    logs: String,
+) -> anyhow::Result<()> {
+    update_runs(project_id, job, |runs| {
+        if let Some(run) = runs.iter_mut().find(|run| run.id == run_id) {
+            run.finished_at = Some(Utc::now());
+            run.exit_code = exit_code;
+            run.logs = logs;
+        }
+    })
+    .await
+}
+
+/// Forgets the runs of a removed job
+pub async fn remove_runs(project_id: Uuid, job: &str) {
+    let _lock = RUNS_LOCK.lock().await;
+    let _ = tokio::fs::remove_file(runs_path(project_id, job)).await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn at(hour: u32, min: u32, sec: u32) -> DateTime<Utc> {
+        // A Monday
+        Utc.with_ymd_and_hms(2024, 1, 1, hour, min, sec).unwrap()
+    }
+
+    #[test]
+    fn five_fields_run_on_the_minute() {
+        assert_eq!(next_run("*/15 * * * *", at(0, 7, 30)), Some(at(0, 15, 0)));
+        assert_eq!(next_run("  0 3 * * *  ", at(0, 7, 30)), Some(at(3, 0, 0)));
+    }
+
+    #[test]
+    fn six_fields_keep_their_seconds() {
+        assert_eq!(next_run("30 0 12 * * *", at(0, 7, 30)), Some(at(12, 0, 30)));
+    }
+
+    #[test]
+    fn weekday_names() {
+        assert_eq!(
+            next_run("0 9 * * Mon", at(10, 0, 0)),
+            Some(Utc.with_ymd_and_hms(2024, 1, 8, 9, 0, 0).unwrap())
+        );
+        assert_eq!(
+            next_run("0 9 * * Mon-Fri", at(10, 0, 0)),
+            Some(Utc.with_ymd_and_hms(2024, 1, 2, 9, 0, 0).unwrap())
+        );
+    }
+
+    #[test]
+    fn invalid_schedules() {
+        for expression in ["", "every day", "* * * *", "61 * * * *"] {
+            let err = parse_schedule(expression).unwrap_err();
+            assert!(
+                err.starts_with("Invalid schedule: "),
+                "{expression:?}: {err}"
+            );
+            assert_eq!(next_run(expression, at(0, 0, 0)), None);
+        }
+    }
+}
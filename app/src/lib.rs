@@ -6,6 +6,7 @@ use crate::{
 
 use crate::auth::AuthType;
 use crate::auth::Login;
+use crate::pages::audit::AuditLog;
 use crate::pages::dashboard::Dashboard;
 use crate::pages::home::HomePage;
 use crate::pages::project::container_page::ContainerPage;
@@ -15,7 +16,8 @@ use crate::pages::project::{
     project_page::{DomainsList, ProjectPage},
     ProjectsHome, ProjectsList,
 };
-// use crate::pages::settings::Settings;
+use crate::pages::settings::Settings;
+use crate::pages::users::Users;
 
 use leptos::prelude::*;
 use leptos_meta::*;
@@ -25,18 +27,41 @@ use leptos_router::*;
 // use leptos_toaster::Toaster; (Removed)
 use crate::components::toaster::Toaster;
 
+#[cfg(feature = "ssr")]
+pub mod alerts;
 pub mod api;
+#[cfg(feature = "ssr")]
+pub mod audit;
 pub mod auth;
 pub mod common;
 pub mod components;
+pub mod compose;
 #[cfg(feature = "ssr")]
 pub mod context;
+#[cfg(feature = "ssr")]
+pub mod deploy;
 pub mod error_template;
 pub mod file_manager;
 pub mod hooks;
+#[cfg(feature = "ssr")]
+pub mod jobs;
+#[cfg(feature = "ssr")]
+pub mod log_sinks;
+#[cfg(feature = "ssr")]
+pub mod log_store;
+#[cfg(feature = "ssr")]
+pub mod metrics_store;
 pub mod pages;
+#[cfg(feature = "ssr")]
+pub mod registry_store;
 pub mod updates;
+#[cfg(feature = "ssr")]
+pub mod uptime;
+#[cfg(feature = "ssr")]
+pub mod users;
 pub mod utils;
+#[cfg(feature = "ssr")]
+pub mod webhooks;
 
 pub fn shell(options: LeptosOptions) -> impl IntoView {
     view! {
@@ -118,6 +143,30 @@ pub fn App() -> impl IntoView {
                             }
                         />
 
+                        <Route
+                            ssr=SsrMode::PartiallyBlocked
+                            path=leptos_router::path!("audit")
+                            view=move || view! {
+                                <AuthCheck is_auth_required=true/>
+                                <AuditLog/>
+                            }
+                        />
+
+                        <Route
+                            ssr=SsrMode::PartiallyBlocked
+                            path=leptos_router::path!("users")
+                            view=move || view! {
+                                <AuthCheck is_auth_required=true/>
+                                <Users/>
+                            }
+                        />
+
+                        <Route
+                            ssr=SsrMode::PartiallyBlocked
+                            path=leptos_router::path!("settings")
+                            view=Settings
+                        />
+
                         <ParentRoute
                             ssr=SsrMode::PartiallyBlocked
                             path=leptos_router::path!("projects")
@@ -0,0 +1,390 @@
+//! Delivery of log batches to a project's [`LogSink`](crate::common::LogSink)s. Batching and
+//! retrying is done by the server's log forwarder, this only knows how to talk to each sink.
+
+use std::{collections::BTreeMap, path::PathBuf, time::Duration};
+
+use chrono::{DateTime, Utc};
+use tokio::{
+    io::AsyncWriteExt,
+    net::{TcpStream, UdpSocket},
+};
+
+use crate::common::{get_home_path, LogSinkKind, LogStream, SyslogTransport};
+
+/// Deliveries taking longer than this count as failed
+const DELIVERY_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A line to forward, from a container or the gateway
+#[derive(Clone, Debug)]
+pub struct SinkRecord {
+    /// Container name, `primary` or `gateway`
+    pub source: String,
+    pub stream: LogStream,
+    pub ts: DateTime<Utc>,
+    pub text: String,
+}
+
+impl SinkRecord {
+    pub fn is_gateway(&self) -> bool {
+        self.source == "gateway"
+    }
+}
+
+/// Sends the records of one project in a single batch where the sink allows it
+pub async fn deliver(
+    kind: &LogSinkKind,
+    project_name: &str,
+    records: &[SinkRecord],
+) -> anyhow::Result<()> {
+    let delivery = async {
+        match kind {
+            LogSinkKind::Syslog { address, transport } => {
+                deliver_syslog(address, *transport, project_name, records).await
+            }
+            LogSinkKind::Http { url, authorization } => {
+                deliver_http(url, authorization.as_deref(), project_name, records).await
+            }
+            LogSinkKind::File {
+                path,
+                max_size_mb,
+                max_files,
+            } => deliver_file(path, *max_size_mb, *max_files, project_name, records).await,
+        }
+    };
+    tokio::time::timeout(DELIVERY_TIMEOUT, delivery)
+        .await
+        .map_err(|_| anyhow::anyhow!("Timed out delivering to {kind}"))?
+}
+
+/// `<PRI>1 TIMESTAMP HOSTNAME APP-NAME PROCID MSGID STRUCTURED-DATA MSG` with the user
+/// facility, info for stdout and error for stderr
+fn syslog_message(hostname: &str, project_name: &str, record: &SinkRecord) -> String {
+    let severity = match record.stream {
+        LogStream::Stdout => 6,
+        LogStream::Stderr => 3,
+    };
+    let app_name = syslog_field(project_name, 48);
+    let proc_id = syslog_field(&record.source, 128);
+    format!(
+        "<{}>1 {} {hostname} {app_name} {proc_id} - - {}",
+        8 + severity,
+        record
+            .ts
+            .to_rfc3339_opts(chrono::SecondsFormat::Micros, true),
+        record.text
+    )
+}
+
+/// Header fields are printable ASCII without spaces
+fn syslog_field(value: &str, max_len: usize) -> String {
+    let field = value
+        .chars()
+        .filter(|c| c.is_ascii_graphic())
+        .take(max_len)
+        .collect::<String>();
+    if field.is_empty() {
+        "-".to_string()
+    } else {
+        field
+    }
+}
+
+fn hostname() -> String {
+    std::env::var("HOSTNAME")
+        .ok()
+        .map(|hostname| syslog_field(&hostname, 255))
+        .unwrap_or("selfcloud".to_string())
+}
+
+async fn deliver_syslog(
+    address: &str,
+    transport: SyslogTransport,
+    project_name: &str,
+    records: &[SinkRecord],
+) -> anyhow::Result<()> {
+    let hostname = hostname();
+    match transport {
+        SyslogTransport::Udp => {
+            let socket = UdpSocket::bind("0.0.0.0:0").await?;
+            socket.connect(address).await?;
+            for record in records {
+                socket
+                    .send(syslog_message(&hostname, project_name, record).as_bytes())
+                    .await?;
+            }
+        }
+        SyslogTransport::Tcp => {
+            // RFC6587 octet counting, messages may contain newlines
+            let mut data = Vec::new();
+            for record in records {
+                let message = syslog_message(&hostname, project_name, record);
+                data.extend_from_slice(format!("{} {message}", message.len()).as_bytes());
+            }
+            let mut stream = TcpStream::connect(address).await?;
+            stream.write_all(&data).await?;
+            stream.shutdown().await?;
+        }
+    }
+    Ok(())
+}
+
+async fn deliver_http(
+    url: &str,
+    authorization: Option<&str>,
+    project_name: &str,
+    records: &[SinkRecord],
+) -> anyhow::Result<()> {
+    let mut streams = BTreeMap::<(&str, &str), Vec<[String; 2]>>::new();
+    for record in records {
+        let stream = match record.stream {
+            LogStream::Stdout => "stdout",
+            LogStream::Stderr => "stderr",
+        };
+        let nanos = record
+            .ts
+            .timestamp_nanos_opt()
+            .unwrap_or_default()
+            .to_string();
+        streams
+            .entry((record.source.as_str(), stream))
+            .or_default()
+            .push([nanos, record.text.clone()]);
+    }
+    let body = serde_json::json!({
+        "streams": streams
+            .into_iter()
+            .map(|((source, stream), values)| serde_json::json!({
+                "stream": {
+                    "project": project_name,
+                    "source": source,
+                    "stream": stream,
+                },
+                "values": values,
+            }))
+            .collect::<Vec<_>>(),
+    });
+
+    let mut request = reqwest::Client::new().post(url).json(&body);
+    if let Some(authorization) = authorization {
+        request = request.header(http::header::AUTHORIZATION, authorization);
+    }
+    let response = request.send().await?;
+    if !response.status().is_success() {
+        anyhow::bail!("{url} answered {}", response.status());
+    }
+    Ok(())
+}
+
+/// Relative paths are kept under `log_sinks/` in the SelfCloud home
+fn sink_file_path(path: &str) -> PathBuf {
+    let path = PathBuf::from(path);
+    if path.is_absolute() {
+        path
+    } else {
+        get_home_path().join("log_sinks").join(path)
+    }
+}
+
+async fn deliver_file(
+    path: &str,
+    max_size_mb: u64,
+    max_files: u32,
+    project_name: &str,
+    records: &[SinkRecord],
+) -> anyhow::Result<()> {
+    let path = sink_file_path(path);
+    if let Some(parent) = path.parent() {
+        tokio::fs::create_dir_all(parent).await?;
+    }
+    let size = tokio::fs::metadata(&path)
+        .await
+        .map(|metadata| metadata.len())
+        .unwrap_or_default();
+    if size >= max_size_mb.max(1).saturating_mul(1024 * 1024) {
+        rotate(&path, max_files).await?;
+    }
+
+    let mut data = String::new();
+    for record in records {
+        let stream = match record.stream {
+            LogStream::Stdout => "stdout",
+            LogStream::Stderr => "stderr",
+        };
+        data.push_str(&format!(
+            "{} {project_name} {} {stream} {}\n",
+            record.ts.to_rfc3339(),
+            record.source,
+            record.text
+        ));
+    }
+    let mut file = tokio::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .await?;
+    file.write_all(data.as_bytes()).await?;
+    Ok(())
+}
+
+/// Shifts `path` to `path.1`, `path.1` to `path.2` and so on, dropping the last one
+async fn rotate(path: &std::path::Path, max_files: u32) -> anyhow::Result<()> {
+    let numbered = |n: u32| PathBuf::from(format!("{}.{n}", path.display()));
+    let max_files = max_files.max(1);
+    let _ = tokio::fs::remove_file(numbered(max_files)).await;
+    for n in (1..max_files).rev() {
+        let _ = tokio::fs::rename(numbered(n), numbered(n + 1)).await;
+    }
+    tokio::fs::rename(path, numbered(1)).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::{io::AsyncReadExt, net::TcpListener};
+
+    fn record(source: &str, stream: LogStream, text: &str) -> SinkRecord {
+        SinkRecord {
+            source: source.to_string(),
+            stream,
+            ts: DateTime::from_timestamp(1_700_000_000, 123_456_000).unwrap(),
+            text: text.to_string(),
+        }
+    }
+
+    /// Answers a single request with `204 No Content`, returns its lowercased head and body
+    async fn receive_request(listener: TcpListener) -> (String, Vec<u8>) {
+        let (mut stream, _) = listener.accept().await.unwrap();
+        let mut data = Vec::new();
+        let mut buf = [0; 4096];
+        loop {
+            let len = stream.read(&mut buf).await.unwrap();
+            assert!(len > 0, "connection closed before the request was complete");
+            data.extend_from_slice(&buf[..len]);
+            let Some(end) = data.windows(4).position(|window| window == b"\r\n\r\n") else {
+                continue;
+            };
+            let head = String::from_utf8_lossy(&data[..end]).to_lowercase();
+            let content_length = head
+                .lines()
+                .find_map(|line| line.strip_prefix("content-length:"))
+                .map(|len| len.trim().parse::<usize>().unwrap())
+                .unwrap_or_default();
+            let body = end + 4;
+            if data.len() >= body + content_length {
+                stream
+                    .write_all(b"HTTP/1.1 204 No Content\r\ncontent-length: 0\r\n\r\n")
+                    .await
+                    .unwrap();
+                return (head, data[body..body + content_length].to_vec());
+            }
+        }
+    }
+
+    #[test]
+    fn syslog_message_header() {
+        assert_eq!(
+            syslog_message(
+                "host",
+                "my app",
+                &record("primary", LogStream::Stderr, "boom")
+            ),
+            "<11>1 2023-11-14T22:13:20.123456Z host myapp primary - - boom"
+        );
+        assert_eq!(
+            syslog_message("host", " ", &record("", LogStream::Stdout, "GET /")),
+            "<14>1 2023-11-14T22:13:20.123456Z host - - - - GET /"
+        );
+    }
+
+    #[tokio::test]
+    async fn syslog_over_udp_sends_a_datagram_per_record() {
+        let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let address = socket.local_addr().unwrap().to_string();
+        let records = [
+            record("primary", LogStream::Stdout, "one"),
+            record("worker", LogStream::Stderr, "two"),
+        ];
+
+        deliver_syslog(&address, SyslogTransport::Udp, "app", &records)
+            .await
+            .unwrap();
+        let mut buf = [0; 1024];
+        for record in records.iter() {
+            let len = socket.recv(&mut buf).await.unwrap();
+            assert_eq!(
+                std::str::from_utf8(&buf[..len]).unwrap(),
+                syslog_message(&hostname(), "app", record)
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn syslog_over_tcp_counts_octets() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        let received = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut data = String::new();
+            stream.read_to_string(&mut data).await.unwrap();
+            data
+        });
+        let records = [
+            record("primary", LogStream::Stdout, "multi\nline"),
+            record("worker", LogStream::Stderr, "ünïcödé"),
+        ];
+
+        deliver_syslog(&address, SyslogTransport::Tcp, "app", &records)
+            .await
+            .unwrap();
+        let data = received.await.unwrap();
+        let mut rest = data.as_str();
+        for record in records.iter() {
+            let (len, message) = rest.split_once(' ').unwrap();
+            let len = len.parse::<usize>().unwrap();
+            assert_eq!(&message[..len], syslog_message(&hostname(), "app", record));
+            rest = &message[len..];
+        }
+        assert!(rest.is_empty(), "{rest:?} left over");
+    }
+
+    #[tokio::test]
+    async fn http_pushes_loki_streams() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/loki/api/v1/push", listener.local_addr().unwrap());
+        let received = tokio::spawn(receive_request(listener));
+        let records = [
+            record("primary", LogStream::Stdout, "one"),
+            record("gateway", LogStream::Stdout, "GET /"),
+            record("primary", LogStream::Stderr, "oops"),
+            record("primary", LogStream::Stdout, "two"),
+        ];
+
+        deliver_http(&url, Some("Bearer secret"), "app", &records)
+            .await
+            .unwrap();
+        let (head, body) = received.await.unwrap();
+        assert!(head.starts_with("post /loki/api/v1/push "), "{head}");
+        assert!(head.contains("authorization: bearer secret"), "{head}");
+        let ts = "1700000000123456000";
+        assert_eq!(
+            serde_json::from_slice::<serde_json::Value>(&body).unwrap(),
+            serde_json::json!({
+                "streams": [
+                    {
+                        "stream": { "project": "app", "source": "gateway", "stream": "stdout" },
+                        "values": [[ts, "GET /"]],
+                    },
+                    {
+                        "stream": { "project": "app", "source": "primary", "stream": "stderr" },
+                        "values": [[ts, "oops"]],
+                    },
+                    {
+                        "stream": { "project": "app", "source": "primary", "stream": "stdout" },
+                        "values": [[ts, "one"], [ts, "two"]],
+                    },
+                ],
+            })
+        );
+    }
+}
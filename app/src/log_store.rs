@@ -0,0 +1,319 @@
+//! Container output persisted by the log collector so it outlives the containers.
+//!
+//! Every container of a project gets a directory under `logs/<project id>/`, holding
+//! segments named `<container id>_<unix start>.jsonl` with one [`LogLine`] per line. A
+//! segment is closed once it grows past [`SEGMENT_BYTES`] so retention can drop the old
+//! output of long running containers too.
+
+use std::{
+    collections::VecDeque,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+use chrono::{DateTime, Utc};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use uuid::Uuid;
+
+use crate::common::{
+    get_home_path, ContainerSlot, LogLine, LogMatch, LogQuery, LogRetention, LogSearch,
+};
+
+const SEGMENT_BYTES: u64 = 16 * 1024 * 1024;
+
+/// Matches returned by a search, the download endpoint is not limited
+pub const MAX_SEARCH_MATCHES: usize = 2000;
+
+fn project_dir(project_id: Uuid) -> PathBuf {
+    get_home_path().join("logs").join(project_id.to_string())
+}
+
+fn slot_dir(project_id: Uuid, slot: &ContainerSlot) -> PathBuf {
+    project_dir(project_id).join(match slot {
+        ContainerSlot::Primary => "primary".to_string(),
+        ContainerSlot::Support(name) => format!("support_{name}"),
+    })
+}
+
+struct Segment {
+    path: PathBuf,
+    version: String,
+    started: i64,
+    size: u64,
+    modified: SystemTime,
+}
+
+/// Segments in `dir`, oldest first
+async fn segments(dir: &Path) -> Vec<Segment> {
+    let mut segments = vec![];
+    let Ok(mut entries) = tokio::fs::read_dir(dir).await else {
+        return segments;
+    };
+    while let Ok(Some(entry)) = entries.next_entry().await {
+        let path = entry.path();
+        let Some((version, started)) = path
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .and_then(|stem| stem.rsplit_once('_'))
+            .and_then(|(version, started)| Some((version.to_string(), started.parse().ok()?)))
+        else {
+            continue;
+        };
+        let Ok(metadata) = entry.metadata().await else {
+            continue;
+        };
+        segments.push(Segment {
+            path,
+            version,
+            started,
+            size: metadata.len(),
+            modified: metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH),
+        });
+    }
+    segments.sort_by_key(|segment| segment.started);
+    segments
+}
+
+/// Appends the output of one container to its newest segment
+pub struct LogWriter {
+    dir: PathBuf,
+    version: String,
+    file: tokio::fs::File,
+    written: u64,
+}
+
+impl LogWriter {
+    pub async fn open(
+        project_id: Uuid,
+        slot: &ContainerSlot,
+        version: &str,
+    ) -> anyhow::Result<Self> {
+        let dir = slot_dir(project_id, slot);
+        tokio::fs::create_dir_all(&dir).await?;
+        let current = segments(&dir)
+            .await
+            .into_iter()
+            .rev()
+            .find(|segment| segment.version == version && segment.size < SEGMENT_BYTES);
+        let (path, written) = match current {
+            Some(segment) => (segment.path, segment.size),
+            None => (segment_path(&dir, version), 0),
+        };
+        Ok(Self {
+            file: open_append(&path).await?,
+            dir,
+            version: version.to_string(),
+            written,
+        })
+    }
+
+    pub async fn write(&mut self, line: &LogLine) -> anyhow::Result<()> {
+        if self.written >= SEGMENT_BYTES {
+            self.file = open_append(&segment_path(&self.dir, &self.version)).await?;
+            self.written = 0;
+        }
+        let mut data = serde_json::to_vec(line)?;
+        data.push(b'\n');
+        self.file.write_all(&data).await?;
+        self.written += data.len() as u64;
+        Ok(())
+    }
+}
+
+fn segment_path(dir: &Path, version: &str) -> PathBuf {
+    dir.join(format!("{version}_{}.jsonl", Utc::now().timestamp()))
+}
+
+async fn open_append(path: &Path) -> std::io::Result<tokio::fs::File> {
+    tokio::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .await
+}
+
+/// Time of the last persisted line of a container, to resume collecting after it
+pub async fn last_timestamp(
+    project_id: Uuid,
+    slot: &ContainerSlot,
+    version: &str,
+) -> Option<DateTime<Utc>> {
+    let segment = segments(&slot_dir(project_id, slot))
+        .await
+        .into_iter()
+        .rev()
+        .find(|segment| segment.version == version)?;
+    let data = tokio::fs::read_to_string(segment.path).await.ok()?;
+    data.lines()
+        .rev()
+        .find_map(|line| serde_json::from_str::<LogLine>(line).ok())
+        .map(|line| line.ts)
+}
+
+/// Calls `f` with every persisted line of the queried container that matches, oldest
+/// segment first
+pub async fn for_each_match(
+    project_id: Uuid,
+    query: &LogQuery,
+    mut f: impl FnMut(LogMatch),
+) -> anyhow::Result<()> {
+    let since = query.since_time().map_err(anyhow::Error::msg)?;
+    let until = query.until_time().map_err(anyhow::Error::msg)?;
+    let text = query.text.to_lowercase();
+
+    for segment in segments(&slot_dir(project_id, &query.slot())).await {
+        let after_until = until.is_some_and(|until| segment.started > until.timestamp());
+        let before_since =
+            since.is_some_and(|since| DateTime::<Utc>::from(segment.modified) < since);
+        if after_until || before_since {
+            continue;
+        }
+        let file = tokio::fs::File::open(&segment.path).await?;
+        let mut lines = BufReader::new(file).lines();
+        while let Some(line) = lines.next_line().await? {
+            let Ok(line) = serde_json::from_str::<LogLine>(&line) else {
+                continue;
+            };
+            if since.is_some_and(|since| line.ts < since)
+                || until.is_some_and(|until| line.ts > until)
+                || (!text.is_empty() && !line.text.to_lowercase().contains(&text))
+            {
+                continue;
+            }
+            f(LogMatch {
+                version: segment.version.clone(),
+                line,
+            });
+        }
+    }
+    Ok(())
+}
+
+/// The newest [`MAX_SEARCH_MATCHES`] matches of the query
+pub async fn search(project_id: Uuid, query: &LogQuery) -> anyhow::Result<LogSearch> {
+    let mut matches = VecDeque::new();
+    let mut truncated = false;
+    for_each_match(project_id, query, |found| {
+        matches.push_back(found);
+        if matches.len() > MAX_SEARCH_MATCHES {
+            matches.pop_front();
+            truncated = true;
+        }
+    })
+    .await?;
+    Ok(LogSearch {
+        matches: matches.into(),
+        truncated,
+    })
+}
+
+/// Drops segments past the project's retention, oldest first. The newest segment of
+/// every container is kept since the collector may still be writing to it.
+pub async fn enforce_retention(project_id: Uuid, retention: &LogRetention) -> anyhow::Result<()> {
+    let max_age = Duration::from_secs(u64::from(retention.max_age_days) * 24 * 60 * 60);
+    let mut removable = vec![];
+    let mut total_size = 0;
+    let Ok(mut slots) = tokio::fs::read_dir(project_dir(project_id)).await else {
+        return Ok(());
+    };
+    while let Some(slot) = slots.next_entry().await? {
+        let mut slot_segments = segments(&slot.path()).await;
+        if let Some(newest) = slot_segments.pop() {
+            total_size += newest.size;
+        }
+        removable.extend(slot_segments);
+    }
+    removable.sort_by_key(|segment| segment.modified);
+    total_size += removable.iter().map(|segment| segment.size).sum::<u64>();
+
+    let max_size = retention.max_size_mb.saturating_mul(1024 * 1024);
+    for segment in removable {
+        let expired = segment
+            .modified
+            .elapsed()
+            .is_ok_and(|elapsed| elapsed > max_age);
+        if !expired && total_size <= max_size {
+            break;
+        }
+        tokio::fs::remove_file(&segment.path).await?;
+        total_size -= segment.size;
+    }
+    Ok(())
+}
+
+/// Forgets the logs of a deleted project
+pub async fn remove_project_logs(project_id: Uuid) {
+    let _ = tokio::fs::remove_dir_all(project_dir(project_id)).await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MB: u64 = 1024 * 1024;
+
+    /// A segment of `size` bytes last written `age_days` ago
+    fn write_segment(project_id: Uuid, slot: &str, started: i64, size: u64, age_days: u64) {
+        let dir = project_dir(project_id).join(slot);
+        std::fs::create_dir_all(&dir).unwrap();
+        let file = std::fs::File::create(dir.join(format!("v1_{started}.jsonl"))).unwrap();
+        file.set_len(size).unwrap();
+        let modified = SystemTime::now() - Duration::from_secs(age_days * 24 * 60 * 60);
+        // Later segments of the same day are written later
+        file.set_modified(modified - Duration::from_secs(100 - started as u64))
+            .unwrap();
+    }
+
+    fn remaining(project_id: Uuid, slot: &str) -> Vec<String> {
+        let mut names = std::fs::read_dir(project_dir(project_id).join(slot))
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+            .collect::<Vec<_>>();
+        names.sort();
+        names
+    }
+
+    #[tokio::test]
+    async fn oldest_segments_go_until_the_size_fits() {
+        crate::common::test_home();
+        let project_id = Uuid::new_v4();
+        for started in 1..=3 {
+            write_segment(project_id, "primary", started, MB, 0);
+        }
+        write_segment(project_id, "support_db", 1, MB, 0);
+        let retention = LogRetention {
+            max_age_days: 30,
+            max_size_mb: 3,
+        };
+
+        enforce_retention(project_id, &retention).await.unwrap();
+        assert_eq!(
+            remaining(project_id, "primary"),
+            ["v1_2.jsonl", "v1_3.jsonl"]
+        );
+        assert_eq!(remaining(project_id, "support_db"), ["v1_1.jsonl"]);
+        remove_project_logs(project_id).await;
+    }
+
+    #[tokio::test]
+    async fn expired_segments_go_but_the_newest_stays() {
+        crate::common::test_home();
+        let project_id = Uuid::new_v4();
+        write_segment(project_id, "primary", 1, 10, 20);
+        write_segment(project_id, "primary", 2, 10, 5);
+        write_segment(project_id, "primary", 3, 10, 1);
+        write_segment(project_id, "support_db", 1, 10, 20);
+        let retention = LogRetention {
+            max_age_days: 7,
+            max_size_mb: u64::MAX,
+        };
+
+        enforce_retention(project_id, &retention).await.unwrap();
+        assert_eq!(
+            remaining(project_id, "primary"),
+            ["v1_2.jsonl", "v1_3.jsonl"]
+        );
+        assert_eq!(remaining(project_id, "support_db"), ["v1_1.jsonl"]);
+        remove_project_logs(project_id).await;
+    }
+}
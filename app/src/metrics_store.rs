@@ -0,0 +1,263 @@
+//! Downsampled container and host metrics kept by the metrics collector.
+//!
+//! Every series, the host or one container slot of a project, gets a directory under
+//! `metrics/` with one JSONL file of [`MetricPoint`]s per [`MetricsRange`]. Samples are
+//! averaged into the resolution of each range before they are written and [`compact`]
+//! drops points older than their range, so the files stay at a few thousand lines.
+
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+use chrono::{DateTime, Utc};
+use tokio::io::AsyncWriteExt;
+use uuid::Uuid;
+
+use crate::common::{get_home_path, ContainerSlot, MetricPoint, MetricsRange};
+
+fn metrics_dir() -> PathBuf {
+    get_home_path().join("metrics")
+}
+
+pub enum MetricsSeries {
+    Host,
+    Container(Uuid, ContainerSlot),
+}
+
+impl MetricsSeries {
+    fn dir(&self) -> PathBuf {
+        match self {
+            MetricsSeries::Host => metrics_dir().join("host"),
+            MetricsSeries::Container(project_id, slot) => {
+                metrics_dir().join(project_id.to_string()).join(match slot {
+                    ContainerSlot::Primary => "primary".to_string(),
+                    ContainerSlot::Support(name) => format!("support_{name}"),
+                })
+            }
+        }
+    }
+}
+
+fn range_path(dir: &Path, range: MetricsRange) -> PathBuf {
+    dir.join(format!("{}.jsonl", range.label()))
+}
+
+struct Bucket {
+    start: i64,
+    samples: Vec<MetricPoint>,
+}
+
+impl Bucket {
+    /// The bucket's samples as one point at the start of the bucket
+    fn average(&self) -> Option<MetricPoint> {
+        let count = self.samples.len() as f64;
+        if self.samples.is_empty() {
+            return None;
+        }
+        let mean =
+            |value: fn(&MetricPoint) -> f64| self.samples.iter().map(value).sum::<f64>() / count;
+        let mean_opt = |value: fn(&MetricPoint) -> Option<f64>| {
+            let values = self.samples.iter().filter_map(value).collect::<Vec<_>>();
+            (!values.is_empty()).then(|| values.iter().sum::<f64>() / values.len() as f64)
+        };
+        Some(MetricPoint {
+            ts: DateTime::from_timestamp(self.start, 0)?,
+            cpu_percent: mean(|point| point.cpu_percent),
+            memory_bytes: mean(|point| point.memory_bytes),
+            net_rx_bytes: mean(|point| point.net_rx_bytes),
+            net_tx_bytes: mean(|point| point.net_tx_bytes),
+            block_read_bytes: mean_opt(|point| point.block_read_bytes),
+            block_write_bytes: mean_opt(|point| point.block_write_bytes),
+        })
+    }
+}
+
+/// Averages samples into the resolution of every range. A bucket is written once a sample
+/// of the next bucket arrives, buckets still open on shutdown are lost.
+#[derive(Default)]
+pub struct MetricsRecorder {
+    buckets: HashMap<(PathBuf, MetricsRange), Bucket>,
+}
+
+impl MetricsRecorder {
+    pub async fn record(
+        &mut self,
+        series: &MetricsSeries,
+        sample: MetricPoint,
+    ) -> anyhow::Result<()> {
+        let dir = series.dir();
+        let ts = sample.ts.timestamp();
+        for range in MetricsRange::ALL {
+            let start = ts - ts.rem_euclid(range.resolution_secs());
+            let bucket = self
+                .buckets
+                .entry((dir.clone(), range))
+                .or_insert_with(|| Bucket {
+                    start,
+                    samples: vec![],
+                });
+            if bucket.start != start {
+                let closed = std::mem::replace(
+                    bucket,
+                    Bucket {
+                        start,
+                        samples: vec![],
+                    },
+                );
+                if let Some(point) = closed.average() {
+                    append(&dir, range, &point).await?;
+                }
+            }
+            bucket.samples.push(sample.clone());
+        }
+        Ok(())
+    }
+}
+
+async fn append(dir: &Path, range: MetricsRange, point: &MetricPoint) -> anyhow::Result<()> {
+    tokio::fs::create_dir_all(dir).await?;
+    let mut data = serde_json::to_vec(point)?;
+    data.push(b'\n');
+    let mut file = tokio::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(range_path(dir, range))
+        .await?;
+    file.write_all(&data).await?;
+    Ok(())
+}
+
+async fn read_points(path: &Path, since: DateTime<Utc>) -> Vec<MetricPoint> {
+    let Ok(data) = tokio::fs::read_to_string(path).await else {
+        return vec![];
+    };
+    data.lines()
+        .filter_map(|line| serde_json::from_str::<MetricPoint>(line).ok())
+        .filter(|point| point.ts >= since)
+        .collect()
+}
+
+/// Stored points of the series within the range, oldest first
+pub async fn query(series: &MetricsSeries, range: MetricsRange) -> Vec<MetricPoint> {
+    read_points(
+        &range_path(&series.dir(), range),
+        Utc::now() - range.duration(),
+    )
+    .await
+}
+
+/// Rewrites the files of every series without the points older than their range
+pub async fn compact() -> anyhow::Result<()> {
+    let mut dirs = vec![];
+    let Ok(mut entries) = tokio::fs::read_dir(metrics_dir()).await else {
+        return Ok(());
+    };
+    while let Some(entry) = entries.next_entry().await? {
+        if entry.file_name() == "host" {
+            dirs.push(entry.path());
+            continue;
+        }
+        let Ok(mut slots) = tokio::fs::read_dir(entry.path()).await else {
+            continue;
+        };
+        while let Some(slot) = slots.next_entry().await? {
+            dirs.push(slot.path());
+        }
+    }
+
+    for dir in dirs {
+        for range in MetricsRange::ALL {
+            let path = range_path(&dir, range);
+            if !tokio::fs::try_exists(&path).await.unwrap_or_default() {
+                continue;
+            }
+            let mut data = vec![];
+            for point in read_points(&path, Utc::now() - range.duration()).await {
+                data.extend(serde_json::to_vec(&point)?);
+                data.push(b'\n');
+            }
+            let temp = path.with_extension("jsonl.tmp");
+            tokio::fs::write(&temp, data).await?;
+            tokio::fs::rename(&temp, &path).await?;
+        }
+    }
+    Ok(())
+}
+
+/// Forgets the metrics of a deleted project
+pub async fn remove_project_metrics(project_id: Uuid) {
+    let _ = tokio::fs::remove_dir_all(metrics_dir().join(project_id.to_string())).await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(ts: i64, cpu_percent: f64, block_read_bytes: Option<f64>) -> MetricPoint {
+        MetricPoint {
+            ts: DateTime::from_timestamp(ts, 0).unwrap(),
+            cpu_percent,
+            memory_bytes: 100.0,
+            net_rx_bytes: 0.0,
+            net_tx_bytes: 0.0,
+            block_read_bytes,
+            block_write_bytes: None,
+        }
+    }
+
+    #[test]
+    fn buckets_average_their_samples() {
+        let bucket = Bucket {
+            start: 60,
+            samples: vec![
+                sample(61, 10.0, Some(4.0)),
+                sample(65, 30.0, None),
+                sample(69, 20.0, Some(8.0)),
+            ],
+        };
+        let point = bucket.average().unwrap();
+        assert_eq!(point.ts.timestamp(), 60);
+        assert_eq!(point.cpu_percent, 20.0);
+        assert_eq!(point.memory_bytes, 100.0);
+        // Samples without a value do not count towards its average
+        assert_eq!(point.block_read_bytes, Some(6.0));
+        assert_eq!(point.block_write_bytes, None);
+
+        let empty = Bucket {
+            start: 60,
+            samples: vec![],
+        };
+        assert_eq!(empty.average(), None);
+    }
+
+    #[tokio::test]
+    async fn samples_are_written_once_their_bucket_closes() {
+        crate::common::test_home();
+        let project_id = Uuid::new_v4();
+        let series = MetricsSeries::Container(project_id, ContainerSlot::Primary);
+        let recent = Utc::now().timestamp() - 40 * 60;
+        let start = recent - recent.rem_euclid(MetricsRange::Week.resolution_secs());
+
+        let mut recorder = MetricsRecorder::default();
+        for (offset, cpu_percent) in [(0, 10.0), (5, 20.0), (10, 30.0), (60, 40.0)] {
+            recorder
+                .record(&series, sample(start + offset, cpu_percent, None))
+                .await
+                .unwrap();
+        }
+
+        let hour = query(&series, MetricsRange::Hour).await;
+        let cpu = |points: &[MetricPoint]| {
+            points
+                .iter()
+                .map(|point| (point.ts.timestamp() - start, point.cpu_percent))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(cpu(&hour), [(0, 15.0), (10, 30.0)]);
+        let day = query(&series, MetricsRange::Day).await;
+        assert_eq!(cpu(&day), [(0, 20.0)]);
+        assert!(query(&series, MetricsRange::Week).await.is_empty());
+        remove_project_metrics(project_id).await;
+    }
+}
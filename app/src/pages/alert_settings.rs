@@ -0,0 +1,383 @@
+use leptos::prelude::*;
+use uuid::Uuid;
+
+use crate::api::{
+    get_alert_settings, get_firing_alerts, get_projects, AddAlertRule, AddNotificationChannel,
+    DeleteAlertRule, DeleteNotificationChannel, SetAlertRuleEnabled, SetSmtpSettings,
+    TestNotificationChannel,
+};
+use crate::components::toaster::{ToastVariant, ToasterContext};
+
+/// Alert rules, the channels notifications go to and the SMTP server for email channels
+#[component]
+pub fn AlertSettingsPanel() -> impl IntoView {
+    let add_rule_action = ServerAction::<AddAlertRule>::new();
+    let enable_rule_action = ServerAction::<SetAlertRuleEnabled>::new();
+    let delete_rule_action = ServerAction::<DeleteAlertRule>::new();
+    let add_channel_action = ServerAction::<AddNotificationChannel>::new();
+    let delete_channel_action = ServerAction::<DeleteNotificationChannel>::new();
+    let test_channel_action = ServerAction::<TestNotificationChannel>::new();
+    let smtp_action = ServerAction::<SetSmtpSettings>::new();
+    let toast_context = expect_context::<ToasterContext>();
+
+    let settings = Resource::new(
+        move || {
+            (
+                add_rule_action.version().get(),
+                enable_rule_action.version().get(),
+                delete_rule_action.version().get(),
+                add_channel_action.version().get(),
+                delete_channel_action.version().get(),
+                smtp_action.version().get(),
+            )
+        },
+        |_| get_alert_settings(),
+    );
+    let firing = Resource::new(|| (), |_| get_firing_alerts());
+    let projects = Resource::new(|| (), |_| get_projects());
+    let (condition, set_condition) = signal("container_crashed".to_string());
+    let (channel_kind, set_channel_kind) = signal("generic".to_string());
+
+    let toast_outcome = |value: RwSignal<Option<Result<(), ServerFnError>>>,
+                         success: Option<&'static str>| {
+        let toast_context = toast_context.clone();
+        Effect::new(move |_| match value.get() {
+            Some(Ok(_)) => {
+                if let Some(success) = success {
+                    toast_context.toast(success, ToastVariant::Success);
+                }
+            }
+            Some(Err(err)) => toast_context.toast(err.to_string(), ToastVariant::Error),
+            None => {}
+        });
+    };
+    toast_outcome(add_rule_action.value(), Some("Alert Rule Added"));
+    toast_outcome(enable_rule_action.value(), None);
+    toast_outcome(delete_rule_action.value(), Some("Alert Rule Deleted"));
+    toast_outcome(add_channel_action.value(), Some("Channel Added"));
+    toast_outcome(delete_channel_action.value(), Some("Channel Deleted"));
+    toast_outcome(test_channel_action.value(), Some("Test Notification Sent"));
+    toast_outcome(smtp_action.value(), Some("SMTP Server Saved"));
+
+    let project_name = move |project_id: Option<Uuid>| match project_id {
+        None => "All projects".to_string(),
+        Some(project_id) => projects
+            .get()
+            .and_then(|projects| projects.ok())
+            .and_then(|projects| {
+                projects
+                    .into_iter()
+                    .find(|project| project.id == project_id)
+                    .map(|project| project.name)
+            })
+            .unwrap_or(project_id.to_string()),
+    };
+    let input_class = "border p-2 rounded-md dark:bg-white/10 dark:border-white/5";
+    let submit_class = "cursor-pointer self-start border p-2 px-10 rounded bg-slate-800 text-white dark:border-none dark:bg-white/90 dark:text-black";
+
+    view! {
+        <div class="bg-white dark:bg-zinc-900 rounded-lg p-6 shadow mt-4 flex flex-col gap-4">
+            <h2 class="text-xl font-semibold">"Alerts"</h2>
+            <Transition>
+                {move || {
+                    firing
+                        .get()
+                        .and_then(|firing| firing.ok())
+                        .map(|firing| {
+                            if firing.is_empty() {
+                                view! { <div class="text-sm text-green-600">"Nothing is firing"</div> }
+                                    .into_any()
+                            } else {
+                                firing
+                                    .into_iter()
+                                    .map(|alert| {
+                                        view! {
+                                            <div class="text-sm p-2 rounded border border-red-600">
+                                                <div class="font-semibold text-red-600">{alert.summary}</div>
+                                                <div class="dark:text-white/50">
+                                                    {alert.details} " · since "
+                                                    {alert.since.format("%Y-%m-%d %H:%M UTC").to_string()}
+                                                </div>
+                                            </div>
+                                        }
+                                    })
+                                    .collect_view()
+                                    .into_any()
+                            }
+                        })
+                }}
+            </Transition>
+
+            <div class="text-lg">"Rules"</div>
+            <Transition>
+                {move || {
+                    settings
+                        .get()
+                        .and_then(|settings| settings.ok())
+                        .map(|settings| {
+                            settings
+                                .rules
+                                .into_iter()
+                                .map(|rule| {
+                                    let rule_id = rule.id;
+                                    let enabled = rule.enabled;
+                                    view! {
+                                        <div class="flex gap-4 items-center text-sm p-2 rounded border dark:border-white/10">
+                                            <div class="flex flex-col grow">
+                                                <div class="font-semibold">
+                                                    {rule.condition.to_string()}
+                                                    {(!enabled).then(|| " (disabled)")}
+                                                </div>
+                                                <div class="dark:text-white/50">
+                                                    {move || project_name(rule.project_id)}
+                                                </div>
+                                            </div>
+                                            <button
+                                                type="button"
+                                                class="p-2 px-4 rounded border"
+                                                on:click=move |_| {
+                                                    enable_rule_action
+                                                        .dispatch(SetAlertRuleEnabled {
+                                                            rule_id,
+                                                            enabled: !enabled,
+                                                        });
+                                                }
+                                            >
+                                                {if enabled { "Disable" } else { "Enable" }}
+                                            </button>
+                                            <button
+                                                type="button"
+                                                class="p-2 px-4 rounded border border-red-600 text-red-600"
+                                                on:click=move |_| {
+                                                    delete_rule_action.dispatch(DeleteAlertRule { rule_id });
+                                                }
+                                            >
+                                                "Delete"
+                                            </button>
+                                        </div>
+                                    }
+                                })
+                                .collect_view()
+                        })
+                }}
+            </Transition>
+            <ActionForm action=add_rule_action>
+                <div class="flex gap-2 items-end flex-wrap">
+                    <div class="flex flex-col">
+                        <label class="text-sm dark:text-white/50">"Condition"</label>
+                        <select
+                            name="condition"
+                            class=input_class
+                            on:change=move |ev| set_condition.set(event_target_value(&ev))
+                        >
+                            <option value="container_crashed">"Container crashed"</option>
+                            <option value="cpu_above">"Container CPU above"</option>
+                            <option value="host_disk_above">"Host disk above"</option>
+                            <option value="certificate_failed">"Certificate renewal failed"</option>
+                        </select>
+                    </div>
+                    <Show when=move || {
+                        matches!(condition.get().as_str(), "cpu_above" | "host_disk_above")
+                    }>
+                        <div class="flex flex-col">
+                            <label class="text-sm dark:text-white/50">"Threshold (%)"</label>
+                            <input
+                                type="number"
+                                name="percent"
+                                min="1"
+                                max="100"
+                                step="any"
+                                value=move || if condition.get() == "cpu_above" { "90" } else { "85" }
+                                class=format!("{input_class} w-28")
+                            />
+                        </div>
+                    </Show>
+                    <Show when=move || condition.get() == "cpu_above">
+                        <div class="flex flex-col">
+                            <label class="text-sm dark:text-white/50">"For (minutes)"</label>
+                            <input
+                                type="number"
+                                name="minutes"
+                                min="1"
+                                max="60"
+                                value="5"
+                                class=format!("{input_class} w-28")
+                            />
+                        </div>
+                    </Show>
+                    <div class="flex flex-col">
+                        <label class="text-sm dark:text-white/50">"Project"</label>
+                        <select
+                            name="project_id"
+                            class=input_class
+                            disabled=move || condition.get() == "host_disk_above"
+                        >
+                            <option value="">"All projects"</option>
+                            {move || {
+                                projects
+                                    .get()
+                                    .and_then(|projects| projects.ok())
+                                    .unwrap_or_default()
+                                    .into_iter()
+                                    .map(|project| {
+                                        view! {
+                                            <option value=project.id.to_string()>{project.name}</option>
+                                        }
+                                    })
+                                    .collect_view()
+                            }}
+                        </select>
+                    </div>
+                    <input type="submit" value="Add Rule" class=submit_class/>
+                </div>
+            </ActionForm>
+
+            <div class="text-lg">"Notification Channels"</div>
+            <Transition>
+                {move || {
+                    settings
+                        .get()
+                        .and_then(|settings| settings.ok())
+                        .map(|settings| {
+                            settings
+                                .channels
+                                .into_iter()
+                                .map(|channel| {
+                                    let channel_id = channel.id;
+                                    view! {
+                                        <div class="flex gap-4 items-center text-sm p-2 rounded border dark:border-white/10">
+                                            <div class="grow font-mono truncate">{channel.kind.to_string()}</div>
+                                            <button
+                                                type="button"
+                                                class="p-2 px-4 rounded border disabled:opacity-50"
+                                                disabled=move || test_channel_action.pending().get()
+                                                on:click=move |_| {
+                                                    test_channel_action
+                                                        .dispatch(TestNotificationChannel { channel_id });
+                                                }
+                                            >
+                                                "Send Test"
+                                            </button>
+                                            <button
+                                                type="button"
+                                                class="p-2 px-4 rounded border border-red-600 text-red-600"
+                                                on:click=move |_| {
+                                                    delete_channel_action
+                                                        .dispatch(DeleteNotificationChannel { channel_id });
+                                                }
+                                            >
+                                                "Delete"
+                                            </button>
+                                        </div>
+                                    }
+                                })
+                                .collect_view()
+                        })
+                }}
+            </Transition>
+            <ActionForm action=add_channel_action>
+                <div class="flex gap-2 items-end flex-wrap">
+                    <div class="flex flex-col">
+                        <label class="text-sm dark:text-white/50">"Kind"</label>
+                        <select
+                            name="kind"
+                            class=input_class
+                            on:change=move |ev| set_channel_kind.set(event_target_value(&ev))
+                        >
+                            <option value="generic">"Webhook (JSON)"</option>
+                            <option value="slack">"Slack webhook"</option>
+                            <option value="discord">"Discord webhook"</option>
+                            <option value="email">"Email"</option>
+                        </select>
+                    </div>
+                    <div class="flex flex-col grow">
+                        <label class="text-sm dark:text-white/50">
+                            {move || if channel_kind.get() == "email" { "Recipient" } else { "URL" }}
+                        </label>
+                        <input type="text" name="target" class=input_class/>
+                    </div>
+                    <input type="submit" value="Add Channel" class=submit_class/>
+                </div>
+            </ActionForm>
+
+            <div class="text-lg">"SMTP Server"</div>
+            <div class="text-sm dark:text-white/50">
+                "Used by email channels. Leave the host empty to remove it, the password is kept when left empty"
+            </div>
+            <Transition>
+                {move || {
+                    settings
+                        .get()
+                        .and_then(|settings| settings.ok())
+                        .map(|settings| {
+                            let smtp = settings.smtp;
+                            let value = |f: fn(&crate::common::SmtpSettings) -> String| {
+                                smtp.as_ref().map(f).unwrap_or_default()
+                            };
+                            view! {
+                                <ActionForm action=smtp_action>
+                                    <div class="flex gap-2 items-end flex-wrap">
+                                        <div class="flex flex-col">
+                                            <label class="text-sm dark:text-white/50">"Host"</label>
+                                            <input
+                                                type="text"
+                                                name="host"
+                                                value=value(|smtp| smtp.host.clone())
+                                                class=input_class
+                                            />
+                                        </div>
+                                        <div class="flex flex-col">
+                                            <label class="text-sm dark:text-white/50">"Port"</label>
+                                            <input
+                                                type="number"
+                                                name="port"
+                                                value=smtp
+                                                    .as_ref()
+                                                    .map(|smtp| smtp.port)
+                                                    .unwrap_or(587)
+                                                    .to_string()
+                                                class=format!("{input_class} w-24")
+                                            />
+                                        </div>
+                                        <div class="flex flex-col">
+                                            <label class="text-sm dark:text-white/50">"Username"</label>
+                                            <input
+                                                type="text"
+                                                name="username"
+                                                value=value(|smtp| smtp.username.clone())
+                                                class=input_class
+                                            />
+                                        </div>
+                                        <div class="flex flex-col">
+                                            <label class="text-sm dark:text-white/50">"Password"</label>
+                                            <input type="password" name="password" class=input_class/>
+                                        </div>
+                                        <div class="flex flex-col">
+                                            <label class="text-sm dark:text-white/50">"Sender"</label>
+                                            <input
+                                                type="text"
+                                                name="from"
+                                                value=value(|smtp| smtp.from.clone())
+                                                placeholder="SelfCloud <alerts@example.com>"
+                                                class=input_class
+                                            />
+                                        </div>
+                                        <label class="flex gap-2 items-center text-sm p-2">
+                                            <input
+                                                type="checkbox"
+                                                name="starttls"
+                                                value="true"
+                                                checked=smtp.as_ref().is_none_or(|smtp| smtp.starttls)
+                                            />
+                                            "STARTTLS"
+                                        </label>
+                                        <input type="submit" value="Save" class=submit_class/>
+                                    </div>
+                                </ActionForm>
+                            }
+                        })
+                }}
+            </Transition>
+        </div>
+    }
+}
@@ -0,0 +1,215 @@
+use leptos::prelude::*;
+use uuid::Uuid;
+
+use crate::api::{get_projects, GetAuditLog};
+use crate::common::{AuditEntry, AuditQuery};
+
+/// Who changed what across all projects, newest first
+#[component]
+pub fn AuditLog() -> impl IntoView {
+    let search_action = ServerAction::<GetAuditLog>::new();
+    let projects = Resource::new(|| (), |_| get_projects());
+    let (actor, set_actor) = signal(String::new());
+    let (action, set_action) = signal(String::new());
+    let (project_id, set_project_id) = signal(None::<Uuid>);
+    let (since, set_since) = signal(String::new());
+    let (until, set_until) = signal(String::new());
+
+    let query = move || AuditQuery {
+        actor: actor.get(),
+        action: action.get(),
+        project_id: project_id.get(),
+        since: Some(since.get()).filter(|since| !since.is_empty()),
+        until: Some(until.get()).filter(|until| !until.is_empty()),
+    };
+    let export_href = move || {
+        format!(
+            "/events/audit/export?{}",
+            serde_urlencoded::to_string(query()).unwrap_or_default()
+        )
+    };
+
+    // Latest entries right away
+    Effect::new(move |_| {
+        search_action.dispatch(GetAuditLog {
+            query: AuditQuery::default(),
+        });
+    });
+
+    view! {
+        <div class="flex flex-col gap-2 p-4">
+            <h1 class="text-2xl font-bold">"Audit Log"</h1>
+            <form
+                class="flex gap-2 items-end flex-wrap"
+                on:submit=move |ev| {
+                    ev.prevent_default();
+                    search_action.dispatch(GetAuditLog { query: query() });
+                }
+            >
+                <div class="flex flex-col grow">
+                    <label class="text-sm dark:text-white/50">"Actor"</label>
+                    <input
+                        type="text"
+                        placeholder="Name, email or token"
+                        class="border p-2 rounded-md dark:bg-white/10 dark:border-white/5"
+                        prop:value=actor
+                        on:input=move |ev| set_actor.set(event_target_value(&ev))
+                    />
+                </div>
+                <div class="flex flex-col grow">
+                    <label class="text-sm dark:text-white/50">"Action"</label>
+                    <input
+                        type="text"
+                        placeholder="e.g. deploy or project.image"
+                        class="border p-2 rounded-md dark:bg-white/10 dark:border-white/5"
+                        prop:value=action
+                        on:input=move |ev| set_action.set(event_target_value(&ev))
+                    />
+                </div>
+                <div class="flex flex-col">
+                    <label class="text-sm dark:text-white/50">"Project"</label>
+                    <select
+                        class="border p-2 rounded-md dark:bg-white/10 dark:border-white/5"
+                        on:change=move |ev| {
+                            set_project_id.set(Uuid::parse_str(&event_target_value(&ev)).ok())
+                        }
+                    >
+                        <option value="">"All projects"</option>
+                        <Transition>
+                            {move || {
+                                projects
+                                    .get()
+                                    .and_then(|projects| projects.ok())
+                                    .map(|projects| {
+                                        projects
+                                            .into_iter()
+                                            .map(|project| {
+                                                view! {
+                                                    <option value=project.id.to_string()>
+                                                        {project.name}
+                                                    </option>
+                                                }
+                                            })
+                                            .collect_view()
+                                    })
+                            }}
+                        </Transition>
+                    </select>
+                </div>
+                <div class="flex flex-col">
+                    <label class="text-sm dark:text-white/50">"From (UTC)"</label>
+                    <input
+                        type="datetime-local"
+                        class="border p-2 rounded-md dark:bg-white/10 dark:border-white/5"
+                        prop:value=since
+                        on:input=move |ev| set_since.set(event_target_value(&ev))
+                    />
+                </div>
+                <div class="flex flex-col">
+                    <label class="text-sm dark:text-white/50">"To (UTC)"</label>
+                    <input
+                        type="datetime-local"
+                        class="border p-2 rounded-md dark:bg-white/10 dark:border-white/5"
+                        prop:value=until
+                        on:input=move |ev| set_until.set(event_target_value(&ev))
+                    />
+                </div>
+                <input
+                    type="submit"
+                    value="Search"
+                    class="cursor-pointer border p-2 px-6 rounded bg-slate-800 text-white dark:border-none dark:bg-white/90 dark:text-black"
+                />
+                <a href=export_href target="_blank" class="p-2 px-6 rounded border">
+                    "Export JSON"
+                </a>
+            </form>
+            {move || {
+                if search_action.pending().get() {
+                    return view! { <div class="text-sm dark:text-white/50">"Searching..."</div> }
+                        .into_any();
+                }
+                match search_action.value().get() {
+                    None => ().into_any(),
+                    Some(Err(err)) => {
+                        view! { <div class="text-sm text-red-500">{err.to_string()}</div> }
+                            .into_any()
+                    }
+                    Some(Ok(search)) => {
+                        view! {
+                            <div class="text-sm dark:text-white/50">
+                                {format!("{} entries", search.entries.len())}
+                                {search
+                                    .truncated
+                                    .then_some(", older entries left out, narrow the search or export them")}
+                            </div>
+                            <div class="flex flex-col gap-1">
+                                {search
+                                    .entries
+                                    .into_iter()
+                                    .map(|entry| view! { <AuditEntryRow entry/> })
+                                    .collect_view()}
+                            </div>
+                        }
+                            .into_any()
+                    }
+                }
+            }}
+        </div>
+    }
+}
+
+#[component]
+fn AuditEntryRow(entry: AuditEntry) -> impl IntoView {
+    let (show_changes, set_show_changes) = signal(false);
+    let changes = entry
+        .changes
+        .0
+        .iter()
+        .filter_map(|operation| serde_json::to_value(operation).ok())
+        .map(|operation| {
+            let value = operation
+                .get("value")
+                .map(|value| value.to_string())
+                .unwrap_or_default();
+            format!(
+                "{} {} {value}",
+                operation["op"].as_str().unwrap_or_default(),
+                operation["path"].as_str().unwrap_or_default()
+            )
+        })
+        .collect::<Vec<_>>();
+    let has_changes = !changes.is_empty();
+
+    view! {
+        <div class="flex flex-col gap-1 text-sm p-2 rounded border dark:border-white/10">
+            <div class="flex gap-4 items-center flex-wrap">
+                <div class="font-mono dark:text-white/50">
+                    {entry.ts.format("%Y-%m-%d %H:%M:%S").to_string()}
+                </div>
+                <div class="font-mono font-semibold">{entry.action}</div>
+                <div>{entry.actor.to_string()}</div>
+                {entry
+                    .project_name
+                    .map(|name| view! { <div class="dark:text-white/50">{name}</div> })}
+                <div class="grow dark:text-white/50 break-all">{entry.detail}</div>
+                <Show when=move || has_changes>
+                    <button
+                        type="button"
+                        class="p-1 px-4 rounded border"
+                        on:click=move |_| set_show_changes.update(|show| *show = !*show)
+                    >
+                        "Changes"
+                    </button>
+                </Show>
+            </div>
+            <Show when=move || show_changes.get()>
+                <div class="bg-black text-white p-2 font-mono text-xs overflow-auto rounded max-h-[400px]">
+                    {changes
+                        .iter()
+                        .map(|change| view! { <div class="break-all">{change.clone()}</div> })
+                        .collect_view()}
+                </div>
+            </Show>
+        </div>
+    }
+}
@@ -1,5 +1,6 @@
 use crate::common::{ProcessInfo, SystemStats};
 use crate::components::file_browser::FileBrowser;
+use crate::components::metrics_history::MetricsHistory;
 use crate::components::terminal::TerminalComponent;
 use crate::hooks::use_socket::{use_socket, WsMessage};
 use leptos::prelude::*;
@@ -29,7 +30,11 @@ pub fn Dashboard() -> impl IntoView {
              <div class="max-w-7xl mx-auto space-y-6">
                  <div class="flex justify-between items-center">
                      <h1 class="text-3xl font-bold bg-clip-text text-transparent bg-gradient-to-r from-blue-500 to-purple-600">"Dashboard"</h1>
-                     <A href="/projects" attr:class="px-4 py-2 bg-blue-600 text-white rounded hover:bg-blue-700 transition-colors">"Projects"</A>
+                     <div class="flex gap-2">
+                         <A href="/users" attr:class="px-4 py-2 border border-blue-600 text-blue-600 rounded hover:bg-blue-600/10 transition-colors">"Users"</A>
+                         <A href="/audit" attr:class="px-4 py-2 border border-blue-600 text-blue-600 rounded hover:bg-blue-600/10 transition-colors">"Audit Log"</A>
+                         <A href="/projects" attr:class="px-4 py-2 bg-blue-600 text-white rounded hover:bg-blue-700 transition-colors">"Projects"</A>
+                     </div>
                  </div>
 
                  <div class="grid grid-cols-1 md:grid-cols-2 lg:grid-cols-4 gap-4">
@@ -76,6 +81,10 @@ pub fn Dashboard() -> impl IntoView {
                       </div>
                  </div>
 
+                 <div class="bg-white dark:bg-gray-900 rounded-xl shadow-sm border border-gray-100 dark:border-gray-800">
+                     <MetricsHistory project_id=None container=None/>
+                 </div>
+
                  <div class="grid grid-cols-1 lg:grid-cols-2 gap-6 h-[600px]">
                      <div class="bg-black rounded-xl shadow-lg border border-gray-800 overflow-hidden flex flex-col">
                          <div class="bg-gray-900 p-2 border-b border-gray-800 flex justify-between items-center px-4">
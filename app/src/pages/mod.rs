@@ -1,4 +1,7 @@
+pub mod alert_settings;
+pub mod audit;
 pub mod dashboard;
 pub mod home;
 pub mod project;
 pub mod settings;
+pub mod users;
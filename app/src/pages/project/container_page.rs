@@ -1,14 +1,19 @@
 use crate::components::toaster::{ToastVariant, ToasterContext};
 
 use leptos::prelude::*;
-use leptos_use::{use_interval_fn, utils::Pausable};
 use uuid::Uuid;
 
 use crate::api::{
-    inspect_container, PauseContainer, ResumeContainer, StartContainer, StopContainer,
+    get_container_runtime, inspect_container, PauseContainer, ResumeContainer, StartContainer,
+    StopContainer,
 };
-use crate::common::TtyChunk;
+use crate::common::{
+    ContainerSlot, HealthStatus, LogsParams, Project, ProjectEvent, ProjectType, TtyChunk,
+};
+use crate::pages::project::image_build::ImageBuild;
+use crate::pages::project::log_history::LogHistory;
 use leptos_router::hooks::use_query_map;
+use leptos_router::params::ParamsMap;
 // use leptos_icons::Icon;
 use crate::hooks::use_socket::{use_socket, WsMessage};
 // use leptos::signal::{SignalGet, SignalWith};
@@ -28,17 +33,16 @@ pub fn ContainerPage() -> impl IntoView {
         },
     );
 
-    let Pausable {
-        // pause,
-        // resume,
-        // is_active,
-        ..
-    } = use_interval_fn(
-        move || {
-            container.refetch();
-        },
-        5000,
-    );
+    let project_events = expect_context::<ReadSignal<Option<ProjectEvent>>>();
+    Effect::new(move |_| {
+        if let Some(event @ (ProjectEvent::ContainerStatusChanged { .. } | ProjectEvent::Lagged)) =
+            project_events.get()
+        {
+            if event.concerns(id.get_untracked()) {
+                container.refetch();
+            }
+        }
+    });
     let toast_context = expect_context::<ToasterContext>();
     let pause_container_action = ServerAction::<PauseContainer>::new();
     Effect::new({
@@ -85,6 +89,22 @@ pub fn ContainerPage() -> impl IntoView {
     });
     let query = use_query_map();
     let sub_page = Memo::new(move |_| query.get().get("page").map(|s| s.clone()));
+    let selected = Memo::new(move |_| selected_container(&query.get()));
+    let project = expect_context::<Resource<Result<Project, ServerFnError>>>();
+    let support_names = Memo::new(move |_| {
+        let mut names = project
+            .get()
+            .and_then(|p| p.ok())
+            .and_then(|p| match p.project_type {
+                ProjectType::Container {
+                    support_containers, ..
+                } => Some(support_containers.into_keys().collect::<Vec<_>>()),
+                ProjectType::PortForward(_) => None,
+            })
+            .unwrap_or_default();
+        names.sort();
+        names
+    });
     let id_val = id.get();
 
     view! {
@@ -109,10 +129,43 @@ pub fn ContainerPage() -> impl IntoView {
 
                 <ContainerControls container_id=id_val/>
 
+                <div class="flex gap-1 flex-wrap">
+                    {move || {
+                        let page = sub_page.get().filter(|page| page != "build").unwrap_or("logs".to_string());
+                        std::iter::once(None)
+                            .chain(support_names.get().into_iter().map(Some))
+                            .map(|name| {
+                                let active = selected.get() == name;
+                                let href = format!(
+                                    "/projects/{}/container?page={}{}",
+                                    id_val,
+                                    page,
+                                    container_query(&name),
+                                );
+                                view! {
+                                    <a
+                                        href=href
+                                        class=format!(
+                                            "px-4 py-2 rounded-xl text-sm font-medium border transition-all {}",
+                                            if active {
+                                                "bg-white dark:bg-gray-800 text-blue-600 dark:text-blue-400 border-blue-500"
+                                            } else {
+                                                "text-gray-500 border-gray-200 dark:border-gray-800 hover:bg-gray-100 dark:text-gray-400 dark:hover:bg-gray-800"
+                                            },
+                                        )
+                                    >
+                                        {name.unwrap_or("Primary".to_string())}
+                                    </a>
+                                }
+                            })
+                            .collect_view()
+                    }}
+                </div>
+
                 <div class="rounded-xl border border-gray-200 dark:border-gray-800 bg-white dark:bg-gray-900 shadow-sm overflow-hidden">
                    <div class="flex gap-1 p-2 border-b border-gray-100 dark:border-gray-800 bg-gray-50/50 dark:bg-gray-900/50">
                        <a
-                           href=move || format!("/projects/{}/container?page=logs", id_val)
+                           href=move || format!("/projects/{}/container?page=logs{}", id_val, container_query(&selected.get()))
                            class=move || {
                                let active = sub_page.get().as_deref() == Some("logs");
                                format!(
@@ -128,7 +181,23 @@ pub fn ContainerPage() -> impl IntoView {
                            "Logs"
                        </a>
                        <a
-                           href=move || format!("/projects/{}/container?page=stats", id_val)
+                           href=move || format!("/projects/{}/container?page=history{}", id_val, container_query(&selected.get()))
+                           class=move || {
+                               let active = sub_page.get().as_deref() == Some("history");
+                               format!(
+                                   "px-4 py-2 rounded-lg text-sm font-medium transition-all {}",
+                                   if active {
+                                       "bg-white dark:bg-gray-800 text-blue-600 dark:text-blue-400 shadow-sm"
+                                   } else {
+                                       "text-gray-500 hover:text-gray-900 dark:text-gray-400 dark:hover:text-gray-200 hover:bg-gray-100 dark:hover:bg-gray-800"
+                                   }
+                               )
+                           }
+                       >
+                           "History"
+                       </a>
+                       <a
+                           href=move || format!("/projects/{}/container?page=stats{}", id_val, container_query(&selected.get()))
                            class=move || {
                                let active = sub_page.get().as_deref() == Some("stats");
                                format!(
@@ -144,7 +213,7 @@ pub fn ContainerPage() -> impl IntoView {
                            "Stats"
                        </a>
                        <a
-                           href=move || format!("/projects/{}/container?page=attach", id_val)
+                           href=move || format!("/projects/{}/container?page=attach{}", id_val, container_query(&selected.get()))
                            class=move || {
                                let active = sub_page.get().as_deref() == Some("attach");
                                format!(
@@ -159,6 +228,23 @@ pub fn ContainerPage() -> impl IntoView {
                        >
                            "Terminal"
                        </a>
+                       <a
+                           href=move || format!("/projects/{}/container?page=build", id_val)
+                           class=move || {
+                               let active = sub_page.get().as_deref() == Some("build");
+                               format!(
+                                   "px-4 py-2 rounded-lg text-sm font-medium transition-all {} {}",
+                                   if selected.get().is_some() { "hidden" } else { "" },
+                                   if active {
+                                       "bg-white dark:bg-gray-800 text-blue-600 dark:text-blue-400 shadow-sm"
+                                   } else {
+                                       "text-gray-500 hover:text-gray-900 dark:text-gray-400 dark:hover:text-gray-200 hover:bg-gray-100 dark:hover:bg-gray-800"
+                                   }
+                               )
+                           }
+                       >
+                           "Build"
+                       </a>
                    </div>
 
                    <div class="min-h-[400px]">
@@ -177,6 +263,26 @@ fn ContainerControls(container_id: Uuid) -> impl IntoView {
         |id| async move { inspect_container(id).await },
     );
 
+    let runtime = Resource::new(
+        move || container_id,
+        |id| async move { get_container_runtime(id).await },
+    );
+
+    let project_events = expect_context::<ReadSignal<Option<ProjectEvent>>>();
+    Effect::new(move |_| {
+        match project_events.get() {
+            Some(ProjectEvent::ContainerStatusChanged {
+                project_id,
+                container: ContainerSlot::Primary,
+                ..
+            }) if project_id == container_id => {}
+            Some(ProjectEvent::Lagged) => {}
+            _ => return,
+        }
+        inspect.refetch();
+        runtime.refetch();
+    });
+
     let start = ServerAction::<StartContainer>::new();
     let stop = ServerAction::<StopContainer>::new();
     let pause = ServerAction::<PauseContainer>::new();
@@ -197,6 +303,34 @@ fn ContainerControls(container_id: Uuid) -> impl IntoView {
                                  <div class=format!("w-3 h-3 rounded-full {}", if running { "bg-green-500" } else { "bg-red-500" })></div>
                                  <span class="text-sm font-medium dark:text-gray-300 uppercase mr-4">{status}</span>
 
+                                 {move || runtime.get().and_then(|r| r.ok()).map(|runtime| {
+                                     let health = match runtime.health {
+                                         HealthStatus::NoCheck => None,
+                                         HealthStatus::Starting => Some(("starting", "text-yellow-500")),
+                                         HealthStatus::Healthy => Some(("healthy", "text-green-500")),
+                                         HealthStatus::Unhealthy => Some(("unhealthy", "text-red-500")),
+                                     };
+                                     view! {
+                                         {health.map(|(label, class)| view! {
+                                             <span class=format!("text-sm mr-4 {class}")>{label}</span>
+                                         })}
+                                         <span class="text-sm dark:text-gray-400 mr-4" title=runtime.last_failure.clone().unwrap_or_default()>
+                                             {format!("Restarts: {}", runtime.restart_count)}
+                                         </span>
+                                         {runtime.is_crash_looping().then(|| view! {
+                                             <span class="text-xs px-2 py-1 rounded bg-red-700 text-white mr-4">
+                                                 {format!(
+                                                     "Crash loop{}",
+                                                     runtime
+                                                         .next_retry_at
+                                                         .map(|at| format!(", retrying at {}", at.format("%H:%M:%S")))
+                                                         .unwrap_or_default(),
+                                                 )}
+                                             </span>
+                                         })}
+                                     }
+                                 })}
+
                                  {if !running {
                                      view! {
                                          <ActionForm action=start>
@@ -252,6 +386,19 @@ fn ContainerControls(container_id: Uuid) -> impl IntoView {
     }
 }
 
+/// Support container picked with `?container=`, `None` for the primary container
+fn selected_container(query: &ParamsMap) -> Option<String> {
+    query.get("container").filter(|name| !name.is_empty())
+}
+
+/// Query suffix that keeps the selected container when switching pages
+fn container_query(container: &Option<String>) -> String {
+    container
+        .as_ref()
+        .map(|name| format!("&container={name}"))
+        .unwrap_or_default()
+}
+
 #[component]
 fn ContainerSubPages(id: Uuid) -> impl IntoView {
     let query = use_query_map();
@@ -265,10 +412,17 @@ fn ContainerSubPages(id: Uuid) -> impl IntoView {
 
     view! {
         <div>
-            {move || match page().as_str() {
-                "stats" => view! { <ContainerStats container_id=id/> }.into_any(),
-                "attach" => view! { <ContainerAttach container_id=id/> }.into_any(),
-                _ => view! { <ContainerLogs container_id=id/> }.into_any(),
+            {move || {
+                let container = selected_container(&query.get());
+                match page().as_str() {
+                    "history" => view! { <LogHistory project_id=id container/> }.into_any(),
+                    "stats" => view! { <ContainerStats container_id=id container/> }.into_any(),
+                    "attach" => view! { <ContainerAttach container_id=id container/> }.into_any(),
+                    "build" if container.is_none() => {
+                        view! { <ImageBuild project_id=id/> }.into_any()
+                    }
+                    _ => view! { <ContainerLogs container_id=id container/> }.into_any(),
+                }
             }}
         </div>
     }
@@ -284,11 +438,17 @@ struct StatsHistory {
 }
 
 #[component]
-pub fn ContainerStats(container_id: Uuid) -> impl IntoView {
+pub fn ContainerStats(container_id: Uuid, container: Option<String>) -> impl IntoView {
     use crate::components::apex_chart::{ApexChart, ChartSeries};
+    use crate::components::metrics_history::MetricsHistory;
     use chrono::Utc;
 
-    let socket = use_socket(&format!("/events/container/{container_id}/stats/ws"));
+    let history_container = container.clone();
+
+    let socket = use_socket(&format!(
+        "/events/container/{container_id}/stats/ws?container={}",
+        container.as_deref().unwrap_or_default()
+    ));
     let message = socket.message;
 
     let (stats_history, set_stats_history) = signal(StatsHistory::default());
@@ -459,6 +619,23 @@ pub fn ContainerStats(container_id: Uuid) -> impl IntoView {
         ]
     });
 
+    let project = expect_context::<Resource<Result<Project, ServerFnError>>>();
+    let slot = match container {
+        Some(name) => ContainerSlot::Support(name),
+        None => ContainerSlot::Primary,
+    };
+    let limits = Memo::new(move |_| {
+        project
+            .get()
+            .and_then(|p| p.ok())
+            .and_then(|p| {
+                p.project_type
+                    .get_container(&slot)
+                    .map(|c| c.limits.clone())
+            })
+            .unwrap_or_default()
+    });
+
     let common_options = serde_json::json!({
         "chart": {
             "type": "area",
@@ -477,6 +654,40 @@ pub fn ContainerStats(container_id: Uuid) -> impl IntoView {
     });
 
     view! {
+        <div class="px-4 pt-4 flex gap-6 flex-wrap text-sm dark:text-gray-300">
+            <div>
+                "Memory: "
+                {move || {
+                    let usage = stats_history.get().memory.last().map(|m| m.1).unwrap_or_default();
+                    match limits.get().memory_mb {
+                        Some(limit) => format!("{usage:.1} / {limit} MiB"),
+                        None => format!("{usage:.1} MiB (unlimited)"),
+                    }
+                }}
+            </div>
+            <div>
+                "CPU: "
+                {move || {
+                    let usage = stats_history.get().cpu.last().map(|c| c.1).unwrap_or_default();
+                    match limits.get().cpus {
+                        Some(cpus) => format!("{usage:.1}% (limit {:.0}%)", cpus * 100.0),
+                        None => format!("{usage:.1}% (unlimited)"),
+                    }
+                }}
+            </div>
+            <div>
+                "Processes: "
+                {move || {
+                    let current = current_stats
+                        .with(|stats| stats["pids_stats"]["current"].as_u64())
+                        .unwrap_or_default();
+                    match limits.get().pids_limit {
+                        Some(limit) => format!("{current} / {limit}"),
+                        None => format!("{current} (unlimited)"),
+                    }
+                }}
+            </div>
+        </div>
         <div class="p-4 grid grid-cols-1 md:grid-cols-2 gap-4">
              <div class="bg-white dark:bg-gray-900 p-4 rounded-xl shadow-sm border border-gray-200 dark:border-gray-800">
                 <h3 class="text-lg font-bold mb-4 dark:text-white">"CPU Usage"</h3>
@@ -491,28 +702,146 @@ pub fn ContainerStats(container_id: Uuid) -> impl IntoView {
                 <ApexChart series=network_series options=Signal::from(common_options) height="300"/>
              </div>
         </div>
+        <MetricsHistory project_id=Some(container_id) container=history_container/>
+    }
+}
+
+/// History replayed when the logs tab opens, docker replays everything otherwise
+const DEFAULT_TAIL: u64 = 200;
+
+#[component]
+pub fn ContainerLogs(container_id: Uuid, container: Option<String>) -> impl IntoView {
+    let (params, set_params) = signal(LogsParams {
+        tail: Some(DEFAULT_TAIL),
+        ..Default::default()
+    });
+    let (tail, set_tail) = signal(DEFAULT_TAIL.to_string());
+    let (since, set_since) = signal(String::new());
+    let (until, set_until) = signal(String::new());
+    let (timestamps, set_timestamps) = signal(false);
+    let (stdout, set_stdout) = signal(true);
+    let (stderr, set_stderr) = signal(true);
+    let (grep, set_grep) = signal(String::new());
+
+    let apply = move |ev: leptos::ev::SubmitEvent| {
+        ev.prevent_default();
+        let time = |value: String| Some(value).filter(|value| !value.is_empty());
+        set_params.set(LogsParams {
+            tail: tail.get().trim().parse().ok(),
+            since: time(since.get()),
+            until: time(until.get()),
+            timestamps: timestamps.get(),
+            stdout: stdout.get(),
+            stderr: stderr.get(),
+        });
+    };
+    let input_class = "border p-1 rounded-md dark:bg-white/10 dark:border-white/5";
+
+    view! {
+        <form class="flex gap-3 items-end flex-wrap p-2 text-sm dark:text-gray-300" on:submit=apply>
+            <div class="flex flex-col">
+                <label class="text-xs dark:text-white/50">"Tail"</label>
+                <input
+                    type="number"
+                    min="0"
+                    placeholder="All"
+                    class=format!("{input_class} w-24")
+                    prop:value=tail
+                    on:input=move |ev| set_tail.set(event_target_value(&ev))
+                />
+            </div>
+            <div class="flex flex-col">
+                <label class="text-xs dark:text-white/50">"Since (UTC)"</label>
+                <input
+                    type="datetime-local"
+                    class=input_class
+                    prop:value=since
+                    on:input=move |ev| set_since.set(event_target_value(&ev))
+                />
+            </div>
+            <div class="flex flex-col">
+                <label class="text-xs dark:text-white/50">"Until (UTC)"</label>
+                <input
+                    type="datetime-local"
+                    class=input_class
+                    prop:value=until
+                    on:input=move |ev| set_until.set(event_target_value(&ev))
+                />
+            </div>
+            <label class="flex gap-1 items-center">
+                <input
+                    type="checkbox"
+                    prop:checked=timestamps
+                    on:change=move |ev| set_timestamps.set(event_target_checked(&ev))
+                />
+                "Timestamps"
+            </label>
+            <label class="flex gap-1 items-center">
+                <input
+                    type="checkbox"
+                    prop:checked=stdout
+                    on:change=move |ev| set_stdout.set(event_target_checked(&ev))
+                />
+                "stdout"
+            </label>
+            <label class="flex gap-1 items-center">
+                <input
+                    type="checkbox"
+                    prop:checked=stderr
+                    on:change=move |ev| set_stderr.set(event_target_checked(&ev))
+                />
+                "stderr"
+            </label>
+            <input type="submit" value="Apply" class="cursor-pointer border p-1 px-4 rounded"/>
+            <div class="flex flex-col grow">
+                <label class="text-xs dark:text-white/50">"Grep"</label>
+                <input
+                    type="text"
+                    placeholder="Only show lines containing, ignoring case"
+                    class=input_class
+                    prop:value=grep
+                    on:input=move |ev| set_grep.set(event_target_value(&ev))
+                />
+            </div>
+        </form>
+        {move || {
+            let url = format!(
+                "/events/container/{container_id}/logs/ws?container={}&{}",
+                container.as_deref().unwrap_or_default(),
+                serde_urlencoded::to_string(params.get()).unwrap_or_default(),
+            );
+            view! { <LogsStream url grep/> }
+        }}
     }
 }
 
+/// Follows the logs websocket at `url`, showing the lines matching `grep` highlighted
 #[component]
-pub fn ContainerLogs(container_id: Uuid) -> impl IntoView {
-    let socket = use_socket(&format!("/events/container/{container_id}/logs/ws"));
+fn LogsStream(url: String, grep: ReadSignal<String>) -> impl IntoView {
+    let socket = use_socket(&url);
     let message = socket.message;
     let ready_state = socket.ready_state;
 
-    let (logs, set_logs) = signal(Vec::<String>::new());
+    // Lines with whether they came from stderr
+    let (logs, set_logs) = signal(Vec::<(bool, String)>::new());
 
     Effect::new(move |_| {
         message.with(|msg| {
             if let Some(WsMessage::Binary(bytes)) = msg {
                 if let Ok(chunk) = bincode::deserialize::<TtyChunk>(&bytes) {
-                    match chunk {
-                        TtyChunk::StdOut(bytes) | TtyChunk::StdErr(bytes) => {
-                            let s = String::from_utf8_lossy(&bytes).to_string();
-                            set_logs.update(|l| l.push(s));
-                        }
-                        _ => {}
-                    }
+                    let (is_stderr, bytes) = match chunk {
+                        TtyChunk::StdOut(bytes) => (false, bytes),
+                        TtyChunk::StdErr(bytes) => (true, bytes),
+                        TtyChunk::StdIn(_) => return,
+                    };
+                    let text = String::from_utf8_lossy(&bytes).to_string();
+                    set_logs.update(|l| {
+                        l.extend(
+                            text.trim_end_matches('\n')
+                                .split('\n')
+                                .map(|line| (is_stderr, line.to_string())),
+                        )
+                    });
                 }
             }
         });
@@ -523,17 +852,63 @@ pub fn ContainerLogs(container_id: Uuid) -> impl IntoView {
             <div class="text-gray-500 mb-2">
                 "Status: " {move || ready_state.get().to_string()}
             </div>
-            {move || logs.get().into_iter().map(|line| view! { <div>{line}</div> }).collect_view()}
+            {move || {
+                let needle = grep.get().to_lowercase();
+                logs.with(|logs| {
+                    logs.iter()
+                        .filter(|(_, line)| {
+                            needle.is_empty() || line.to_lowercase().contains(&needle)
+                        })
+                        .map(|(is_stderr, line)| {
+                            let class = if *is_stderr { "text-red-300" } else { "" };
+                            view! {
+                                <div class=class>
+                                    {highlight(line, &needle)}
+                                </div>
+                            }
+                        })
+                        .collect_view()
+                })
+            }}
         </div>
     }
 }
 
+/// Wraps the case insensitive matches of `needle` in `mark`
+fn highlight(line: &str, needle: &str) -> impl IntoView {
+    let mut parts = vec![];
+    let lower = line.to_lowercase();
+    // Lowercasing can change byte lengths, highlighting is skipped for such lines
+    if !needle.is_empty() && lower.len() == line.len() {
+        let mut rest = 0;
+        for (start, _) in lower.match_indices(needle) {
+            parts.push((false, line[rest..start].to_string()));
+            parts.push((true, line[start..start + needle.len()].to_string()));
+            rest = start + needle.len();
+        }
+        parts.push((false, line[rest..].to_string()));
+    } else {
+        parts.push((false, line.to_string()));
+    }
+    parts
+        .into_iter()
+        .map(|(matched, text)| {
+            if matched {
+                view! { <mark class="bg-yellow-400 text-black">{text}</mark> }.into_any()
+            } else {
+                text.into_any()
+            }
+        })
+        .collect_view()
+}
+
 #[component]
-pub fn ContainerAttach(container_id: Uuid) -> impl IntoView {
+pub fn ContainerAttach(container_id: Uuid, container: Option<String>) -> impl IntoView {
     use crate::components::terminal::TerminalComponent;
     let url = move || {
         format!(
-            "/events/container/{container_id}/attach/ws?command=sh&size_width=80&size_height=24"
+            "/events/container/{container_id}/attach/ws?command=sh&size_width=80&size_height=24{}",
+            container_query(&container)
         )
     };
 
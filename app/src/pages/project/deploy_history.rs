@@ -0,0 +1,143 @@
+use leptos::prelude::*;
+use uuid::Uuid;
+
+use crate::api::{RollbackDeployment, SetRetainedDeployments};
+use crate::common::DeployHistory;
+use crate::components::toaster::{ToastVariant, ToasterContext};
+
+/// Retained deployments of the primary image, newest first, each can be rolled back to
+#[component]
+pub fn DeployHistorySettings(id: Uuid, deploy_history: DeployHistory) -> impl IntoView {
+    let rollback_action = ServerAction::<RollbackDeployment>::new();
+    let retain_action = ServerAction::<SetRetainedDeployments>::new();
+    let toast_context = expect_context::<ToasterContext>();
+    let set_trigger = expect_context::<WriteSignal<()>>();
+
+    Effect::new({
+        let toast_context = toast_context.clone();
+        move |_| match rollback_action.value().get() {
+            Some(Ok(_)) => {
+                toast_context.toast("Rolled Back", ToastVariant::Success);
+                set_trigger.set(());
+            }
+            Some(Err(err)) => toast_context.toast(err.to_string(), ToastVariant::Error),
+            None => {}
+        }
+    });
+    Effect::new(move |_| match retain_action.value().get() {
+        Some(Ok(_)) => {
+            toast_context.toast("Retention Updated", ToastVariant::Success);
+            set_trigger.set(());
+        }
+        Some(Err(err)) => toast_context.toast(err.to_string(), ToastVariant::Error),
+        None => {}
+    });
+
+    let active = deploy_history.active;
+    let pinned = deploy_history.pinned;
+    let mut deployments = deploy_history.deployments;
+    deployments.reverse();
+
+    view! {
+        <div class="text-xl">"Deployments"</div>
+        <div class="text-sm dark:text-white/50">
+            "Images deployed to the primary container, older ones are removed beyond the retained count"
+        </div>
+        <Show when=move || pinned>
+            <div class="text-sm dark:text-white/50">
+                "Rolled back, automatic deploys of the image source wait for the next deploy"
+            </div>
+        </Show>
+        <ActionForm action=retain_action>
+            <input name="id" type="hidden" prop:value=id.to_string()/>
+            <div class="flex gap-2 items-end p-2">
+                <div class="flex flex-col">
+                    <label class="text-sm dark:text-white/50">"Retained Images"</label>
+                    <input
+                        type="number"
+                        min="1"
+                        name="retain"
+                        prop:value=deploy_history.retain
+                        class="border p-2 rounded-md dark:bg-white/10 dark:border-white/5"
+                    />
+                </div>
+                <input
+                    type="submit"
+                    value="Save"
+                    class="cursor-pointer block border p-2 px-10 rounded bg-slate-800 text-white dark:border-none dark:bg-white/90 dark:text-black"
+                />
+            </div>
+        </ActionForm>
+        <div class="flex flex-col gap-1 p-2">
+            {if deployments.is_empty() {
+                view! { <div class="text-sm dark:text-white/50">"Nothing deployed yet"</div> }
+                    .into_any()
+            } else {
+                deployments
+                    .into_iter()
+                    .map(|deployment| {
+                        let is_active = active == Some(deployment.id);
+                        let deployment_id = deployment.id;
+                        view! {
+                            <div class="flex gap-4 items-center text-sm p-2 rounded border dark:border-white/10">
+                                <div class="flex flex-col grow min-w-0">
+                                    <div class="font-mono truncate">
+                                        {deployment.image_id.trim_start_matches("sha256:").chars().take(12).collect::<String>()}
+                                        {deployment
+                                            .git_sha
+                                            .map(|sha| format!(" @ {}", sha.chars().take(12).collect::<String>()))}
+                                    </div>
+                                    <div class="dark:text-white/50">
+                                        {deployment.deployed_at.format("%Y-%m-%d %H:%M UTC").to_string()}
+                                        " · "
+                                        {deployment.source}
+                                        " · "
+                                        {deployment.status.to_string()}
+                                    </div>
+                                    {deployment
+                                        .hook_runs
+                                        .into_iter()
+                                        .map(|run| {
+                                            view! {
+                                                <details class="dark:text-white/50">
+                                                    <summary class="cursor-pointer">
+                                                        {format!("{} hook {}", run.kind, run.outcome())}
+                                                    </summary>
+                                                    <pre class="bg-black text-white p-2 rounded text-xs overflow-auto max-h-64">
+                                                        {run.logs}
+                                                    </pre>
+                                                </details>
+                                            }
+                                        })
+                                        .collect_view()}
+                                </div>
+                                {if is_active {
+                                    view! { <div class="px-4 text-green-600">"Active"</div> }.into_any()
+                                } else {
+                                    view! {
+                                        <button
+                                            type="button"
+                                            class="p-2 px-4 rounded border disabled:opacity-50"
+                                            disabled=move || rollback_action.pending().get()
+                                            on:click=move |_| {
+                                                rollback_action
+                                                    .dispatch(RollbackDeployment {
+                                                        id,
+                                                        deployment_id,
+                                                    });
+                                            }
+                                        >
+                                            "Rollback"
+                                        </button>
+                                    }
+                                        .into_any()
+                                }}
+                            </div>
+                        }
+                    })
+                    .collect_view()
+                    .into_any()
+            }}
+        </div>
+    }
+}
@@ -0,0 +1,69 @@
+use leptos::prelude::*;
+use uuid::Uuid;
+
+use crate::api::SetDeployHooks;
+use crate::common::DeployHooks;
+use crate::components::toaster::{ToastVariant, ToasterContext};
+
+/// Commands run from the new image around each deployment
+#[component]
+pub fn DeployHooksSettings(id: Uuid, hooks: DeployHooks) -> impl IntoView {
+    let set_hooks_action = ServerAction::<SetDeployHooks>::new();
+    let toast_context = expect_context::<ToasterContext>();
+    let set_trigger = expect_context::<WriteSignal<()>>();
+
+    Effect::new(move |_| match set_hooks_action.value().get() {
+        Some(Ok(_)) => {
+            toast_context.toast("Hooks Updated", ToastVariant::Success);
+            set_trigger.set(());
+        }
+        Some(Err(err)) => toast_context.toast(err.to_string(), ToastVariant::Error),
+        None => {}
+    });
+
+    view! {
+        <div class="text-xl">"Deploy Hooks"</div>
+        <div class="text-sm dark:text-white/50">
+            "Run in a one-off container from the new image with the project env, a failing pre-deploy hook keeps the old container running"
+        </div>
+        <ActionForm action=set_hooks_action>
+            <input name="id" type="hidden" prop:value=id.to_string()/>
+            <div class="flex flex-col gap-2 p-2">
+                <div class="flex flex-col">
+                    <label class="text-sm dark:text-white/50">"Pre-deploy"</label>
+                    <input
+                        type="text"
+                        name="pre_deploy"
+                        prop:value=hooks.pre_deploy.clone().unwrap_or_default()
+                        placeholder="e.g. ./migrate"
+                        class="border p-2 rounded-md font-mono dark:bg-white/10 dark:border-white/5"
+                    />
+                </div>
+                <div class="flex flex-col">
+                    <label class="text-sm dark:text-white/50">"Post-deploy"</label>
+                    <input
+                        type="text"
+                        name="post_deploy"
+                        prop:value=hooks.post_deploy.clone().unwrap_or_default()
+                        class="border p-2 rounded-md font-mono dark:bg-white/10 dark:border-white/5"
+                    />
+                </div>
+                <div class="flex flex-col">
+                    <label class="text-sm dark:text-white/50">"Timeout (s)"</label>
+                    <input
+                        type="number"
+                        min="1"
+                        name="timeout_secs"
+                        prop:value=hooks.timeout_secs
+                        class="border p-2 rounded-md w-32 dark:bg-white/10 dark:border-white/5"
+                    />
+                </div>
+                <input
+                    type="submit"
+                    value="Save"
+                    class="cursor-pointer self-start border p-2 px-10 rounded bg-slate-800 text-white dark:border-none dark:bg-white/90 dark:text-black"
+                />
+            </div>
+        </ActionForm>
+    }
+}
@@ -0,0 +1,111 @@
+use leptos::prelude::*;
+use uuid::Uuid;
+
+use crate::common::BuildEvent;
+use crate::hooks::use_socket::{use_socket, WsMessage};
+
+/// Uploads a source tarball or git bundle to be built on the server and follows the build log
+#[component]
+pub fn ImageBuild(project_id: Uuid) -> impl IntoView {
+    let socket = use_socket(&format!("/events/project/{project_id}/build/ws"));
+    let message = socket.message;
+    let ready_state = socket.ready_state;
+
+    let (logs, set_logs) = signal(Vec::<String>::new());
+    let (result, set_result) = signal(None::<Result<String, String>>);
+
+    Effect::new(move |_| {
+        message.with(|msg| {
+            if let Some(WsMessage::Binary(bytes)) = msg {
+                if let Ok(event) = bincode::deserialize::<BuildEvent>(bytes) {
+                    match event {
+                        BuildEvent::Started { id } => {
+                            set_result.set(None);
+                            set_logs.set(vec![format!("Build {id} started")]);
+                        }
+                        BuildEvent::Log(line) => set_logs.update(|logs| logs.push(line)),
+                        BuildEvent::Finished { image } => set_result.set(Some(Ok(image))),
+                        BuildEvent::Failed(err) => set_result.set(Some(Err(err))),
+                    }
+                }
+            }
+        });
+    });
+
+    view! {
+        <div class="flex flex-col gap-4 p-4">
+            <iframe name="build-upload" class="hidden"></iframe>
+            <form
+                method="post"
+                action="/cloud/image/build"
+                enctype="multipart/form-data"
+                target="build-upload"
+                class="flex flex-col gap-2"
+            >
+                <input type="hidden" name="project_id" value=project_id.to_string()/>
+                <div class="flex gap-4 flex-wrap">
+                    <div class="flex flex-col">
+                        <label class="text-sm dark:text-white/50">"Dockerfile"</label>
+                        <input
+                            type="text"
+                            name="dockerfile"
+                            value="Dockerfile"
+                            class="border p-2 rounded-md dark:bg-white/10 dark:border-white/5"
+                        />
+                    </div>
+                    <div class="flex flex-col">
+                        <label class="text-sm dark:text-white/50">"Git SHA"</label>
+                        <input
+                            type="text"
+                            name="git_sha"
+                            placeholder="Optional"
+                            class="border p-2 rounded-md dark:bg-white/10 dark:border-white/5"
+                        />
+                    </div>
+                    <div class="flex flex-col">
+                        <label class="text-sm dark:text-white/50">
+                            "Source (.tar, .tar.gz or git bundle)"
+                        </label>
+                        <input type="file" name="source" required class="p-2"/>
+                    </div>
+                </div>
+                <div class="flex flex-col">
+                    <label class="text-sm dark:text-white/50">"Build Args"</label>
+                    <textarea
+                        name="build_args"
+                        rows="3"
+                        placeholder="KEY=VALUE, one per line"
+                        class="border p-2 rounded-md font-mono text-sm dark:bg-white/10 dark:border-white/5"
+                    ></textarea>
+                </div>
+                <input
+                    type="submit"
+                    value="Build and Deploy"
+                    class="cursor-pointer self-start border p-2 px-10 rounded bg-slate-800 text-white dark:border-none dark:bg-white/90 dark:text-black"
+                />
+            </form>
+            {move || {
+                result
+                    .get()
+                    .map(|result| match result {
+                        Ok(image) => {
+                            view! {
+                                <div class="text-sm text-green-600">"Deployed " {image}</div>
+                            }
+                                .into_any()
+                        }
+                        Err(err) => {
+                            view! { <div class="text-sm text-red-600">"Build failed: " {err}</div> }
+                                .into_any()
+                        }
+                    })
+            }}
+            <div class="bg-black text-white p-4 font-mono text-xs h-96 overflow-auto rounded whitespace-pre-wrap">
+                <div class="text-gray-500 mb-2">
+                    "Status: " {move || ready_state.get().to_string()}
+                </div>
+                {move || logs.get().into_iter().map(|line| view! { <div>{line}</div> }).collect_view()}
+            </div>
+        </div>
+    }
+}
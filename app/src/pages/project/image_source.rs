@@ -0,0 +1,135 @@
+use leptos::prelude::*;
+use uuid::Uuid;
+
+use crate::api::{DeployImage, SetImageSource};
+use crate::common::ImageSource;
+use crate::components::toaster::{ToastVariant, ToasterContext};
+
+/// Registry image the primary container is deployed from, with a manual deploy button
+#[component]
+pub fn ImageSourceSettings(id: Uuid, image_source: Option<ImageSource>) -> impl IntoView {
+    let set_image_source_action = ServerAction::<SetImageSource>::new();
+    let deploy_action = ServerAction::<DeployImage>::new();
+    let toast_context = expect_context::<ToasterContext>();
+    let set_trigger = expect_context::<WriteSignal<()>>();
+
+    Effect::new({
+        let toast_context = toast_context.clone();
+        move |_| match set_image_source_action.value().get() {
+            Some(Ok(_)) => {
+                toast_context.toast("Image Source Updated", ToastVariant::Success);
+                set_trigger.set(());
+            }
+            Some(Err(err)) => toast_context.toast(err.to_string(), ToastVariant::Error),
+            None => {}
+        }
+    });
+    Effect::new(move |_| match deploy_action.value().get() {
+        Some(Ok(digest)) => {
+            toast_context.toast(format!("Deployed {digest}"), ToastVariant::Success);
+            set_trigger.set(());
+        }
+        Some(Err(err)) => toast_context.toast(err.to_string(), ToastVariant::Error),
+        None => {}
+    });
+
+    let has_source = image_source.is_some();
+    let source = image_source.unwrap_or_else(|| ImageSource::new(String::new()));
+    let deployed = source.deployed_digest.clone().map(|digest| {
+        format!(
+            "{digest} at {}",
+            source
+                .deployed_at
+                .map(|at| at.format("%Y-%m-%d %H:%M UTC").to_string())
+                .unwrap_or_default()
+        )
+    });
+
+    view! {
+        <div class="text-xl">"Image Source"</div>
+        <div class="text-sm dark:text-white/50">
+            "Pull the primary image from a registry instead of uploading it, leave empty to upload with a token"
+        </div>
+        <ActionForm action=set_image_source_action>
+            <input name="id" type="hidden" prop:value=id.to_string()/>
+            <div class="flex flex-col gap-2 p-2">
+                <div class="flex flex-col">
+                    <label class="text-sm dark:text-white/50">"Image"</label>
+                    <input
+                        type="text"
+                        name="reference"
+                        prop:value=source.reference.clone()
+                        placeholder="e.g. ghcr.io/org/app:latest"
+                        class="border p-2 rounded-md dark:bg-white/10 dark:border-white/5"
+                    />
+                </div>
+                <div class="flex gap-4 flex-wrap">
+                    <div class="flex flex-col">
+                        <label class="text-sm dark:text-white/50">"Registry Username"</label>
+                        <input
+                            type="text"
+                            name="username"
+                            prop:value=source.username.clone().unwrap_or_default()
+                            autocomplete="off"
+                            class="border p-2 rounded-md dark:bg-white/10 dark:border-white/5"
+                        />
+                    </div>
+                    <div class="flex flex-col">
+                        <label class="text-sm dark:text-white/50">"Registry Password"</label>
+                        <input
+                            type="password"
+                            name="password"
+                            placeholder=if source.password.is_some() { "Unchanged" } else { "" }
+                            autocomplete="new-password"
+                            class="border p-2 rounded-md dark:bg-white/10 dark:border-white/5"
+                        />
+                    </div>
+                    <div class="flex flex-col">
+                        <label class="text-sm dark:text-white/50">"Poll Interval (s)"</label>
+                        <input
+                            type="number"
+                            min="30"
+                            name="poll_interval_secs"
+                            prop:value=source.poll_interval_secs
+                            class="border p-2 rounded-md dark:bg-white/10 dark:border-white/5"
+                        />
+                    </div>
+                </div>
+                <label class="flex gap-2 items-center text-sm">
+                    <input
+                        type="checkbox"
+                        name="auto_deploy"
+                        value="true"
+                        prop:checked=source.auto_deploy
+                    />
+                    "Deploy automatically when the image changes"
+                </label>
+                {deployed
+                    .map(|deployed| {
+                        view! {
+                            <div class="text-sm dark:text-white/50 font-mono break-all">
+                                "Deployed " {deployed}
+                            </div>
+                        }
+                    })}
+                <div class="flex gap-2">
+                    <input
+                        type="submit"
+                        value="Save"
+                        class="cursor-pointer block border p-2 px-10 rounded bg-slate-800 text-white dark:border-none dark:bg-white/90 dark:text-black"
+                    />
+                    <button
+                        type="button"
+                        class="p-2 px-10 rounded border disabled:opacity-50"
+                        disabled=move || !has_source || deploy_action.pending().get()
+                        on:click=move |_| {
+                            deploy_action.dispatch(DeployImage { id });
+                        }
+                    >
+                        {move || if deploy_action.pending().get() { "Deploying..." } else { "Deploy Now" }}
+                    </button>
+                </div>
+            </div>
+        </ActionForm>
+    }
+}
@@ -0,0 +1,96 @@
+use leptos::prelude::*;
+use leptos_router::components::A;
+
+use crate::api::ImportCompose;
+
+/// Creates a container project from a pasted `docker-compose.yml`
+#[component]
+pub fn ImportCompose(#[prop(into)] on_import: Callback<()>) -> impl IntoView {
+    let import_action = ServerAction::<ImportCompose>::new();
+
+    Effect::new(move |_| {
+        if let Some(Ok(_)) = import_action.value().get() {
+            on_import.run(());
+        }
+    });
+
+    view! {
+        <div class="p-4 border rounded shadow-sm bg-white dark:bg-zinc-900 dark:border-zinc-800">
+            <h2 class="text-xl font-semibold mb-2">"Import docker-compose"</h2>
+            <ActionForm action=import_action attr:class="flex flex-col gap-3 max-w-xl">
+                <label class="flex flex-col gap-1">
+                    <span class="text-sm font-medium">"Project Name"</span>
+                    <input
+                        type="text"
+                        name="name"
+                        class="p-2 border rounded focus:ring-2 focus:ring-blue-500 outline-none dark:bg-zinc-800 dark:border-zinc-700"
+                        required
+                    />
+                </label>
+                <label class="flex flex-col gap-1">
+                    <span class="text-sm font-medium">"Primary Service"</span>
+                    <input
+                        type="text"
+                        name="primary"
+                        class="p-2 border rounded focus:ring-2 focus:ring-blue-500 outline-none dark:bg-zinc-800 dark:border-zinc-700"
+                        placeholder="Detected when empty"
+                    />
+                </label>
+                <label class="flex flex-col gap-1">
+                    <span class="text-sm font-medium">"docker-compose.yml"</span>
+                    <textarea
+                        name="compose"
+                        rows="12"
+                        class="p-2 border rounded font-mono text-sm focus:ring-2 focus:ring-blue-500 outline-none dark:bg-zinc-800 dark:border-zinc-700"
+                        required
+                    ></textarea>
+                </label>
+                <input
+                    type="submit"
+                    value="Import"
+                    class="border p-2 px-10 rounded bg-slate-800 text-white disabled:cursor-no-drop disabled:bg-slate-200 disabled:text-black dark:disabled:bg-white/20 dark:disabled:text-white dark:border-none dark:bg-white/90 dark:text-black"
+                    disabled=move || import_action.pending().get()
+                />
+            </ActionForm>
+            {move || match import_action.value().get() {
+                Some(Ok(import)) => {
+                    view! {
+                        <div class="flex flex-col gap-2 mt-4">
+                            <div>
+                                "Imported with "
+                                <span class="font-semibold">{import.primary}</span>
+                                " as the primary container. "
+                                <A
+                                    href=import.project_id.to_string()
+                                    attr:class="underline text-blue-600 dark:text-blue-400"
+                                >
+                                    "Open project"
+                                </A>
+                            </div>
+                            {(!import.warnings.is_empty())
+                                .then(|| {
+                                    view! {
+                                        <div class="text-sm text-amber-700 dark:text-amber-400">
+                                            "Not imported:"
+                                        </div>
+                                        <ul class="list-disc pl-6 text-sm text-amber-700 dark:text-amber-400">
+                                            {import
+                                                .warnings
+                                                .into_iter()
+                                                .map(|warning| view! { <li>{warning}</li> })
+                                                .collect::<Vec<_>>()}
+                                        </ul>
+                                    }
+                                })}
+                        </div>
+                    }
+                        .into_any()
+                }
+                Some(Err(err)) => {
+                    view! { <div class="mt-4 text-red-600">{err.to_string()}</div> }.into_any()
+                }
+                None => ().into_any(),
+            }}
+        </div>
+    }
+}
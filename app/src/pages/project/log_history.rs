@@ -0,0 +1,185 @@
+use leptos::prelude::*;
+use uuid::Uuid;
+
+use crate::api::{SearchLogs, SetLogRetention};
+use crate::common::{LogQuery, LogRetention, LogStream};
+use crate::components::toaster::{ToastVariant, ToasterContext};
+
+/// Search through the persisted output of a container, including earlier deployments
+#[component]
+pub fn LogHistory(project_id: Uuid, container: Option<String>) -> impl IntoView {
+    let search_action = ServerAction::<SearchLogs>::new();
+    let (text, set_text) = signal(String::new());
+    let (since, set_since) = signal(String::new());
+    let (until, set_until) = signal(String::new());
+
+    let query = move || LogQuery {
+        container: container.clone(),
+        text: text.get(),
+        since: Some(since.get()).filter(|since| !since.is_empty()),
+        until: Some(until.get()).filter(|until| !until.is_empty()),
+    };
+    let download_href = {
+        let query = query.clone();
+        move || {
+            format!(
+                "/events/container/{project_id}/logs/download?{}",
+                serde_urlencoded::to_string(query()).unwrap_or_default()
+            )
+        }
+    };
+
+    view! {
+        <div class="flex flex-col gap-2 p-4">
+            <form
+                class="flex gap-2 items-end flex-wrap"
+                on:submit=move |ev| {
+                    ev.prevent_default();
+                    search_action
+                        .dispatch(SearchLogs {
+                            id: project_id,
+                            query: query(),
+                        });
+                }
+            >
+                <div class="flex flex-col grow">
+                    <label class="text-sm dark:text-white/50">"Text"</label>
+                    <input
+                        type="text"
+                        placeholder="Matches anywhere in the line, ignoring case"
+                        class="border p-2 rounded-md dark:bg-white/10 dark:border-white/5"
+                        prop:value=text
+                        on:input=move |ev| set_text.set(event_target_value(&ev))
+                    />
+                </div>
+                <div class="flex flex-col">
+                    <label class="text-sm dark:text-white/50">"From (UTC)"</label>
+                    <input
+                        type="datetime-local"
+                        class="border p-2 rounded-md dark:bg-white/10 dark:border-white/5"
+                        prop:value=since
+                        on:input=move |ev| set_since.set(event_target_value(&ev))
+                    />
+                </div>
+                <div class="flex flex-col">
+                    <label class="text-sm dark:text-white/50">"To (UTC)"</label>
+                    <input
+                        type="datetime-local"
+                        class="border p-2 rounded-md dark:bg-white/10 dark:border-white/5"
+                        prop:value=until
+                        on:input=move |ev| set_until.set(event_target_value(&ev))
+                    />
+                </div>
+                <input
+                    type="submit"
+                    value="Search"
+                    class="cursor-pointer border p-2 px-6 rounded bg-slate-800 text-white dark:border-none dark:bg-white/90 dark:text-black"
+                />
+                <a href=download_href target="_blank" class="p-2 px-6 rounded border">
+                    "Download"
+                </a>
+            </form>
+            {move || {
+                if search_action.pending().get() {
+                    return view! { <div class="text-sm dark:text-white/50">"Searching..."</div> }
+                        .into_any();
+                }
+                match search_action.value().get() {
+                    None => ().into_any(),
+                    Some(Err(err)) => {
+                        view! { <div class="text-sm text-red-500">{err.to_string()}</div> }
+                            .into_any()
+                    }
+                    Some(Ok(search)) => {
+                        view! {
+                            <div class="text-sm dark:text-white/50">
+                                {format!("{} lines", search.matches.len())}
+                                {search
+                                    .truncated
+                                    .then_some(", older matches left out, narrow the search or download them")}
+                            </div>
+                            <div class="bg-black text-white p-4 font-mono text-xs overflow-auto rounded max-h-[600px]">
+                                {search
+                                    .matches
+                                    .into_iter()
+                                    .map(|found| {
+                                        let class = match found.line.stream {
+                                            LogStream::Stdout => "",
+                                            LogStream::Stderr => "text-red-300",
+                                        };
+                                        view! {
+                                            <div class=class>
+                                                <span class="text-gray-500">
+                                                    {found.line.ts.format("%Y-%m-%d %H:%M:%S ").to_string()}
+                                                    {found.version}
+                                                    " "
+                                                </span>
+                                                {found.line.text}
+                                            </div>
+                                        }
+                                    })
+                                    .collect_view()}
+                            </div>
+                        }
+                            .into_any()
+                    }
+                }
+            }}
+        </div>
+    }
+}
+
+/// How long the collected container output of the project is kept
+#[component]
+pub fn LogRetentionSettings(id: Uuid, log_retention: LogRetention) -> impl IntoView {
+    let retention_action = ServerAction::<SetLogRetention>::new();
+    let toast_context = expect_context::<ToasterContext>();
+    let set_trigger = expect_context::<WriteSignal<()>>();
+
+    Effect::new(move |_| match retention_action.value().get() {
+        Some(Ok(_)) => {
+            toast_context.toast("Log Retention Updated", ToastVariant::Success);
+            set_trigger.set(());
+        }
+        Some(Err(err)) => toast_context.toast(err.to_string(), ToastVariant::Error),
+        None => {}
+    });
+
+    view! {
+        <div class="text-xl">"Log Retention"</div>
+        <div class="text-sm dark:text-white/50">
+            "Output of all containers is collected and searchable from the container page, the oldest is removed first"
+        </div>
+        <ActionForm action=retention_action>
+            <input name="id" type="hidden" prop:value=id.to_string()/>
+            <div class="flex gap-2 items-end p-2">
+                <div class="flex flex-col">
+                    <label class="text-sm dark:text-white/50">"Max Age (days)"</label>
+                    <input
+                        type="number"
+                        min="1"
+                        name="max_age_days"
+                        prop:value=log_retention.max_age_days
+                        class="border p-2 rounded-md w-32 dark:bg-white/10 dark:border-white/5"
+                    />
+                </div>
+                <div class="flex flex-col">
+                    <label class="text-sm dark:text-white/50">"Max Size (MB)"</label>
+                    <input
+                        type="number"
+                        min="1"
+                        max=LogRetention::MAX_SIZE_MB.to_string()
+                        name="max_size_mb"
+                        prop:value=log_retention.max_size_mb
+                        class="border p-2 rounded-md w-32 dark:bg-white/10 dark:border-white/5"
+                    />
+                </div>
+                <input
+                    type="submit"
+                    value="Save"
+                    class="cursor-pointer block border p-2 px-10 rounded bg-slate-800 text-white dark:border-none dark:bg-white/90 dark:text-black"
+                />
+            </div>
+        </ActionForm>
+    }
+}
@@ -0,0 +1,209 @@
+use leptos::prelude::*;
+use uuid::Uuid;
+
+use crate::api::{AddLogSink, DeleteLogSink, SetLogSinkEnabled, TestLogSink};
+use crate::common::LogSink;
+use crate::components::toaster::{ToastVariant, ToasterContext};
+
+/// Destinations the container output and gateway access logs of the project are shipped to
+#[component]
+pub fn LogSinks(id: Uuid, log_sinks: Vec<LogSink>) -> impl IntoView {
+    let add_sink_action = ServerAction::<AddLogSink>::new();
+    let enable_sink_action = ServerAction::<SetLogSinkEnabled>::new();
+    let delete_sink_action = ServerAction::<DeleteLogSink>::new();
+    let test_sink_action = ServerAction::<TestLogSink>::new();
+    let toast_context = expect_context::<ToasterContext>();
+    let set_trigger = expect_context::<WriteSignal<()>>();
+    let (kind, set_kind) = signal("syslog_udp".to_string());
+
+    Effect::new({
+        let toast_context = toast_context.clone();
+        move |_| match add_sink_action.value().get() {
+            Some(Ok(_)) => {
+                toast_context.toast("Log Sink Added", ToastVariant::Success);
+                set_trigger.set(());
+            }
+            Some(Err(err)) => toast_context.toast(err.to_string(), ToastVariant::Error),
+            None => {}
+        }
+    });
+    Effect::new({
+        let toast_context = toast_context.clone();
+        move |_| match enable_sink_action.value().get() {
+            Some(Ok(_)) => set_trigger.set(()),
+            Some(Err(err)) => toast_context.toast(err.to_string(), ToastVariant::Error),
+            None => {}
+        }
+    });
+    Effect::new({
+        let toast_context = toast_context.clone();
+        move |_| match delete_sink_action.value().get() {
+            Some(Ok(_)) => {
+                toast_context.toast("Log Sink Deleted", ToastVariant::Success);
+                set_trigger.set(());
+            }
+            Some(Err(err)) => toast_context.toast(err.to_string(), ToastVariant::Error),
+            None => {}
+        }
+    });
+    Effect::new(move |_| match test_sink_action.value().get() {
+        Some(Ok(_)) => toast_context.toast("Test Message Delivered", ToastVariant::Success),
+        Some(Err(err)) => toast_context.toast(err.to_string(), ToastVariant::Error),
+        None => {}
+    });
+
+    view! {
+        <div class="text-xl">"Log Forwarding"</div>
+        <div class="text-sm dark:text-white/50">
+            "Container output and gateway access logs are batched and shipped to these sinks, lines are buffered and retried while a sink is unreachable"
+        </div>
+        <div class="flex flex-col gap-1 p-2">
+            {if log_sinks.is_empty() {
+                view! { <div class="text-sm dark:text-white/50">"No sinks yet"</div> }.into_any()
+            } else {
+                log_sinks
+                    .into_iter()
+                    .map(|sink| {
+                        let sink_id = sink.id;
+                        let enabled = sink.enabled;
+                        let sources = match (sink.containers, sink.gateway) {
+                            (true, true) => "Containers and gateway",
+                            (true, false) => "Containers",
+                            (false, _) => "Gateway",
+                        };
+                        view! {
+                            <div class="flex gap-4 items-center text-sm p-2 rounded border dark:border-white/10">
+                                <div class="flex flex-col grow min-w-0">
+                                    <div class="font-mono truncate">
+                                        {sink.kind.to_string()}
+                                        {(!enabled).then(|| " (disabled)")}
+                                    </div>
+                                    <div class="dark:text-white/50">{sources}</div>
+                                </div>
+                                <button
+                                    type="button"
+                                    class="p-2 px-4 rounded border disabled:opacity-50"
+                                    disabled=move || test_sink_action.pending().get()
+                                    on:click=move |_| {
+                                        test_sink_action.dispatch(TestLogSink { id, sink_id });
+                                    }
+                                >
+                                    "Send Test"
+                                </button>
+                                <button
+                                    type="button"
+                                    class="p-2 px-4 rounded border disabled:opacity-50"
+                                    disabled=move || enable_sink_action.pending().get()
+                                    on:click=move |_| {
+                                        enable_sink_action
+                                            .dispatch(SetLogSinkEnabled {
+                                                id,
+                                                sink_id,
+                                                enabled: !enabled,
+                                            });
+                                    }
+                                >
+                                    {if enabled { "Disable" } else { "Enable" }}
+                                </button>
+                                <button
+                                    type="button"
+                                    class="p-2 px-4 rounded border border-red-600 text-red-600 disabled:opacity-50"
+                                    disabled=move || delete_sink_action.pending().get()
+                                    on:click=move |_| {
+                                        delete_sink_action.dispatch(DeleteLogSink { id, sink_id });
+                                    }
+                                >
+                                    "Delete"
+                                </button>
+                            </div>
+                        }
+                    })
+                    .collect_view()
+                    .into_any()
+            }}
+        </div>
+        <ActionForm action=add_sink_action>
+            <input name="id" type="hidden" prop:value=id.to_string()/>
+            <div class="flex flex-col gap-2 p-2">
+                <div class="flex gap-2 flex-wrap items-end">
+                    <div class="flex flex-col">
+                        <label class="text-sm dark:text-white/50">"Kind"</label>
+                        <select
+                            name="kind"
+                            class="p-2 bg-white border rounded-md dark:bg-white/10 dark:border-white/5"
+                            on:change=move |ev| set_kind.set(event_target_value(&ev))
+                        >
+                            <option value="syslog_udp">"Syslog (UDP)"</option>
+                            <option value="syslog_tcp">"Syslog (TCP)"</option>
+                            <option value="http">"HTTP (Loki push)"</option>
+                            <option value="file">"File"</option>
+                        </select>
+                    </div>
+                    <div class="flex flex-col grow">
+                        <label class="text-sm dark:text-white/50">"Destination"</label>
+                        <input
+                            type="text"
+                            name="target"
+                            placeholder=move || match kind.get().as_str() {
+                                "http" => "https://loki.example.com/loki/api/v1/push",
+                                "file" => "Absolute path, or relative to the SelfCloud home",
+                                _ => "host:514",
+                            }
+                            class="border p-2 rounded-md font-mono dark:bg-white/10 dark:border-white/5"
+                        />
+                    </div>
+                </div>
+                <Show when=move || kind.get() == "http">
+                    <div class="flex flex-col">
+                        <label class="text-sm dark:text-white/50">"Authorization Header"</label>
+                        <input
+                            type="password"
+                            name="authorization"
+                            placeholder="e.g. Basic dXNlcjpwYXNz, optional"
+                            class="border p-2 rounded-md dark:bg-white/10 dark:border-white/5"
+                        />
+                    </div>
+                </Show>
+                <Show when=move || kind.get() == "file">
+                    <div class="flex gap-2">
+                        <div class="flex flex-col">
+                            <label class="text-sm dark:text-white/50">"Rotate at (MB)"</label>
+                            <input
+                                type="number"
+                                min="1"
+                                name="max_size_mb"
+                                value="100"
+                                class="border p-2 rounded-md w-32 dark:bg-white/10 dark:border-white/5"
+                            />
+                        </div>
+                        <div class="flex flex-col">
+                            <label class="text-sm dark:text-white/50">"Rotated Files Kept"</label>
+                            <input
+                                type="number"
+                                min="1"
+                                name="max_files"
+                                value="5"
+                                class="border p-2 rounded-md w-32 dark:bg-white/10 dark:border-white/5"
+                            />
+                        </div>
+                    </div>
+                </Show>
+                <div class="flex gap-4">
+                    <label class="flex gap-2 items-center text-sm">
+                        <input type="checkbox" name="containers" value="true" checked/>
+                        "Container output"
+                    </label>
+                    <label class="flex gap-2 items-center text-sm">
+                        <input type="checkbox" name="gateway" value="true"/>
+                        "Gateway access logs"
+                    </label>
+                </div>
+                <input
+                    type="submit"
+                    value="Add Sink"
+                    class="cursor-pointer self-start border p-2 px-10 rounded bg-slate-800 text-white dark:border-none dark:bg-white/90 dark:text-black"
+                />
+            </div>
+        </ActionForm>
+    }
+}
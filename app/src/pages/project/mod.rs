@@ -4,11 +4,23 @@ use leptos::prelude::*;
 use leptos_router::components::{Outlet, A};
 
 use crate::api::get_projects;
+use crate::common::ProjectEvent;
+use crate::hooks::use_project_events::use_project_events;
+use import_compose::ImportCompose;
 
 pub mod container_page;
+pub mod deploy_history;
+pub mod deploy_hooks;
+pub mod image_build;
+pub mod image_source;
+pub mod import_compose;
+pub mod log_history;
+pub mod log_sinks;
 pub mod project_page;
+pub mod scheduled_jobs;
 pub mod settings;
 pub mod support_containers;
+pub mod uptime;
 
 #[component]
 pub fn ProjectsList() -> impl IntoView {
@@ -20,6 +32,7 @@ pub fn ProjectsList() -> impl IntoView {
             result.unwrap_or_default()
         },
     );
+    let project_events = expect_context::<ReadSignal<Option<ProjectEvent>>>();
     let create_project = ServerAction::<AddProject>::new();
     let (new_project_name, set_new_project_name) = signal(String::new());
 
@@ -29,6 +42,18 @@ pub fn ProjectsList() -> impl IntoView {
         projects.refetch();
     });
 
+    Effect::new(move |_| {
+        if let Some(
+            ProjectEvent::ProjectCreated { .. }
+            | ProjectEvent::ProjectUpdated { .. }
+            | ProjectEvent::ProjectRemoved { .. }
+            | ProjectEvent::Lagged,
+        ) = project_events.get()
+        {
+            projects.refetch();
+        }
+    });
+
     view! {
         <div class="p-2">
 
@@ -65,6 +90,8 @@ pub fn ProjectsList() -> impl IntoView {
                     </ActionForm>
                 </div>
 
+                <ImportCompose on_import=move |_| projects.refetch()/>
+
             <Suspense>
 
                 {move || {
@@ -97,5 +124,6 @@ pub fn ProjectsList() -> impl IntoView {
 
 #[component]
 pub fn ProjectsHome() -> impl IntoView {
+    provide_context(use_project_events());
     view! { <Outlet/> }
 }
@@ -1,15 +1,23 @@
 use std::collections::BinaryHeap;
 use std::sync::Arc;
 
+use crate::components::container_options::{
+    DependsOnFields, HealthCheckFields, ResourceLimitsFields, RestartPolicyFields,
+};
 use crate::components::toaster::{ToastVariant, ToasterContext};
+use crate::pages::project::deploy_history::DeployHistorySettings;
+use crate::pages::project::deploy_hooks::DeployHooksSettings;
+use crate::pages::project::image_source::ImageSourceSettings;
+use crate::pages::project::log_history::LogRetentionSettings;
+use crate::pages::project::log_sinks::LogSinks;
+use crate::pages::project::scheduled_jobs::ScheduledJobs;
+use crate::pages::project::uptime::{DomainUptimeList, StatusPageSettings};
 use leptos::either::Either;
 use leptos::prelude::*;
 use leptos::server_fn::ServerFn;
 use leptos_router::components::{Form, Outlet, A};
 use leptos_router::hooks::{use_location, use_params};
 use leptos_router::params::Params;
-use leptos_use::use_interval_fn;
-use leptos_use::utils::Pausable;
 use std::collections::HashMap;
 use uuid::Uuid;
 
@@ -23,6 +31,7 @@ use crate::common::EnvironmentVar;
 use crate::common::ExposedPort;
 use crate::common::PortForward;
 use crate::common::Project;
+use crate::common::ProjectEvent;
 use crate::common::ProjectType;
 use crate::common::Volume;
 
@@ -56,6 +65,17 @@ pub fn ProjectPage() -> impl IntoView {
     provide_context(set_trigger);
     provide_context(id);
 
+    let project_events = expect_context::<ReadSignal<Option<ProjectEvent>>>();
+    Effect::new(move |_| {
+        if let Some(event @ (ProjectEvent::ProjectUpdated { .. } | ProjectEvent::Lagged)) =
+            project_events.get()
+        {
+            if event.concerns(id.get_untracked()) {
+                set_trigger.set(());
+            }
+        }
+    });
+
     view! {
             <div class="p-4">
                 <Transition>
@@ -236,12 +256,24 @@ pub fn GeneralSettings() -> impl IntoView {
                             exposed_ports: vec![].into(),
                             support_containers: HashMap::new(),
                             tokens: HashMap::new(),
+                            image_source: None,
+                            deploy_history: Default::default(),
+                            hooks: Default::default(),
+                            jobs: HashMap::new(),
+                            log_retention: Default::default(),
+                            log_sinks: vec![],
 
                             primary_container: Container {
                                 #[cfg(feature = "ssr")]
                                 status: crate::common::ContainerStatus::None,
                                 env_vars: vec![].into(),
                                 volumes: vec![].into(),
+                                restart_policy: Default::default(),
+                                healthcheck: None,
+                                limits: Default::default(),
+                                depends_on: vec![],
+                                #[cfg(feature = "ssr")]
+                                runtime: Default::default(),
                             },
                         })
                     }
@@ -357,7 +389,13 @@ pub fn GeneralSettings() -> impl IntoView {
                                 primary_container: container,
                                 exposed_ports,
                                 tokens,
-                                ..
+                                support_containers,
+                                image_source,
+                                deploy_history,
+                                hooks,
+                                jobs,
+                                log_retention,
+                                log_sinks,
                             } => {
                                 let (exposed_ports, set_exposed_ports) = signal({
                                     let mut map = vec![];
@@ -380,7 +418,33 @@ pub fn GeneralSettings() -> impl IntoView {
                                     }
                                     map
                                 });
+                                let restart_policy = container.restart_policy;
+                                let healthcheck = container.healthcheck;
+                                let limits = container.limits;
+                                let depends_on = container.depends_on;
+                                let mut support_names = support_containers
+                                    .into_keys()
+                                    .collect::<Vec<_>>();
+                                support_names.sort();
                                 view! {
+                                    <ImageSourceSettings id=id.get_untracked() image_source=image_source/>
+                                    <div class="h-4"></div>
+                                    <DeployHistorySettings
+                                        id=id.get_untracked()
+                                        deploy_history=deploy_history
+                                    />
+                                    <div class="h-4"></div>
+                                    <DeployHooksSettings id=id.get_untracked() hooks=hooks/>
+                                    <div class="h-4"></div>
+                                    <ScheduledJobs id=id.get_untracked() jobs=jobs/>
+                                    <div class="h-4"></div>
+                                    <LogRetentionSettings
+                                        id=id.get_untracked()
+                                        log_retention=log_retention
+                                    />
+                                    <div class="h-4"></div>
+                                    <LogSinks id=id.get_untracked() log_sinks=log_sinks/>
+                                    <div class="h-4"></div>
                                     <ActionForm action=update_image_action>
                                         <input
                                             name="id"
@@ -658,6 +722,28 @@ pub fn GeneralSettings() -> impl IntoView {
                                             </button>
                                         </div>
 
+                                        <div class="h-4"></div>
+                                        <RestartPolicyFields
+                                            name="restart_policy".to_string()
+                                            policy=restart_policy
+                                        />
+
+                                        <div class="h-4"></div>
+                                        <HealthCheckFields
+                                            name="healthcheck".to_string()
+                                            healthcheck=healthcheck
+                                        />
+
+                                        <div class="h-4"></div>
+                                        <ResourceLimitsFields name="limits".to_string() limits=limits/>
+
+                                        <div class="h-4"></div>
+                                        <DependsOnFields
+                                            name="depends_on".to_string()
+                                            depends_on=depends_on
+                                            options=support_names
+                                        />
+
                                         <div class="h-4"></div>
                                         <input
                                             type="submit"
@@ -694,17 +780,16 @@ pub fn DomainsList() -> impl IntoView {
         },
     );
 
-    let Pausable {
-        ..
-        // pause,
-        // resume,
-        // is_active,
-    } = use_interval_fn(
-        move || {
-            domains.refetch();
-        },
-        5000,
-    );
+    let project_events = expect_context::<ReadSignal<Option<ProjectEvent>>>();
+    Effect::new(move |_| {
+        if let Some(event @ (ProjectEvent::DomainUpdated { .. } | ProjectEvent::Lagged)) =
+            project_events.get()
+        {
+            if event.concerns(id.get_untracked()) {
+                domains.refetch();
+            }
+        }
+    });
 
     let (new_domain, set_new_domain) = signal(String::new());
 
@@ -812,5 +897,8 @@ pub fn DomainsList() -> impl IntoView {
             />
 
         </Transition>
+
+        <DomainUptimeList/>
+        <StatusPageSettings/>
     }
 }
@@ -0,0 +1,237 @@
+use std::collections::HashMap;
+
+use leptos::prelude::*;
+use uuid::Uuid;
+
+use crate::api::{get_job_runs, DeleteJob, RunJobNow, SetJob};
+use crate::common::{ConcurrencyPolicy, ScheduledJob};
+use crate::components::toaster::{ToastVariant, ToasterContext};
+
+/// Cron jobs run in one-off containers on the project network
+#[component]
+pub fn ScheduledJobs(id: Uuid, jobs: HashMap<String, ScheduledJob>) -> impl IntoView {
+    let set_job_action = ServerAction::<SetJob>::new();
+    let delete_job_action = ServerAction::<DeleteJob>::new();
+    let run_job_action = ServerAction::<RunJobNow>::new();
+    let toast_context = expect_context::<ToasterContext>();
+    let set_trigger = expect_context::<WriteSignal<()>>();
+
+    Effect::new({
+        let toast_context = toast_context.clone();
+        move |_| match set_job_action.value().get() {
+            Some(Ok(_)) => {
+                toast_context.toast("Job Saved", ToastVariant::Success);
+                set_trigger.set(());
+            }
+            Some(Err(err)) => toast_context.toast(err.to_string(), ToastVariant::Error),
+            None => {}
+        }
+    });
+    Effect::new({
+        let toast_context = toast_context.clone();
+        move |_| match delete_job_action.value().get() {
+            Some(Ok(_)) => {
+                toast_context.toast("Job Deleted", ToastVariant::Success);
+                set_trigger.set(());
+            }
+            Some(Err(err)) => toast_context.toast(err.to_string(), ToastVariant::Error),
+            None => {}
+        }
+    });
+    Effect::new(move |_| match run_job_action.value().get() {
+        Some(Ok(_)) => toast_context.toast("Job Starting", ToastVariant::Success),
+        Some(Err(err)) => toast_context.toast(err.to_string(), ToastVariant::Error),
+        None => {}
+    });
+
+    let mut jobs = jobs.into_iter().collect::<Vec<_>>();
+    jobs.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+    view! {
+        <div class="text-xl">"Scheduled Jobs"</div>
+        <div class="text-sm dark:text-white/50">
+            "Cron schedules in UTC, each run gets a one-off container with the primary env and volumes. Saving an existing name replaces that job"
+        </div>
+        <div class="flex flex-col gap-1 p-2">
+            {if jobs.is_empty() {
+                view! { <div class="text-sm dark:text-white/50">"No jobs yet"</div> }.into_any()
+            } else {
+                jobs.into_iter()
+                    .map(|(name, job)| {
+                        let run_name = name.clone();
+                        let delete_name = name.clone();
+                        view! {
+                            <div class="flex flex-col gap-1 text-sm p-2 rounded border dark:border-white/10">
+                                <div class="flex gap-4 items-center">
+                                    <div class="flex flex-col grow min-w-0">
+                                        <div class="font-semibold">
+                                            {name.clone()}
+                                            {(!job.enabled).then(|| " (disabled)")}
+                                        </div>
+                                        <div class="font-mono truncate dark:text-white/50">
+                                            {format!("{}  {}", job.schedule, job.command)}
+                                        </div>
+                                        <div class="dark:text-white/50">
+                                            {job.image.clone().unwrap_or("Primary image".to_string())}
+                                            " · "
+                                            {match job.concurrency {
+                                                ConcurrencyPolicy::Forbid => "Skip while running",
+                                                ConcurrencyPolicy::Replace => "Replace running",
+                                            }}
+                                        </div>
+                                    </div>
+                                    <button
+                                        type="button"
+                                        class="p-2 px-4 rounded border disabled:opacity-50"
+                                        disabled=move || run_job_action.pending().get()
+                                        on:click=move |_| {
+                                            run_job_action
+                                                .dispatch(RunJobNow {
+                                                    id,
+                                                    name: run_name.clone(),
+                                                });
+                                        }
+                                    >
+                                        "Run Now"
+                                    </button>
+                                    <button
+                                        type="button"
+                                        class="p-2 px-4 rounded border border-red-600 text-red-600 disabled:opacity-50"
+                                        disabled=move || delete_job_action.pending().get()
+                                        on:click=move |_| {
+                                            delete_job_action
+                                                .dispatch(DeleteJob {
+                                                    id,
+                                                    name: delete_name.clone(),
+                                                });
+                                        }
+                                    >
+                                        "Delete"
+                                    </button>
+                                </div>
+                                <JobRuns id name/>
+                            </div>
+                        }
+                    })
+                    .collect_view()
+                    .into_any()
+            }}
+        </div>
+        <ActionForm action=set_job_action>
+            <input name="id" type="hidden" prop:value=id.to_string()/>
+            <div class="flex flex-col gap-2 p-2">
+                <div class="flex gap-2 flex-wrap">
+                    <div class="flex flex-col">
+                        <label class="text-sm dark:text-white/50">"Name"</label>
+                        <input
+                            type="text"
+                            name="name"
+                            placeholder="e.g. cleanup"
+                            class="border p-2 rounded-md dark:bg-white/10 dark:border-white/5"
+                        />
+                    </div>
+                    <div class="flex flex-col">
+                        <label class="text-sm dark:text-white/50">"Schedule"</label>
+                        <input
+                            type="text"
+                            name="schedule"
+                            placeholder="*/15 * * * *"
+                            class="border p-2 rounded-md font-mono dark:bg-white/10 dark:border-white/5"
+                        />
+                    </div>
+                    <div class="flex flex-col">
+                        <label class="text-sm dark:text-white/50">"Concurrency"</label>
+                        <select
+                            name="concurrency"
+                            class="p-2 bg-white border rounded-md dark:bg-white/10 dark:border-white/5"
+                        >
+                            <option value="Forbid">"Skip while running"</option>
+                            <option value="Replace">"Replace running"</option>
+                        </select>
+                    </div>
+                </div>
+                <div class="flex flex-col">
+                    <label class="text-sm dark:text-white/50">"Command"</label>
+                    <input
+                        type="text"
+                        name="command"
+                        placeholder="e.g. ./manage.py clearsessions"
+                        class="border p-2 rounded-md font-mono dark:bg-white/10 dark:border-white/5"
+                    />
+                </div>
+                <div class="flex flex-col">
+                    <label class="text-sm dark:text-white/50">"Image"</label>
+                    <input
+                        type="text"
+                        name="image"
+                        placeholder="Primary image when empty"
+                        class="border p-2 rounded-md dark:bg-white/10 dark:border-white/5"
+                    />
+                </div>
+                <label class="flex gap-2 items-center text-sm">
+                    <input type="checkbox" name="enabled" value="true" checked/>
+                    "Enabled"
+                </label>
+                <input
+                    type="submit"
+                    value="Save Job"
+                    class="cursor-pointer self-start border p-2 px-10 rounded bg-slate-800 text-white dark:border-none dark:bg-white/90 dark:text-black"
+                />
+            </div>
+        </ActionForm>
+    }
+}
+
+#[component]
+fn JobRuns(id: Uuid, name: String) -> impl IntoView {
+    let (refresh, set_refresh) = signal(());
+    let runs = Resource::new(
+        move || (refresh.get(), name.clone()),
+        move |(_, name)| async move { get_job_runs(id, name).await },
+    );
+
+    view! {
+        <details class="dark:text-white/50">
+            <summary class="cursor-pointer">
+                "Runs "
+                <button type="button" class="underline" on:click=move |_| set_refresh.set(())>
+                    "Refresh"
+                </button>
+            </summary>
+            <Transition fallback=move || view! { <div>"Loading runs..."</div> }>
+                {move || {
+                    runs.get()
+                        .map(|runs| match runs {
+                            Ok(runs) if runs.is_empty() => view! { <div>"Not run yet"</div> }.into_any(),
+                            Ok(runs) => {
+                                runs.into_iter()
+                                    .map(|run| {
+                                        let outcome = match (run.finished_at, run.exit_code) {
+                                            (None, _) => "running".to_string(),
+                                            (Some(_), Some(code)) => format!("exited with {code}"),
+                                            (Some(_), None) => "did not finish".to_string(),
+                                        };
+                                        view! {
+                                            <details class="pl-4">
+                                                <summary class="cursor-pointer">
+                                                    {run.started_at.format("%Y-%m-%d %H:%M:%S UTC").to_string()}
+                                                    {run.manual.then(|| " (manual)")}
+                                                    " · "
+                                                    {outcome}
+                                                </summary>
+                                                <pre class="bg-black text-white p-2 rounded text-xs overflow-auto max-h-64">
+                                                    {run.logs}
+                                                </pre>
+                                            </details>
+                                        }
+                                    })
+                                    .collect_view()
+                                    .into_any()
+                            }
+                            Err(err) => view! { <div>{err.to_string()}</div> }.into_any(),
+                        })
+                }}
+            </Transition>
+        </details>
+    }
+}
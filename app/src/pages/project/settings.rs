@@ -5,6 +5,7 @@ use uuid::Uuid;
 use crate::api::{DeleteProject, UpdateProjectNameToken};
 use crate::common::{Project, ProjectType, Token};
 use crate::components::toaster::{ToastVariant, ToasterContext};
+use crate::components::webhooks::Webhooks;
 use crate::utils::random_ascii_string;
 
 #[component]
@@ -62,6 +63,11 @@ pub fn ProjectSettings() -> impl IntoView {
                                 let (tokens, set_tokens) = signal(tokens);
                                 view! {
                                     <div class="text-md">"Tokens"</div>
+                                    <div class="text-sm dark:text-white/50 font-mono break-all">
+                                        "docker login <panel host> -u " {move || id.get().to_string()} " -p <token>"
+                                        <br/>
+                                        "docker push <panel host>/" {move || id.get().to_string()} ":latest"
+                                    </div>
                                     <div class="">
                                         <For
                                             each=move || tokens.get().into_iter()
@@ -175,6 +181,10 @@ pub fn ProjectSettings() -> impl IntoView {
 
             </ActionForm>
 
+            <div class="flex flex-col gap-2 mt-5">
+                {move || view! { <Webhooks project_id=Some(id.get())/> }}
+            </div>
+
             <button
                 class="p-2 rounded bg-red-700 px-6 text-white mt-5"
                 on:click=move |_| {
@@ -1,5 +1,5 @@
 use crate::common::Project;
-use crate::common::{Container, ProjectType, SupportContainer};
+use crate::common::{ProjectType, SupportContainer, SupportPort, Volume};
 use leptos::prelude::*;
 use leptos::server_fn::ServerFn;
 use leptos_router::components::Form;
@@ -7,6 +7,9 @@ use uuid::Uuid;
 
 use crate::api::SetSupportContainers;
 use crate::common::EnvironmentVar;
+use crate::components::container_options::{
+    DependsOnFields, HealthCheckFields, ResourceLimitsFields, RestartPolicyFields,
+};
 
 #[component]
 pub fn SupportContainers() -> impl IntoView {
@@ -61,15 +64,7 @@ pub fn SupportContainers() -> impl IntoView {
                                                         containers
                                                             .insert(
                                                                 new_service_name.get_untracked(),
-                                                                SupportContainer {
-                                                                    image: String::new(),
-                                                                    container: Container {
-                                                                        env_vars: vec![].into(),
-                                                                        volumes: vec![].into(),
-                                                                        #[cfg(feature = "ssr")]
-                                                                        status: crate::common::ContainerStatus::None,
-                                                                    },
-                                                                },
+                                                                SupportContainer::new(String::new()),
                                                             );
                                                         set_support_containers.set(containers);
                                                     }
@@ -97,6 +92,18 @@ pub fn SupportContainers() -> impl IntoView {
                                             key=|p| p.0.clone()
                                             children=move |cont| {
                                                 let name = StoredValue::new(cont.0.clone());
+                                                let restart_policy = StoredValue::new(
+                                                    cont.1.container.restart_policy.clone(),
+                                                );
+                                                let healthcheck = StoredValue::new(
+                                                    cont.1.container.healthcheck.clone(),
+                                                );
+                                                let limits = StoredValue::new(
+                                                    cont.1.container.limits.clone(),
+                                                );
+                                                let depends_on = StoredValue::new(
+                                                    cont.1.container.depends_on.clone(),
+                                                );
                                                 let (env_vars, set_env_vars) = signal({
                                                     let mut map = Vec::with_capacity(
                                                         cont.1.container.env_vars.len(),
@@ -106,10 +113,37 @@ pub fn SupportContainers() -> impl IntoView {
                                                     }
                                                     map
                                                 });
+                                                let (volumes, set_volumes) = signal({
+                                                    let mut map = vec![];
+                                                    for vol in cont.1.container.volumes.into_iter() {
+                                                        map.push((map.len(), vol));
+                                                    }
+                                                    map
+                                                });
+                                                let (ports, set_ports) = signal({
+                                                    let mut map = vec![];
+                                                    for port in cont.1.ports.into_iter() {
+                                                        map.push((map.len(), port));
+                                                    }
+                                                    map
+                                                });
                                                 view! {
                                                     <div class="border p-4 dark:bg-white/10 bg-black/10 dark:border-white/20 rounded-md">
 
-                                                        <div class="text-md">{name.get_value()}</div>
+                                                        <div class="flex justify-between items-center">
+                                                            <div class="text-md">{name.get_value()}</div>
+                                                            <button
+                                                                type="button"
+                                                                class="p-2 rounded bg-red-700 px-6 text-white"
+                                                                on:click=move |_| {
+                                                                    let mut containers = support_containers.get_untracked();
+                                                                    containers.remove(&name.get_value());
+                                                                    set_support_containers.set(containers);
+                                                                }
+                                                            >
+                                                                "Remove Service"
+                                                            </button>
+                                                        </div>
                                                         <div class="h-4"></div>
                                                         <input
                                                             name=format!(
@@ -135,6 +169,38 @@ pub fn SupportContainers() -> impl IntoView {
                                                         />
                                                         <div class="h-4"></div>
 
+                                                        <div class="flex gap-4 flex-wrap">
+                                                            <div class="flex flex-col grow">
+                                                                <label class="text-sm dark:text-white/50">
+                                                                    "Command"
+                                                                </label>
+                                                                <input
+                                                                    class="p-2 border rounded bg-white dark:bg-white/10 dark:border-white/5"
+                                                                    name=format!(
+                                                                        "support_containers[{}][command]",
+                                                                        name.get_value(),
+                                                                    )
+                                                                    prop:value=cont.1.command.unwrap_or_default()
+                                                                    placeholder="Image default"
+                                                                />
+                                                            </div>
+                                                            <div class="flex flex-col grow">
+                                                                <label class="text-sm dark:text-white/50">
+                                                                    "Entrypoint"
+                                                                </label>
+                                                                <input
+                                                                    class="p-2 border rounded bg-white dark:bg-white/10 dark:border-white/5"
+                                                                    name=format!(
+                                                                        "support_containers[{}][entrypoint]",
+                                                                        name.get_value(),
+                                                                    )
+                                                                    prop:value=cont.1.entrypoint.unwrap_or_default()
+                                                                    placeholder="Image default"
+                                                                />
+                                                            </div>
+                                                        </div>
+                                                        <div class="h-4"></div>
+
                                                         <div class="text-md">"Environment Variable"</div>
 
                                                         <div class="">
@@ -225,6 +291,198 @@ pub fn SupportContainers() -> impl IntoView {
                                                             </button>
                                                         </div>
 
+                                                        <div class="h-4"></div>
+                                                        <div class="text-md">"Volumes / Persistent Storage"</div>
+                                                        <div>
+                                                            <For
+                                                                each=move || volumes.get().into_iter()
+                                                                key=|p| p.0
+                                                                children=move |(index, vol)| {
+                                                                    view! {
+                                                                        <div class="flex gap-4 flex-wrap p-2 border dark:border-white/20 m-2 rounded">
+                                                                            <div class="flex flex-col">
+                                                                                <label class="text-sm dark:text-white/50">
+                                                                                    "Volume Name"
+                                                                                </label>
+                                                                                <input
+                                                                                    prop:value=vol.name
+                                                                                    type="text"
+                                                                                    name=format!(
+                                                                                        "support_containers[{}][volumes][{}][name]",
+                                                                                        name.get_value(),
+                                                                                        index,
+                                                                                    )
+                                                                                    class="border p-2 rounded-md dark:bg-white/10 dark:border-white/5"
+                                                                                    placeholder="e.g. pgdata"
+                                                                                />
+                                                                            </div>
+                                                                            <div class="flex flex-col">
+                                                                                <label class="text-sm dark:text-white/50">
+                                                                                    "Container Path"
+                                                                                </label>
+                                                                                <input
+                                                                                    prop:value=vol.container_path
+                                                                                    type="text"
+                                                                                    name=format!(
+                                                                                        "support_containers[{}][volumes][{}][container_path]",
+                                                                                        name.get_value(),
+                                                                                        index,
+                                                                                    )
+                                                                                    class="border p-2 rounded-md dark:bg-white/10 dark:border-white/5"
+                                                                                    placeholder="e.g. /var/lib/postgresql/data"
+                                                                                />
+                                                                            </div>
+                                                                            <button
+                                                                                type="button"
+                                                                                class="p-2 rounded bg-red-700 px-6 text-white mt-5"
+                                                                                on:click=move |_| {
+                                                                                    let mut v = volumes.get_untracked();
+                                                                                    v.retain(|(i, _)| *i != index);
+                                                                                    set_volumes.set(v);
+                                                                                }
+                                                                            >
+                                                                                "Remove"
+                                                                            </button>
+                                                                        </div>
+                                                                    }
+                                                                }
+                                                            />
+                                                            <button
+                                                                type="button"
+                                                                class="p-2 rounded border bg-white/90 px-6 text-black"
+                                                                on:click=move |_| {
+                                                                    let new_vol = Volume {
+                                                                        name: "".to_string(),
+                                                                        container_path: "".to_string(),
+                                                                    };
+                                                                    let mut v = volumes.get_untracked();
+                                                                    v.push((v.last().map(|p| p.0).unwrap_or_default() + 1, new_vol));
+                                                                    set_volumes.set(v);
+                                                                }
+                                                            >
+                                                                "Add Volume"
+                                                            </button>
+                                                        </div>
+
+                                                        <div class="h-4"></div>
+                                                        <div class="text-md">"Published Ports"</div>
+                                                        <div>
+                                                            <For
+                                                                each=move || ports.get().into_iter()
+                                                                key=|p| p.0
+                                                                children=move |(index, port)| {
+                                                                    view! {
+                                                                        <div class="flex gap-4 flex-wrap p-2 border dark:border-white/20 m-2 rounded">
+                                                                            <div class="flex flex-col">
+                                                                                <label class="text-sm dark:text-white/50">
+                                                                                    "Container Port"
+                                                                                </label>
+                                                                                <input
+                                                                                    prop:value=port.port
+                                                                                    type="number"
+                                                                                    required
+                                                                                    name=format!(
+                                                                                        "support_containers[{}][ports][{}][port]",
+                                                                                        name.get_value(),
+                                                                                        index,
+                                                                                    )
+                                                                                    class="border p-2 rounded-md dark:bg-white/10 dark:border-white/5"
+                                                                                />
+                                                                            </div>
+                                                                            <div class="flex flex-col">
+                                                                                <label class="text-sm dark:text-white/50">
+                                                                                    "Host Port"
+                                                                                </label>
+                                                                                <input
+                                                                                    prop:value=port.host_port
+                                                                                    type="number"
+                                                                                    required
+                                                                                    name=format!(
+                                                                                        "support_containers[{}][ports][{}][host_port]",
+                                                                                        name.get_value(),
+                                                                                        index,
+                                                                                    )
+                                                                                    class="border p-2 rounded-md dark:bg-white/10 dark:border-white/5"
+                                                                                />
+                                                                            </div>
+                                                                            <button
+                                                                                type="button"
+                                                                                class="p-2 rounded bg-red-700 px-6 text-white mt-5"
+                                                                                on:click=move |_| {
+                                                                                    let mut p = ports.get_untracked();
+                                                                                    p.retain(|(i, _)| *i != index);
+                                                                                    set_ports.set(p);
+                                                                                }
+                                                                            >
+                                                                                "Remove"
+                                                                            </button>
+                                                                        </div>
+                                                                    }
+                                                                }
+                                                            />
+                                                            <button
+                                                                type="button"
+                                                                class="p-2 rounded border bg-white/90 px-6 text-black"
+                                                                on:click=move |_| {
+                                                                    let mut p = ports.get_untracked();
+                                                                    p.push((
+                                                                        p.last().map(|p| p.0).unwrap_or_default() + 1,
+                                                                        SupportPort { port: 0, host_port: 0 },
+                                                                    ));
+                                                                    set_ports.set(p);
+                                                                }
+                                                            >
+                                                                "Add Port"
+                                                            </button>
+                                                        </div>
+
+                                                        <div class="h-4"></div>
+                                                        <RestartPolicyFields
+                                                            name=format!(
+                                                                "support_containers[{}][restart_policy]",
+                                                                name.get_value(),
+                                                            )
+                                                            policy=restart_policy.get_value()
+                                                        />
+
+                                                        <div class="h-4"></div>
+                                                        <HealthCheckFields
+                                                            name=format!(
+                                                                "support_containers[{}][healthcheck]",
+                                                                name.get_value(),
+                                                            )
+                                                            healthcheck=healthcheck.get_value()
+                                                        />
+
+                                                        <div class="h-4"></div>
+                                                        <ResourceLimitsFields
+                                                            name=format!(
+                                                                "support_containers[{}][limits]",
+                                                                name.get_value(),
+                                                            )
+                                                            limits=limits.get_value()
+                                                        />
+
+                                                        <div class="h-4"></div>
+                                                        {move || {
+                                                            let mut others = support_containers
+                                                                .get()
+                                                                .into_keys()
+                                                                .filter(|other| *other != name.get_value())
+                                                                .collect::<Vec<_>>();
+                                                            others.sort();
+                                                            view! {
+                                                                <DependsOnFields
+                                                                    name=format!(
+                                                                        "support_containers[{}][depends_on]",
+                                                                        name.get_value(),
+                                                                    )
+                                                                    depends_on=depends_on.get_value()
+                                                                    options=others
+                                                                />
+                                                            }
+                                                        }}
+
                                                     </div>
                                                 }
                                             }
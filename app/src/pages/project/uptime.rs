@@ -0,0 +1,212 @@
+use leptos::prelude::*;
+use uuid::Uuid;
+
+use crate::api::{get_project_uptime, SetStatusPage};
+use crate::common::{Project, UptimeCheck};
+use crate::components::toaster::{ToastVariant, ToasterContext};
+
+fn percent(uptime: Option<f64>) -> String {
+    uptime
+        .map(|uptime| format!("{uptime:.2}%"))
+        .unwrap_or("-".to_string())
+}
+
+fn last_check(check: &UptimeCheck) -> String {
+    let mut text = match (check.status, check.latency_ms) {
+        (Some(status), Some(latency)) => format!("HTTP {status} in {latency} ms"),
+        _ => check.reason(),
+    };
+    match (check.tls_valid, check.tls_expires_in_days) {
+        (Some(false), _) => text.push_str(", certificate invalid"),
+        (_, Some(days)) => text.push_str(&format!(", certificate expires in {days} days")),
+        _ => {}
+    }
+    text
+}
+
+/// Probe results of the project's domains with their incidents
+#[component]
+pub fn DomainUptimeList() -> impl IntoView {
+    let id = expect_context::<Signal<Uuid>>();
+    let uptime = Resource::new(move || id.get(), get_project_uptime);
+
+    view! {
+        <div class="p-2 text-xl">"Uptime"</div>
+        <div class="px-2 text-sm dark:text-white/50">
+            "Every domain is requested once a minute, over HTTPS once it has a certificate"
+        </div>
+        <Transition>
+            {move || {
+                uptime
+                    .get()
+                    .and_then(|uptime| uptime.ok())
+                    .map(|domains| {
+                        if domains.is_empty() {
+                            return view! { <div class="p-2 text-sm">"No checks yet"</div> }
+                                .into_any();
+                        }
+                        domains
+                            .into_iter()
+                            .map(|domain| {
+                                let up = domain.last.as_ref().map(|last| last.is_up());
+                                view! {
+                                    <div class="p-2">
+                                        <div class="w-full border bg-white dark:bg-white/10 dark:border-white/20 rounded-md p-4 flex flex-col gap-2">
+                                            <div class="flex items-center gap-2">
+                                                <div
+                                                    class="w-2 h-2 rounded-full"
+                                                    class=("bg-green-500", up == Some(true))
+                                                    class=("bg-red-500", up == Some(false))
+                                                    class=("bg-slate-400", up.is_none())
+                                                ></div>
+                                                <div class="text-lg">{domain.domain}</div>
+                                            </div>
+                                            <div class="text-sm dark:text-white/50">
+                                                {domain.last.as_ref().map(last_check)}
+                                            </div>
+                                            <div class="flex gap-6 text-sm">
+                                                <div>"24h " {percent(domain.uptime_day)}</div>
+                                                <div>"7d " {percent(domain.uptime_week)}</div>
+                                                <div>"30d " {percent(domain.uptime_month)}</div>
+                                                <div>
+                                                    "Latency "
+                                                    {domain
+                                                        .latency_ms_day
+                                                        .map(|latency| format!("{latency} ms"))
+                                                        .unwrap_or("-".to_string())}
+                                                </div>
+                                            </div>
+                                            <div class="flex flex-col text-sm">
+                                                {domain
+                                                    .incidents
+                                                    .into_iter()
+                                                    .take(10)
+                                                    .map(|incident| {
+                                                        view! {
+                                                            <div class="text-red-600 dark:text-red-400">
+                                                                {incident.started.format("%Y-%m-%d %H:%M UTC").to_string()}
+                                                                " · "
+                                                                {incident.reason}
+                                                                " · "
+                                                                {match incident.ended {
+                                                                    Some(ended) => {
+                                                                        format!(
+                                                                            "resolved after {} min",
+                                                                            (ended - incident.started).num_minutes().max(1),
+                                                                        )
+                                                                    }
+                                                                    None => "ongoing".to_string(),
+                                                                }}
+                                                            </div>
+                                                        }
+                                                    })
+                                                    .collect_view()}
+                                            </div>
+                                        </div>
+                                    </div>
+                                }
+                            })
+                            .collect_view()
+                            .into_any()
+                    })
+            }}
+        </Transition>
+    }
+}
+
+/// Opt-in public page with the uptime of the project's domains
+#[component]
+pub fn StatusPageSettings() -> impl IntoView {
+    let id = expect_context::<Signal<Uuid>>();
+    let project = expect_context::<Resource<Result<Project, ServerFnError>>>();
+    let set_trigger = expect_context::<WriteSignal<()>>();
+    let toast_context = expect_context::<ToasterContext>();
+    let status_page_action = ServerAction::<SetStatusPage>::new();
+
+    Effect::new(move |_| match status_page_action.value().get() {
+        Some(Ok(_)) => {
+            toast_context.toast("Status Page Updated", ToastVariant::Success);
+            set_trigger.set(());
+        }
+        Some(Err(err)) => toast_context.toast(err.to_string(), ToastVariant::Error),
+        None => {}
+    });
+
+    let status_page = Memo::new(move |_| {
+        project
+            .get()
+            .and_then(|project| project.ok())
+            .and_then(|project| project.status_page)
+    });
+
+    view! {
+        <div class="p-2 text-xl">"Status Page"</div>
+        <div class="px-2 text-sm dark:text-white/50">
+            "Served publicly by the gateway on its own domain, with the uptime and incidents of the domains above. Point the domain at this server, a certificate is requested like for any other domain"
+        </div>
+        <div class="p-2">
+            <ActionForm action=status_page_action>
+                <input type="hidden" name="id" prop:value=move || id.get().to_string()/>
+                <div class="flex gap-2 items-end flex-wrap">
+                    <div class="flex flex-col grow">
+                        <label class="text-sm dark:text-white/50">"Domain"</label>
+                        <input
+                            name="domain"
+                            placeholder="status.example.com"
+                            prop:value=move || {
+                                status_page.get().map(|page| page.domain.to_string()).unwrap_or_default()
+                            }
+                            class="p-2 border rounded bg-white dark:bg-white/10 dark:border-white/5"
+                        />
+                    </div>
+                    <div class="flex flex-col grow">
+                        <label class="text-sm dark:text-white/50">"Title"</label>
+                        <input
+                            name="title"
+                            placeholder="Defaults to the project name"
+                            prop:value=move || {
+                                status_page.get().map(|page| page.title).unwrap_or_default()
+                            }
+                            class="p-2 border rounded bg-white dark:bg-white/10 dark:border-white/5"
+                        />
+                    </div>
+                    <input
+                        type="submit"
+                        value="Save"
+                        class="border p-2 px-10 rounded bg-slate-800 text-white dark:border-none dark:bg-white/90 dark:text-black"
+                    />
+                </div>
+            </ActionForm>
+            <Show when=move || status_page.get().is_some()>
+                <div class="flex gap-4 items-center pt-2 text-sm">
+                    <a
+                        class="underline"
+                        target="_blank"
+                        href=move || {
+                            status_page
+                                .get()
+                                .map(|page| format!("https://{}/", page.domain))
+                                .unwrap_or_default()
+                        }
+                    >
+                        "Open status page"
+                    </a>
+                    <button
+                        type="button"
+                        class="p-2 px-4 rounded border border-red-600 text-red-600"
+                        on:click=move |_| {
+                            status_page_action
+                                .dispatch(SetStatusPage {
+                                    id: id.get_untracked(),
+                                    domain: String::new(),
+                                    title: String::new(),
+                                });
+                        }
+                    >
+                        "Turn Off"
+                    </button>
+                </div>
+            </Show>
+        </div>
+    }
+}
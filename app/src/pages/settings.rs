@@ -1,4 +1,6 @@
 use crate::components::nav_bar::NavBar;
+use crate::components::webhooks::Webhooks;
+use crate::pages::alert_settings::AlertSettingsPanel;
 use crate::AuthCheck;
 use leptos::prelude::*;
 
@@ -92,6 +94,12 @@ pub fn Settings() -> impl IntoView {
                 }}
 
             </div>
+
+            <AlertSettingsPanel/>
+
+            <div class="bg-white dark:bg-zinc-900 rounded-lg p-6 shadow mt-4 flex flex-col gap-2">
+                <Webhooks project_id=None/>
+            </div>
         </div>
     }
 }
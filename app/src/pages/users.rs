@@ -0,0 +1,188 @@
+use leptos::prelude::*;
+
+use crate::auth::{get_users, AddUser, AuthType, ChangePassword, RemoveUser, ResetPassword, User};
+use crate::components::toaster::{ToastVariant, ToasterContext};
+
+/// Users who can sign in to the panel, and the signed in user's own password
+#[component]
+pub fn Users() -> impl IntoView {
+    let add_action = ServerAction::<AddUser>::new();
+    let remove_action = ServerAction::<RemoveUser>::new();
+    let reset_action = ServerAction::<ResetPassword>::new();
+    let change_action = ServerAction::<ChangePassword>::new();
+    let toast_context = expect_context::<ToasterContext>();
+    let auth = expect_context::<Resource<Result<AuthType, ServerFnError>>>();
+
+    let users = Resource::new(
+        move || (add_action.version().get(), remove_action.version().get()),
+        |_| get_users(),
+    );
+    let current_email = move || {
+        auth.get()
+            .and_then(|auth| auth.ok())
+            .and_then(|auth| auth.as_authorized().map(|user| user.email.clone()))
+    };
+
+    let toast_outcome = |value: RwSignal<Option<Result<(), ServerFnError>>>,
+                         success: &'static str| {
+        let toast_context = toast_context.clone();
+        Effect::new(move |_| match value.get() {
+            Some(Ok(_)) => toast_context.toast(success, ToastVariant::Success),
+            Some(Err(err)) => toast_context.toast(err.to_string(), ToastVariant::Error),
+            None => {}
+        });
+    };
+    toast_outcome(add_action.value(), "User Added");
+    toast_outcome(remove_action.value(), "User Removed");
+    toast_outcome(reset_action.value(), "Password Reset");
+    toast_outcome(change_action.value(), "Password Changed");
+
+    let input_class = "border p-2 rounded-md dark:bg-white/10 dark:border-white/5";
+    let submit_class = "cursor-pointer self-start border p-2 px-10 rounded bg-slate-800 text-white dark:border-none dark:bg-white/90 dark:text-black";
+
+    view! {
+        <div class="p-4 max-w-4xl mx-auto flex flex-col gap-4">
+            <h1 class="text-2xl font-bold">"Users"</h1>
+
+            <div class="bg-white dark:bg-zinc-900 rounded-lg p-6 shadow flex flex-col gap-2">
+                <h2 class="text-xl font-semibold">"Panel Users"</h2>
+                <div class="text-sm dark:text-white/50">
+                    "Everyone listed can sign in and manage every project. Removing a user ends their sessions"
+                </div>
+                <Transition>
+                    {move || {
+                        users
+                            .get()
+                            .and_then(|users| users.ok())
+                            .map(|users| {
+                                let current_email = current_email();
+                                users
+                                    .into_iter()
+                                    .map(|user| {
+                                        let is_current = current_email.as_ref() == Some(&user.email);
+                                        view! {
+                                            <UserRow user is_current remove_action reset_action/>
+                                        }
+                                    })
+                                    .collect_view()
+                            })
+                    }}
+                </Transition>
+            </div>
+
+            <div class="bg-white dark:bg-zinc-900 rounded-lg p-6 shadow flex flex-col gap-2">
+                <h2 class="text-xl font-semibold">"Add User"</h2>
+                <ActionForm action=add_action>
+                    <div class="flex gap-2 items-end flex-wrap">
+                        <div class="flex flex-col">
+                            <label class="text-sm dark:text-white/50">"Name"</label>
+                            <input type="text" name="name" required class=input_class/>
+                        </div>
+                        <div class="flex flex-col grow">
+                            <label class="text-sm dark:text-white/50">"Email"</label>
+                            <input type="email" name="email" required class=input_class/>
+                        </div>
+                        <div class="flex flex-col">
+                            <label class="text-sm dark:text-white/50">"Password"</label>
+                            <input
+                                type="password"
+                                name="password"
+                                required
+                                minlength="8"
+                                autocomplete="new-password"
+                                class=input_class
+                            />
+                        </div>
+                        <input type="submit" value="Add" class=submit_class/>
+                    </div>
+                </ActionForm>
+            </div>
+
+            <div class="bg-white dark:bg-zinc-900 rounded-lg p-6 shadow flex flex-col gap-2">
+                <h2 class="text-xl font-semibold">"Change Your Password"</h2>
+                <ActionForm action=change_action>
+                    <div class="flex gap-2 items-end flex-wrap">
+                        <div class="flex flex-col">
+                            <label class="text-sm dark:text-white/50">"Current Password"</label>
+                            <input
+                                type="password"
+                                name="current_password"
+                                required
+                                autocomplete="current-password"
+                                class=input_class
+                            />
+                        </div>
+                        <div class="flex flex-col">
+                            <label class="text-sm dark:text-white/50">"New Password"</label>
+                            <input
+                                type="password"
+                                name="new_password"
+                                required
+                                minlength="8"
+                                autocomplete="new-password"
+                                class=input_class
+                            />
+                        </div>
+                        <input type="submit" value="Change" class=submit_class/>
+                    </div>
+                </ActionForm>
+            </div>
+        </div>
+    }
+}
+
+#[component]
+fn UserRow(
+    user: User,
+    is_current: bool,
+    remove_action: ServerAction<RemoveUser>,
+    reset_action: ServerAction<ResetPassword>,
+) -> impl IntoView {
+    let (password, set_password) = signal(String::new());
+    let email = StoredValue::new(user.email.clone());
+
+    view! {
+        <div class="flex gap-4 items-center flex-wrap text-sm p-2 rounded border dark:border-white/10">
+            <div class="flex flex-col grow min-w-0">
+                <div class="font-semibold">{user.name} {is_current.then_some(" (you)")}</div>
+                <div class="dark:text-white/50 truncate">{user.email}</div>
+            </div>
+            <input
+                type="password"
+                placeholder="New password"
+                autocomplete="new-password"
+                class="border p-2 rounded-md dark:bg-white/10 dark:border-white/5"
+                prop:value=password
+                on:input=move |ev| set_password.set(event_target_value(&ev))
+            />
+            <button
+                type="button"
+                class="p-2 px-4 rounded border disabled:opacity-50"
+                disabled=move || password.get().is_empty()
+                on:click=move |_| {
+                    reset_action
+                        .dispatch(ResetPassword {
+                            email: email.get_value(),
+                            password: password.get_untracked(),
+                        });
+                    set_password.set(String::new());
+                }
+            >
+                "Reset Password"
+            </button>
+            <button
+                type="button"
+                class="p-2 px-4 rounded border border-red-600 text-red-600 disabled:opacity-50"
+                disabled=is_current
+                on:click=move |_| {
+                    remove_action
+                        .dispatch(RemoveUser {
+                            email: email.get_value(),
+                        });
+                }
+            >
+                "Remove"
+            </button>
+        </div>
+    }
+}
@@ -0,0 +1,249 @@
+//! Storage of the OCI registry the server exposes under `/v2/`.
+//!
+//! Every project's repository lives under `registry/<project id>/`, with `blobs/` holding
+//! layers, configs and manifests by digest, `manifests/` the media type of each manifest,
+//! `tags/` the digest every tag points to and `uploads/` chunked uploads in progress.
+//! What only expired deployments used is pruned along with them.
+
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+use uuid::Uuid;
+
+use crate::common::{get_home_path, Deployment};
+
+pub fn repository_dir(project_id: Uuid) -> PathBuf {
+    get_home_path()
+        .join("registry")
+        .join(project_id.to_string())
+}
+
+/// Forgets everything pushed to a deleted project
+pub async fn remove_project_registry(project_id: Uuid) {
+    let _ = tokio::fs::remove_dir_all(repository_dir(project_id)).await;
+}
+
+/// Pushes younger than this are never pruned, their manifest may not be deployed yet
+const PRUNE_GRACE: Duration = Duration::from_secs(60 * 60);
+
+/// Deletes what was pushed for deployments that are no longer retained.
+///
+/// A manifest is kept while a retained deployment runs its image, docker reports either
+/// the config or the manifest digest as image id. Tags pointing elsewhere are removed,
+/// then the blobs no kept manifest references and abandoned uploads.
+pub async fn prune(project_id: Uuid, retained: &[Deployment]) -> anyhow::Result<()> {
+    let root = repository_dir(project_id);
+    if !tokio::fs::try_exists(&root).await? {
+        return Ok(());
+    }
+    let retained = retained
+        .iter()
+        .map(|deployment| deployment.image_id.as_str())
+        .collect::<HashSet<_>>();
+
+    // Hex digests of the blobs each stored manifest references
+    let mut references = HashMap::new();
+    for (digest, _) in entries(&root.join("manifests")).await? {
+        let Some(manifest) = tokio::fs::read(root.join("blobs").join(&digest))
+            .await
+            .ok()
+            .and_then(|manifest| serde_json::from_slice::<serde_json::Value>(&manifest).ok())
+        else {
+            continue;
+        };
+        let blobs = referenced_digests(&manifest)
+            .iter()
+            .filter_map(|blob| blob.strip_prefix("sha256:").map(str::to_string))
+            .collect::<Vec<_>>();
+        references.insert(digest, blobs);
+    }
+    // A manifest with everything it references, through the manifests of an index
+    let closure = |digest: &str| {
+        let mut blobs = HashSet::from([digest.to_string()]);
+        let mut pending = vec![digest.to_string()];
+        while let Some(digest) = pending.pop() {
+            for blob in references.get(&digest).into_iter().flatten() {
+                if blobs.insert(blob.clone()) {
+                    pending.push(blob.clone());
+                }
+            }
+        }
+        blobs
+    };
+
+    let mut kept = references
+        .keys()
+        .filter(|digest| {
+            closure(digest)
+                .iter()
+                .any(|blob| retained.contains(format!("sha256:{blob}").as_str()))
+        })
+        .cloned()
+        .collect::<HashSet<_>>();
+    for (tag, age) in entries(&root.join("tags")).await? {
+        let path = root.join("tags").join(tag);
+        let digest = tokio::fs::read_to_string(&path).await?;
+        let digest = digest.trim().trim_start_matches("sha256:").to_string();
+        if age < PRUNE_GRACE || kept.contains(&digest) {
+            kept.insert(digest);
+        } else {
+            tokio::fs::remove_file(&path).await?;
+        }
+    }
+
+    let used = kept
+        .iter()
+        .flat_map(|digest| closure(digest))
+        .collect::<HashSet<_>>();
+    let mut removed = 0;
+    for (digest, age) in entries(&root.join("blobs")).await? {
+        if age >= PRUNE_GRACE && !used.contains(&digest) {
+            tokio::fs::remove_file(root.join("blobs").join(&digest)).await?;
+            let _ = tokio::fs::remove_file(root.join("manifests").join(&digest)).await;
+            removed += 1;
+        }
+    }
+    remove_stale_uploads(&root).await?;
+    if removed > 0 {
+        tracing::info!("Pruned {removed} registry blobs of {project_id}");
+    }
+    Ok(())
+}
+
+/// Deletes uploads of every repository that were not written to for a while, pushes
+/// interrupted for good leave them behind even when nothing is ever deployed again
+pub async fn expire_uploads() -> anyhow::Result<()> {
+    let registry = get_home_path().join("registry");
+    for (project_id, _) in entries(&registry).await? {
+        remove_stale_uploads(&registry.join(project_id)).await?;
+    }
+    Ok(())
+}
+
+async fn remove_stale_uploads(root: &Path) -> anyhow::Result<()> {
+    for (upload, age) in entries(&root.join("uploads")).await? {
+        if age >= PRUNE_GRACE {
+            tokio::fs::remove_file(root.join("uploads").join(upload)).await?;
+        }
+    }
+    Ok(())
+}
+
+/// Names of the files in `dir` with the time since they were last written
+async fn entries(dir: &Path) -> anyhow::Result<Vec<(String, Duration)>> {
+    let mut dir = match tokio::fs::read_dir(dir).await {
+        Ok(dir) => dir,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(vec![]),
+        Err(err) => return Err(err.into()),
+    };
+    let mut entries = vec![];
+    while let Some(entry) = dir.next_entry().await? {
+        let modified = entry.metadata().await?.modified()?;
+        let age = SystemTime::now()
+            .duration_since(modified)
+            .unwrap_or_default();
+        entries.push((entry.file_name().to_string_lossy().into_owned(), age));
+    }
+    Ok(entries)
+}
+
+/// Config, layers and child manifests a manifest references
+pub fn referenced_digests(manifest: &serde_json::Value) -> Vec<String> {
+    let mut digests = vec![];
+    if let Some(config) = manifest["config"]["digest"].as_str() {
+        digests.push(config.to_string());
+    }
+    for key in ["layers", "manifests"] {
+        if let Some(entries) = manifest[key].as_array() {
+            digests.extend(
+                entries
+                    .iter()
+                    .filter_map(|entry| entry["digest"].as_str().map(str::to_string)),
+            );
+        }
+    }
+    digests
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Writes a file last modified `age` ago
+    fn write(root: &Path, path: &str, data: &str, age: Duration) {
+        let path = root.join(path);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(&path, data).unwrap();
+        let file = std::fs::File::options().write(true).open(&path).unwrap();
+        file.set_modified(SystemTime::now() - age).unwrap();
+    }
+
+    fn write_manifest(root: &Path, digest: &str, config: &str, layers: &[&str], age: Duration) {
+        let manifest = serde_json::json!({
+            "config": { "digest": format!("sha256:{config}") },
+            "layers": layers
+                .iter()
+                .map(|layer| serde_json::json!({ "digest": format!("sha256:{layer}") }))
+                .collect::<Vec<_>>(),
+        });
+        write(root, &format!("blobs/{digest}"), &manifest.to_string(), age);
+        write(
+            root,
+            &format!("manifests/{digest}"),
+            "application/vnd.oci.image.manifest.v1+json",
+            age,
+        );
+        for blob in std::iter::once(&config).chain(layers) {
+            write(root, &format!("blobs/{blob}"), blob, age);
+        }
+    }
+
+    fn names(root: &Path, dir: &str) -> Vec<String> {
+        let mut names = std::fs::read_dir(root.join(dir))
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+            .collect::<Vec<_>>();
+        names.sort();
+        names
+    }
+
+    #[tokio::test]
+    async fn prune_keeps_what_retained_deployments_use() {
+        crate::common::test_home();
+        let project_id = Uuid::new_v4();
+        let root = repository_dir(project_id);
+        let old = PRUNE_GRACE * 2;
+        write_manifest(&root, "m1", "c1", &["shared", "l1"], old);
+        write_manifest(&root, "m2", "c2", &["shared", "l2"], old);
+        // Pushed moments ago, its deploy may still be running
+        write_manifest(&root, "m3", "c3", &["l3"], Duration::ZERO);
+        write(&root, "tags/latest", "sha256:m1", old);
+        write(&root, "tags/previous", "sha256:m2", old);
+        write(&root, "uploads/abandoned", "", old);
+        write(&root, "uploads/running", "", Duration::ZERO);
+
+        // Docker reports the config digest as image id
+        let retained = Deployment {
+            id: Uuid::new_v4(),
+            image_id: "sha256:c1".to_string(),
+            deployed_at: chrono::Utc::now(),
+            source: "test".to_string(),
+            git_sha: None,
+            status: Default::default(),
+            hook_runs: vec![],
+        };
+        prune(project_id, &[retained]).await.unwrap();
+
+        assert_eq!(names(&root, "tags"), ["latest"]);
+        assert_eq!(
+            names(&root, "blobs"),
+            ["c1", "c3", "l1", "l3", "m1", "m3", "shared"]
+        );
+        assert_eq!(names(&root, "manifests"), ["m1", "m3"]);
+        assert_eq!(names(&root, "uploads"), ["running"]);
+        remove_project_registry(project_id).await;
+    }
+}
@@ -11,6 +11,9 @@ const REPO_NAME: &str = "self-cloud";
 #[server(CheckUpdate, "/api")]
 pub async fn check_update() -> Result<UpdateStatus, ServerFnError> {
     use std::env;
+
+    crate::api::user()?;
+
     let client = reqwest::Client::new();
     let url = format!(
         "https://api.github.com/repos/{}/{}/releases/tags/nightly",
@@ -75,6 +78,8 @@ pub async fn perform_update() -> Result<String, ServerFnError> {
     use std::os::unix::fs::PermissionsExt;
     use std::process::Command;
 
+    let user = crate::api::user()?;
+
     // 1. Identify architecture
     let arch = env::consts::ARCH;
     let asset_name = match arch {
@@ -216,6 +221,7 @@ pub async fn perform_update() -> Result<String, ServerFnError> {
         .unwrap_or_else(|_| "systemctl --user restart selfcloud".to_string());
 
     tracing::info!("Triggering restart with: {}", restart_cmd);
+    crate::api::audit(&user, "system.update", None).await;
 
     tokio::spawn(async move {
         tokio::time::sleep(std::time::Duration::from_millis(500)).await;
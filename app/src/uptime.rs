@@ -0,0 +1,162 @@
+//! Uptime checks of project domains kept by the uptime monitor.
+//!
+//! Every probed domain gets a JSONL file of [`UptimeCheck`]s under `uptime/<project>/`,
+//! holding the last 30 days. The monitor keeps the same checks in memory and publishes a
+//! [`DomainUptime`] summary of each domain here for the panel and the public status pages.
+
+use std::{collections::HashMap, path::PathBuf};
+
+use chrono::Utc;
+use once_cell::sync::Lazy;
+use tokio::io::AsyncWriteExt;
+use uuid::Uuid;
+
+use crate::common::{get_home_path, DomainUptime, UptimeCheck, UptimeIncident};
+
+/// Checks older than this are dropped on compaction
+pub const RETENTION: chrono::Duration = chrono::Duration::days(30);
+/// Incidents shown per domain
+const MAX_INCIDENTS: usize = 50;
+
+/// Summaries by project as of the monitor's last round of checks
+static PUBLISHED: Lazy<std::sync::RwLock<HashMap<Uuid, Vec<DomainUptime>>>> =
+    Lazy::new(Default::default);
+
+fn uptime_dir() -> PathBuf {
+    get_home_path().join("uptime")
+}
+
+fn checks_path(project_id: Uuid, domain: &str) -> PathBuf {
+    uptime_dir()
+        .join(project_id.to_string())
+        .join(format!("{}.jsonl", domain.to_ascii_lowercase()))
+}
+
+pub async fn append_check(
+    project_id: Uuid,
+    domain: &str,
+    check: &UptimeCheck,
+) -> anyhow::Result<()> {
+    let path = checks_path(project_id, domain);
+    if let Some(parent) = path.parent() {
+        tokio::fs::create_dir_all(parent).await?;
+    }
+    let mut data = serde_json::to_vec(check)?;
+    data.push(b'\n');
+    let mut file = tokio::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .await?;
+    file.write_all(&data).await?;
+    Ok(())
+}
+
+/// Stored checks of the domain within the retention, oldest first
+pub async fn load_checks(project_id: Uuid, domain: &str) -> Vec<UptimeCheck> {
+    let Ok(data) = tokio::fs::read_to_string(checks_path(project_id, domain)).await else {
+        return vec![];
+    };
+    let since = Utc::now() - RETENTION;
+    data.lines()
+        .filter_map(|line| serde_json::from_str::<UptimeCheck>(line).ok())
+        .filter(|check| check.ts >= since)
+        .collect()
+}
+
+/// Replaces the stored checks of the domain, used to drop the expired ones
+pub async fn rewrite_checks<'a>(
+    project_id: Uuid,
+    domain: &str,
+    checks: impl IntoIterator<Item = &'a UptimeCheck>,
+) -> anyhow::Result<()> {
+    let path = checks_path(project_id, domain);
+    let mut data = vec![];
+    for check in checks {
+        data.extend(serde_json::to_vec(check)?);
+        data.push(b'\n');
+    }
+    let temp = path.with_extension("jsonl.tmp");
+    tokio::fs::write(&temp, data).await?;
+    tokio::fs::rename(&temp, &path).await?;
+    Ok(())
+}
+
+/// Forgets the checks of a deleted project
+pub async fn remove_project_uptime(project_id: Uuid) {
+    let _ = tokio::fs::remove_dir_all(uptime_dir().join(project_id.to_string())).await;
+}
+
+/// Uptime percentages, latency and incidents of the domain from its checks, oldest first
+pub fn summarize<'a>(
+    domain: &str,
+    checks: impl IntoIterator<Item = &'a UptimeCheck> + Clone,
+) -> DomainUptime {
+    let now = Utc::now();
+    let uptime = |window: chrono::Duration| {
+        let since = now - window;
+        let (up, total) = checks
+            .clone()
+            .into_iter()
+            .filter(|check| check.ts >= since)
+            .fold((0, 0), |(up, total), check| {
+                (up + usize::from(check.is_up()), total + 1)
+            });
+        (total > 0).then(|| up as f64 / total as f64 * 100.0)
+    };
+
+    let day = now - chrono::Duration::days(1);
+    let latencies = checks
+        .clone()
+        .into_iter()
+        .filter(|check| check.ts >= day && check.is_up())
+        .filter_map(|check| check.latency_ms)
+        .collect::<Vec<_>>();
+
+    let mut incidents = vec![];
+    let mut open: Option<UptimeIncident> = None;
+    let mut last = None;
+    for check in checks {
+        if check.is_up() {
+            if let Some(mut incident) = open.take() {
+                incident.ended = Some(check.ts);
+                incidents.push(incident);
+            }
+        } else if open.is_none() {
+            open = Some(UptimeIncident {
+                started: check.ts,
+                ended: None,
+                reason: check.reason(),
+            });
+        }
+        last = Some(check);
+    }
+    incidents.extend(open);
+    incidents.reverse();
+    incidents.truncate(MAX_INCIDENTS);
+
+    DomainUptime {
+        domain: domain.to_string(),
+        last: last.cloned(),
+        uptime_day: uptime(chrono::Duration::days(1)),
+        uptime_week: uptime(chrono::Duration::days(7)),
+        uptime_month: uptime(RETENTION),
+        latency_ms_day: (!latencies.is_empty())
+            .then(|| latencies.iter().sum::<u64>() / latencies.len() as u64),
+        incidents,
+    }
+}
+
+pub fn publish(summaries: HashMap<Uuid, Vec<DomainUptime>>) {
+    *PUBLISHED.write().expect("uptime lock poisoned") = summaries;
+}
+
+/// Published summaries of the project's domains, sorted by domain
+pub fn project_uptime(project_id: Uuid) -> Vec<DomainUptime> {
+    PUBLISHED
+        .read()
+        .expect("uptime lock poisoned")
+        .get(&project_id)
+        .cloned()
+        .unwrap_or_default()
+}
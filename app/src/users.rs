@@ -0,0 +1,268 @@
+//! Panel users, kept in `users.json` keyed by email.
+//!
+//! Passwords are stored as argon2 PHC strings. Files from before hashing still hold plain
+//! passwords, those are hashed when the server starts or, for users added to the file by
+//! hand later, on their first login. The file is read again whenever it changes on disk,
+//! so edits apply without a restart.
+
+use std::time::SystemTime;
+
+use argon2::{
+    password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString},
+    Argon2,
+};
+use once_cell::sync::Lazy;
+use uuid::Uuid;
+
+use crate::auth::{AuthorizedUsers, User, UserWithPass};
+use crate::common::get_home_path;
+
+pub const MIN_PASSWORD_LEN: usize = 8;
+
+/// Serializes read-modify-write of the users file
+static USERS_LOCK: Lazy<tokio::sync::Mutex<()>> = Lazy::new(Default::default);
+
+/// Users as of the file's last modification time
+static CACHE: Lazy<std::sync::RwLock<Option<(SystemTime, AuthorizedUsers)>>> =
+    Lazy::new(Default::default);
+
+fn users_path() -> std::path::PathBuf {
+    get_home_path().join("users.json")
+}
+
+/// The stored users, re-read when the file changed since the last call
+pub fn users() -> anyhow::Result<AuthorizedUsers> {
+    let path = users_path();
+    let modified = std::fs::metadata(&path)?.modified()?;
+    if let Some((cached_at, users)) = CACHE.read().expect("users cache poisoned").as_ref() {
+        if *cached_at == modified {
+            return Ok(users.clone());
+        }
+    }
+    let users = serde_json::from_slice::<AuthorizedUsers>(&std::fs::read(&path)?)?;
+    *CACHE.write().expect("users cache poisoned") = Some((modified, users.clone()));
+    Ok(users)
+}
+
+/// Whether a signed in user still exists, sessions of removed users stop working
+pub fn is_current(user: &User) -> bool {
+    match users() {
+        Ok(users) => users
+            .get(&user.email)
+            .is_some_and(|stored| stored.user.id == user.id),
+        Err(err) => {
+            tracing::error!("Cannot read users {err:?}");
+            false
+        }
+    }
+}
+
+/// Applies `f` to the stored users, nothing is saved when it fails
+pub async fn update_users(
+    f: impl FnOnce(&mut AuthorizedUsers) -> Result<(), String>,
+) -> anyhow::Result<()> {
+    let _lock = USERS_LOCK.lock().await;
+    let mut users = users()?;
+    f(&mut users).map_err(anyhow::Error::msg)?;
+    let path = users_path();
+    tokio::fs::write(&path, serde_json::to_vec_pretty(&users)?).await?;
+    // A write within the previous modification time's resolution must not be missed
+    let modified = tokio::fs::metadata(&path).await?.modified()?;
+    *CACHE.write().expect("users cache poisoned") = Some((modified, users));
+    Ok(())
+}
+
+fn is_hashed(pass: &str) -> bool {
+    PasswordHash::new(pass).is_ok()
+}
+
+pub async fn hash_password(password: String) -> anyhow::Result<String> {
+    tokio::task::spawn_blocking(move || {
+        let salt = SaltString::encode_b64(&rand::random::<[u8; 16]>())
+            .map_err(|err| anyhow::anyhow!("Cannot encode salt {err}"))?;
+        Argon2::default()
+            .hash_password(password.as_bytes(), &salt)
+            .map(|hash| hash.to_string())
+            .map_err(|err| anyhow::anyhow!("Cannot hash password {err}"))
+    })
+    .await?
+}
+
+async fn verify_password(password: String, pass: String) -> anyhow::Result<bool> {
+    tokio::task::spawn_blocking(move || {
+        let hash = PasswordHash::new(&pass)
+            .map_err(|err| anyhow::anyhow!("Stored password is not a valid hash {err}"))?;
+        Ok(Argon2::default()
+            .verify_password(password.as_bytes(), &hash)
+            .is_ok())
+    })
+    .await?
+}
+
+pub fn validate_password(password: &str) -> Result<(), String> {
+    if password.chars().count() < MIN_PASSWORD_LEN {
+        return Err(format!(
+            "Password needs at least {MIN_PASSWORD_LEN} characters"
+        ));
+    }
+    Ok(())
+}
+
+/// The user with `email` if `password` is theirs
+pub async fn authenticate(email: &str, password: &str) -> anyhow::Result<Option<User>> {
+    let Some(stored) = users()?.remove(email) else {
+        return Ok(None);
+    };
+    if is_hashed(&stored.pass) {
+        let valid = verify_password(password.to_string(), stored.pass).await?;
+        return Ok(valid.then_some(stored.user));
+    }
+
+    // Added to the file by hand, hash it now that it's known to be right
+    if stored.pass != password {
+        return Ok(None);
+    }
+    let hash = hash_password(password.to_string()).await?;
+    let id = stored.user.id;
+    update_users(|users| {
+        if let Some(user) = users.get_mut(email).filter(|user| user.user.id == id) {
+            user.pass = hash;
+        }
+        Ok(())
+    })
+    .await?;
+    Ok(Some(stored.user))
+}
+
+/// Hashes every plain password left in the users file, run once when the server starts
+pub async fn migrate_passwords() -> anyhow::Result<()> {
+    let plain = users()?
+        .into_iter()
+        .filter(|(_, user)| !is_hashed(&user.pass))
+        .collect::<Vec<_>>();
+    if plain.is_empty() {
+        return Ok(());
+    }
+
+    let mut hashes = Vec::with_capacity(plain.len());
+    for (email, user) in plain {
+        hashes.push((email, user.user.id, hash_password(user.pass).await?));
+    }
+    let migrated = hashes.len();
+    update_users(|users| {
+        for (email, id, hash) in hashes {
+            if let Some(user) = users.get_mut(&email).filter(|user| user.user.id == id) {
+                user.pass = hash;
+            }
+        }
+        Ok(())
+    })
+    .await?;
+    tracing::info!("Hashed the passwords of {migrated} users");
+    Ok(())
+}
+
+/// Adds a user with a hashed password
+pub async fn add_user(name: String, email: String, password: String) -> anyhow::Result<User> {
+    validate_password(&password).map_err(anyhow::Error::msg)?;
+    let user = User {
+        id: Uuid::new_v4(),
+        name,
+        email,
+    };
+    let pass = hash_password(password).await?;
+    update_users(|users| {
+        if users.contains_key(&user.email) {
+            return Err("A user with this email exists".to_string());
+        }
+        users.insert(
+            user.email.clone(),
+            UserWithPass {
+                user: user.clone(),
+                pass,
+            },
+        );
+        Ok(())
+    })
+    .await?;
+    Ok(user)
+}
+
+/// Replaces the password of the user with `email`
+pub async fn set_password(email: &str, password: String) -> anyhow::Result<()> {
+    validate_password(&password).map_err(anyhow::Error::msg)?;
+    let pass = hash_password(password).await?;
+    update_users(|users| {
+        let user = users.get_mut(email).ok_or("No user with this email")?;
+        user.pass = pass;
+        Ok(())
+    })
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stored(email: &str, pass: &str) -> (String, UserWithPass) {
+        let user = User {
+            id: Uuid::new_v4(),
+            name: email.to_string(),
+            email: email.to_string(),
+        };
+        (
+            email.to_string(),
+            UserWithPass {
+                user,
+                pass: pass.to_string(),
+            },
+        )
+    }
+
+    /// Edits the file like an admin would, without waiting for a new modification time
+    fn write_users(users: &AuthorizedUsers) {
+        std::fs::write(users_path(), serde_json::to_vec(users).unwrap()).unwrap();
+        *CACHE.write().unwrap() = None;
+    }
+
+    // One test, the users file is found through the process wide SELF_CLOUD_HOME
+    #[tokio::test]
+    async fn plain_passwords_are_hashed() {
+        crate::common::test_home();
+        let plain = AuthorizedUsers::from([
+            stored("a@example.com", "password-a"),
+            stored("b@example.com", "password-b"),
+        ]);
+        write_users(&plain);
+
+        migrate_passwords().await.unwrap();
+        let mut migrated = users().unwrap();
+        for (email, user) in plain.iter() {
+            assert_eq!(migrated[email].user, user.user);
+            assert!(is_hashed(&migrated[email].pass), "{email} not hashed");
+        }
+        let user = authenticate("a@example.com", "password-a").await.unwrap();
+        assert_eq!(user, Some(plain["a@example.com"].user.clone()));
+        assert_eq!(
+            authenticate("a@example.com", "password-b").await.unwrap(),
+            None
+        );
+
+        // Added by hand after the server started
+        migrated.extend([stored("c@example.com", "password-c")]);
+        write_users(&migrated);
+        assert_eq!(authenticate("c@example.com", "wrong").await.unwrap(), None);
+        assert_eq!(users().unwrap()["c@example.com"].pass, "password-c");
+        let user = authenticate("c@example.com", "password-c").await.unwrap();
+        assert_eq!(user, Some(migrated["c@example.com"].user.clone()));
+        assert!(is_hashed(&users().unwrap()["c@example.com"].pass));
+        let user = authenticate("c@example.com", "password-c").await.unwrap();
+        assert_eq!(user, Some(migrated["c@example.com"].user.clone()));
+        assert_eq!(
+            authenticate("d@example.com", "password-c").await.unwrap(),
+            None
+        );
+
+        std::fs::remove_file(users_path()).unwrap();
+    }
+}
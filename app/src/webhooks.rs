@@ -0,0 +1,373 @@
+//! Outgoing webhooks on project lifecycle events.
+//!
+//! Events are emitted where they happen and queued for the webhook dispatcher of the
+//! server, which delivers them to the matching [`WebhookSubscription`]s of `webhooks.json`,
+//! retries failed deliveries and logs every attempt to `webhooks/deliveries.jsonl`.
+//! Changes of a project are turned into events by [`emit_project_changes`] whenever the
+//! project context stores a new version of it.
+
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use sha2::Sha256;
+use tokio::{io::AsyncWriteExt, sync::mpsc};
+use uuid::Uuid;
+
+use crate::common::{
+    get_home_path, DeployStatus, Project, ProjectType, WebhookDelivery, WebhookEventKind,
+    WebhookSubscription,
+};
+
+/// Events beyond this many waiting for the dispatcher are dropped
+const QUEUE_SIZE: usize = 1024;
+const DELIVERY_TIMEOUT: Duration = Duration::from_secs(15);
+/// Attempts kept in the delivery log
+const LOGGED_DELIVERIES: usize = 2000;
+
+type EventQueue = (
+    mpsc::Sender<WebhookEvent>,
+    std::sync::Mutex<Option<mpsc::Receiver<WebhookEvent>>>,
+);
+
+static QUEUE: Lazy<EventQueue> = Lazy::new(|| {
+    let (sender, receiver) = mpsc::channel(QUEUE_SIZE);
+    (sender, std::sync::Mutex::new(Some(receiver)))
+});
+
+/// Serializes read-modify-write of the subscriptions file
+static SETTINGS_LOCK: Lazy<tokio::sync::Mutex<()>> = Lazy::new(Default::default);
+
+/// Serializes appends to the delivery log with its compaction, so no attempt is lost
+static DELIVERIES_LOCK: Lazy<tokio::sync::Mutex<()>> = Lazy::new(Default::default);
+
+#[derive(Serialize, Clone, Debug)]
+pub struct WebhookEvent {
+    pub id: Uuid,
+    pub event: WebhookEventKind,
+    pub ts: DateTime<Utc>,
+    pub project_id: Option<Uuid>,
+    pub data: serde_json::Value,
+}
+
+impl WebhookEvent {
+    pub fn new(event: WebhookEventKind, project_id: Option<Uuid>, data: serde_json::Value) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            event,
+            ts: Utc::now(),
+            project_id,
+            data,
+        }
+    }
+}
+
+/// The JSON body posted for the event, the same for every attempt
+pub fn payload(event: &WebhookEvent, project_name: Option<String>) -> Vec<u8> {
+    serde_json::to_vec(&serde_json::json!({
+        "id": event.id,
+        "event": event.event,
+        "ts": event.ts,
+        "project": event.project_id.map(|id| serde_json::json!({ "id": id, "name": project_name })),
+        "data": event.data,
+    }))
+    .unwrap_or_default()
+}
+
+/// Queues the event for delivery, never blocks
+pub fn emit(event: WebhookEventKind, project_id: Option<Uuid>, data: serde_json::Value) {
+    if let Err(err) = QUEUE.0.try_send(WebhookEvent::new(event, project_id, data)) {
+        tracing::warn!("Dropping webhook event {event} {err}");
+    }
+}
+
+/// The receiving end of the queue, handed out once to the dispatcher
+pub fn take_events() -> Option<mpsc::Receiver<WebhookEvent>> {
+    QUEUE.1.lock().expect("webhook queue lock poisoned").take()
+}
+
+/// The stored configuration of the project, without what deployments change
+pub(crate) fn configuration(project: &Project) -> serde_json::Value {
+    let mut value = serde_json::to_value(project).unwrap_or_default();
+    if let Some(container) = value
+        .pointer_mut("/project_type/Container")
+        .and_then(|container| container.as_object_mut())
+    {
+        container.remove("deploy_history");
+        if let Some(source) = container
+            .get_mut("image_source")
+            .and_then(|source| source.as_object_mut())
+        {
+            source.remove("deployed_digest");
+            source.remove("deployed_at");
+        }
+    }
+    value
+}
+
+/// Emits the events `new` differs from `old` by, `old` is `None` for a new project
+pub fn emit_project_changes(old: Option<&Project>, new: &Project) {
+    let project_id = Some(new.id);
+    let Some(old) = old else {
+        emit(
+            WebhookEventKind::ProjectCreated,
+            project_id,
+            serde_json::json!({}),
+        );
+        return;
+    };
+    if configuration(old) != configuration(new) {
+        emit(
+            WebhookEventKind::ProjectUpdated,
+            project_id,
+            serde_json::json!({}),
+        );
+    }
+
+    if let (
+        ProjectType::Container {
+            deploy_history: old_history,
+            ..
+        },
+        ProjectType::Container {
+            deploy_history: new_history,
+            ..
+        },
+    ) = (&old.project_type, &new.project_type)
+    {
+        let active = new_history
+            .deployments
+            .iter()
+            .find(|deployment| Some(deployment.id) == new_history.active);
+        if let Some(deployment) = active {
+            let previous = old_history
+                .deployments
+                .iter()
+                .find(|previous| previous.id == deployment.id);
+            let data = serde_json::json!({
+                "deployment_id": deployment.id,
+                "image_id": deployment.image_id,
+                "source": deployment.source,
+                "git_sha": deployment.git_sha,
+                "status": deployment.status.to_string(),
+            });
+            let restarted = old_history.active != new_history.active
+                || previous.is_some_and(|previous| {
+                    previous.status.is_done() && !deployment.status.is_done()
+                });
+            match (previous, &deployment.status) {
+                (None, _) => emit(WebhookEventKind::DeployStarted, project_id, data),
+                _ if restarted => emit(WebhookEventKind::DeployStarted, project_id, data),
+                (Some(previous), status) if &previous.status != status => match status {
+                    DeployStatus::Healthy => {
+                        emit(WebhookEventKind::DeploySucceeded, project_id, data)
+                    }
+                    DeployStatus::Failed(_) => {
+                        emit(WebhookEventKind::DeployFailed, project_id, data)
+                    }
+                    _ => {}
+                },
+                _ => {}
+            }
+        }
+    }
+
+    let old_containers = old.project_type.containers();
+    for (slot, container) in new.project_type.containers() {
+        let Some((_, previous)) = old_containers
+            .iter()
+            .find(|(old_slot, _)| *old_slot == slot)
+        else {
+            continue;
+        };
+        let runtime = &container.runtime;
+        let data = serde_json::json!({
+            "container": slot.to_string(),
+            "restart_count": runtime.restart_count,
+            "consecutive_failures": runtime.consecutive_failures,
+            "last_failure": runtime.last_failure,
+            "oom_killed": runtime.oom_killed,
+        });
+        let was_running = previous.status.is_running();
+        let is_running = container.status.is_running();
+        if !was_running && is_running {
+            emit(WebhookEventKind::ContainerStarted, project_id, data.clone());
+        } else if was_running && !is_running && runtime.stopped_by_user {
+            emit(WebhookEventKind::ContainerStopped, project_id, data.clone());
+        }
+        if runtime.consecutive_failures > previous.runtime.consecutive_failures {
+            emit(WebhookEventKind::ContainerCrashed, project_id, data);
+        }
+    }
+}
+
+pub async fn load_subscriptions() -> Vec<WebhookSubscription> {
+    let Ok(data) = tokio::fs::read(get_home_path().join("webhooks.json")).await else {
+        return vec![];
+    };
+    match serde_json::from_slice(&data) {
+        Ok(subscriptions) => subscriptions,
+        Err(err) => {
+            tracing::warn!("Cannot parse webhook subscriptions {err:?}");
+            vec![]
+        }
+    }
+}
+
+/// Applies `f` to the stored subscriptions, nothing is saved when it fails
+pub async fn update_subscriptions(
+    f: impl FnOnce(&mut Vec<WebhookSubscription>) -> Result<(), String>,
+) -> anyhow::Result<()> {
+    let _lock = SETTINGS_LOCK.lock().await;
+    let mut subscriptions = load_subscriptions().await;
+    f(&mut subscriptions).map_err(anyhow::Error::msg)?;
+    tokio::fs::write(
+        get_home_path().join("webhooks.json"),
+        serde_json::to_vec(&subscriptions)?,
+    )
+    .await?;
+    Ok(())
+}
+
+/// `sha256=` and the hex HMAC of `<timestamp>.<body>` keyed with the secret
+pub fn sign(secret: &str, timestamp: i64, body: &[u8]) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC takes keys of any size");
+    mac.update(timestamp.to_string().as_bytes());
+    mac.update(b".");
+    mac.update(body);
+    format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+/// Posts the payload to the subscription once, the outcome is not logged
+pub async fn deliver(
+    client: &reqwest::Client,
+    subscription: &WebhookSubscription,
+    event: &WebhookEvent,
+    body: &[u8],
+    attempt: u32,
+) -> WebhookDelivery {
+    let timestamp = Utc::now().timestamp();
+    let response = client
+        .post(&subscription.url)
+        .timeout(DELIVERY_TIMEOUT)
+        .header(reqwest::header::CONTENT_TYPE, "application/json")
+        .header(reqwest::header::USER_AGENT, "SelfCloud-Webhooks")
+        .header("X-SelfCloud-Event", event.event.as_str())
+        .header("X-SelfCloud-Delivery", event.id.to_string())
+        .header("X-SelfCloud-Timestamp", timestamp.to_string())
+        .header(
+            "X-SelfCloud-Signature",
+            sign(&subscription.secret, timestamp, body),
+        )
+        .body(body.to_vec())
+        .send()
+        .await;
+
+    let mut delivery = WebhookDelivery {
+        event_id: event.id,
+        subscription_id: subscription.id,
+        event: event.event,
+        ts: Utc::now(),
+        attempt,
+        status: None,
+        error: None,
+        next_attempt_at: None,
+    };
+    match response {
+        Ok(response) => {
+            let status = response.status();
+            delivery.status = Some(status.as_u16());
+            if !status.is_success() {
+                delivery.error = Some(format!("Answered {status}"));
+            }
+        }
+        Err(err) if err.is_timeout() => delivery.error = Some("Timed out".to_string()),
+        Err(err) => delivery.error = Some(err.without_url().to_string()),
+    }
+    delivery
+}
+
+fn deliveries_path() -> std::path::PathBuf {
+    get_home_path().join("webhooks").join("deliveries.jsonl")
+}
+
+pub async fn log_delivery(delivery: &WebhookDelivery) -> anyhow::Result<()> {
+    let path = deliveries_path();
+    if let Some(parent) = path.parent() {
+        tokio::fs::create_dir_all(parent).await?;
+    }
+    let mut data = serde_json::to_vec(delivery)?;
+    data.push(b'\n');
+    let _lock = DELIVERIES_LOCK.lock().await;
+    let mut file = tokio::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .await?;
+    file.write_all(&data).await?;
+    Ok(())
+}
+
+async fn read_deliveries() -> Vec<WebhookDelivery> {
+    let Ok(data) = tokio::fs::read_to_string(deliveries_path()).await else {
+        return vec![];
+    };
+    data.lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect()
+}
+
+/// Logged attempts for the subscription, newest first
+pub async fn deliveries(subscription_id: Uuid, limit: usize) -> Vec<WebhookDelivery> {
+    let mut deliveries = read_deliveries()
+        .await
+        .into_iter()
+        .filter(|delivery| delivery.subscription_id == subscription_id)
+        .collect::<Vec<_>>();
+    deliveries.reverse();
+    deliveries.truncate(limit);
+    deliveries
+}
+
+/// Drops all but the newest logged attempts
+pub async fn compact_deliveries() -> anyhow::Result<()> {
+    let _lock = DELIVERIES_LOCK.lock().await;
+    let deliveries = read_deliveries().await;
+    if deliveries.len() <= LOGGED_DELIVERIES {
+        return Ok(());
+    }
+    let mut data = vec![];
+    for delivery in &deliveries[deliveries.len() - LOGGED_DELIVERIES..] {
+        data.extend(serde_json::to_vec(delivery)?);
+        data.push(b'\n');
+    }
+    let path = deliveries_path();
+    let temp = path.with_extension("jsonl.tmp");
+    tokio::fs::write(&temp, data).await?;
+    tokio::fs::rename(&temp, &path).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sign_is_hmac_sha256_of_timestamp_and_body() {
+        assert_eq!(
+            sign(
+                "whsec_test",
+                1_700_000_000,
+                br#"{"event":"deploy.succeeded"}"#
+            ),
+            "sha256=4963971fec3f34258b00c3b01f2da5b1eb98c720aa323df0b75a39c4ca5dec76"
+        );
+        assert_eq!(
+            sign("", 0, b""),
+            "sha256=b849d5a581847b281957065739df36df2463d1977ea8d6e1e4e6cf33fadc68c3"
+        );
+    }
+}
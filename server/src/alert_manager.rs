@@ -0,0 +1,280 @@
+//! Evaluates the alert rules against container state, stored metrics, host disks and
+//! certificate orders, and notifies every channel once when an alert starts firing and
+//! once when it resolves. What is firing is stored, so a restart does not notify it again.
+
+use std::{collections::HashMap, time::Duration};
+
+use app::{
+    alerts::{self, Notification},
+    common::{
+        AlertCondition, AlertRule, AlertSettings, ContainerSlot, FiringAlert, MetricsRange,
+        ProjectType,
+    },
+    context::ProjectContext,
+    metrics_store::{self, MetricsSeries},
+};
+use chrono::Utc;
+use pingora::{
+    server::ShutdownWatch,
+    services::background::{background_service, BackgroundService, GenBackgroundService},
+};
+use sysinfo::Disks;
+use tracing::{info, warn};
+
+use crate::tls_gen::CertFailures;
+
+const EVALUATION_INTERVAL: Duration = Duration::from_secs(30);
+
+pub struct AlertManager {
+    project_context: ProjectContext,
+    cert_failures: CertFailures,
+}
+
+impl AlertManager {
+    pub fn to_service(
+        project_context: ProjectContext,
+        cert_failures: CertFailures,
+    ) -> GenBackgroundService<Self> {
+        background_service(
+            "alert_manager",
+            Self {
+                project_context,
+                cert_failures,
+            },
+        )
+    }
+
+    /// Alerts whose condition holds right now, keyed by rule and subject
+    async fn evaluate(
+        &self,
+        settings: &AlertSettings,
+        disks: &mut Disks,
+    ) -> HashMap<String, FiringAlert> {
+        let mut firing = HashMap::new();
+        let mut fire = |rule: &AlertRule, subject: String, summary: String, details: String| {
+            firing.insert(
+                format!("{}/{subject}", rule.id),
+                FiringAlert {
+                    rule_id: rule.id,
+                    summary,
+                    details,
+                    since: Utc::now(),
+                },
+            );
+        };
+        let projects = self.project_context.get_projects().await;
+
+        for rule in settings.rules.iter().filter(|rule| rule.enabled) {
+            let watched = projects.iter().filter(|project| {
+                rule.project_id
+                    .is_none_or(|project_id| project_id == project.id)
+            });
+            match &rule.condition {
+                AlertCondition::ContainerCrashed => {
+                    for project in watched {
+                        for (slot, container) in containers(&project.project_type) {
+                            let runtime = &container.runtime;
+                            if runtime.consecutive_failures == 0 || runtime.stopped_by_user {
+                                continue;
+                            }
+                            fire(
+                                rule,
+                                format!("{}/{slot}", project.id),
+                                format!("{slot} container of {} crashed", project.name),
+                                format!(
+                                    "{}, {} failures in a row",
+                                    runtime.last_failure.as_deref().unwrap_or("Unknown reason"),
+                                    runtime.consecutive_failures
+                                ),
+                            );
+                        }
+                    }
+                }
+                AlertCondition::CpuAbove { percent, minutes } => {
+                    let window = chrono::Duration::minutes(i64::from(*minutes));
+                    for project in watched {
+                        for (slot, container) in containers(&project.project_type) {
+                            if container.status.as_running().is_none() {
+                                continue;
+                            }
+                            let series = MetricsSeries::Container(project.id, slot.clone());
+                            let points = metrics_store::query(&series, MetricsRange::Hour).await;
+                            let start = Utc::now() - window;
+                            let recent = points
+                                .iter()
+                                .filter(|point| point.ts >= start)
+                                .collect::<Vec<_>>();
+                            // The samples need to span the window, not just its last minute
+                            let covered = recent.first().is_some_and(|first| {
+                                first.ts - start < chrono::Duration::seconds(60)
+                            });
+                            if !covered || recent.iter().any(|point| point.cpu_percent <= *percent)
+                            {
+                                continue;
+                            }
+                            let average = recent.iter().map(|point| point.cpu_percent).sum::<f64>()
+                                / recent.len() as f64;
+                            fire(
+                                rule,
+                                format!("{}/{slot}", project.id),
+                                format!(
+                                    "{slot} container of {} is above {percent}% CPU",
+                                    project.name
+                                ),
+                                format!("Averaged {average:.1}% over the last {minutes} minutes"),
+                            );
+                        }
+                    }
+                }
+                AlertCondition::HostDiskAbove { percent } => {
+                    disks.refresh_list();
+                    for disk in disks.iter().filter(|disk| disk.total_space() > 0) {
+                        let used = (disk.total_space() - disk.available_space()) as f64
+                            / disk.total_space() as f64
+                            * 100.0;
+                        if used <= *percent {
+                            continue;
+                        }
+                        let mount = disk.mount_point().to_string_lossy().to_string();
+                        fire(
+                            rule,
+                            format!("disk/{mount}"),
+                            format!("Host disk {mount} is {used:.0}% full"),
+                            format!(
+                                "{} GB of {} GB available",
+                                disk.available_space() / 1_000_000_000,
+                                disk.total_space() / 1_000_000_000
+                            ),
+                        );
+                    }
+                }
+                AlertCondition::CertificateFailed => {
+                    let failures = self
+                        .cert_failures
+                        .read()
+                        .expect("cert failures lock poisoned")
+                        .clone();
+                    for (domain, failure) in failures {
+                        let project = self
+                            .project_context
+                            .get_domain(&domain)
+                            .await
+                            .and_then(|status| status.project.upgrade());
+                        let Some(project) = project else {
+                            continue;
+                        };
+                        if rule
+                            .project_id
+                            .is_some_and(|project_id| project_id != project.id)
+                        {
+                            continue;
+                        }
+                        fire(
+                            rule,
+                            format!("cert/{domain}"),
+                            format!("Certificate for {domain} of {} failed", project.name),
+                            format!(
+                                "{}, {} attempts, retrying at {}",
+                                failure.error, failure.attempts, failure.retry_at
+                            ),
+                        );
+                    }
+                }
+            }
+        }
+        firing
+    }
+}
+
+fn containers(project_type: &ProjectType) -> Vec<(ContainerSlot, &app::common::Container)> {
+    let ProjectType::Container {
+        primary_container,
+        support_containers,
+        ..
+    } = project_type
+    else {
+        return vec![];
+    };
+    std::iter::once((ContainerSlot::Primary, primary_container))
+        .chain(
+            support_containers
+                .iter()
+                .map(|(name, support)| (ContainerSlot::Support(name.clone()), &support.container)),
+        )
+        .collect()
+}
+
+async fn notify_all(settings: &AlertSettings, alert: &FiringAlert, resolved: bool) {
+    let notification = Notification {
+        summary: alert.summary.clone(),
+        details: alert.details.clone(),
+        resolved,
+        ts: Utc::now(),
+    };
+    for channel in settings.channels.iter().filter(|channel| channel.enabled) {
+        if let Err(err) = alerts::notify(channel, settings.smtp.as_ref(), &notification).await {
+            warn!(
+                "Cannot notify {} of {} {err:#}",
+                channel.kind, alert.summary
+            );
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl BackgroundService for AlertManager {
+    async fn start(&self, mut shutdown: ShutdownWatch) {
+        let mut period = tokio::time::interval(EVALUATION_INTERVAL);
+        let mut disks = Disks::new_with_refreshed_list();
+        let mut firing = alerts::load_firing().await;
+
+        loop {
+            tokio::select! {
+                _ = shutdown.changed() => {
+                    info!("Shutdown received");
+                    break;
+                }
+                _ = period.tick() => {
+                    let settings = alerts::load_settings().await;
+                    let mut current = self.evaluate(&settings, &mut disks).await;
+
+                    for (key, alert) in current.iter_mut() {
+                        match firing.get(key) {
+                            Some(previous) => alert.since = previous.since,
+                            None => {
+                                info!("Alert firing: {}", alert.summary);
+                                notify_all(&settings, alert, false).await;
+                            }
+                        }
+                    }
+                    for (key, alert) in firing.iter() {
+                        if current.contains_key(key) {
+                            continue;
+                        }
+                        // Removed or disabled rules stop firing silently
+                        let active_rule = settings
+                            .rules
+                            .iter()
+                            .any(|rule| rule.id == alert.rule_id && rule.enabled);
+                        if active_rule {
+                            info!("Alert resolved: {}", alert.summary);
+                            notify_all(&settings, alert, true).await;
+                        }
+                    }
+                    let changed = firing.len() != current.len()
+                        || current.keys().any(|key| !firing.contains_key(key));
+                    firing = current;
+                    if changed {
+                        if let Err(err) = alerts::save_firing(&firing).await {
+                            warn!("Cannot save firing alerts {err:?}");
+                        }
+                    }
+
+                    let mut published = firing.values().cloned().collect::<Vec<_>>();
+                    published.sort_by_key(|alert| alert.since);
+                    alerts::set_firing(published);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,36 @@
+use app::{audit, common::AuditQuery};
+use axum::{
+    extract::Query,
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+};
+use axum_extra::extract::cookie::CookieJar;
+use chrono::Utc;
+
+use crate::container_events::ensure_authorized_user;
+
+/// Every audit log entry matching the query as a JSON array, newest first
+pub async fn audit_export(
+    jar: CookieJar,
+    Query(query): Query<AuditQuery>,
+) -> Result<Response, (StatusCode, String)> {
+    ensure_authorized_user(jar)?;
+    let entries = audit::search(&query)
+        .await
+        .map_err(|err| (StatusCode::BAD_REQUEST, err.to_string()))?;
+    let body = serde_json::to_string_pretty(&entries)
+        .map_err(|err| (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()))?;
+
+    let file_name = format!("audit-{}.json", Utc::now().format("%Y%m%d%H%M%S"));
+    Ok((
+        [
+            (header::CONTENT_TYPE, "application/json".to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"{file_name}\""),
+            ),
+        ],
+        body,
+    )
+        .into_response())
+}
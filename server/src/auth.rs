@@ -1,9 +1,19 @@
-use std::collections::HashMap;
+use app::common::{Project, ProjectType};
 
-use app::{auth::UserWithPass, common::get_home_path};
-
-pub async fn get_authorized_users() -> anyhow::Result<HashMap<String, UserWithPass>> {
-    let users = tokio::fs::read(get_home_path().join("users.json")).await?;
-    let users = serde_json::from_slice::<HashMap<String, UserWithPass>>(&users)?;
-    Ok(users)
+/// Checks `token` against the project's upload tokens, used by the CI facing endpoints.
+/// Returns the token's description to record who deployed.
+pub fn verify_project_token(project: &Project, token: &str) -> anyhow::Result<String> {
+    let ProjectType::Container { tokens, .. } = &project.project_type else {
+        anyhow::bail!("Project not of type container");
+    };
+    let token = tokens
+        .get(token)
+        .ok_or(anyhow::anyhow!("Project token not valid"))?;
+    if let Some(expiry) = &token.expiry {
+        let current_date = chrono::Utc::now().naive_utc().date();
+        if &current_date > expiry {
+            anyhow::bail!("Project token not valid");
+        }
+    }
+    Ok(token.description.clone())
 }
@@ -1,6 +1,9 @@
 use std::sync::Arc;
 
-use app::common::{AttachParams, TtyChunk};
+use app::{
+    audit,
+    common::{AttachParams, AuditActor, TtyChunk},
+};
 use axum::{
     extract::{
         ws::{Message, WebSocket},
@@ -14,39 +17,64 @@ use docker_api::{
     Container,
 };
 use futures::{AsyncWriteExt, SinkExt, StreamExt};
-use http::StatusCode;
 use tracing::warn;
 use uuid::Uuid;
 
 use crate::leptos_service::AppState;
 
-use super::ensure_authorized_user;
+use super::{ensure_authorized_user, running_container, ContainerSelector};
 
 pub async fn container_attach_ws(
     State(app_state): State<AppState>,
     jar: CookieJar,
     Path(project_id): Path<Uuid>,
+    Query(selector): Query<ContainerSelector>,
     Query(attach_params): Query<AttachParams>,
     ws: WebSocketUpgrade,
 ) -> Result<Response, (axum::http::StatusCode, String)> {
-    ensure_authorized_user(jar)?;
-    let container = {
-        let project = app_state
-            .project_context
-            .get_project(project_id)
-            .await
-            .ok_or((StatusCode::BAD_REQUEST, "Project doesnt exist".to_string()))?;
-        let container = project
-            .project_type
-            .try_get_primary()
-            .ok_or((StatusCode::BAD_REQUEST, "Project not container".to_string()))?
-            .status
-            .as_running()
-            .ok_or((StatusCode::BAD_REQUEST, "Container not running".to_string()))?;
-        container.clone()
-    };
+    let user = ensure_authorized_user(jar)?;
+    let container = running_container(&app_state, project_id, &selector).await?;
+    let project = app_state.project_context.get_project(project_id).await;
+    let detail = format!(
+        "{} {}",
+        selector.slot().docker_name(project_id),
+        attach_params.command
+    );
+
+    Ok(ws.on_upgrade(move |socket| async move {
+        audit::record_session(
+            AuditActor::from(&user),
+            "container.attach",
+            project.as_deref(),
+            Some(detail),
+            handle_attach_socket(socket, container, attach_params),
+        )
+        .await
+    }))
+}
 
-    Ok(ws.on_upgrade(|socket| handle_attach_socket(socket, container, attach_params)))
+/// Options to exec `command` with stdout and stderr attached. With a console size the
+/// exec gets a TTY and stdin for interactive use, without one the output stays
+/// multiplexed so stdout and stderr can be told apart.
+pub(crate) fn exec_opts(
+    command: Vec<String>,
+    working_dir: &str,
+    console_size: Option<ConsoleSize>,
+) -> (ExecCreateOpts, ExecStartOpts) {
+    let mut create = ExecCreateOpts::builder()
+        .command(command)
+        .attach_stderr(true)
+        .attach_stdout(true)
+        .working_dir(working_dir);
+    let mut start = ExecStartOpts::builder();
+    if let Some(ConsoleSize { height, width }) = console_size {
+        create = create
+            .attach_stdin(true)
+            .tty(true)
+            .console_size(ConsoleSize { height, width });
+        start = start.tty(true).console_size(ConsoleSize { height, width });
+    }
+    (create.build(), start.build())
 }
 
 async fn handle_attach_socket(
@@ -54,29 +82,15 @@ async fn handle_attach_socket(
     container: Arc<Container>,
     attach_params: AttachParams,
 ) {
-    let exec_multiplexer = container
-        .exec(
-            &ExecCreateOpts::builder()
-                .command(vec![attach_params.command])
-                .attach_stderr(true)
-                .attach_stdout(true)
-                .attach_stdin(true)
-                .working_dir("/")
-                .tty(true)
-                .console_size(ConsoleSize {
-                    height: attach_params.size_height,
-                    width: attach_params.size_width,
-                })
-                .build(),
-            &ExecStartOpts::builder()
-                .tty(true)
-                .console_size(ConsoleSize {
-                    height: attach_params.size_height,
-                    width: attach_params.size_width,
-                })
-                .build(),
-        )
-        .await;
+    let (create_opts, start_opts) = exec_opts(
+        vec![attach_params.command],
+        "/",
+        Some(ConsoleSize {
+            height: attach_params.size_height,
+            width: attach_params.size_width,
+        }),
+    );
+    let exec_multiplexer = container.exec(&create_opts, &start_opts).await;
     match exec_multiplexer {
         Ok(multiplexer) => {
             let (mut receiver_out, mut input_sender) = multiplexer.split();
@@ -0,0 +1,168 @@
+//! Runs a command in a project container and answers with its output once it exits, the
+//! non-interactive counterpart of [`super::attach`] for CI and scripts.
+
+use std::time::{Duration, Instant};
+
+use app::{
+    audit,
+    common::{get_docker, AuditActor, TtyChunk},
+};
+use axum::{extract::State, Json};
+use docker_api::Exec;
+use futures::StreamExt;
+use http::StatusCode;
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use crate::{auth::verify_project_token, leptos_service::AppState};
+
+use super::{attach::exec_opts, ContainerSelector};
+
+/// Used when the request doesn't set a timeout
+const DEFAULT_TIMEOUT_SECS: u64 = 60;
+const MAX_TIMEOUT_SECS: u64 = 60 * 60;
+/// Waited beyond the timeout for the output of the killed command
+const KILL_GRACE: Duration = Duration::from_secs(5);
+/// Output beyond this is dropped from the start of each stream
+const MAX_OUTPUT_BYTES: usize = 1024 * 1024;
+
+#[derive(Deserialize)]
+pub struct ExecRequest {
+    project_id: Uuid,
+    token: String,
+    #[serde(flatten)]
+    selector: ContainerSelector,
+    command: Vec<String>,
+    #[serde(default)]
+    working_dir: Option<String>,
+    #[serde(default)]
+    timeout_secs: Option<u64>,
+}
+
+#[derive(Serialize)]
+pub struct ExecResponse {
+    stdout: String,
+    stderr: String,
+    /// `None` when the command was killed at the timeout
+    exit_code: Option<i64>,
+    timed_out: bool,
+}
+
+/// Execs `command` in a running project container, authenticated with a project token
+pub async fn container_exec(
+    State(state): State<AppState>,
+    Json(request): Json<ExecRequest>,
+) -> Result<Json<ExecResponse>, (StatusCode, String)> {
+    let project = state
+        .project_context
+        .get_project(request.project_id)
+        .await
+        .ok_or((StatusCode::BAD_REQUEST, "Project doesnt exist".to_string()))?;
+    let token = verify_project_token(&project, &request.token)
+        .map_err(|err| (StatusCode::UNAUTHORIZED, err.to_string()))?;
+    if request.command.is_empty() {
+        return Err((StatusCode::BAD_REQUEST, "Command is empty".to_string()));
+    }
+    let slot = request.selector.slot();
+    let container = project
+        .project_type
+        .get_container(&slot)
+        .ok_or((
+            StatusCode::BAD_REQUEST,
+            "Container doesnt exist".to_string(),
+        ))?
+        .status
+        .as_running()
+        .ok_or((StatusCode::BAD_REQUEST, "Container not running".to_string()))?
+        .clone();
+
+    info!(
+        "Exec in {} of {} by {token}: {:?}",
+        slot.docker_name(project.id),
+        project.name,
+        request.command
+    );
+    audit::record(
+        AuditActor::Token {
+            project_id: project.id,
+            description: token,
+        },
+        "container.exec",
+        Some(project.as_ref()),
+        Default::default(),
+        Some(format!(
+            "{} {:?}",
+            slot.docker_name(project.id),
+            request.command
+        )),
+    )
+    .await;
+    let timeout = Duration::from_secs(
+        request
+            .timeout_secs
+            .unwrap_or(DEFAULT_TIMEOUT_SECS)
+            .clamp(1, MAX_TIMEOUT_SECS),
+    );
+    // Killed inside the container, the server never signals processes it did not start
+    let command = [
+        "timeout",
+        "-s",
+        "KILL",
+        timeout.as_secs().to_string().as_str(),
+    ]
+    .into_iter()
+    .map(String::from)
+    .chain(request.command)
+    .collect();
+    let (create_opts, start_opts) =
+        exec_opts(command, request.working_dir.as_deref().unwrap_or("/"), None);
+    let exec = Exec::create(get_docker(), container.id().clone(), &create_opts)
+        .await
+        .map_err(|err| (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()))?;
+    let mut output = exec
+        .start(&start_opts)
+        .await
+        .map_err(|err| (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()))?;
+
+    let (mut stdout, mut stderr) = (Vec::new(), Vec::new());
+    let collect = async {
+        while let Some(chunk) = output.next().await {
+            match chunk.map(TtyChunk::from) {
+                Ok(TtyChunk::StdOut(bytes)) => append_capped(&mut stdout, &bytes),
+                Ok(TtyChunk::StdErr(bytes)) => append_capped(&mut stderr, &bytes),
+                Ok(TtyChunk::StdIn(_)) => {}
+                Err(err) => {
+                    warn!("Exec output failed {err:?}");
+                    break;
+                }
+            }
+        }
+    };
+    let started = Instant::now();
+    let collected = tokio::time::timeout(timeout + KILL_GRACE, collect).await;
+    let timed_out = collected.is_err() || started.elapsed() >= timeout;
+    let exit_code = if timed_out {
+        None
+    } else {
+        exec.inspect()
+            .await
+            .map_err(|err| (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()))?
+            .exit_code
+            .map(|code| code as i64)
+    };
+
+    Ok(Json(ExecResponse {
+        stdout: String::from_utf8_lossy(&stdout).into_owned(),
+        stderr: String::from_utf8_lossy(&stderr).into_owned(),
+        exit_code,
+        timed_out,
+    }))
+}
+
+fn append_capped(buffer: &mut Vec<u8>, bytes: &[u8]) {
+    buffer.extend_from_slice(bytes);
+    if buffer.len() > MAX_OUTPUT_BYTES {
+        buffer.drain(..buffer.len() - MAX_OUTPUT_BYTES);
+    }
+}
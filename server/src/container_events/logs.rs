@@ -1,58 +1,70 @@
-use std::sync::Arc;
+use std::{fmt::Write, sync::Arc};
 
-use app::common::TtyChunk;
+use app::{
+    common::{LogQuery, LogStream, LogsParams, TtyChunk},
+    log_store,
+};
 use axum::{
     extract::{
         ws::{Message, WebSocket},
-        Path, State, WebSocketUpgrade,
+        Path, Query, State, WebSocketUpgrade,
     },
-    response::Response,
+    response::{IntoResponse, Response},
 };
 use axum_extra::extract::cookie::CookieJar;
+use chrono::{DateTime, Utc};
 use docker_api::{opts::LogsOpts, Container};
-use http::StatusCode;
+use http::{header, StatusCode};
 use tokio_stream::StreamExt;
 use tracing::warn;
 use uuid::Uuid;
 
 use crate::leptos_service::AppState;
 
-use super::ensure_authorized_user;
+use super::{ensure_authorized_user, running_container, ContainerSelector};
 
 pub async fn container_logs_ws(
     State(app_state): State<AppState>,
     jar: CookieJar,
     Path(project_id): Path<Uuid>,
+    Query(selector): Query<ContainerSelector>,
+    Query(params): Query<LogsParams>,
     ws: WebSocketUpgrade,
 ) -> Result<Response, (axum::http::StatusCode, String)> {
     ensure_authorized_user(jar)?;
-    let container = {
-        let project = app_state
-            .project_context
-            .get_project(project_id)
-            .await
-            .ok_or((StatusCode::BAD_REQUEST, "Project doesnt exist".to_string()))?;
-        let container = project
-            .project_type
-            .try_get_primary()
-            .ok_or((StatusCode::BAD_REQUEST, "Project not container".to_string()))?
-            .status
-            .as_running()
-            .ok_or((StatusCode::BAD_REQUEST, "Container not running".to_string()))?;
-        container.clone()
-    };
+    let container = running_container(&app_state, project_id, &selector).await?;
+    let since = params
+        .since_time()
+        .map_err(|err| (StatusCode::BAD_REQUEST, err))?;
+    let until = params
+        .until_time()
+        .map_err(|err| (StatusCode::BAD_REQUEST, err))?;
+
+    let mut opts = LogsOpts::builder()
+        .follow(until.is_none())
+        .stderr(params.stderr)
+        .stdout(params.stdout)
+        // `until` is applied to the lines here, which needs their timestamps
+        .timestamps(params.timestamps || until.is_some());
+    if let Some(tail) = params.tail {
+        opts = opts.n_lines(tail as usize);
+    }
+    if let Some(since) = &since {
+        opts = opts.since(since);
+    }
+    let until = until.map(|until| (until, params.timestamps));
 
-    Ok(ws.on_upgrade(|socket| handle_logs_socket(socket, container)))
+    Ok(ws.on_upgrade(move |socket| handle_logs_socket(socket, container, opts.build(), until)))
 }
 
-async fn handle_logs_socket(mut socket: WebSocket, container: Arc<Container>) {
-    let mut logs_stream = container.logs(
-        &LogsOpts::builder()
-            .follow(true)
-            .stderr(true)
-            .stdout(true)
-            .build(),
-    );
+/// `until` is the time after which lines are dropped, with whether to keep their timestamp
+async fn handle_logs_socket(
+    mut socket: WebSocket,
+    container: Arc<Container>,
+    opts: LogsOpts,
+    until: Option<(DateTime<Utc>, bool)>,
+) {
+    let mut logs_stream = container.logs(&opts);
     loop {
         tokio::select! {
             rec = socket.recv() => {
@@ -65,7 +77,13 @@ async fn handle_logs_socket(mut socket: WebSocket, container: Arc<Container>) {
             Some(item) = logs_stream.next() => {
                 match item {
                     Ok(item) => {
-                        let item = TtyChunk::from(item);
+                        let mut item = TtyChunk::from(item);
+                        if let Some((until, keep_timestamp)) = until {
+                            match before_until(item, until, keep_timestamp) {
+                                Some(before) => item = before,
+                                None => continue,
+                            }
+                        }
                         if let Ok(serialized_data) = bincode::serialize(&item) {
                             if let Err(err) = socket.send(Message::Binary(serialized_data.into())).await {
                                 warn!("Failed to send msg {err:?}");
@@ -84,3 +102,70 @@ async fn handle_logs_socket(mut socket: WebSocket, container: Arc<Container>) {
 
     // }
 }
+
+/// Drops a timestamped chunk written after `until`
+fn before_until(chunk: TtyChunk, until: DateTime<Utc>, keep_timestamp: bool) -> Option<TtyChunk> {
+    let strip = |bytes: Vec<u8>| {
+        let split = bytes.iter().position(|b| *b == b' ')?;
+        let ts = std::str::from_utf8(&bytes[..split]).ok()?;
+        if DateTime::parse_from_rfc3339(ts).ok()? > until {
+            return None;
+        }
+        Some(if keep_timestamp {
+            bytes
+        } else {
+            bytes[split + 1..].to_vec()
+        })
+    };
+    match chunk {
+        TtyChunk::StdOut(bytes) => strip(bytes).map(TtyChunk::StdOut),
+        TtyChunk::StdErr(bytes) => strip(bytes).map(TtyChunk::StdErr),
+        TtyChunk::StdIn(bytes) => Some(TtyChunk::StdIn(bytes)),
+    }
+}
+
+/// Persisted logs matching the query as a text file, one `<time> <container> <stream> <text>`
+/// line each
+pub async fn container_logs_download(
+    jar: CookieJar,
+    Path(project_id): Path<Uuid>,
+    Query(query): Query<LogQuery>,
+) -> Result<Response, (StatusCode, String)> {
+    ensure_authorized_user(jar)?;
+    let mut body = String::new();
+    log_store::for_each_match(project_id, &query, |found| {
+        let stream = match found.line.stream {
+            LogStream::Stdout => "stdout",
+            LogStream::Stderr => "stderr",
+        };
+        let _ = writeln!(
+            body,
+            "{} {} {stream} {}",
+            found.line.ts.to_rfc3339(),
+            found.version,
+            found.line.text
+        );
+    })
+    .await
+    .map_err(|err| (StatusCode::BAD_REQUEST, err.to_string()))?;
+
+    let file_name = format!(
+        "{}-{}.log",
+        query.container.as_deref().unwrap_or("primary"),
+        Utc::now().format("%Y%m%d%H%M%S")
+    );
+    Ok((
+        [
+            (
+                header::CONTENT_TYPE,
+                "text/plain; charset=utf-8".to_string(),
+            ),
+            (
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"{file_name}\""),
+            ),
+        ],
+        body,
+    )
+        .into_response())
+}
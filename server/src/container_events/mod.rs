@@ -1,8 +1,19 @@
-use app::auth::{server::get_user_from_cookie, AuthType, User};
+use std::sync::Arc;
+
+use app::{
+    auth::{server::get_user_from_cookie, AuthType, User},
+    common::ContainerSlot,
+};
 use axum_extra::extract::cookie::CookieJar;
+use docker_api::Container;
 use http::StatusCode;
+use serde::Deserialize;
+use uuid::Uuid;
+
+use crate::leptos_service::AppState;
 
 pub mod attach;
+pub mod exec;
 pub mod logs;
 pub mod stats;
 
@@ -26,3 +37,43 @@ pub fn ensure_authorized_user(jar: CookieJar) -> Result<User, (StatusCode, Strin
         AuthType::Authorized(user) => Ok(user),
     }
 }
+
+/// `?container=<name>` picks a support container, the primary container is used without it
+#[derive(Deserialize)]
+pub struct ContainerSelector {
+    #[serde(default)]
+    container: Option<String>,
+}
+
+impl ContainerSelector {
+    pub fn slot(&self) -> ContainerSlot {
+        match self.container.as_deref().filter(|name| !name.is_empty()) {
+            Some(name) => ContainerSlot::Support(name.to_string()),
+            None => ContainerSlot::Primary,
+        }
+    }
+}
+
+/// The selected container of the project, if it is running
+pub async fn running_container(
+    app_state: &AppState,
+    project_id: Uuid,
+    selector: &ContainerSelector,
+) -> Result<Arc<Container>, (StatusCode, String)> {
+    let project = app_state
+        .project_context
+        .get_project(project_id)
+        .await
+        .ok_or((StatusCode::BAD_REQUEST, "Project doesnt exist".to_string()))?;
+    let container = project
+        .project_type
+        .get_container(&selector.slot())
+        .ok_or((
+            StatusCode::BAD_REQUEST,
+            "Container doesnt exist".to_string(),
+        ))?
+        .status
+        .as_running()
+        .ok_or((StatusCode::BAD_REQUEST, "Container not running".to_string()))?;
+    Ok(container.clone())
+}
@@ -3,43 +3,29 @@ use std::sync::Arc;
 use axum::{
     extract::{
         ws::{Message, WebSocket},
-        Path, State, WebSocketUpgrade,
+        Path, Query, State, WebSocketUpgrade,
     },
     response::Response,
 };
 use axum_extra::extract::cookie::CookieJar;
 use docker_api::Container;
-use http::StatusCode;
 use tokio_stream::StreamExt;
 use tracing::warn;
 use uuid::Uuid;
 
 use crate::leptos_service::AppState;
 
-use super::ensure_authorized_user;
+use super::{ensure_authorized_user, running_container, ContainerSelector};
 
 pub async fn container_stats_ws(
     State(app_state): State<AppState>,
     jar: CookieJar,
     Path(project_id): Path<Uuid>,
+    Query(selector): Query<ContainerSelector>,
     ws: WebSocketUpgrade,
 ) -> Result<Response, (axum::http::StatusCode, String)> {
     ensure_authorized_user(jar)?;
-    let container = {
-        let project = app_state
-            .project_context
-            .get_project(project_id)
-            .await
-            .ok_or((StatusCode::BAD_REQUEST, "Project doesnt exist".to_string()))?;
-        let container = project
-            .project_type
-            .try_get_primary()
-            .ok_or((StatusCode::BAD_REQUEST, "Project not container".to_string()))?
-            .status
-            .as_running()
-            .ok_or((StatusCode::BAD_REQUEST, "Container not running".to_string()))?;
-        container.clone()
-    };
+    let container = running_container(&app_state, project_id, &selector).await?;
 
     Ok(ws.on_upgrade(|socket| handle_stats_socket(socket, container)))
 }
@@ -1,15 +1,25 @@
-use std::sync::Arc;
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::Instant,
+};
 
 use app::{
-    common::{get_docker, ContainerStatus, Project, ProjectType, SupportContainer},
+    common::{
+        get_docker, split_command, start_order, ContainerRuntime, ContainerSlot, ContainerStatus,
+        Dependency, DependencyCondition, DeployStatus, HealthCheck, HealthStatus, HookKind,
+        Project, ProjectType, ResourceLimits, SupportContainer,
+    },
     context::ProjectContext,
 };
 use docker_api::{
     opts::{
-        ContainerCreateOpts, ContainerRemoveOpts, ContainerStopOpts, NetworkCreateOpts, PublishPort,
+        ContainerCreateOpts, ContainerCreateOptsBuilder, ContainerRemoveOpts, ContainerStopOpts,
+        ExecCreateOpts, ExecStartOpts, NetworkCreateOpts, PublishPort,
     },
-    Container, Docker, Id,
+    Container, Docker, Exec, Id,
 };
+use futures::StreamExt;
 use leptos::logging::warn;
 use pingora::{
     protocols::ALPN,
@@ -20,13 +30,36 @@ use pingora::{
 use tracing::info;
 use uuid::Uuid;
 
+use crate::{
+    deploy_hooks::{abort_deploy, run_hook},
+    job_scheduler::JobScheduler,
+};
+
+/// A container that keeps running this long is no longer considered crash looping
+const STABLE_RUNNING_SECS: i64 = 600;
+
 pub struct ContainerManager {
     project_context: ProjectContext,
+    probes: Arc<Mutex<HashMap<String, ProbeState>>>,
+    jobs: JobScheduler,
+}
+
+#[derive(Default)]
+struct ProbeState {
+    last_run: Option<Instant>,
+    in_flight: bool,
 }
 
 impl ContainerManager {
     pub fn to_service(project_context: ProjectContext) -> GenBackgroundService<Self> {
-        background_service("container_manager", Self { project_context })
+        background_service(
+            "container_manager",
+            Self {
+                project_context,
+                probes: Arc::new(Mutex::new(HashMap::new())),
+                jobs: JobScheduler::default(),
+            },
+        )
     }
 }
 
@@ -44,12 +77,26 @@ impl BackgroundService for ContainerManager {
                 _ = period.tick() => {
                     tracing::debug!("Container tick");
                     let  peers = self.project_context.get_projects().await;
+                    let requested_runs = app::jobs::take_run_requests();
                     for project in peers.iter() {
+                        self.jobs.tick(project, &requested_runs);
                         if let ProjectType::Container{
                             primary_container: container,
+                            support_containers,
                             ..
                         } = &project.project_type {
-                            if container.status.is_none(){
+                            for (name, support_container) in support_containers.iter() {
+                                let slot = ContainerSlot::Support(name.clone());
+                                // New or reconfigured support container next to an already running project
+                                if support_container.container.status.is_none() && container.status.is_running() {
+                                    self.start_support_container(project.id, slot);
+                                } else {
+                                    self.supervise(project, slot, &support_container.container).await;
+                                }
+                            }
+                            if !container.status.is_none() {
+                                self.supervise(project, ContainerSlot::Primary, container).await;
+                            } else {
                                 let mut project_t = project.clone().as_ref().clone();
                                 if let ProjectType::Container{ primary_container: container, ..} = &mut project_t.project_type{
                                     container.status = ContainerStatus::Creating;
@@ -64,17 +111,7 @@ impl BackgroundService for ContainerManager {
 
                                     if let Err(err) = run_and_set_container(project.clone(), context.clone()).await {
                                         warn!("Failed to run container {err:?}");
-
-                                        {
-                                            let mut new_p = project.as_ref().clone();
-                                            if let ProjectType::Container{primary_container: container, ..} = &mut new_p.project_type {
-                                                container.status = ContainerStatus::Failed;
-                                            }
-                                            if let Err(err) =  context.update_project(new_p.id, Arc::new(new_p)).await {
-                                                warn!("Failed to update project status {err:?}");
-                                            }
-
-                                        }
+                                        mark_failed(&mut context, project.id, &ContainerSlot::Primary, format!("{err}")).await;
                                     }
                                 });
                             }
@@ -86,6 +123,576 @@ impl BackgroundService for ContainerManager {
     }
 }
 
+impl ContainerManager {
+    /// Checks a single container on every tick, retrying failed containers once their
+    /// backoff elapsed and noticing containers that exited or became unhealthy.
+    async fn supervise(
+        &self,
+        project: &Project,
+        slot: ContainerSlot,
+        container: &app::common::Container,
+    ) {
+        let mut context = self.project_context.clone();
+        match &container.status {
+            ContainerStatus::Failed if container.should_retry() => {
+                info!(
+                    "Retrying {slot} container of {} after backoff",
+                    project.name
+                );
+                if slot == ContainerSlot::Primary {
+                    update_container(&mut context, project.id, &slot, |c| {
+                        c.status = ContainerStatus::None
+                    })
+                    .await;
+                } else {
+                    self.start_support_container(project.id, slot);
+                }
+            }
+            ContainerStatus::Running(docker_container)
+                if !container.runtime.stopped_by_user && !container.runtime.completed =>
+            {
+                let inspect = match docker_container.inspect().await {
+                    Ok(inspect) => inspect,
+                    Err(err) => {
+                        warn!(
+                            "Cannot inspect {slot} container of {} {err:?}",
+                            project.name
+                        );
+                        mark_failed(&mut context, project.id, &slot, "Container went missing")
+                            .await;
+                        return;
+                    }
+                };
+                let state = inspect.state.as_ref();
+                let running = state.and_then(|s| s.running).unwrap_or(false);
+                let restarting = state.and_then(|s| s.restarting).unwrap_or(false);
+                if !running && !restarting {
+                    let succeeded = state
+                        .and_then(|s| s.exit_code)
+                        .map(|c| c == 0)
+                        .unwrap_or(false);
+                    if succeeded && is_one_off(project, &slot) {
+                        info!("{slot} container of {} completed", project.name);
+                        mark_completed(&mut context, project.id, &slot).await;
+                        return;
+                    }
+                    if state.and_then(|s| s.oom_killed).unwrap_or(false) {
+                        info!("{slot} container of {} ran out of memory", project.name);
+                        mark_oom_killed(&mut context, project.id, &slot).await;
+                        return;
+                    }
+                    let reason = format!(
+                        "Exited with code {}",
+                        state
+                            .and_then(|s| s.exit_code)
+                            .map(|c| c.to_string())
+                            .unwrap_or("unknown".to_string())
+                    );
+                    info!("{slot} container of {} stopped: {reason}", project.name);
+                    mark_failed(&mut context, project.id, &slot, reason).await;
+                    return;
+                }
+                if restarting {
+                    return;
+                }
+
+                let runtime = &container.runtime;
+                if runtime.consecutive_failures > 0 && runtime.health != HealthStatus::Unhealthy {
+                    let stable = runtime
+                        .started_at
+                        .map(|at| {
+                            chrono::Utc::now() - at > chrono::Duration::seconds(STABLE_RUNNING_SECS)
+                        })
+                        .unwrap_or(false);
+                    if stable {
+                        info!("{slot} container of {} is stable again", project.name);
+                        update_container(&mut context, project.id, &slot, |c| {
+                            c.runtime.consecutive_failures = 0
+                        })
+                        .await;
+                    }
+                }
+
+                if let Some(check) = &container.healthcheck {
+                    self.schedule_probe(project.id, slot, docker_container.clone(), check.clone());
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn start_support_container(&self, project_id: Uuid, slot: ContainerSlot) {
+        let ContainerSlot::Support(name) = slot.clone() else {
+            return;
+        };
+        let mut context = self.project_context.clone();
+        tokio::spawn(async move {
+            update_container(&mut context, project_id, &slot, |c| {
+                c.status = ContainerStatus::Creating
+            })
+            .await;
+            if let Err(err) = restart_support_container(project_id, &name, context.clone()).await {
+                warn!("Failed to start support container {name} {err:?}");
+                mark_failed(&mut context, project_id, &slot, format!("{err}")).await;
+            }
+        });
+    }
+
+    fn schedule_probe(
+        &self,
+        project_id: Uuid,
+        slot: ContainerSlot,
+        docker_container: Arc<Container>,
+        check: HealthCheck,
+    ) {
+        let key = slot.docker_name(project_id);
+        {
+            let mut probes = self.probes.lock().unwrap();
+            let state = probes.entry(key.clone()).or_default();
+            let due = state
+                .last_run
+                .map(|last| last.elapsed().as_secs() >= check.interval_secs.max(1))
+                .unwrap_or(true);
+            if state.in_flight || !due {
+                return;
+            }
+            state.in_flight = true;
+            state.last_run = Some(Instant::now());
+        }
+
+        let probes = self.probes.clone();
+        let mut context = self.project_context.clone();
+        tokio::spawn(async move {
+            let healthy = run_health_probe(&docker_container, &check).await;
+            record_probe_result(&mut context, project_id, &slot, &check, healthy).await;
+            if let Some(state) = probes.lock().unwrap().get_mut(&key) {
+                state.in_flight = false;
+            }
+        });
+    }
+}
+
+/// Applies `f` to the latest state of a project and saves it.
+pub(crate) async fn update_project_with(
+    context: &mut ProjectContext,
+    project_id: Uuid,
+    f: impl FnOnce(&mut Project),
+) {
+    let Some(project) = context.get_project(project_id).await else {
+        warn!("Project {project_id} not found for container update");
+        return;
+    };
+    let mut project = project.as_ref().clone();
+    f(&mut project);
+    if let Err(err) = context.update_project(project_id, Arc::new(project)).await {
+        warn!("Failed to update project status {err:?}");
+    }
+}
+
+/// Applies `f` to the latest state of a project container and saves it.
+pub(crate) async fn update_container(
+    context: &mut ProjectContext,
+    project_id: Uuid,
+    slot: &ContainerSlot,
+    f: impl FnOnce(&mut app::common::Container),
+) {
+    update_project_with(context, project_id, |project| {
+        match project.project_type.get_container_mut(slot) {
+            Some(container) => f(container),
+            None => warn!("Container {slot} not found in project {project_id}"),
+        }
+    })
+    .await;
+}
+
+/// Marks the container as failed and stops routing traffic to it.
+pub(crate) async fn mark_failed(
+    context: &mut ProjectContext,
+    project_id: Uuid,
+    slot: &ContainerSlot,
+    reason: impl Into<String>,
+) {
+    let reason = reason.into();
+    fail_container(context, project_id, slot, |runtime| {
+        runtime.record_failure(reason)
+    })
+    .await;
+}
+
+/// Like [`mark_failed`], but the container is only brought back up
+/// when its limits allow restarting on OOM.
+pub(crate) async fn mark_oom_killed(
+    context: &mut ProjectContext,
+    project_id: Uuid,
+    slot: &ContainerSlot,
+) {
+    fail_container(context, project_id, slot, |runtime| {
+        runtime.record_failure("Killed after running out of memory");
+        runtime.oom_killed = true;
+    })
+    .await;
+}
+
+async fn fail_container(
+    context: &mut ProjectContext,
+    project_id: Uuid,
+    slot: &ContainerSlot,
+    record: impl FnOnce(&mut ContainerRuntime),
+) {
+    update_project_with(context, project_id, |project| {
+        if let ProjectType::Container { exposed_ports, .. } = &mut project.project_type {
+            if *slot == ContainerSlot::Primary {
+                for port in exposed_ports.iter_mut() {
+                    port.peer = None;
+                }
+            }
+        }
+        let Some(c) = project.project_type.get_container_mut(slot) else {
+            return;
+        };
+        c.status = ContainerStatus::Failed;
+        record(&mut c.runtime);
+        if c.runtime.is_crash_looping() {
+            warn!(
+                "{slot} container of {project_id} is crash looping, retrying in {}s",
+                c.runtime.backoff().num_seconds()
+            );
+        }
+        let reason = c.runtime.last_failure.clone().unwrap_or_default();
+        if *slot == ContainerSlot::Primary {
+            if let ProjectType::Container { deploy_history, .. } = &mut project.project_type {
+                deploy_history.advance(DeployStatus::Failed(reason));
+            }
+        }
+    })
+    .await;
+}
+
+/// Moves the project's active deployment on, saving only when it changes
+async fn advance_deployment(context: &mut ProjectContext, project_id: Uuid, status: DeployStatus) {
+    let Some(project) = context.get_project(project_id).await else {
+        return;
+    };
+    let ProjectType::Container { deploy_history, .. } = &project.project_type else {
+        return;
+    };
+    if !deploy_history.would_advance(&status) {
+        return;
+    }
+    update_project_with(context, project_id, |project| {
+        if let ProjectType::Container { deploy_history, .. } = &mut project.project_type {
+            deploy_history.advance(status);
+        }
+    })
+    .await;
+}
+
+/// Marks a one-off container that exited successfully so it isn't restarted.
+pub(crate) async fn mark_completed(
+    context: &mut ProjectContext,
+    project_id: Uuid,
+    slot: &ContainerSlot,
+) {
+    update_container(context, project_id, slot, |c| c.runtime.completed = true).await;
+}
+
+/// Whether another container in the project waits for this one to run to completion
+pub(crate) fn is_one_off(project: &Project, slot: &ContainerSlot) -> bool {
+    let ContainerSlot::Support(name) = slot else {
+        return false;
+    };
+    let ProjectType::Container {
+        primary_container,
+        support_containers,
+        ..
+    } = &project.project_type
+    else {
+        return false;
+    };
+    std::iter::once(primary_container)
+        .chain(support_containers.values().map(|s| &s.container))
+        .flat_map(|c| c.depends_on.iter())
+        .any(|dep| &dep.name == name && dep.condition == DependencyCondition::CompletedSuccessfully)
+}
+
+/// Waits until every dependency reached its readiness condition,
+/// giving up after the dependency's timeout.
+async fn wait_for_dependencies(project: &Project, depends_on: &[Dependency]) -> anyhow::Result<()> {
+    let docker = get_docker();
+    for dep in depends_on {
+        let slot = ContainerSlot::Support(dep.name.clone());
+        let container = docker.containers().get(slot.docker_name(project.id));
+        let healthcheck = project
+            .project_type
+            .get_container(&slot)
+            .and_then(|c| c.healthcheck.as_ref());
+        info!("Waiting for {} to be {}", dep.name, dep.condition.as_str());
+        let wait = async {
+            loop {
+                if dependency_ready(&container, dep, healthcheck).await? {
+                    return Ok::<_, anyhow::Error>(());
+                }
+                tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+            }
+        };
+        tokio::time::timeout(std::time::Duration::from_secs(dep.timeout_secs), wait)
+            .await
+            .map_err(|_| {
+                anyhow::anyhow!(
+                    "Timed out waiting for {} to be {}",
+                    dep.name,
+                    dep.condition.as_str()
+                )
+            })??;
+    }
+    Ok(())
+}
+
+async fn dependency_ready(
+    container: &Container,
+    dep: &Dependency,
+    healthcheck: Option<&HealthCheck>,
+) -> anyhow::Result<bool> {
+    let inspect = container.inspect().await?;
+    let state = inspect.state.as_ref();
+    let running = state.and_then(|s| s.running).unwrap_or(false);
+    match dep.condition {
+        DependencyCondition::CompletedSuccessfully => {
+            if running {
+                return Ok(false);
+            }
+            match state.and_then(|s| s.exit_code) {
+                Some(0) => Ok(true),
+                code => Err(anyhow::anyhow!("{} exited with code {code:?}", dep.name)),
+            }
+        }
+        // Might still be restarting
+        _ if !running => Ok(false),
+        DependencyCondition::Started => Ok(true),
+        DependencyCondition::TcpPort => {
+            let port = dep
+                .port
+                .ok_or(anyhow::anyhow!("No port to wait for on {}", dep.name))?;
+            let ip = inspect
+                .network_settings
+                .as_ref()
+                .and_then(|n| n.networks.as_ref())
+                .and_then(|networks| {
+                    networks
+                        .values()
+                        .find_map(|n| n.ip_address.clone().filter(|ip| !ip.is_empty()))
+                });
+            let Some(ip) = ip else {
+                return Ok(false);
+            };
+            let connect = tokio::net::TcpStream::connect((ip.as_str(), port));
+            Ok(
+                tokio::time::timeout(std::time::Duration::from_secs(1), connect)
+                    .await
+                    .map(|r| r.is_ok())
+                    .unwrap_or(false),
+            )
+        }
+        DependencyCondition::Healthy => match healthcheck {
+            Some(check) => Ok(run_health_probe(container, check).await),
+            None => {
+                warn!("{} has no healthcheck, treating it as started", dep.name);
+                Ok(true)
+            }
+        },
+    }
+}
+
+/// Marks the container as running, for the primary container the gateway
+/// peers are pointed at the host ports docker published.
+pub(crate) async fn mark_running(
+    context: &mut ProjectContext,
+    project_id: Uuid,
+    slot: &ContainerSlot,
+    container: Container,
+) -> anyhow::Result<()> {
+    let inspect = container.inspect().await?;
+    update_project_with(context, project_id, |project| {
+        if let ProjectType::Container { exposed_ports, .. } = &mut project.project_type {
+            if *slot == ContainerSlot::Primary {
+                if let Some(ports) = inspect.network_settings.and_then(|n| n.ports) {
+                    tracing::info!("Container running with ports {ports:#?}");
+                    for port in exposed_ports.iter_mut() {
+                        let port_q = format!("{}/tcp", port.port);
+                        let exposed_port = ports.get(&port_q);
+                        if let Some(host_port) = exposed_port
+                            .and_then(|p| p.to_owned())
+                            .and_then(|p| p.first().cloned())
+                            .and_then(|p| p.host_port)
+                            .and_then(|p| p.parse::<u16>().ok())
+                        {
+                            let mut peer = HttpPeer::new(
+                                format!("127.0.0.1:{host_port}"),
+                                false,
+                                String::new(),
+                            );
+                            peer.options.alpn = ALPN::H2H1;
+                            port.peer = Some(Arc::new(peer))
+                        }
+                    }
+                }
+            }
+        }
+        if let Some(c) = project.project_type.get_container_mut(slot) {
+            c.status = ContainerStatus::Running(Arc::new(container));
+            c.runtime.record_start();
+        }
+        if let ProjectType::Container {
+            primary_container,
+            deploy_history,
+            ..
+        } = &mut project.project_type
+        {
+            if *slot == ContainerSlot::Primary {
+                deploy_history.advance(DeployStatus::ContainerCreated);
+                // Running is all a container without a healthcheck can report
+                if primary_container.healthcheck.is_none() {
+                    deploy_history.advance(DeployStatus::Healthy);
+                }
+            }
+        }
+    })
+    .await;
+    Ok(())
+}
+
+/// Runs the healthcheck command once, the check passes when it exits with 0 within the timeout.
+async fn run_health_probe(container: &Container, check: &HealthCheck) -> bool {
+    let docker = get_docker();
+    let probe = async {
+        let exec = Exec::create(
+            docker,
+            container.id().clone(),
+            &ExecCreateOpts::builder()
+                .command(vec!["sh", "-c", check.command.as_str()])
+                .attach_stdout(true)
+                .attach_stderr(true)
+                .build(),
+        )
+        .await?;
+        let mut output = exec.start(&ExecStartOpts::builder().build()).await?;
+        while output.next().await.is_some() {}
+        let inspect = exec.inspect().await?;
+        Ok::<_, docker_api::Error>(inspect.exit_code == Some(0))
+    };
+    match tokio::time::timeout(
+        std::time::Duration::from_secs(check.timeout_secs.max(1)),
+        probe,
+    )
+    .await
+    {
+        Ok(Ok(healthy)) => healthy,
+        Ok(Err(err)) => {
+            warn!("Healthcheck exec failed {err:?}");
+            false
+        }
+        Err(_) => false,
+    }
+}
+
+async fn record_probe_result(
+    context: &mut ProjectContext,
+    project_id: Uuid,
+    slot: &ContainerSlot,
+    check: &HealthCheck,
+    healthy: bool,
+) {
+    let Some(project) = context.get_project(project_id).await else {
+        return;
+    };
+    let Some(container) = project.project_type.get_container(slot) else {
+        return;
+    };
+    let runtime = &container.runtime;
+
+    if healthy {
+        if runtime.health != HealthStatus::Healthy || runtime.failing_checks > 0 {
+            update_container(context, project_id, slot, |c| {
+                c.runtime.health = HealthStatus::Healthy;
+                c.runtime.failing_checks = 0;
+            })
+            .await;
+        }
+        if *slot == ContainerSlot::Primary {
+            advance_deployment(context, project_id, DeployStatus::Healthy).await;
+        }
+        return;
+    }
+
+    let in_start_period = runtime
+        .started_at
+        .map(|at| {
+            chrono::Utc::now() - at < chrono::Duration::seconds(check.start_period_secs as i64)
+        })
+        .unwrap_or(false);
+    if in_start_period {
+        return;
+    }
+
+    let failing_checks = runtime.failing_checks + 1;
+    if failing_checks < check.retries.max(1) {
+        update_container(context, project_id, slot, |c| {
+            c.runtime.failing_checks = failing_checks
+        })
+        .await;
+        return;
+    }
+
+    warn!("{slot} container of {project_id} unhealthy after {failing_checks} checks, restarting");
+    update_container(context, project_id, slot, |c| {
+        c.runtime.health = HealthStatus::Unhealthy;
+    })
+    .await;
+    // Failed before it is stopped, so the `die` event of the stop is not counted again
+    mark_failed(context, project_id, slot, "Healthcheck failed").await;
+    if let ContainerStatus::Running(docker_container) = &container.status {
+        if let Err(err) = docker_container
+            .stop(&ContainerStopOpts::builder().build())
+            .await
+        {
+            warn!("Cannot stop unhealthy container {err:?}");
+        }
+    }
+}
+
+async fn restart_support_container(
+    project_id: Uuid,
+    name: &str,
+    mut context: ProjectContext,
+) -> anyhow::Result<()> {
+    let project = context
+        .get_project(project_id)
+        .await
+        .ok_or(anyhow::anyhow!("Project {project_id} not found"))?;
+    let ProjectType::Container {
+        support_containers, ..
+    } = &project.project_type
+    else {
+        return Ok(());
+    };
+    let Some(support_container) = support_containers.get(name) else {
+        return Ok(());
+    };
+    wait_for_dependencies(&project, &support_container.container.depends_on).await?;
+    let docker = get_docker();
+    let network = get_network(&docker, project_id).await?;
+    let container =
+        run_support_container(&docker, project_id, name, support_container, &network).await?;
+    info!("Restarted support_container {name}");
+    mark_running(
+        &mut context,
+        project_id,
+        &ContainerSlot::Support(name.to_string()),
+        container,
+    )
+    .await
+}
+
 async fn run_and_set_container(
     project: Arc<Project>,
     mut project_context: ProjectContext,
@@ -97,16 +704,14 @@ async fn run_and_set_container(
         ..
     } = &project.project_type
     {
-        if let ContainerStatus::Running(container) = &container.status {
-            container
-                .stop(&ContainerStopOpts::builder().build())
-                .await?;
-        }
         let docker = get_docker();
         let network = get_network(&docker, project.id).await?;
 
         info!("Running support_containers");
-        for (name, support_container) in support_containers {
+        let order = start_order(support_containers).map_err(|err| anyhow::anyhow!(err))?;
+        for name in order.iter() {
+            let support_container = &support_containers[name];
+            wait_for_dependencies(&project, &support_container.container.depends_on).await?;
             info!("Running support selfcloud_container {name}");
             let container =
                 run_support_container(&docker, project.id, name, support_container, &network)
@@ -120,6 +725,7 @@ async fn run_and_set_container(
                 let mut new_support_container = support_container.clone();
                 new_support_container.container.status =
                     ContainerStatus::Running(Arc::new(container));
+                new_support_container.container.runtime.record_start();
                 support_containers.insert(name.clone(), new_support_container);
             }
             project_context
@@ -127,6 +733,24 @@ async fn run_and_set_container(
                 .await?;
         }
 
+        wait_for_dependencies(&project, &container.depends_on).await?;
+
+        // Runs before the old container goes away so a failure can leave it serving
+        if let Some(run) = run_hook(&mut project_context, &project, HookKind::PreDeploy).await {
+            if !run.succeeded() {
+                let reason = format!("Pre-deploy hook {}", run.outcome());
+                if let Some(running) = running_primary(&docker, project.id).await {
+                    return abort_deploy(&mut project_context, project.id, running, reason).await;
+                }
+                anyhow::bail!(reason);
+            }
+        }
+        if let ContainerStatus::Running(container) = &container.status {
+            container
+                .stop(&ContainerStopOpts::builder().build())
+                .await?;
+        }
+
         let image_id = format!("selfcloud_image_{}:latest", project.id.to_string());
         info!("Running Image id {image_id}");
         let image = docker.images().get(image_id);
@@ -140,7 +764,7 @@ async fn run_and_set_container(
             let mut running_container = if let Ok(inspect) = inspect {
                 info!("Container exists with id {:?}", inspect.image);
                 info!("Image id {:?}", image_inspect.id);
-                if image_inspect.id == inspect.image {
+                if image_inspect.id == inspect.image && limits_match(&inspect, &container.limits) {
                     if let Some(state) = &inspect.state {
                         if !state.running.unwrap_or(false) {
                             if let Err(err) = docker_container.start().await {
@@ -194,7 +818,12 @@ async fn run_and_set_container(
                             format!("selfcloud_{}_{}:{}", project_id, v.name, v.container_path)
                         }))
                         .network_mode(network)
+                        .restart_policy(
+                            container.restart_policy.kind.docker_name(),
+                            container.restart_policy.max_retries as u64,
+                        )
                         .publish_all_ports();
+                    builder = with_limits(builder, &container.limits);
 
                     for expose_port in exposed_ports.iter() {
                         if let Some(host_port) = expose_port.host_port {
@@ -240,46 +869,18 @@ async fn run_and_set_container(
                 running_container = Some(container)
             }
             if let Some(container) = running_container {
-                let inspect = container.inspect().await?;
-
+                mark_running(
+                    &mut project_context,
+                    project.id,
+                    &ContainerSlot::Primary,
+                    container,
+                )
+                .await?;
+                if let Some(run) =
+                    run_hook(&mut project_context, &project, HookKind::PostDeploy).await
                 {
-                    let mut project = project.as_ref().clone();
-                    if let ProjectType::Container {
-                        primary_container: cont,
-                        exposed_ports,
-                        ..
-                    } = &mut project.project_type
-                    {
-                        if let Some(network) = inspect.network_settings {
-                            if let Some(ports) = network.ports {
-                                tracing::info!("Container running with ports {ports:#?}");
-                                for port in exposed_ports.iter_mut() {
-                                    let port_q = format!("{}/tcp", port.port);
-                                    let exposed_port = ports.get(&port_q);
-                                    if let Some(host_port) = exposed_port
-                                        .and_then(|p| p.to_owned())
-                                        .and_then(|p| p.first().cloned())
-                                        .and_then(|p| p.host_port)
-                                        .and_then(|p| p.parse::<u16>().ok())
-                                    {
-                                        let mut peer = HttpPeer::new(
-                                            format!("127.0.0.1:{host_port}"),
-                                            false,
-                                            String::new(),
-                                        );
-                                        peer.options.alpn = ALPN::H2H1;
-                                        port.peer = Some(Arc::new(peer))
-                                    }
-                                }
-                            }
-                        }
-                        cont.status = ContainerStatus::Running(Arc::new(container));
-                    }
-                    if let Err(err) = project_context
-                        .update_project(project.id, Arc::new(project))
-                        .await
-                    {
-                        warn!("Failed to update project status {err:?}");
+                    if !run.succeeded() {
+                        warn!("Post-deploy hook of {} {}", project.name, run.outcome());
                     }
                 }
             } else {
@@ -294,7 +895,17 @@ async fn run_and_set_container(
     Ok(())
 }
 
-async fn get_network(docker: &Docker, project_id: Uuid) -> anyhow::Result<Id> {
+/// The primary container of the previous deployment if it is still up. Its status was
+/// reset when the new deployment was recorded, so it is looked up in docker by name
+async fn running_primary(docker: &Docker, project_id: Uuid) -> Option<Arc<Container>> {
+    let container = docker
+        .containers()
+        .get(ContainerSlot::Primary.docker_name(project_id));
+    let running = container.inspect().await.ok()?.state?.running?;
+    running.then(|| Arc::new(container))
+}
+
+pub(crate) async fn get_network(docker: &Docker, project_id: Uuid) -> anyhow::Result<Id> {
     let networks = docker.networks();
     let network_id = format!("selfcloud_network_{}", project_id);
     let network = networks.get(&network_id);
@@ -318,7 +929,12 @@ async fn run_support_container(
 ) -> anyhow::Result<Container> {
     let container_id = format!("selfcloud_supportcontainer_{}_{}", project_id, name);
     let docker_container = docker.containers().get(&container_id);
-    if let Ok(inspect) = docker_container.inspect().await {
+    let existing = docker_container
+        .inspect()
+        .await
+        .ok()
+        .filter(|inspect| limits_match(inspect, &support_container.container.limits));
+    if let Some(inspect) = existing {
         if inspect.state.and_then(|s| s.running).unwrap_or(false) {
             return Ok(docker.containers().get(&container_id));
         } else {
@@ -336,11 +952,19 @@ async fn run_support_container(
         .remove(&ContainerRemoveOpts::builder().force(true).build())
         .await;
 
-    let opts = ContainerCreateOpts::builder()
+    let mut builder = ContainerCreateOpts::builder()
         .image(&support_container.image)
         .name(container_id)
         .network_mode(network_id)
         .hostname(name)
+        .restart_policy(
+            support_container
+                .container
+                .restart_policy
+                .kind
+                .docker_name(),
+            support_container.container.restart_policy.max_retries as u64,
+        )
         .env(
             support_container
                 .container
@@ -348,9 +972,61 @@ async fn run_support_container(
                 .iter()
                 .map(|ev| format!("{}={}", ev.key, ev.val)),
         )
-        .build();
+        .volumes(
+            support_container
+                .container
+                .volumes
+                .iter()
+                .map(|v| format!("selfcloud_{}_{}:{}", project_id, v.name, v.container_path)),
+        );
+    if let Some(command) = &support_container.command {
+        builder = builder.command(split_command(command));
+    }
+    if let Some(entrypoint) = &support_container.entrypoint {
+        builder = builder.entrypoint(split_command(entrypoint));
+    }
+    for port in support_container.ports.iter() {
+        builder = builder.expose(PublishPort::tcp(port.port as u32), port.host_port as u32);
+    }
+    let opts = with_limits(builder, &support_container.container.limits).build();
     let new_container = docker.containers().create(&opts).await?;
     new_container.start().await?;
 
     Ok(new_container)
 }
+
+pub(crate) fn with_limits(
+    mut builder: ContainerCreateOptsBuilder,
+    limits: &ResourceLimits,
+) -> ContainerCreateOptsBuilder {
+    if let Some(memory) = limits.memory_bytes() {
+        builder = builder.memory(memory);
+    }
+    if let Some(cpu_shares) = limits.cpu_shares {
+        builder = builder.cpu_shares(cpu_shares);
+    }
+    if let Some(cpus) = limits.cpus {
+        builder = builder.cpus(cpus);
+    }
+    if let Some(pids_limit) = limits.pids_limit {
+        builder = builder.pids_limit(pids_limit);
+    }
+    builder
+}
+
+/// Whether an existing container was created with the currently configured limits,
+/// docker reports unset limits as `0`.
+fn limits_match(
+    inspect: &docker_api::models::ContainerInspect200Response,
+    limits: &ResourceLimits,
+) -> bool {
+    let Some(host_config) = &inspect.host_config else {
+        return true;
+    };
+    let set = |value: Option<i64>| value.filter(|v| *v > 0);
+    set(host_config.memory.map(|v| v as i64)) == limits.memory_bytes().map(|v| v as i64)
+        && set(host_config.cpu_shares.map(|v| v as i64)) == limits.cpu_shares.map(i64::from)
+        && set(host_config.nano_cpus.map(|v| v as i64))
+            == limits.cpus.map(|cpus| (cpus * 1_000_000_000.0) as i64)
+        && set(host_config.pids_limit.map(|v| v as i64)) == set(limits.pids_limit)
+}
@@ -0,0 +1,126 @@
+//! Runs a project's [`DeployHooks`] once per deployment, recording every run on the
+//! deployment so a failed hook is retried with the next attempt.
+
+use std::{sync::Arc, time::Duration};
+
+use app::{
+    common::{
+        get_docker, ContainerStatus, DeployHooks, DeployStatus, HookKind, HookRun, Project,
+        ProjectType,
+    },
+    context::ProjectContext,
+    deploy::image_tag,
+};
+use docker_api::{opts::TagOpts, Container};
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use crate::{container_manager::update_project_with, oneoff::run_one_off};
+
+/// Runs the hook when it is configured and hasn't succeeded yet for the active deployment
+pub(crate) async fn run_hook(
+    context: &mut ProjectContext,
+    project: &Project,
+    kind: HookKind,
+) -> Option<HookRun> {
+    let ProjectType::Container {
+        primary_container,
+        deploy_history,
+        hooks,
+        ..
+    } = &project.project_type
+    else {
+        return None;
+    };
+    let command = hooks.command(kind)?;
+    let deployment = deploy_history
+        .active_deployment()
+        .filter(|deployment| deployment.hook_pending(kind))?;
+
+    let started_at = chrono::Utc::now();
+    let (exit_code, logs) = match run_one_off(
+        project.id,
+        &kind.to_string(),
+        &format!("{}:{}", image_tag(project.id), deployment.tag()),
+        primary_container,
+        command,
+        hook_timeout(hooks),
+    )
+    .await
+    {
+        Ok(output) => (output.exit_code, output.logs),
+        Err(err) => (None, format!("Cannot run hook: {err:#}")),
+    };
+    let run = HookRun {
+        kind,
+        command: command.to_string(),
+        started_at,
+        finished_at: chrono::Utc::now(),
+        exit_code,
+        logs,
+    };
+    info!("{kind} hook of {} {}", project.name, run.outcome());
+
+    let deployment_id = deployment.id;
+    update_project_with(context, project.id, |project| {
+        if let ProjectType::Container { deploy_history, .. } = &mut project.project_type {
+            if let Some(deployment) = deploy_history
+                .deployments
+                .iter_mut()
+                .find(|deployment| deployment.id == deployment_id)
+            {
+                deployment.hook_runs.push(run.clone());
+            }
+        }
+    })
+    .await;
+    Some(run)
+}
+
+fn hook_timeout(hooks: &DeployHooks) -> Duration {
+    Duration::from_secs(hooks.timeout_secs.max(1))
+}
+
+/// Gives up on the active deployment after its pre-deploy hook failed, pointing `latest`
+/// back at the image the still running container was created from
+pub(crate) async fn abort_deploy(
+    context: &mut ProjectContext,
+    project_id: Uuid,
+    running: Arc<Container>,
+    reason: String,
+) -> anyhow::Result<()> {
+    warn!("Keeping the running container of {project_id}: {reason}");
+    let running_image = running.inspect().await?.image;
+    if let Some(image) = &running_image {
+        get_docker()
+            .images()
+            .get(image.as_str())
+            .tag(
+                &TagOpts::builder()
+                    .repo(image_tag(project_id))
+                    .tag("latest")
+                    .build(),
+            )
+            .await?;
+    }
+
+    update_project_with(context, project_id, |project| {
+        if let ProjectType::Container {
+            primary_container,
+            deploy_history,
+            ..
+        } = &mut project.project_type
+        {
+            deploy_history.advance(DeployStatus::Failed(reason));
+            deploy_history.active = deploy_history
+                .deployments
+                .iter()
+                .rev()
+                .find(|deployment| Some(&deployment.image_id) == running_image.as_ref())
+                .map(|deployment| deployment.id);
+            primary_container.status = ContainerStatus::Running(running);
+        }
+    })
+    .await;
+    Ok(())
+}
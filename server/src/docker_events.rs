@@ -0,0 +1,186 @@
+use app::{
+    common::{get_docker, ContainerSlot, ContainerStatus},
+    context::ProjectContext,
+};
+use docker_api::{
+    models::EventMessage,
+    opts::{EventFilter, EventFilterType, EventsOpts},
+};
+use futures::StreamExt;
+use pingora::{
+    server::ShutdownWatch,
+    services::background::{background_service, BackgroundService, GenBackgroundService},
+};
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use crate::container_manager::{
+    is_one_off, mark_completed, mark_failed, mark_oom_killed, mark_running, update_container,
+};
+
+/// Follows docker's `/events` stream so containers dying or being removed outside of
+/// self cloud are reflected right away instead of on the next container manager tick.
+pub struct DockerEventsService {
+    project_context: ProjectContext,
+}
+
+impl DockerEventsService {
+    pub fn to_service(project_context: ProjectContext) -> GenBackgroundService<Self> {
+        background_service("docker_events", Self { project_context })
+    }
+}
+
+#[async_trait::async_trait]
+impl BackgroundService for DockerEventsService {
+    async fn start(&self, mut shutdown: ShutdownWatch) {
+        let docker = get_docker();
+        let opts = EventsOpts::builder()
+            .filter([EventFilter::Type(EventFilterType::Container)])
+            .build();
+
+        loop {
+            let mut events = docker.events(&opts);
+            info!("Listening to docker events");
+            loop {
+                tokio::select! {
+                    _ = shutdown.changed() => {
+                        info!("Shutdown received");
+                        return;
+                    }
+                    event = events.next() => {
+                        match event {
+                            Some(Ok(event)) => {
+                                let mut context = self.project_context.clone();
+                                handle_event(&mut context, event).await;
+                            }
+                            Some(Err(err)) => {
+                                warn!("Docker event stream errored {err:?}");
+                                break;
+                            }
+                            None => {
+                                warn!("Docker event stream ended");
+                                break;
+                            }
+                        }
+                    }
+                }
+            }
+
+            // The daemon may be restarting, reconnect after a short delay
+            tokio::select! {
+                _ = shutdown.changed() => {
+                    info!("Shutdown received");
+                    return;
+                }
+                _ = tokio::time::sleep(std::time::Duration::from_secs(5)) => {}
+            }
+        }
+    }
+}
+
+async fn handle_event(context: &mut ProjectContext, event: EventMessage) {
+    let Some(action) = event.action else {
+        return;
+    };
+    let Some(attributes) = event.actor.and_then(|actor| actor.attributes) else {
+        return;
+    };
+    let Some((project_id, slot)) = attributes
+        .get("name")
+        .and_then(|name| ContainerSlot::from_docker_name(name))
+    else {
+        return;
+    };
+    let Some(project) = context.get_project(project_id).await else {
+        return;
+    };
+    let Some(container) = project.project_type.get_container(&slot) else {
+        return;
+    };
+
+    tracing::debug!(
+        "Docker event {action} for {slot} container of {}",
+        project.name
+    );
+    match action.as_str() {
+        "die" => {
+            // Containers stopped by the panel or by the manager after failing them
+            if container.runtime.stopped_by_user
+                || container.runtime.completed
+                || !container.status.is_running()
+            {
+                return;
+            }
+            if attributes.get("exitCode").map(String::as_str) == Some("0")
+                && is_one_off(&project, &slot)
+            {
+                info!("{slot} container of {} completed", project.name);
+                mark_completed(context, project_id, &slot).await;
+                return;
+            }
+            if is_oom_killed(&slot, project_id).await {
+                info!("{slot} container of {} ran out of memory", project.name);
+                mark_oom_killed(context, project_id, &slot).await;
+                return;
+            }
+            let reason = format!(
+                "Exited with code {}",
+                attributes
+                    .get("exitCode")
+                    .map(String::as_str)
+                    .unwrap_or("unknown")
+            );
+            info!("{slot} container of {} died: {reason}", project.name);
+            mark_failed(context, project_id, &slot, reason).await;
+        }
+        "destroy" => {
+            if !container.status.is_running() {
+                return;
+            }
+            info!("{slot} container of {} was removed", project.name);
+            if container.runtime.stopped_by_user {
+                update_container(context, project_id, &slot, |c| {
+                    c.status = ContainerStatus::Failed
+                })
+                .await;
+            } else {
+                mark_failed(context, project_id, &slot, "Container was removed").await;
+            }
+        }
+        "start" => {
+            if container.runtime.stopped_by_user && container.status.is_running() {
+                update_container(context, project_id, &slot, |c| {
+                    c.runtime.stopped_by_user = false;
+                    c.runtime.record_start();
+                })
+                .await;
+                return;
+            }
+            // Started by docker's restart policy or by hand, pick it back up
+            if !container.status.is_failed() {
+                return;
+            }
+            let docker_container = get_docker().containers().get(slot.docker_name(project_id));
+            info!(
+                "{slot} container of {} started outside self cloud",
+                project.name
+            );
+            if let Err(err) = mark_running(context, project_id, &slot, docker_container).await {
+                warn!("Cannot mark {slot} container running {err:?}");
+            }
+        }
+        _ => {}
+    }
+}
+
+async fn is_oom_killed(slot: &ContainerSlot, project_id: Uuid) -> bool {
+    get_docker()
+        .containers()
+        .get(slot.docker_name(project_id))
+        .inspect()
+        .await
+        .ok()
+        .and_then(|inspect| inspect.state)
+        .and_then(|state| state.oom_killed)
+        .unwrap_or(false)
+}
@@ -1,8 +1,9 @@
 use std::sync::Arc;
 
 use app::{
-    common::{DomainStatus, Project, SSLProvisioning},
+    common::{DomainStatus, LogStream, Project, ProjectType, SSLProvisioning},
     context::ProjectContext,
+    log_sinks::SinkRecord,
 };
 use axum::{body::Bytes, http::header};
 use openssl::ssl::NameType;
@@ -18,15 +19,19 @@ use pingora::{
 use tracing::{info, warn};
 use unicase::UniCase;
 
+use crate::{log_forwarder::LogShipper, status_page};
+
 pub struct Gateway {
     provisioning_gateway: Box<HttpPeer>,
     project_context: ProjectContext,
+    shipper: LogShipper,
 }
 
 impl Gateway {
     pub fn to_service(
         server: &Server,
         project_context: ProjectContext,
+        shipper: LogShipper,
     ) -> Service<HttpProxy<Self>> {
         let http_port = 8080;
         let https_port = 4433;
@@ -37,6 +42,7 @@ impl Gateway {
         let service = Self {
             provisioning_gateway,
             project_context: project_context.clone(),
+            shipper,
         };
         let mut service =
             http_proxy_service_with_name(&server.configuration, service, "gateway_proxy");
@@ -173,6 +179,24 @@ impl ProxyHttp for Gateway {
             // info!("No domain status");
         }
 
+        // Certificate orders still need their challenges answered by the panel
+        if let Some(domain) = _ctx
+            .domain
+            .as_ref()
+            .filter(|domain| !matches!(domain.ssl_provision, SSLProvisioning::Provisioning))
+        {
+            if let Some(project) = domain.project.upgrade() {
+                if let Some(page) = project
+                    .status_page
+                    .as_ref()
+                    .filter(|page| page.domain == _ctx.host)
+                {
+                    status_page::serve(_session, project.id, page).await;
+                    return Ok(true);
+                }
+            }
+        }
+
         let ip = _session.client_addr().and_then(|s| s.as_inet().cloned());
         let headers = _session.req_header_mut();
         // info!("Adding X-Forwarded headers");
@@ -207,10 +231,10 @@ impl ProxyHttp for Gateway {
                         host: &UniCase<String>,
                     ) -> anyhow::Result<Box<HttpPeer>> {
                         match &project.project_type {
-                            app::common::ProjectType::PortForward(port) => {
+                            ProjectType::PortForward(port) => {
                                 return Ok(Box::new(port.peer.as_ref().clone()));
                             }
-                            app::common::ProjectType::Container {
+                            ProjectType::Container {
                                 primary_container: container,
                                 exposed_ports,
                                 ..
@@ -244,6 +268,59 @@ impl ProxyHttp for Gateway {
             "no peer for given host",
         ));
     }
+
+    /// Forwards an access log line to the project's log sinks that want gateway logs
+    async fn logging(&self, session: &mut Session, e: Option<&pingora::Error>, ctx: &mut Self::CTX)
+    where
+        Self::CTX: Send + Sync,
+    {
+        let Some(project) = ctx
+            .domain
+            .as_ref()
+            .and_then(|domain| domain.project.upgrade())
+        else {
+            return;
+        };
+        let ProjectType::Container { log_sinks, .. } = &project.project_type else {
+            return;
+        };
+        if !log_sinks.iter().any(|sink| sink.enabled && sink.gateway) {
+            return;
+        }
+
+        let status = session
+            .response_written()
+            .map(|response| response.status.as_u16())
+            .unwrap_or_default();
+        let request = session.req_header();
+        let client = session
+            .client_addr()
+            .and_then(|addr| addr.as_inet().map(|inet| inet.ip().to_string()))
+            .unwrap_or("-".to_string());
+        let mut text = format!(
+            "{client} {} {} {} {status} {}",
+            ctx.host,
+            request.method,
+            request.uri,
+            session.body_bytes_sent()
+        );
+        if let Some(e) = e {
+            text.push_str(&format!(" {e}"));
+        }
+        self.shipper.ship(
+            project.id,
+            SinkRecord {
+                source: "gateway".to_string(),
+                stream: if e.is_some() || status >= 500 {
+                    LogStream::Stderr
+                } else {
+                    LogStream::Stdout
+                },
+                ts: chrono::Utc::now(),
+                text,
+            },
+        );
+    }
 }
 
 struct CertSolver {
@@ -0,0 +1,507 @@
+use std::{
+    collections::HashMap,
+    io::Read,
+    path::{Component, Path, PathBuf},
+    sync::{Arc, Mutex, PoisonError},
+};
+
+use app::{
+    audit,
+    common::{get_docker, AuditActor, BuildEvent},
+    context::ProjectContext,
+    deploy::image_tag,
+};
+use axum::{
+    extract::{
+        ws::{Message, WebSocket},
+        Multipart, Path as UrlPath, State, WebSocketUpgrade,
+    },
+    response::Response,
+};
+use axum_extra::extract::cookie::CookieJar;
+use docker_api::{models::ImageBuildChunk, opts::ImageBuildOpts};
+use futures::StreamExt;
+use http::StatusCode;
+use tokio::{io::AsyncWriteExt, sync::broadcast};
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use crate::{
+    auth::verify_project_token,
+    container_events::{ensure_authorized_user, get_auth},
+    image_uploader::{deploy_loaded_image, PushError},
+    leptos_service::AppState,
+};
+
+/// Build output of every project, kept until the next build so a page opened
+/// mid build still sees everything
+#[derive(Clone, Default)]
+pub struct Builds(Arc<Mutex<HashMap<Uuid, ProjectBuild>>>);
+
+struct ProjectBuild {
+    history: Vec<BuildEvent>,
+    sender: broadcast::Sender<BuildEvent>,
+}
+
+impl ProjectBuild {
+    fn new() -> Self {
+        Self {
+            history: vec![],
+            sender: broadcast::channel(512).0,
+        }
+    }
+
+    fn is_running(&self) -> bool {
+        self.history.last().is_some_and(|event| !event.is_done())
+    }
+}
+
+impl Builds {
+    /// Registers a new build, only one build per project runs at a time
+    fn start(&self, project_id: Uuid) -> anyhow::Result<RunningBuild> {
+        let mut builds = self.0.lock().expect("builds lock poisoned");
+        let build = builds.entry(project_id).or_insert_with(ProjectBuild::new);
+        if build.is_running() {
+            anyhow::bail!("A build is already running for this project");
+        }
+        let id = Uuid::new_v4();
+        build.history.clear();
+        build.history.push(BuildEvent::Started { id });
+        let _ = build.sender.send(BuildEvent::Started { id });
+        Ok(RunningBuild {
+            builds: self.clone(),
+            project_id,
+            id,
+            finished: false,
+        })
+    }
+
+    fn emit(&self, project_id: Uuid, event: BuildEvent) {
+        // Also called while a panicking build unwinds
+        let mut builds = self.0.lock().unwrap_or_else(PoisonError::into_inner);
+        let build = builds.entry(project_id).or_insert_with(ProjectBuild::new);
+        build.history.push(event.clone());
+        let _ = build.sender.send(event);
+    }
+
+    fn log(&self, project_id: Uuid, line: impl Into<String>) {
+        self.emit(project_id, BuildEvent::Log(line.into()));
+    }
+
+    /// Events of the latest build so far and a receiver for everything after them
+    fn subscribe(&self, project_id: Uuid) -> (Vec<BuildEvent>, broadcast::Receiver<BuildEvent>) {
+        let mut builds = self.0.lock().expect("builds lock poisoned");
+        let build = builds.entry(project_id).or_insert_with(ProjectBuild::new);
+        (build.history.clone(), build.sender.subscribe())
+    }
+}
+
+/// A started build, reported as failed if it is dropped before it finished, e.g. when
+/// the build task panics, so the project is not stuck with a running build
+struct RunningBuild {
+    builds: Builds,
+    project_id: Uuid,
+    id: Uuid,
+    finished: bool,
+}
+
+impl RunningBuild {
+    fn log(&self, line: impl Into<String>) {
+        self.builds.log(self.project_id, line);
+    }
+
+    fn finish(mut self, event: BuildEvent) {
+        self.finished = true;
+        self.builds.emit(self.project_id, event);
+    }
+}
+
+impl Drop for RunningBuild {
+    fn drop(&mut self) {
+        if !self.finished {
+            warn!("Build {} ended without an outcome", self.id);
+            self.builds.emit(
+                self.project_id,
+                BuildEvent::Failed("The build was interrupted".to_string()),
+            );
+        }
+    }
+}
+
+struct BuildRequest {
+    dockerfile: String,
+    build_args: HashMap<String, String>,
+    source: tempfile::NamedTempFile,
+    /// Who started the build, recorded with the deployment
+    started_by: String,
+    git_sha: Option<String>,
+}
+
+/// Accepts a source tarball (optionally gzipped) or a git bundle and builds it with
+/// docker, authenticated with a project token or a logged in session. Fields must come
+/// before the `source` file: `project_id`, `token`, `dockerfile`, `git_sha` and
+/// `build_args` with one `KEY=VALUE` per line.
+pub async fn build_image(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    mut multipart: Multipart,
+) -> Result<(StatusCode, String), PushError> {
+    let user = match get_auth(jar) {
+        app::auth::AuthType::Authorized(user) => Some(user),
+        app::auth::AuthType::UnAuthorized => None,
+    };
+    let mut project_id = None;
+    let mut token = None;
+    let mut dockerfile = "Dockerfile".to_string();
+    let mut build_args = HashMap::new();
+    let mut git_sha = None;
+
+    while let Some(mut field) = multipart.next_field().await? {
+        let name = field
+            .name()
+            .ok_or(anyhow::anyhow!("Unnamed field"))?
+            .to_string();
+        match name.as_str() {
+            "project_id" => project_id = Some(Uuid::parse_str(field.text().await?.trim())?),
+            "token" => token = Some(field.text().await?),
+            "git_sha" => git_sha = Some(field.text().await?.trim().to_string()),
+            "dockerfile" => {
+                let path = field.text().await?;
+                if !path.trim().is_empty() {
+                    dockerfile = path.trim().to_string();
+                }
+            }
+            "build_args" => {
+                for line in field.text().await?.lines() {
+                    if let Some((key, val)) = line.split_once('=') {
+                        build_args.insert(key.trim().to_string(), val.to_string());
+                    }
+                }
+            }
+            "source" => {
+                let Some(project_id) = project_id else {
+                    return Ok((StatusCode::BAD_REQUEST, format!("No Project Id")));
+                };
+                let project = state
+                    .project_context
+                    .get_project(project_id)
+                    .await
+                    .ok_or(anyhow::anyhow!("project with given id not present"))?;
+                let (started_by, actor) = match &user {
+                    Some(user) => (format!("Build by {}", user.name), AuditActor::from(user)),
+                    None => {
+                        let Some(token) = &token else {
+                            return Ok((StatusCode::UNAUTHORIZED, format!("No Upload Token")));
+                        };
+                        let description = verify_project_token(&project, token)?;
+                        (
+                            format!("Build with {description}"),
+                            AuditActor::Token {
+                                project_id,
+                                description,
+                            },
+                        )
+                    }
+                };
+                if !project.project_type.is_container() {
+                    return Ok((StatusCode::BAD_REQUEST, format!("Project not container")));
+                }
+
+                let source = tempfile::NamedTempFile::new()?;
+                let mut file = tokio::fs::File::from_std(source.reopen()?);
+                while let Some(chunk) = field.chunk().await? {
+                    file.write_all(&chunk).await?;
+                }
+                file.flush().await?;
+
+                let running = state.builds.start(project_id)?;
+                let build_id = running.id;
+                info!("Starting build {build_id} for {}", project.name);
+                audit::record(
+                    actor,
+                    "image.build",
+                    Some(project.as_ref()),
+                    Default::default(),
+                    Some(format!("Build {build_id}")),
+                )
+                .await;
+                let request = BuildRequest {
+                    dockerfile,
+                    build_args,
+                    source,
+                    started_by,
+                    git_sha,
+                };
+                tokio::spawn(run_build(state.project_context.clone(), running, request));
+                return Ok((StatusCode::ACCEPTED, build_id.to_string()));
+            }
+            name => return Ok((StatusCode::BAD_REQUEST, format!("Unknown field {name:?}"))),
+        }
+    }
+    Ok((StatusCode::BAD_REQUEST, format!("No source field")))
+}
+
+async fn run_build(mut context: ProjectContext, running: RunningBuild, request: BuildRequest) {
+    let project_id = running.project_id;
+    let event = match build(&running.builds, project_id, running.id, &request).await {
+        Ok(build_tag) => {
+            running.log(format!("Deploying {build_tag}\n"));
+            let deployed = deploy_loaded_image(
+                &mut context,
+                project_id,
+                build_tag.clone(),
+                request.started_by,
+                request.git_sha,
+            )
+            .await;
+            // The deployment tagged the image for the project, or it is not used at all
+            if let Err(err) = get_docker().images().get(build_tag.as_str()).delete().await {
+                warn!("Cannot remove build tag {build_tag} {err:?}");
+            }
+            match deployed {
+                Ok(deployment) => {
+                    running.log(format!("Deployment {}\n", deployment.id));
+                    BuildEvent::Finished {
+                        image: format!("{}:{}", image_tag(project_id), deployment.tag()),
+                    }
+                }
+                Err(err) => {
+                    warn!("Cannot deploy build {} {err:?}", running.id);
+                    BuildEvent::Failed(format!("{err:#}"))
+                }
+            }
+        }
+        Err(err) => {
+            warn!("Build {} failed {err:?}", running.id);
+            BuildEvent::Failed(format!("{err:#}"))
+        }
+    };
+    running.finish(event);
+}
+
+async fn build(
+    builds: &Builds,
+    project_id: Uuid,
+    build_id: Uuid,
+    request: &BuildRequest,
+) -> anyhow::Result<String> {
+    let context_dir = tempfile::tempdir()?;
+    unpack_source(
+        request.source.path().to_path_buf(),
+        context_dir.path().to_path_buf(),
+    )
+    .await?;
+
+    let dockerfile = Path::new(&request.dockerfile);
+    if !dockerfile
+        .components()
+        .all(|component| matches!(component, Component::Normal(_) | Component::CurDir))
+    {
+        anyhow::bail!("Dockerfile path must stay inside the source");
+    }
+    let dockerfile_metadata = std::fs::symlink_metadata(context_dir.path().join(dockerfile));
+    if !dockerfile_metadata.is_ok_and(|metadata| metadata.is_file()) {
+        anyhow::bail!("No {} in the source", request.dockerfile);
+    }
+    builds.log(
+        project_id,
+        format!("Building {} with docker\n", request.dockerfile),
+    );
+
+    let tag = format!("selfcloud_build_{project_id}:{}", build_id.simple());
+    let opts = ImageBuildOpts::builder(context_dir.path())
+        .dockerfile(&request.dockerfile)
+        .tag(&tag)
+        .rm(true)
+        .build_args(&request.build_args)
+        .build();
+    let docker = get_docker();
+    let images = docker.images();
+    let mut stream = images.build(&opts);
+    while let Some(chunk) = stream.next().await {
+        match chunk? {
+            ImageBuildChunk::Update { stream } => builds.log(project_id, stream),
+            ImageBuildChunk::Error { error, .. } => anyhow::bail!(error),
+            ImageBuildChunk::Digest { aux } => {
+                builds.log(project_id, format!("Built {}\n", aux.id))
+            }
+            ImageBuildChunk::PullStatus { status, id, .. } => builds.log(
+                project_id,
+                match id {
+                    Some(id) => format!("{id}: {status}\n"),
+                    None => format!("{status}\n"),
+                },
+            ),
+        }
+    }
+    Ok(tag)
+}
+
+/// Unpacks a tar, gzipped tar or git bundle into `dest`. Links are not allowed, docker
+/// follows them when it packs the build context and would copy files of the host.
+async fn unpack_source(archive: PathBuf, dest: PathBuf) -> anyhow::Result<()> {
+    let mut magic = [0u8; 16];
+    let read = std::fs::File::open(&archive)?.read(&mut magic)?;
+    let magic = &magic[..read];
+
+    if magic.starts_with(b"# v2 git bundle") || magic.starts_with(b"# v3 git bundle") {
+        let status = tokio::process::Command::new("git")
+            .arg("clone")
+            .arg("--quiet")
+            // Symlinks are checked out as plain files holding the link target
+            .args(["-c", "core.symlinks=false"])
+            .arg(&archive)
+            .arg(&dest)
+            .status()
+            .await?;
+        if !status.success() {
+            anyhow::bail!("Cannot clone the git bundle");
+        }
+        return Ok(());
+    }
+
+    let gzipped = magic.starts_with(&[0x1f, 0x8b]);
+    tokio::task::spawn_blocking(move || -> anyhow::Result<()> {
+        let file = std::fs::File::open(archive)?;
+        if gzipped {
+            unpack_tar(tar::Archive::new(flate2::read::GzDecoder::new(file)), &dest)
+        } else {
+            unpack_tar(tar::Archive::new(file), &dest)
+        }
+    })
+    .await?
+}
+
+fn unpack_tar(mut archive: tar::Archive<impl Read>, dest: &Path) -> anyhow::Result<()> {
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let kind = entry.header().entry_type();
+        if kind.is_symlink() || kind.is_hard_link() {
+            anyhow::bail!(
+                "Links are not supported in the source, {} is one",
+                entry.path()?.display()
+            );
+        }
+        entry.unpack_in(dest)?;
+    }
+    Ok(())
+}
+
+pub async fn build_logs_ws(
+    State(app_state): State<AppState>,
+    jar: CookieJar,
+    UrlPath(project_id): UrlPath<Uuid>,
+    ws: WebSocketUpgrade,
+) -> Result<Response, (axum::http::StatusCode, String)> {
+    ensure_authorized_user(jar)?;
+    let builds = app_state.builds.clone();
+    Ok(ws.on_upgrade(move |socket| handle_build_socket(socket, builds, project_id)))
+}
+
+async fn handle_build_socket(mut socket: WebSocket, builds: Builds, project_id: Uuid) {
+    let (history, mut receiver) = builds.subscribe(project_id);
+    for event in history {
+        if !send_event(&mut socket, &event).await {
+            return;
+        }
+    }
+    loop {
+        tokio::select! {
+            rec = socket.recv() => {
+                if rec.is_none() {
+                    tracing::debug!("Exiting build socket, ws closed");
+                    break;
+                }
+            }
+            event = receiver.recv() => {
+                match event {
+                    Ok(event) => {
+                        if !send_event(&mut socket, &event).await {
+                            break;
+                        }
+                    }
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        let event = BuildEvent::Log(format!("... {skipped} lines skipped\n"));
+                        if !send_event(&mut socket, &event).await {
+                            break;
+                        }
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                }
+            }
+        }
+    }
+}
+
+async fn send_event(socket: &mut WebSocket, event: &BuildEvent) -> bool {
+    let Ok(serialized) = bincode::serialize(event) else {
+        return true;
+    };
+    if let Err(err) = socket.send(Message::Binary(serialized.into())).await {
+        warn!("Failed to send msg {err:?}");
+        return false;
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn archive(
+        build: impl FnOnce(&mut tar::Builder<Vec<u8>>),
+    ) -> tar::Archive<std::io::Cursor<Vec<u8>>> {
+        let mut builder = tar::Builder::new(Vec::new());
+        build(&mut builder);
+        tar::Archive::new(std::io::Cursor::new(builder.into_inner().unwrap()))
+    }
+
+    fn append_file(builder: &mut tar::Builder<Vec<u8>>, path: &str, data: &[u8]) {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(data.len() as u64);
+        header.set_mode(0o644);
+        builder.append_data(&mut header, path, data).unwrap();
+    }
+
+    fn append_link(builder: &mut tar::Builder<Vec<u8>>, kind: tar::EntryType, path: &str) {
+        let mut header = tar::Header::new_gnu();
+        header.set_entry_type(kind);
+        header.set_size(0);
+        builder
+            .append_link(&mut header, path, "/etc/passwd")
+            .unwrap();
+    }
+
+    #[test]
+    fn unpacks_files_and_directories() {
+        let dest = tempfile::tempdir().unwrap();
+        let archive = archive(|builder| {
+            append_file(builder, "Dockerfile", b"FROM scratch\n");
+            append_file(builder, "src/main.rs", b"fn main() {}\n");
+        });
+
+        unpack_tar(archive, dest.path()).unwrap();
+        assert_eq!(
+            std::fs::read(dest.path().join("src/main.rs")).unwrap(),
+            b"fn main() {}\n"
+        );
+    }
+
+    #[test]
+    fn rejects_links() {
+        for kind in [tar::EntryType::Symlink, tar::EntryType::Link] {
+            let dest = tempfile::tempdir().unwrap();
+            let archive = archive(|builder| {
+                append_file(builder, "Dockerfile", b"FROM scratch\nCOPY passwd .\n");
+                append_link(builder, kind, "passwd");
+            });
+
+            let err = unpack_tar(archive, dest.path()).unwrap_err();
+            assert_eq!(
+                err.to_string(),
+                "Links are not supported in the source, passwd is one"
+            );
+            assert!(std::fs::symlink_metadata(dest.path().join("passwd")).is_err());
+        }
+    }
+}
@@ -0,0 +1,211 @@
+use std::{
+    collections::HashMap,
+    convert::Infallible,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use app::{
+    audit,
+    common::{AuditActor, DeployStatus, Project, ProjectType},
+    context::ProjectContext,
+    deploy::deploy_image,
+};
+use axum::{
+    extract::{Path, Query, State},
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        IntoResponse, Response,
+    },
+    Form,
+};
+use futures::Stream;
+use http::StatusCode;
+use pingora::{
+    server::ShutdownWatch,
+    services::background::{background_service, BackgroundService, GenBackgroundService},
+};
+use serde::Deserialize;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use crate::{auth::verify_project_token, image_uploader::PushError, leptos_service::AppState};
+
+#[derive(Deserialize)]
+pub struct DeployRequest {
+    project_id: Uuid,
+    token: String,
+}
+
+/// Pulls the project's registry image and redeploys it, authenticated with a project
+/// token like `push_image` so CI can call it after pushing a new tag
+pub async fn deploy_image_handler(
+    State(state): State<AppState>,
+    Form(request): Form<DeployRequest>,
+) -> Result<(StatusCode, String), PushError> {
+    let mut context = state.project_context;
+    let project = context
+        .get_project(request.project_id)
+        .await
+        .ok_or(anyhow::anyhow!("project with given id not present"))?;
+    let token_description = verify_project_token(&project, &request.token)?;
+
+    let digest = deploy_image(&mut context, request.project_id, true).await?;
+    audit::record_change(
+        AuditActor::Token {
+            project_id: request.project_id,
+            description: token_description,
+        },
+        "deploy.start",
+        &context,
+        request.project_id,
+        Some(project.as_ref()),
+        digest.clone(),
+    )
+    .await;
+    Ok((StatusCode::OK, digest.unwrap_or_default()))
+}
+
+/// Streams a deployment's status as server sent events until it turns healthy or fails,
+/// so CI can block on the deployment id `push_image` returned. Each event is named after
+/// the status, e.g. `event: healthy`, with a readable message as data.
+pub async fn deployment_events(
+    State(state): State<AppState>,
+    Path(deployment_id): Path<Uuid>,
+    Query(request): Query<DeployRequest>,
+) -> Result<Response, PushError> {
+    let context = state.project_context;
+    let project = context
+        .get_project(request.project_id)
+        .await
+        .ok_or(anyhow::anyhow!("project with given id not present"))?;
+    verify_project_token(&project, &request.token)?;
+    if deployment_status(&project, deployment_id).is_none() {
+        return Ok((StatusCode::NOT_FOUND, "Deployment not found").into_response());
+    }
+
+    let stream = status_stream(context, request.project_id, deployment_id);
+    Ok(Sse::new(stream)
+        .keep_alive(KeepAlive::default())
+        .into_response())
+}
+
+fn status_stream(
+    context: ProjectContext,
+    project_id: Uuid,
+    deployment_id: Uuid,
+) -> impl Stream<Item = Result<Event, Infallible>> {
+    async_stream::stream! {
+        let mut last = None;
+        loop {
+            let project = context.get_project(project_id).await;
+            let Some(status) = project
+                .as_deref()
+                .and_then(|project| deployment_status(project, deployment_id))
+            else {
+                let status = DeployStatus::Failed("Deployment was removed".to_string());
+                yield Ok(status_event(&status));
+                break;
+            };
+            if last.as_ref() != Some(&status) {
+                yield Ok(status_event(&status));
+                if status.is_done() {
+                    break;
+                }
+                last = Some(status);
+            }
+            tokio::time::sleep(Duration::from_secs(1)).await;
+        }
+    }
+}
+
+fn deployment_status(project: &Project, deployment_id: Uuid) -> Option<DeployStatus> {
+    let ProjectType::Container { deploy_history, .. } = &project.project_type else {
+        return None;
+    };
+    deploy_history
+        .deployments
+        .iter()
+        .find(|deployment| deployment.id == deployment_id)
+        .map(|deployment| deployment.status.clone())
+}
+
+fn status_event(status: &DeployStatus) -> Event {
+    let name = match status {
+        DeployStatus::ImageLoaded => "image_loaded",
+        DeployStatus::ContainerCreated => "container_created",
+        DeployStatus::Healthy => "healthy",
+        DeployStatus::Failed(_) => "failed",
+    };
+    Event::default().event(name).data(status.to_string())
+}
+
+/// Polls registries of projects with auto deploy enabled and redeploys when the
+/// image behind the reference changes
+pub struct ImagePoller {
+    project_context: ProjectContext,
+    last_polled: Arc<Mutex<HashMap<Uuid, Instant>>>,
+}
+
+impl ImagePoller {
+    pub fn to_service(project_context: ProjectContext) -> GenBackgroundService<Self> {
+        background_service(
+            "image_poller",
+            Self {
+                project_context,
+                last_polled: Arc::new(Mutex::new(HashMap::new())),
+            },
+        )
+    }
+
+    /// Whether the project's poll interval elapsed, marking it polled if so
+    fn is_due(&self, project_id: Uuid, interval: Duration) -> bool {
+        let mut last_polled = self.last_polled.lock().expect("poller lock poisoned");
+        let now = Instant::now();
+        match last_polled.get(&project_id) {
+            Some(last) if now.duration_since(*last) < interval => false,
+            _ => {
+                last_polled.insert(project_id, now);
+                true
+            }
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl BackgroundService for ImagePoller {
+    async fn start(&self, mut shutdown: ShutdownWatch) {
+        let mut period = tokio::time::interval(Duration::from_secs(30));
+
+        loop {
+            tokio::select! {
+                _ = shutdown.changed() => {
+                    info!("Shutdown received");
+                    break;
+                }
+                _ = period.tick() => {
+                    let projects = self.project_context.get_projects().await;
+                    for project in projects.iter() {
+                        let ProjectType::Container { image_source: Some(source), .. } = &project.project_type else {
+                            continue;
+                        };
+                        if !source.auto_deploy
+                            || !self.is_due(project.id, Duration::from_secs(source.poll_interval_secs))
+                        {
+                            continue;
+                        }
+                        let project = project.clone();
+                        let mut context = self.project_context.clone();
+                        tokio::spawn(async move {
+                            match deploy_image(&mut context, project.id, false).await {
+                                Ok(Some(digest)) => info!("Auto deployed {digest} for {}", project.name),
+                                Ok(None) => {}
+                                Err(err) => warn!("Cannot poll image for {} {err:?}", project.name),
+                            }
+                        });
+                    }
+                }
+            }
+        }
+    }
+}
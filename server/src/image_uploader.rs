@@ -1,18 +1,23 @@
-use std::sync::Arc;
+use std::path::Path;
 use tokio::io::AsyncWriteExt; // Import for write_all
 
-use app::common::{get_docker, ContainerStatus, ProjectType};
+use app::{
+    audit,
+    common::{get_docker, AuditActor, Deployment, WebhookEventKind},
+    context::ProjectContext,
+    deploy::record_deployment,
+    webhooks,
+};
 use axum::{
     extract::{Multipart, State},
     response::{IntoResponse, Response},
 };
-use docker_api::opts::TagOpts;
 use futures::stream::StreamExt;
 use http::StatusCode;
 use tracing::{info, warn};
 use uuid::Uuid;
 
-use crate::leptos_service::AppState;
+use crate::{auth::verify_project_token, leptos_service::AppState};
 
 use tokio::io::BufReader;
 use tokio_util::io::{ReaderStream, StreamReader};
@@ -25,6 +30,7 @@ pub async fn push_image(
     let mut context = state.project_context;
     let mut token = None;
     let mut project_id = None;
+    let mut git_sha = None;
     loop {
         let field = multipart.next_field().await?;
         if let Some(field) = field {
@@ -39,6 +45,8 @@ pub async fn push_image(
 
                 "project_id" => project_id = Some(Uuid::parse_str(field.text().await?.as_str())?),
 
+                "git_sha" => git_sha = Some(field.text().await?.trim().to_string()),
+
                 "image" => {
                     let Some(token) = token else {
                         return Ok((StatusCode::BAD_REQUEST, format!("No Upload Token")));
@@ -47,34 +55,12 @@ pub async fn push_image(
                         return Ok((StatusCode::BAD_REQUEST, format!("No Project Id")));
                     };
 
-                    {
-                        let project = context
-                            .get_project(project_id)
-                            .await
-                            .ok_or(anyhow::anyhow!("project with given id not present"))?;
-
-                        if let ProjectType::Container { tokens, .. } = &project.project_type {
-                            if let Some(token) = tokens.get(&token) {
-                                if let Some(expiry) = &token.expiry {
-                                    let current_date = chrono::Utc::now().naive_utc().date();
-                                    if &current_date > expiry {
-                                        return Err(
-                                            anyhow::anyhow!("Project token not valid").into()
-                                        );
-                                    }
-                                }
-                            } else {
-                                return Err(anyhow::anyhow!("Project token not valid").into());
-                            }
-                        } else {
-                            return Err(anyhow::anyhow!("Project not of type container").into());
-                        }
-                    };
-                    let docker = get_docker();
-                    let images = docker.images();
-
-                    let id = format!("selfcloud_image_{}", project_id.to_string());
-                    info!("Uploading image to {id}");
+                    let project = context
+                        .get_project(project_id)
+                        .await
+                        .ok_or(anyhow::anyhow!("project with given id not present"))?;
+                    let token_description = verify_project_token(&project, &token)?;
+                    info!("Uploading image for {project_id}");
 
                     // Create a named temporary file
                     let temp_file = tempfile::NamedTempFile::new()?;
@@ -97,49 +83,7 @@ pub async fn push_image(
                     info!("Image saved to temporary file: {:?}", temp_path);
 
                     // Phase 2: Import from file to Docker
-                    let file = tokio::fs::File::open(&temp_path).await?;
-                    let reader = BufReader::new(file);
-                    let stream = ReaderStream::new(reader);
-
-                    let image_result = {
-                        'ba: {
-                            let mut stream = images.import_from_stream(stream);
-                            while let Some(data) = stream.next().await {
-                                match data {
-                                    Ok(data) => match data {
-                                        docker_api::models::ImageBuildChunk::Update { stream } => {
-                                            let reg =
-                                                regex_macro::regex!(r"(?m)Loaded image: (.*)");
-                                            let capture =
-                                                reg.captures(&stream).and_then(|c| c.get(1));
-                                            if let Some(capture) = capture {
-                                                break 'ba Ok(capture.as_str().to_string());
-                                            }
-                                        }
-                                        docker_api::models::ImageBuildChunk::Error {
-                                            error: _,
-                                            error_detail: _,
-                                        } => {
-                                            break 'ba Err(docker_api::Error::Any("failed".into()));
-                                        }
-                                        docker_api::models::ImageBuildChunk::Digest { aux } => {
-                                            break 'ba Ok(aux.id.to_string());
-                                        }
-                                        docker_api::models::ImageBuildChunk::PullStatus {
-                                            status: _,
-                                            id: _,
-                                            progress: _,
-                                            progress_detail: _,
-                                        } => {}
-                                    },
-                                    Err(err) => {
-                                        break 'ba Err(err);
-                                    }
-                                }
-                            }
-                            Err(docker_api::Error::Any("failed".into()))
-                        }
-                    };
+                    let image_result = load_image_archive(&temp_path).await;
 
                     // Cleanup: Delete the temporary file manually since we used keep()
                     if let Err(e) = tokio::fs::remove_file(&temp_path).await {
@@ -154,36 +98,37 @@ pub async fn push_image(
                             return Err(err)?;
                         }
                     };
-                    let tag = image;
-                    let image = docker.images().get(tag);
-                    if let Err(err) = image
-                        .tag(&TagOpts::builder().repo(id).tag("latest").build())
-                        .await
-                    {
-                        warn!("Cannot tag image {err:?}")
-                    }
-
-                    info!("Loaded docker image {image:?}");
-                    {
-                        let project = context.get_project(project_id).await;
-                        if let Some(project) = project {
-                            let mut proj = project.as_ref().clone();
-                            if let ProjectType::Container {
-                                primary_container: container,
-                                ..
-                            } = &mut proj.project_type
-                            {
-                                container.status = ContainerStatus::None;
-                            }
-
-                            if let Err(err) =
-                                context.update_project(project_id, Arc::new(proj)).await
-                            {
-                                warn!("Failed to update project status {err:?}");
-                            }
-                        }
-                    }
-                    return Ok((StatusCode::OK, format!("Accepted")));
+                    webhooks::emit(
+                        WebhookEventKind::ImagePushed,
+                        Some(project_id),
+                        serde_json::json!({
+                            "image": image,
+                            "source": format!("Push with {token_description}"),
+                            "git_sha": git_sha,
+                        }),
+                    );
+                    let deployment = deploy_loaded_image(
+                        &mut context,
+                        project_id,
+                        image.clone(),
+                        format!("Push with {token_description}"),
+                        git_sha,
+                    )
+                    .await?;
+                    audit::record_change(
+                        AuditActor::Token {
+                            project_id,
+                            description: token_description,
+                        },
+                        "image.push",
+                        &context,
+                        project_id,
+                        Some(project.as_ref()),
+                        Some(image),
+                    )
+                    .await;
+                    // CI follows the rollout with /cloud/image/deployments/<id>/events
+                    return Ok((StatusCode::OK, deployment.id.to_string()));
                 }
                 name => return Ok((StatusCode::BAD_REQUEST, format!("Unknown field {name:?}"))),
             }
@@ -195,6 +140,58 @@ pub async fn push_image(
     Ok((StatusCode::BAD_REQUEST, format!("No image field")))
 }
 
+/// Loads a `docker save` style archive into docker, returning the loaded image
+pub async fn load_image_archive(path: &Path) -> anyhow::Result<String> {
+    let docker = get_docker();
+    let images = docker.images();
+
+    let file = tokio::fs::File::open(path).await?;
+    let reader = BufReader::new(file);
+    let stream = ReaderStream::new(reader);
+
+    let mut stream = images.import_from_stream(stream);
+    while let Some(data) = stream.next().await {
+        match data? {
+            docker_api::models::ImageBuildChunk::Update { stream } => {
+                let reg = regex_macro::regex!(r"(?m)Loaded image: (.*)");
+                let capture = reg.captures(&stream).and_then(|c| c.get(1));
+                if let Some(capture) = capture {
+                    return Ok(capture.as_str().to_string());
+                }
+            }
+            docker_api::models::ImageBuildChunk::Error {
+                error: _,
+                error_detail: _,
+            } => {
+                anyhow::bail!("failed");
+            }
+            docker_api::models::ImageBuildChunk::Digest { aux } => {
+                return Ok(aux.id.to_string());
+            }
+            docker_api::models::ImageBuildChunk::PullStatus {
+                status: _,
+                id: _,
+                progress: _,
+                progress_detail: _,
+            } => {}
+        }
+    }
+    anyhow::bail!("failed")
+}
+
+/// Records `image` as a new deployment of the project, the container manager then
+/// recreates the primary container with it
+pub async fn deploy_loaded_image(
+    context: &mut ProjectContext,
+    project_id: Uuid,
+    image: String,
+    source: String,
+    git_sha: Option<String>,
+) -> anyhow::Result<Deployment> {
+    info!("Loaded docker image {image:?}");
+    record_deployment(context, project_id, &image, source, git_sha).await
+}
+
 // Make our own error that wraps `anyhow::Error`.
 pub struct PushError(anyhow::Error);
 
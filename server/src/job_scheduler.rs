@@ -0,0 +1,168 @@
+//! Starts the scheduled jobs of container projects, ticked by the container manager.
+//! Runs are recorded with [`app::jobs`] so the panel can show their logs.
+
+use std::{collections::HashMap, sync::Mutex, time::Duration};
+
+use app::{
+    common::{ConcurrencyPolicy, Container as ContainerConfig, Project, ProjectType, ScheduledJob},
+    deploy::image_tag,
+    jobs,
+};
+use chrono::{DateTime, Utc};
+use tokio::task::JoinHandle;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use crate::oneoff::run_one_off;
+
+/// Jobs still running after this are killed
+const JOB_TIMEOUT: Duration = Duration::from_secs(24 * 60 * 60);
+
+type JobKey = (Uuid, String);
+
+#[derive(Default)]
+pub struct JobScheduler {
+    /// Next fire time of every enabled job, with the schedule it was computed from
+    next_runs: Mutex<HashMap<JobKey, (String, DateTime<Utc>)>>,
+    running: Mutex<HashMap<JobKey, RunningJob>>,
+}
+
+struct RunningJob {
+    run_id: Uuid,
+    task: JoinHandle<()>,
+}
+
+impl JobScheduler {
+    /// Starts the project's jobs that are due and the ones requested with "run now"
+    pub fn tick(&self, project: &Project, requested: &[JobKey]) {
+        let ProjectType::Container {
+            primary_container,
+            jobs: project_jobs,
+            ..
+        } = &project.project_type
+        else {
+            return;
+        };
+        let now = Utc::now();
+        let mut due_jobs = vec![];
+        {
+            let mut next_runs = self.next_runs.lock().expect("job schedule lock poisoned");
+            next_runs.retain(|(project_id, name), _| {
+                *project_id != project.id || project_jobs.get(name).is_some_and(|job| job.enabled)
+            });
+            for (name, job) in project_jobs.iter().filter(|(_, job)| job.enabled) {
+                let key = (project.id, name.clone());
+                let next = next_runs
+                    .get(&key)
+                    .filter(|(schedule, _)| *schedule == job.schedule)
+                    .map(|(_, next)| *next);
+                let due = next.is_some_and(|next| next <= now);
+                // New and rescheduled jobs wait for their next fire time
+                if next.is_none() || due {
+                    match jobs::next_run(&job.schedule, now) {
+                        Some(next) => {
+                            next_runs.insert(key, (job.schedule.clone(), next));
+                        }
+                        None => {
+                            next_runs.remove(&key);
+                        }
+                    }
+                }
+                if due {
+                    due_jobs.push(name.clone());
+                }
+            }
+        }
+
+        for (name, job) in project_jobs.iter() {
+            let manual = requested.contains(&(project.id, name.clone()));
+            if manual || due_jobs.contains(name) {
+                self.start(project.id, name, job, primary_container, manual);
+            }
+        }
+    }
+
+    fn start(
+        &self,
+        project_id: Uuid,
+        name: &str,
+        job: &ScheduledJob,
+        config: &ContainerConfig,
+        manual: bool,
+    ) {
+        let key = (project_id, name.to_string());
+        let mut running = self.running.lock().expect("running jobs lock poisoned");
+        if let Some(current) = running
+            .get(&key)
+            .filter(|current| !current.task.is_finished())
+        {
+            match job.concurrency {
+                ConcurrencyPolicy::Forbid => {
+                    info!("Skipping job {name} of {project_id}, the previous run is still going");
+                    return;
+                }
+                ConcurrencyPolicy::Replace => {
+                    info!("Replacing the running job {name} of {project_id}");
+                    current.task.abort();
+                    let (name, previous) = (name.to_string(), current.run_id);
+                    tokio::spawn(async move {
+                        let logs = "Replaced by a newer run".to_string();
+                        if let Err(err) =
+                            jobs::finish_run(project_id, &name, previous, None, logs).await
+                        {
+                            warn!("Cannot record replaced run of {name} {err:?}");
+                        }
+                    });
+                }
+            }
+        }
+
+        let run_id = Uuid::new_v4();
+        let image = job
+            .image
+            .clone()
+            .filter(|image| !image.trim().is_empty())
+            .unwrap_or_else(|| format!("{}:latest", image_tag(project_id)));
+        let task = tokio::spawn(run_job(
+            project_id,
+            name.to_string(),
+            run_id,
+            image,
+            job.command.clone(),
+            config.clone(),
+            manual,
+        ));
+        running.insert(key, RunningJob { run_id, task });
+    }
+}
+
+async fn run_job(
+    project_id: Uuid,
+    name: String,
+    run_id: Uuid,
+    image: String,
+    command: String,
+    config: ContainerConfig,
+    manual: bool,
+) {
+    if let Err(err) = jobs::start_run(project_id, &name, run_id, manual).await {
+        warn!("Cannot record run of job {name} {err:?}");
+    }
+    let (exit_code, logs) = match run_one_off(
+        project_id,
+        &format!("job-{name}"),
+        &image,
+        &config,
+        &command,
+        JOB_TIMEOUT,
+    )
+    .await
+    {
+        Ok(output) => (output.exit_code, output.logs),
+        Err(err) => (None, format!("Cannot run job: {err:#}")),
+    };
+    info!("Job {name} of {project_id} finished with {exit_code:?}");
+    if let Err(err) = jobs::finish_run(project_id, &name, run_id, exit_code, logs).await {
+        warn!("Cannot record run of job {name} {err:?}");
+    }
+}
@@ -1,5 +1,5 @@
 use app::{
-    auth::{server::get_user_from_cookie, AuthType, AuthorizedUsers},
+    auth::{server::get_user_from_cookie, AuthType},
     common::add_port_forward_project,
     context::ProjectContext,
     shell, App,
@@ -29,12 +29,18 @@ use pingora::{
 use tracing::info;
 
 use crate::{
-    auth::get_authorized_users,
+    audit_export::audit_export,
     container_events::{
-        attach::container_attach_ws, logs::container_logs_ws, stats::container_stats_ws,
+        attach::container_attach_ws,
+        exec::container_exec,
+        logs::{container_logs_download, container_logs_ws},
+        stats::container_stats_ws,
     },
     fileserv::file_and_error_handler,
-    image_uploader,
+    image_builder::{self, Builds},
+    image_deploy, image_uploader,
+    project_events::project_events_ws,
+    registry,
     system_monitor::{process_stats_ws, system_stats_ws},
     terminal::terminal_ws,
     tls_gen::{acme_handler, TLSState},
@@ -70,8 +76,8 @@ pub struct AppState {
     leptos_options: LeptosOptions,
     routes: Vec<AxumRouteListing>,
     pub tls_state: TLSState,
-    pub authorized_users: AuthorizedUsers,
     pub project_context: ProjectContext,
+    pub builds: Builds,
 }
 
 async fn run_main(tls_state: TLSState, mut context: ProjectContext) {
@@ -93,15 +99,12 @@ async fn run_main(tls_state: TLSState, mut context: ProjectContext) {
     tracing::info!("Functions: {functions:?}");
 
     tracing::info!("Getting authorized users");
-    let users = match get_authorized_users().await {
-        Ok(users) => users,
-        Err(err) => {
-            eprintln!("Users get failed {err:?}");
+    if let Err(err) = app::users::migrate_passwords().await {
+        eprintln!("Users get failed {err:?}");
 
-            tracing::error!("Cant get authorized users");
-            return;
-        }
-    };
+        tracing::error!("Cant get authorized users");
+        return;
+    }
 
     tracing::info!("Create app state");
 
@@ -109,10 +112,12 @@ async fn run_main(tls_state: TLSState, mut context: ProjectContext) {
         routes: routes.clone(),
         leptos_options,
         tls_state,
-        authorized_users: users,
         project_context: context.clone(),
+        builds: Builds::default(),
     };
 
+    tokio::spawn(registry::expire_uploads());
+
     let compression = tower_http::compression::CompressionLayer::new()
         .gzip(true)
         .br(true)
@@ -125,6 +130,20 @@ async fn run_main(tls_state: TLSState, mut context: ProjectContext) {
             "/cloud/image/push",
             post(image_uploader::push_image).layer(DefaultBodyLimit::max(1024 * 1024 * 400)),
         )
+        .route(
+            "/cloud/image/deploy",
+            post(image_deploy::deploy_image_handler),
+        )
+        .route(
+            "/cloud/image/deployments/{id}/events",
+            get(image_deploy::deployment_events),
+        )
+        .route("/cloud/exec", post(container_exec))
+        .route(
+            "/cloud/image/build",
+            post(image_builder::build_image).layer(DefaultBodyLimit::max(1024 * 1024 * 400)),
+        )
+        .merge(registry::router())
         .route(
             "/api/{*fn_name}",
             get(server_fn_handler).post(server_fn_handler),
@@ -133,7 +152,17 @@ async fn run_main(tls_state: TLSState, mut context: ProjectContext) {
         .route("/.well-known/acme-challenge/{token}", get(acme_handler))
         .route("/events/container/{id}/stats/ws", get(container_stats_ws))
         .route("/events/container/{id}/logs/ws", get(container_logs_ws))
+        .route(
+            "/events/container/{id}/logs/download",
+            get(container_logs_download),
+        )
         .route("/events/container/{id}/attach/ws", get(container_attach_ws))
+        .route(
+            "/events/project/{id}/build/ws",
+            get(image_builder::build_logs_ws),
+        )
+        .route("/events/audit/export", get(audit_export))
+        .route("/events/projects/ws", get(project_events_ws))
         .route("/events/terminal/ws", get(terminal_ws))
         .route("/events/system/stats/ws", get(system_stats_ws))
         .route("/events/system/processes/ws", get(process_stats_ws))
@@ -238,7 +267,6 @@ async fn server_fn_handler(
     handle_server_fns_with_context(
         move || {
             provide_context(auth.clone());
-            provide_context(app_state.authorized_users.clone());
             provide_context(jar.clone());
             provide_context(app_state.project_context.clone());
             // provide_context(app_state.otp_map.clone());
@@ -0,0 +1,205 @@
+//! Follows the output of every running project container into [`app::log_store`] and
+//! applies the projects' log retention.
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use app::{
+    common::{ContainerSlot, LogLine, LogStream, ProjectType, TtyChunk},
+    context::ProjectContext,
+    log_sinks::SinkRecord,
+    log_store::{self, LogWriter},
+};
+use chrono::{DateTime, Utc};
+use docker_api::{opts::LogsOpts, Container};
+use futures::StreamExt;
+use pingora::{
+    server::ShutdownWatch,
+    services::background::{background_service, BackgroundService, GenBackgroundService},
+};
+use tokio::task::JoinHandle;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use crate::log_forwarder::LogShipper;
+
+/// Retention runs every this many collector ticks
+const RETENTION_TICKS: u64 = 60;
+
+pub struct LogCollector {
+    project_context: ProjectContext,
+    shipper: LogShipper,
+    /// Follow tasks by docker container id
+    followers: Mutex<HashMap<String, JoinHandle<()>>>,
+}
+
+impl LogCollector {
+    pub fn to_service(
+        project_context: ProjectContext,
+        shipper: LogShipper,
+    ) -> GenBackgroundService<Self> {
+        background_service(
+            "log_collector",
+            Self {
+                project_context,
+                shipper,
+                followers: Mutex::new(HashMap::new()),
+            },
+        )
+    }
+
+    fn follow_running(&self, project_id: Uuid, containers: Vec<(ContainerSlot, Arc<Container>)>) {
+        let mut followers = self.followers.lock().expect("log followers lock poisoned");
+        for (slot, container) in containers {
+            let id = container.id().to_string();
+            if followers.contains_key(&id) {
+                continue;
+            }
+            let shipper = self.shipper.clone();
+            followers.insert(
+                id,
+                tokio::spawn(follow(project_id, slot, container, shipper)),
+            );
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl BackgroundService for LogCollector {
+    async fn start(&self, mut shutdown: ShutdownWatch) {
+        let mut period = tokio::time::interval(Duration::from_secs(10));
+        let mut ticks = 0u64;
+
+        loop {
+            tokio::select! {
+                _ = shutdown.changed() => {
+                    info!("Shutdown received");
+                    break;
+                }
+                _ = period.tick() => {
+                    self.followers
+                        .lock()
+                        .expect("log followers lock poisoned")
+                        .retain(|_, task| !task.is_finished());
+
+                    let projects = self.project_context.get_projects().await;
+                    for project in projects.iter() {
+                        let ProjectType::Container { primary_container, support_containers, log_retention, .. } = &project.project_type else {
+                            continue;
+                        };
+                        let running = std::iter::once((ContainerSlot::Primary, primary_container))
+                            .chain(support_containers.iter().map(|(name, support)| {
+                                (ContainerSlot::Support(name.clone()), &support.container)
+                            }))
+                            .filter_map(|(slot, container)| {
+                                Some((slot, container.status.as_running()?.clone()))
+                            })
+                            .collect();
+                        self.follow_running(project.id, running);
+
+                        if ticks % RETENTION_TICKS == 0 {
+                            if let Err(err) = log_store::enforce_retention(project.id, log_retention).await {
+                                warn!("Cannot apply log retention of {} {err:?}", project.name);
+                            }
+                        }
+                    }
+                    ticks += 1;
+                }
+            }
+        }
+    }
+}
+
+/// Persists the container's output until it stops, resuming after the last persisted line.
+/// Every new line is also handed to the log forwarder.
+async fn follow(
+    project_id: Uuid,
+    slot: ContainerSlot,
+    container: Arc<Container>,
+    shipper: LogShipper,
+) {
+    let source = match &slot {
+        ContainerSlot::Primary => "primary".to_string(),
+        ContainerSlot::Support(name) => name.clone(),
+    };
+    let version = container
+        .id()
+        .to_string()
+        .chars()
+        .take(12)
+        .collect::<String>();
+    let last = log_store::last_timestamp(project_id, &slot, &version).await;
+    let mut writer = match LogWriter::open(project_id, &slot, &version).await {
+        Ok(writer) => writer,
+        Err(err) => {
+            warn!("Cannot open log segment of {version} {err:?}");
+            return;
+        }
+    };
+
+    let mut opts = LogsOpts::builder()
+        .follow(true)
+        .stdout(true)
+        .stderr(true)
+        .timestamps(true);
+    if let Some(last) = &last {
+        opts = opts.since(last);
+    }
+    let mut stream = container.logs(&opts.build());
+    // Docker splits long lines into several chunks
+    let (mut stdout_partial, mut stderr_partial) = (Vec::new(), Vec::new());
+    while let Some(chunk) = stream.next().await {
+        let (stream_kind, bytes) = match chunk.map(TtyChunk::from) {
+            Ok(TtyChunk::StdOut(bytes)) => (LogStream::Stdout, bytes),
+            Ok(TtyChunk::StdErr(bytes)) => (LogStream::Stderr, bytes),
+            Ok(TtyChunk::StdIn(_)) => continue,
+            Err(err) => {
+                warn!("Log stream of {version} failed {err:?}");
+                break;
+            }
+        };
+        let buffer = match stream_kind {
+            LogStream::Stdout => &mut stdout_partial,
+            LogStream::Stderr => &mut stderr_partial,
+        };
+        buffer.extend_from_slice(&bytes);
+        while let Some(end) = buffer.iter().position(|b| *b == b'\n') {
+            let raw = buffer.drain(..=end).collect::<Vec<_>>();
+            let Some(line) = parse_line(&raw, stream_kind) else {
+                continue;
+            };
+            if last.is_some_and(|last| line.ts <= last) {
+                continue;
+            }
+            if let Err(err) = writer.write(&line).await {
+                warn!("Cannot persist logs of {version} {err:?}");
+                return;
+            }
+            shipper.ship(
+                project_id,
+                SinkRecord {
+                    source: source.clone(),
+                    stream: line.stream,
+                    ts: line.ts,
+                    text: line.text,
+                },
+            );
+        }
+    }
+    tracing::debug!("Stopped collecting logs of {version}");
+}
+
+/// Splits the timestamp docker prefixes every line with off the text
+fn parse_line(raw: &[u8], stream: LogStream) -> Option<LogLine> {
+    let raw = String::from_utf8_lossy(raw);
+    let raw = raw.trim_end_matches(['\n', '\r']);
+    let (ts, text) = raw.split_once(' ').unwrap_or((raw, ""));
+    Some(LogLine {
+        ts: DateTime::parse_from_rfc3339(ts).ok()?.with_timezone(&Utc),
+        stream,
+        text: text.to_string(),
+    })
+}
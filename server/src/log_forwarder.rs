@@ -0,0 +1,256 @@
+//! Ships container output and gateway access logs to the projects' log sinks.
+//!
+//! Producers hand records to a [`LogShipper`] without waiting. The forwarder batches them
+//! per sink and keeps a bounded retry buffer for sinks that are down, backing off between
+//! attempts.
+
+use std::{
+    collections::{HashMap, VecDeque},
+    time::{Duration, Instant},
+};
+
+use app::{
+    common::{LogSink, ProjectType},
+    context::ProjectContext,
+    log_sinks::{deliver, SinkRecord},
+};
+use pingora::{
+    server::ShutdownWatch,
+    services::background::{background_service, BackgroundService, GenBackgroundService},
+};
+use tokio::sync::{mpsc, Mutex};
+use tracing::{info, warn};
+use uuid::Uuid;
+
+/// Records waiting to be forwarded before new ones are dropped
+const CHANNEL_CAPACITY: usize = 10_000;
+/// Records kept per sink while it is failing, the oldest are dropped first
+const MAX_BUFFERED: usize = 20_000;
+const BATCH_SIZE: usize = 500;
+const FLUSH_INTERVAL: Duration = Duration::from_secs(2);
+const MAX_BACKOFF: Duration = Duration::from_secs(300);
+
+/// Hands records to the forwarder, dropping them when it falls behind so the gateway
+/// and the log collector never wait on a slow sink
+#[derive(Clone)]
+pub struct LogShipper {
+    sender: mpsc::Sender<(Uuid, SinkRecord)>,
+}
+
+impl LogShipper {
+    pub fn ship(&self, project_id: Uuid, record: SinkRecord) {
+        let _ = self.sender.try_send((project_id, record));
+    }
+}
+
+pub struct LogForwarder {
+    project_context: ProjectContext,
+    receiver: Mutex<mpsc::Receiver<(Uuid, SinkRecord)>>,
+}
+
+#[derive(Default)]
+struct SinkState {
+    buffer: VecDeque<SinkRecord>,
+    failures: u32,
+    retry_at: Option<Instant>,
+    dropped: usize,
+}
+
+impl LogForwarder {
+    pub fn to_service(project_context: ProjectContext) -> (GenBackgroundService<Self>, LogShipper) {
+        let (sender, receiver) = mpsc::channel(CHANNEL_CAPACITY);
+        (
+            background_service(
+                "log_forwarder",
+                Self {
+                    project_context,
+                    receiver: Mutex::new(receiver),
+                },
+            ),
+            LogShipper { sender },
+        )
+    }
+
+    async fn flush(
+        &self,
+        incoming: &mut HashMap<Uuid, Vec<SinkRecord>>,
+        sinks: &mut HashMap<(Uuid, Uuid), SinkState>,
+    ) {
+        let projects = self.project_context.get_projects().await;
+        let mut configured = vec![];
+        for project in projects.iter() {
+            let ProjectType::Container { log_sinks, .. } = &project.project_type else {
+                continue;
+            };
+            let records = incoming.remove(&project.id).unwrap_or_default();
+            for sink in log_sinks.iter().filter(|sink| sink.enabled) {
+                let key = (project.id, sink.id);
+                configured.push(key);
+                let state = sinks.entry(key).or_default();
+                state.buffer.extend(
+                    records
+                        .iter()
+                        .filter(|record| accepts(sink, record))
+                        .cloned(),
+                );
+                if state.buffer.len() > MAX_BUFFERED {
+                    let excess = state.buffer.len() - MAX_BUFFERED;
+                    state.buffer.drain(..excess);
+                    state.dropped += excess;
+                }
+                send_buffered(sink, &project.name, state).await;
+            }
+        }
+        incoming.clear();
+        sinks.retain(|key, _| configured.contains(key));
+    }
+}
+
+fn accepts(sink: &LogSink, record: &SinkRecord) -> bool {
+    if record.is_gateway() {
+        sink.gateway
+    } else {
+        sink.containers
+    }
+}
+
+/// Sends the buffer in batches until it is empty or the sink fails
+async fn send_buffered(sink: &LogSink, project_name: &str, state: &mut SinkState) {
+    if state
+        .retry_at
+        .is_some_and(|retry_at| Instant::now() < retry_at)
+    {
+        return;
+    }
+    while !state.buffer.is_empty() {
+        let batch = state.buffer.make_contiguous();
+        let batch = &batch[..batch.len().min(BATCH_SIZE)];
+        match deliver(&sink.kind, project_name, batch).await {
+            Ok(()) => {
+                let sent = batch.len();
+                state.buffer.drain(..sent);
+                if state.failures > 0 {
+                    info!(
+                        "Log sink {} of {project_name} recovered, {} records were dropped",
+                        sink.kind, state.dropped
+                    );
+                }
+                state.failures = 0;
+                state.retry_at = None;
+                state.dropped = 0;
+            }
+            Err(err) => {
+                state.failures += 1;
+                let backoff = FLUSH_INTERVAL
+                    .saturating_mul(2u32.saturating_pow(state.failures))
+                    .min(MAX_BACKOFF);
+                warn!(
+                    "Log sink {} of {project_name} failed, retrying in {}s {err:#}",
+                    sink.kind,
+                    backoff.as_secs()
+                );
+                state.retry_at = Some(Instant::now() + backoff);
+                return;
+            }
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl BackgroundService for LogForwarder {
+    async fn start(&self, mut shutdown: ShutdownWatch) {
+        let mut period = tokio::time::interval(FLUSH_INTERVAL);
+        let mut receiver = self.receiver.lock().await;
+        let mut incoming = HashMap::<Uuid, Vec<SinkRecord>>::new();
+        let mut sinks = HashMap::new();
+
+        loop {
+            tokio::select! {
+                _ = shutdown.changed() => {
+                    info!("Shutdown received");
+                    break;
+                }
+                Some((project_id, record)) = receiver.recv() => {
+                    incoming.entry(project_id).or_default().push(record);
+                }
+                _ = period.tick() => {
+                    self.flush(&mut incoming, &mut sinks).await;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use app::common::{LogSinkKind, LogStream, SyslogTransport};
+    use tokio::net::UdpSocket;
+
+    fn sink(kind: LogSinkKind) -> LogSink {
+        LogSink {
+            id: Uuid::new_v4(),
+            kind,
+            containers: true,
+            gateway: false,
+            enabled: true,
+        }
+    }
+
+    fn buffered(count: usize) -> SinkState {
+        SinkState {
+            buffer: (0..count)
+                .map(|n| SinkRecord {
+                    source: "primary".to_string(),
+                    stream: LogStream::Stdout,
+                    ts: chrono::Utc::now(),
+                    text: format!("line {n}"),
+                })
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn send_buffered_drains_the_buffer_in_batches() {
+        let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let sink = sink(LogSinkKind::Syslog {
+            address: socket.local_addr().unwrap().to_string(),
+            transport: SyslogTransport::Udp,
+        });
+        let mut state = buffered(BATCH_SIZE + 1);
+
+        send_buffered(&sink, "app", &mut state).await;
+        assert!(state.buffer.is_empty());
+        assert_eq!(state.failures, 0);
+        let mut buf = [0; 1024];
+        let len = socket.recv(&mut buf).await.unwrap();
+        let message = std::str::from_utf8(&buf[..len]).unwrap();
+        assert!(message.starts_with("<14>1 "), "{message}");
+        assert!(message.ends_with(" app primary - - line 0"), "{message}");
+    }
+
+    #[tokio::test]
+    async fn send_buffered_keeps_records_of_a_failing_sink() {
+        // Nothing listens on the port once the listener is gone
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        drop(listener);
+        let sink = sink(LogSinkKind::Syslog {
+            address,
+            transport: SyslogTransport::Tcp,
+        });
+        let mut state = buffered(3);
+
+        send_buffered(&sink, "app", &mut state).await;
+        assert_eq!(state.buffer.len(), 3);
+        assert_eq!(state.failures, 1);
+        let retry_at = state.retry_at.expect("no backoff after a failure");
+        assert!(retry_at > Instant::now() + FLUSH_INTERVAL);
+
+        // Not retried before the backoff elapsed
+        send_buffered(&sink, "app", &mut state).await;
+        assert_eq!(state.failures, 1);
+        assert_eq!(state.retry_at, Some(retry_at));
+    }
+}
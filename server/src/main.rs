@@ -1,31 +1,55 @@
 use std::{collections::HashMap, sync::RwLock};
 
+use alert_manager::AlertManager;
 use app::context::ProjectContext;
 use container_manager::ContainerManager;
+use docker_events::DockerEventsService;
 use gateway::Gateway;
+use image_deploy::ImagePoller;
 use leptos_service::LeptosService;
+use log_collector::LogCollector;
+use log_forwarder::LogForwarder;
+use metrics_collector::MetricsCollector;
 use pingora::server::{configuration::Opt, Server};
 // use proxy::Gateway;
 use clap::Parser;
 // mod proxy;
 
+mod alert_manager;
+mod audit_export;
 mod auth;
 mod container_events;
 mod container_manager;
+mod deploy_hooks;
+mod docker_events;
 mod fileserv;
 mod gateway;
+mod image_builder;
+mod image_deploy;
 mod image_uploader;
+mod job_scheduler;
 mod leptos_service;
+mod log_collector;
+mod log_forwarder;
+mod metrics_collector;
+mod oneoff;
+mod project_events;
+mod registry;
+mod status_page;
 mod system_monitor;
 mod terminal;
 mod tls_gen;
+mod uptime_monitor;
+mod webhook_dispatcher;
 
 // main.rs
 #[cfg(not(target_env = "msvc"))]
 use tikv_jemallocator::Jemalloc;
-use tls_gen::{TLSGenService, TLSState};
+use tls_gen::{CertFailures, TLSGenService, TLSState};
 use tracing::{level_filters::LevelFilter, warn};
 use tracing_subscriber::{fmt, layer::SubscriberExt, util::SubscriberInitExt, EnvFilter};
+use uptime_monitor::UptimeMonitor;
+use webhook_dispatcher::WebhookDispatcher;
 
 #[cfg(not(target_env = "msvc"))]
 #[global_allocator]
@@ -64,16 +88,34 @@ fn main() {
     let context = ProjectContext::new_empty();
 
     let tls_state = TLSState::new(RwLock::new(HashMap::new()));
+    let cert_failures = CertFailures::new(RwLock::new(HashMap::new()));
 
     let leptos_service = LeptosService::to_service(tls_state.clone(), context.clone());
-    let tls_gen_service = TLSGenService::to_service(tls_state, context.clone());
-    let proxy_service = Gateway::to_service(&my_server, context.clone());
+    let tls_gen_service =
+        TLSGenService::to_service(tls_state, context.clone(), cert_failures.clone());
+    let (log_forwarder_service, log_shipper) = LogForwarder::to_service(context.clone());
+    let proxy_service = Gateway::to_service(&my_server, context.clone(), log_shipper.clone());
+    let docker_events_service = DockerEventsService::to_service(context.clone());
+    let image_poller_service = ImagePoller::to_service(context.clone());
+    let log_collector_service = LogCollector::to_service(context.clone(), log_shipper);
+    let metrics_collector_service = MetricsCollector::to_service(context.clone());
+    let alert_manager_service = AlertManager::to_service(context.clone(), cert_failures);
+    let uptime_monitor_service = UptimeMonitor::to_service(context.clone());
+    let webhook_dispatcher_service = WebhookDispatcher::to_service(context.clone());
     let container_service = ContainerManager::to_service(context);
 
     my_server.add_service(leptos_service);
     my_server.add_service(proxy_service);
     my_server.add_service(tls_gen_service);
     my_server.add_service(container_service);
+    my_server.add_service(docker_events_service);
+    my_server.add_service(image_poller_service);
+    my_server.add_service(log_collector_service);
+    my_server.add_service(log_forwarder_service);
+    my_server.add_service(metrics_collector_service);
+    my_server.add_service(alert_manager_service);
+    my_server.add_service(uptime_monitor_service);
+    my_server.add_service(webhook_dispatcher_service);
 
     my_server.bootstrap();
     my_server.run_forever()
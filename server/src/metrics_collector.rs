@@ -0,0 +1,254 @@
+//! Samples the host and every running project container into [`app::metrics_store`], so
+//! the charts have history beyond the open stats sockets.
+
+use std::{
+    collections::HashMap,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use app::{
+    common::{ContainerSlot, MetricPoint, ProjectType},
+    context::ProjectContext,
+    metrics_store::{self, MetricsRecorder, MetricsSeries},
+};
+use chrono::Utc;
+use docker_api::Container;
+use futures::StreamExt;
+use pingora::{
+    server::ShutdownWatch,
+    services::background::{background_service, BackgroundService, GenBackgroundService},
+};
+use sysinfo::{Networks, System};
+use tracing::{info, warn};
+use uuid::Uuid;
+
+const SAMPLE_INTERVAL: Duration = Duration::from_secs(10);
+/// Old points are compacted away every this many samples
+const COMPACT_TICKS: u64 = 360;
+
+pub struct MetricsCollector {
+    project_context: ProjectContext,
+}
+
+impl MetricsCollector {
+    pub fn to_service(project_context: ProjectContext) -> GenBackgroundService<Self> {
+        background_service("metrics_collector", Self { project_context })
+    }
+
+    async fn running_containers(&self) -> Vec<(Uuid, ContainerSlot, Arc<Container>)> {
+        let projects = self.project_context.get_projects().await;
+        let mut running = vec![];
+        for project in projects.iter() {
+            let ProjectType::Container {
+                primary_container,
+                support_containers,
+                ..
+            } = &project.project_type
+            else {
+                continue;
+            };
+            let containers = std::iter::once((ContainerSlot::Primary, primary_container)).chain(
+                support_containers.iter().map(|(name, support)| {
+                    (ContainerSlot::Support(name.clone()), &support.container)
+                }),
+            );
+            for (slot, container) in containers {
+                if let Some(docker) = container.status.as_running() {
+                    running.push((project.id, slot, docker.clone()));
+                }
+            }
+        }
+        running
+    }
+}
+
+#[async_trait::async_trait]
+impl BackgroundService for MetricsCollector {
+    async fn start(&self, mut shutdown: ShutdownWatch) {
+        let mut period = tokio::time::interval(SAMPLE_INTERVAL);
+        let mut recorder = MetricsRecorder::default();
+        let mut host = HostSampler::new();
+        // Counters of the previous sample by docker container id
+        let mut previous = HashMap::<String, Counters>::new();
+        let mut ticks = 0u64;
+
+        loop {
+            tokio::select! {
+                _ = shutdown.changed() => {
+                    info!("Shutdown received");
+                    break;
+                }
+                _ = period.tick() => {
+                    if let Some(sample) = host.sample() {
+                        if let Err(err) = recorder.record(&MetricsSeries::Host, sample).await {
+                            warn!("Cannot store host metrics {err:?}");
+                        }
+                    }
+
+                    let running = self.running_containers().await;
+                    let counters = futures::future::join_all(
+                        running.iter().map(|(_, _, container)| read_counters(container)),
+                    )
+                    .await;
+                    let mut current = HashMap::new();
+                    for ((project_id, slot, container), counters) in running.into_iter().zip(counters) {
+                        let Some(counters) = counters else {
+                            continue;
+                        };
+                        let id = container.id().to_string();
+                        if let Some(sample) = previous.get(&id).and_then(|prev| counters.since(prev)) {
+                            let series = MetricsSeries::Container(project_id, slot);
+                            if let Err(err) = recorder.record(&series, sample).await {
+                                warn!("Cannot store container metrics {err:?}");
+                            }
+                        }
+                        current.insert(id, counters);
+                    }
+                    previous = current;
+
+                    if ticks % COMPACT_TICKS == 0 {
+                        if let Err(err) = metrics_store::compact().await {
+                            warn!("Cannot compact metrics {err:?}");
+                        }
+                    }
+                    ticks += 1;
+                }
+            }
+        }
+    }
+}
+
+/// Cumulative counters of a container, turned into rates against the previous sample
+struct Counters {
+    at: Instant,
+    cpu_total: u64,
+    system_total: u64,
+    online_cpus: u64,
+    memory_bytes: f64,
+    net_rx: u64,
+    net_tx: u64,
+    block_read: u64,
+    block_write: u64,
+}
+
+impl Counters {
+    fn since(&self, prev: &Counters) -> Option<MetricPoint> {
+        let elapsed = self.at.duration_since(prev.at).as_secs_f64();
+        if elapsed <= 0.0 {
+            return None;
+        }
+        let rate = |now: u64, before: u64| now.saturating_sub(before) as f64 / elapsed;
+        let system_delta = self.system_total.saturating_sub(prev.system_total);
+        let cpu_percent = if system_delta > 0 {
+            self.cpu_total.saturating_sub(prev.cpu_total) as f64 / system_delta as f64
+                * self.online_cpus as f64
+                * 100.0
+        } else {
+            0.0
+        };
+        Some(MetricPoint {
+            ts: Utc::now(),
+            cpu_percent,
+            memory_bytes: self.memory_bytes,
+            net_rx_bytes: rate(self.net_rx, prev.net_rx),
+            net_tx_bytes: rate(self.net_tx, prev.net_tx),
+            block_read_bytes: Some(rate(self.block_read, prev.block_read)),
+            block_write_bytes: Some(rate(self.block_write, prev.block_write)),
+        })
+    }
+}
+
+/// Takes one sample off the container's stats stream
+async fn read_counters(container: &Container) -> Option<Counters> {
+    let mut stream = container.stats();
+    let stats = tokio::time::timeout(Duration::from_secs(5), stream.next())
+        .await
+        .ok()??
+        .ok()?;
+
+    let cpu_stats = &stats["cpu_stats"];
+    let (net_rx, net_tx) = stats["networks"]
+        .as_object()
+        .map(|networks| {
+            networks.values().fold((0, 0), |(rx, tx), network| {
+                (
+                    rx + network["rx_bytes"].as_u64().unwrap_or_default(),
+                    tx + network["tx_bytes"].as_u64().unwrap_or_default(),
+                )
+            })
+        })
+        .unwrap_or_default();
+    let block_bytes = |op: &str| {
+        stats["blkio_stats"]["io_service_bytes_recursive"]
+            .as_array()
+            .map(|entries| {
+                entries
+                    .iter()
+                    .filter(|entry| {
+                        entry["op"]
+                            .as_str()
+                            .is_some_and(|entry_op| entry_op.eq_ignore_ascii_case(op))
+                    })
+                    .filter_map(|entry| entry["value"].as_u64())
+                    .sum()
+            })
+            .unwrap_or_default()
+    };
+
+    Some(Counters {
+        at: Instant::now(),
+        cpu_total: cpu_stats["cpu_usage"]["total_usage"].as_u64()?,
+        system_total: cpu_stats["system_cpu_usage"].as_u64().unwrap_or_default(),
+        online_cpus: cpu_stats["online_cpus"].as_u64().unwrap_or(1),
+        memory_bytes: stats["memory_stats"]["usage"].as_f64().unwrap_or_default(),
+        net_rx,
+        net_tx,
+        block_read: block_bytes("read"),
+        block_write: block_bytes("write"),
+    })
+}
+
+struct HostSampler {
+    sys: System,
+    networks: Networks,
+    previous: Option<(Instant, u64, u64)>,
+}
+
+impl HostSampler {
+    fn new() -> Self {
+        Self {
+            sys: System::new(),
+            networks: Networks::new_with_refreshed_list(),
+            previous: None,
+        }
+    }
+
+    /// CPU usage and network rates need a previous refresh, the first call returns nothing
+    fn sample(&mut self) -> Option<MetricPoint> {
+        self.sys.refresh_cpu();
+        self.sys.refresh_memory();
+        self.networks.refresh();
+
+        let (rx, tx) = self
+            .networks
+            .iter()
+            .filter(|(name, _)| name.as_str() != "lo")
+            .fold((0, 0), |(rx, tx), (_, data)| {
+                (rx + data.total_received(), tx + data.total_transmitted())
+            });
+        let now = Instant::now();
+        let (at, prev_rx, prev_tx) = self.previous.replace((now, rx, tx))?;
+        let elapsed = now.duration_since(at).as_secs_f64().max(f64::EPSILON);
+
+        Some(MetricPoint {
+            ts: Utc::now(),
+            cpu_percent: self.sys.global_cpu_info().cpu_usage() as f64,
+            memory_bytes: self.sys.used_memory() as f64,
+            net_rx_bytes: rx.saturating_sub(prev_rx) as f64 / elapsed,
+            net_tx_bytes: tx.saturating_sub(prev_tx) as f64 / elapsed,
+            block_read_bytes: None,
+            block_write_bytes: None,
+        })
+    }
+}
@@ -0,0 +1,138 @@
+//! Containers that run a single command from an image and exit, used for deploy hooks and
+//! scheduled jobs. They join the project network with the primary container's env and
+//! volumes so they see the same services the app does.
+
+use std::time::Duration;
+
+use app::common::{get_docker, split_image_reference, Container as ContainerConfig, TtyChunk};
+use docker_api::{
+    models::ImageBuildChunk,
+    opts::{ContainerCreateOpts, ContainerRemoveOpts, ContainerStopOpts, LogsOpts, PullOpts},
+};
+use futures::StreamExt;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use crate::container_manager::{get_network, with_limits};
+
+/// Only the end of the output is kept, that is where errors are
+const MAX_LOG_BYTES: usize = 64 * 1024;
+
+pub struct OneOffOutput {
+    /// `None` when the command was killed after the timeout
+    pub exit_code: Option<i64>,
+    pub logs: String,
+}
+
+/// Runs `command` with `sh -c` in a new container from `image` and waits for it to exit
+pub async fn run_one_off(
+    project_id: Uuid,
+    name: &str,
+    image: &str,
+    config: &ContainerConfig,
+    command: &str,
+    timeout: Duration,
+) -> anyhow::Result<OneOffOutput> {
+    let docker = get_docker();
+    let network = get_network(&docker, project_id).await?;
+    ensure_image(image).await?;
+    let container_name = format!("selfcloud_oneoff_{project_id}_{name}");
+    // Left over when the server stopped mid run
+    let _ = docker
+        .containers()
+        .get(&container_name)
+        .remove(&ContainerRemoveOpts::builder().force(true).build())
+        .await;
+
+    let builder = ContainerCreateOpts::builder()
+        .name(&container_name)
+        .image(image)
+        .entrypoint(vec!["sh", "-c", command])
+        .env(
+            config
+                .env_vars
+                .iter()
+                .map(|ev| format!("{}={}", ev.key, ev.val)),
+        )
+        .volumes(
+            config
+                .volumes
+                .iter()
+                .map(|v| format!("selfcloud_{}_{}:{}", project_id, v.name, v.container_path)),
+        )
+        .network_mode(network);
+    let container = docker
+        .containers()
+        .create(&with_limits(builder, &config.limits).build())
+        .await?;
+    info!("Running {name} for {project_id}: {command}");
+    container.start().await?;
+
+    let exit_code = match tokio::time::timeout(timeout, container.wait()).await {
+        Ok(Ok(exit)) => Some(exit.status_code),
+        Ok(Err(err)) => {
+            warn!("Cannot wait for {container_name} {err:?}");
+            None
+        }
+        Err(_) => {
+            warn!("{container_name} timed out after {}s", timeout.as_secs());
+            let _ = container.stop(&ContainerStopOpts::builder().build()).await;
+            None
+        }
+    };
+
+    let mut logs = Vec::new();
+    let mut stream = container.logs(&LogsOpts::builder().stdout(true).stderr(true).build());
+    while let Some(chunk) = stream.next().await {
+        match chunk.map(TtyChunk::from) {
+            Ok(TtyChunk::StdOut(bytes) | TtyChunk::StdErr(bytes)) => {
+                logs.extend_from_slice(&bytes);
+                if logs.len() > MAX_LOG_BYTES * 2 {
+                    logs.drain(..logs.len() - MAX_LOG_BYTES);
+                }
+            }
+            Ok(_) => {}
+            Err(err) => {
+                warn!("Cannot read logs of {container_name} {err:?}");
+                break;
+            }
+        }
+    }
+    if logs.len() > MAX_LOG_BYTES {
+        logs.drain(..logs.len() - MAX_LOG_BYTES);
+    }
+
+    if let Err(err) = container
+        .remove(&ContainerRemoveOpts::builder().force(true).build())
+        .await
+    {
+        warn!("Cannot remove {container_name} {err:?}");
+    }
+
+    Ok(OneOffOutput {
+        exit_code,
+        logs: String::from_utf8_lossy(&logs).into_owned(),
+    })
+}
+
+/// Pulls `image` unless docker already has it
+async fn ensure_image(image: &str) -> anyhow::Result<()> {
+    let docker = get_docker();
+    if docker.images().get(image).inspect().await.is_ok() {
+        return Ok(());
+    }
+    info!("Pulling {image}");
+    let (name, tag) = split_image_reference(image);
+    let mut opts = PullOpts::builder().image(name);
+    if let Some(tag) = tag {
+        opts = opts.tag(tag);
+    }
+    let images = docker.images();
+    let mut stream = images.pull(&opts.build());
+    while let Some(chunk) = stream.next().await {
+        if let ImageBuildChunk::Error { error, .. } = chunk? {
+            anyhow::bail!("Pulling {image} failed: {error}");
+        }
+    }
+    Ok(())
+}
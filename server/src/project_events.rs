@@ -0,0 +1,59 @@
+use app::{common::ProjectEvent, context::ProjectContext};
+use axum::{
+    extract::{
+        ws::{Message, WebSocket},
+        State, WebSocketUpgrade,
+    },
+    response::Response,
+};
+use axum_extra::extract::cookie::CookieJar;
+use tokio::sync::broadcast;
+use tracing::warn;
+
+use crate::{container_events::ensure_authorized_user, leptos_service::AppState};
+
+/// Streams every change of the project context, so the UI updates without polling
+pub async fn project_events_ws(
+    State(app_state): State<AppState>,
+    jar: CookieJar,
+    ws: WebSocketUpgrade,
+) -> Result<Response, (axum::http::StatusCode, String)> {
+    ensure_authorized_user(jar)?;
+    let context = app_state.project_context.clone();
+    Ok(ws.on_upgrade(move |socket| handle_project_events_socket(socket, context)))
+}
+
+async fn handle_project_events_socket(mut socket: WebSocket, context: ProjectContext) {
+    let mut receiver = context.subscribe();
+    loop {
+        tokio::select! {
+            rec = socket.recv() => {
+                if rec.is_none() {
+                    tracing::debug!("Exiting project events socket, ws closed");
+                    break;
+                }
+            }
+            event = receiver.recv() => {
+                let event = match event {
+                    Ok(event) => event,
+                    Err(broadcast::error::RecvError::Lagged(_)) => ProjectEvent::Lagged,
+                    Err(broadcast::error::RecvError::Closed) => break,
+                };
+                if !send_event(&mut socket, &event).await {
+                    break;
+                }
+            }
+        }
+    }
+}
+
+async fn send_event(socket: &mut WebSocket, event: &ProjectEvent) -> bool {
+    let Ok(serialized) = bincode::serialize(event) else {
+        return true;
+    };
+    if let Err(err) = socket.send(Message::Binary(serialized.into())).await {
+        warn!("Failed to send project event {err:?}");
+        return false;
+    }
+    true
+}
@@ -0,0 +1,939 @@
+//! OCI distribution (`/v2/`) endpoint so CI can `docker push` straight to self cloud.
+//!
+//! Repositories are project ids and clients authenticate with basic auth, the project id
+//! as username and one of the project's tokens as password:
+//!
+//! ```sh
+//! docker login cloud.example.com -u <project id> -p <token>
+//! docker push cloud.example.com/<project id>:latest
+//! ```
+//!
+//! Blobs are kept per project under `registry/<project id>` until no retained deployment
+//! uses them, so pushes only upload layers that changed, and chunked uploads can be resumed. Pushing a tag loads the image into
+//! docker and redeploys the primary container like `push_image` does.
+
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+};
+
+use app::{
+    audit,
+    common::{AuditActor, WebhookEventKind},
+    context::ProjectContext,
+    registry_store, webhooks,
+};
+use axum::{
+    body::Body,
+    extract::{DefaultBodyLimit, Path as UrlPath, Query, State},
+    handler::Handler,
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+    routing::{get, post},
+    Router,
+};
+use base64::{prelude::BASE64_STANDARD, Engine};
+use futures::StreamExt;
+use serde::Deserialize;
+use sha2::{Digest as _, Sha256};
+use tokio::io::AsyncWriteExt;
+use tokio_util::io::ReaderStream;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use crate::{
+    auth::verify_project_token,
+    image_uploader::{deploy_loaded_image, load_image_archive},
+    leptos_service::AppState,
+};
+
+/// Manifests are small json documents, unlike layers they are read into memory
+const MAX_MANIFEST_BYTES: usize = 4 * 1024 * 1024;
+
+/// How often uploads abandoned by interrupted pushes are looked for
+const UPLOAD_EXPIRY_INTERVAL: std::time::Duration = std::time::Duration::from_secs(60 * 60);
+
+const MANIFEST_TYPES: [&str; 4] = [
+    "application/vnd.oci.image.manifest.v1+json",
+    "application/vnd.docker.distribution.manifest.v2+json",
+    "application/vnd.oci.image.index.v1+json",
+    "application/vnd.docker.distribution.manifest.list.v2+json",
+];
+
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/v2/", get(base))
+        .route("/v2/{name}/blobs/{digest}", get(get_blob))
+        .route("/v2/{name}/blobs/uploads/", post(start_upload))
+        .route(
+            "/v2/{name}/blobs/uploads/{upload}",
+            get(upload_status)
+                .patch(patch_upload)
+                .put(finish_upload)
+                .delete(cancel_upload),
+        )
+        .route(
+            "/v2/{name}/manifests/{reference}",
+            get(get_manifest).put(put_manifest.layer(DefaultBodyLimit::max(MAX_MANIFEST_BYTES))),
+        )
+        // Layers can be far bigger than any sane request body, they are streamed to disk
+        .layer(DefaultBodyLimit::disable())
+}
+
+/// Removes stale uploads at startup and then periodically
+pub async fn expire_uploads() {
+    let mut period = tokio::time::interval(UPLOAD_EXPIRY_INTERVAL);
+    loop {
+        period.tick().await;
+        if let Err(err) = registry_store::expire_uploads().await {
+            warn!("Cannot expire registry uploads {err:?}");
+        }
+    }
+}
+
+pub struct RegistryError {
+    status: StatusCode,
+    code: &'static str,
+    message: String,
+}
+
+impl RegistryError {
+    fn new(status: StatusCode, code: &'static str, message: impl Into<String>) -> Self {
+        Self {
+            status,
+            code,
+            message: message.into(),
+        }
+    }
+
+    fn unauthorized() -> Self {
+        Self::new(
+            StatusCode::UNAUTHORIZED,
+            "UNAUTHORIZED",
+            "authentication required",
+        )
+    }
+}
+
+impl IntoResponse for RegistryError {
+    fn into_response(self) -> Response {
+        let body = serde_json::json!({
+            "errors": [{ "code": self.code, "message": self.message }]
+        });
+        let mut response = (self.status, axum::Json(body)).into_response();
+        if self.status == StatusCode::UNAUTHORIZED {
+            response.headers_mut().insert(
+                header::WWW_AUTHENTICATE,
+                HeaderValue::from_static("Basic realm=\"selfcloud\""),
+            );
+        }
+        response
+    }
+}
+
+impl<E> From<E> for RegistryError
+where
+    E: Into<anyhow::Error>,
+{
+    fn from(err: E) -> Self {
+        let err: anyhow::Error = err.into();
+        warn!("Registry request failed {err:?}");
+        Self::new(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "UNKNOWN",
+            err.to_string(),
+        )
+    }
+}
+
+/// `sha256:<hex>`, the only digest algorithm accepted
+#[derive(Clone, PartialEq, Eq, Hash)]
+struct Digest(String);
+
+impl Digest {
+    fn parse(digest: &str) -> Option<Self> {
+        let hex = digest.strip_prefix("sha256:")?;
+        let valid = hex.len() == 64
+            && hex
+                .bytes()
+                .all(|b| b.is_ascii_digit() || (b'a'..=b'f').contains(&b));
+        valid.then(|| Self(hex.to_string()))
+    }
+
+    fn of(data: &[u8]) -> Self {
+        Self(format!("{:x}", Sha256::digest(data)))
+    }
+
+    async fn of_file(path: PathBuf) -> anyhow::Result<Self> {
+        tokio::task::spawn_blocking(move || {
+            let mut file = std::fs::File::open(path)?;
+            let mut hasher = Sha256::new();
+            std::io::copy(&mut file, &mut hasher)?;
+            Ok(Self(format!("{:x}", hasher.finalize())))
+        })
+        .await?
+    }
+}
+
+impl std::fmt::Display for Digest {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "sha256:{}", self.0)
+    }
+}
+
+/// On disk storage of one project's repository
+struct Repository {
+    project_id: Uuid,
+    root: PathBuf,
+    /// Description of the token the request authenticated with
+    token_description: String,
+}
+
+impl Repository {
+    fn new(project_id: Uuid, token_description: String) -> Self {
+        Self {
+            project_id,
+            token_description,
+            root: registry_store::repository_dir(project_id),
+        }
+    }
+
+    fn blob_path(&self, digest: &Digest) -> PathBuf {
+        self.root.join("blobs").join(&digest.0)
+    }
+
+    fn upload_path(&self, upload: Uuid) -> PathBuf {
+        self.root.join("uploads").join(upload.to_string())
+    }
+
+    /// Media type of a stored manifest, the manifest itself is a blob
+    fn manifest_type_path(&self, digest: &Digest) -> PathBuf {
+        self.root.join("manifests").join(&digest.0)
+    }
+
+    fn tag_path(&self, tag: &str) -> PathBuf {
+        self.root.join("tags").join(tag)
+    }
+
+    async fn resolve(&self, reference: &str) -> Result<Digest, RegistryError> {
+        if reference.starts_with("sha256:") {
+            return parse_digest(reference);
+        }
+        let tag = parse_tag(reference)?;
+        match tokio::fs::read_to_string(self.tag_path(tag)).await {
+            Ok(digest) => parse_digest(digest.trim()),
+            Err(_) => Err(manifest_unknown(reference)),
+        }
+    }
+
+    async fn read_manifest(&self, digest: &Digest) -> Result<(Vec<u8>, String), RegistryError> {
+        let media_type = tokio::fs::read_to_string(self.manifest_type_path(digest))
+            .await
+            .map_err(|_| manifest_unknown(&digest.to_string()))?;
+        let manifest = tokio::fs::read(self.blob_path(digest)).await?;
+        Ok((manifest, media_type))
+    }
+
+    fn upload_location(&self, upload: Uuid) -> String {
+        format!("/v2/{}/blobs/uploads/{upload}", self.project_id)
+    }
+}
+
+fn parse_tag(tag: &str) -> Result<&str, RegistryError> {
+    let valid = !tag.is_empty()
+        && tag.len() <= 128
+        && !tag.starts_with(['.', '-'])
+        && tag
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || matches!(b, b'_' | b'.' | b'-'));
+    if valid {
+        Ok(tag)
+    } else {
+        Err(RegistryError::new(
+            StatusCode::BAD_REQUEST,
+            "TAG_INVALID",
+            format!("invalid tag {tag}"),
+        ))
+    }
+}
+
+fn parse_digest(digest: &str) -> Result<Digest, RegistryError> {
+    Digest::parse(digest).ok_or(RegistryError::new(
+        StatusCode::BAD_REQUEST,
+        "DIGEST_INVALID",
+        format!("unsupported digest {digest}"),
+    ))
+}
+
+fn manifest_unknown(reference: &str) -> RegistryError {
+    RegistryError::new(
+        StatusCode::NOT_FOUND,
+        "MANIFEST_UNKNOWN",
+        format!("manifest {reference} not found"),
+    )
+}
+
+fn blob_unknown(digest: &Digest) -> RegistryError {
+    RegistryError::new(
+        StatusCode::NOT_FOUND,
+        "BLOB_UNKNOWN",
+        format!("blob {digest} not found"),
+    )
+}
+
+fn upload_unknown() -> RegistryError {
+    RegistryError::new(
+        StatusCode::NOT_FOUND,
+        "BLOB_UPLOAD_UNKNOWN",
+        "upload not found",
+    )
+}
+
+fn basic_auth(headers: &HeaderMap) -> Option<(String, String)> {
+    let value = headers.get(header::AUTHORIZATION)?.to_str().ok()?;
+    let decoded = BASE64_STANDARD.decode(value.strip_prefix("Basic ")?).ok()?;
+    let decoded = String::from_utf8(decoded).ok()?;
+    let (user, password) = decoded.split_once(':')?;
+    Some((user.to_string(), password.to_string()))
+}
+
+/// Checks the basic auth credentials, returning the repository of the project they are for
+async fn authorize(
+    context: &ProjectContext,
+    headers: &HeaderMap,
+    name: Option<&str>,
+) -> Result<Repository, RegistryError> {
+    let (user, token) = basic_auth(headers).ok_or(RegistryError::unauthorized())?;
+    let project_id = Uuid::parse_str(&user).map_err(|_| RegistryError::unauthorized())?;
+    let project = context
+        .get_project(project_id)
+        .await
+        .ok_or(RegistryError::unauthorized())?;
+    let token_description =
+        verify_project_token(&project, &token).map_err(|_| RegistryError::unauthorized())?;
+    if name.is_some_and(|name| name != project_id.to_string()) {
+        return Err(RegistryError::new(
+            StatusCode::FORBIDDEN,
+            "DENIED",
+            "credentials are for another repository",
+        ));
+    }
+    Ok(Repository::new(project_id, token_description))
+}
+
+fn upload_range(len: u64) -> String {
+    format!("0-{}", len.saturating_sub(1))
+}
+
+async fn append_body(path: &Path, body: Body) -> Result<u64, RegistryError> {
+    let mut file = tokio::fs::OpenOptions::new()
+        .append(true)
+        .open(path)
+        .await
+        .map_err(|_| upload_unknown())?;
+    let mut stream = body.into_data_stream();
+    while let Some(chunk) = stream.next().await {
+        file.write_all(&chunk?).await?;
+    }
+    file.flush().await?;
+    Ok(file.metadata().await?.len())
+}
+
+/// Verifies a finished upload against `digest` and moves it into the blob store
+async fn commit_upload(
+    repository: &Repository,
+    upload: Uuid,
+    digest: &Digest,
+) -> Result<Response, RegistryError> {
+    let upload_path = repository.upload_path(upload);
+    let actual = Digest::of_file(upload_path.clone()).await?;
+    if &actual != digest {
+        let _ = tokio::fs::remove_file(&upload_path).await;
+        return Err(RegistryError::new(
+            StatusCode::BAD_REQUEST,
+            "DIGEST_INVALID",
+            format!("uploaded content has digest {actual}, not {digest}"),
+        ));
+    }
+    let blob_path = repository.blob_path(digest);
+    tokio::fs::create_dir_all(blob_path.parent().expect("blob has a parent")).await?;
+    tokio::fs::rename(&upload_path, &blob_path).await?;
+
+    Ok((
+        StatusCode::CREATED,
+        [
+            (
+                header::LOCATION,
+                format!("/v2/{}/blobs/{digest}", repository.project_id),
+            ),
+            (
+                header::HeaderName::from_static("docker-content-digest"),
+                digest.to_string(),
+            ),
+        ],
+    )
+        .into_response())
+}
+
+async fn base(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Response, RegistryError> {
+    authorize(&state.project_context, &headers, None).await?;
+    Ok((
+        [(
+            header::HeaderName::from_static("docker-distribution-api-version"),
+            "registry/2.0",
+        )],
+        axum::Json(serde_json::json!({})),
+    )
+        .into_response())
+}
+
+async fn get_blob(
+    State(state): State<AppState>,
+    UrlPath((name, digest)): UrlPath<(String, String)>,
+    headers: HeaderMap,
+) -> Result<Response, RegistryError> {
+    let repository = authorize(&state.project_context, &headers, Some(&name)).await?;
+    let digest = parse_digest(&digest)?;
+    let file = tokio::fs::File::open(repository.blob_path(&digest))
+        .await
+        .map_err(|_| blob_unknown(&digest))?;
+    let len = file.metadata().await?.len();
+
+    Ok((
+        [
+            (header::CONTENT_LENGTH, len.to_string()),
+            (header::CONTENT_TYPE, "application/octet-stream".to_string()),
+            (
+                header::HeaderName::from_static("docker-content-digest"),
+                digest.to_string(),
+            ),
+        ],
+        Body::from_stream(ReaderStream::new(file)),
+    )
+        .into_response())
+}
+
+#[derive(Deserialize)]
+struct UploadQuery {
+    digest: Option<String>,
+    mount: Option<String>,
+}
+
+async fn start_upload(
+    State(state): State<AppState>,
+    UrlPath(name): UrlPath<String>,
+    Query(query): Query<UploadQuery>,
+    headers: HeaderMap,
+    body: Body,
+) -> Result<Response, RegistryError> {
+    let repository = authorize(&state.project_context, &headers, Some(&name)).await?;
+
+    // Blobs are never shared between projects, a mount only succeeds when the layer
+    // was already pushed to this project, otherwise the client falls back to uploading
+    if let Some(mount) = query.mount {
+        let digest = parse_digest(&mount)?;
+        if tokio::fs::try_exists(repository.blob_path(&digest)).await? {
+            return Ok((
+                StatusCode::CREATED,
+                [
+                    (
+                        header::LOCATION,
+                        format!("/v2/{}/blobs/{digest}", repository.project_id),
+                    ),
+                    (
+                        header::HeaderName::from_static("docker-content-digest"),
+                        digest.to_string(),
+                    ),
+                ],
+            )
+                .into_response());
+        }
+    }
+
+    let upload = Uuid::new_v4();
+    let upload_path = repository.upload_path(upload);
+    tokio::fs::create_dir_all(upload_path.parent().expect("upload has a parent")).await?;
+    tokio::fs::File::create(&upload_path).await?;
+
+    // Monolithic upload in a single POST
+    if let Some(digest) = query.digest {
+        let digest = parse_digest(&digest)?;
+        append_body(&upload_path, body).await?;
+        return commit_upload(&repository, upload, &digest).await;
+    }
+
+    Ok((
+        StatusCode::ACCEPTED,
+        [
+            (header::LOCATION, repository.upload_location(upload)),
+            (header::RANGE, upload_range(0)),
+            (
+                header::HeaderName::from_static("docker-upload-uuid"),
+                upload.to_string(),
+            ),
+        ],
+    )
+        .into_response())
+}
+
+async fn upload_status(
+    State(state): State<AppState>,
+    UrlPath((name, upload)): UrlPath<(String, Uuid)>,
+    headers: HeaderMap,
+) -> Result<Response, RegistryError> {
+    let repository = authorize(&state.project_context, &headers, Some(&name)).await?;
+    let len = tokio::fs::metadata(repository.upload_path(upload))
+        .await
+        .map_err(|_| upload_unknown())?
+        .len();
+
+    Ok((
+        StatusCode::NO_CONTENT,
+        [
+            (header::LOCATION, repository.upload_location(upload)),
+            (header::RANGE, upload_range(len)),
+            (
+                header::HeaderName::from_static("docker-upload-uuid"),
+                upload.to_string(),
+            ),
+        ],
+    )
+        .into_response())
+}
+
+/// Appends a chunk, `Content-Range` must continue where the upload left off so an
+/// interrupted push can resume from the offset reported by [`upload_status`]
+async fn patch_upload(
+    State(state): State<AppState>,
+    UrlPath((name, upload)): UrlPath<(String, Uuid)>,
+    headers: HeaderMap,
+    body: Body,
+) -> Result<Response, RegistryError> {
+    let repository = authorize(&state.project_context, &headers, Some(&name)).await?;
+    let upload_path = repository.upload_path(upload);
+    let len = tokio::fs::metadata(&upload_path)
+        .await
+        .map_err(|_| upload_unknown())?
+        .len();
+
+    let start = headers
+        .get(header::CONTENT_RANGE)
+        .and_then(|range| range.to_str().ok())
+        .and_then(|range| range.split_once('-'))
+        .and_then(|(start, _)| start.trim_start_matches("bytes ").parse::<u64>().ok());
+    if start.is_some_and(|start| start != len) {
+        return Ok((
+            StatusCode::RANGE_NOT_SATISFIABLE,
+            [
+                (header::LOCATION, repository.upload_location(upload)),
+                (header::RANGE, upload_range(len)),
+            ],
+        )
+            .into_response());
+    }
+
+    let len = append_body(&upload_path, body).await?;
+    Ok((
+        StatusCode::ACCEPTED,
+        [
+            (header::LOCATION, repository.upload_location(upload)),
+            (header::RANGE, upload_range(len)),
+            (
+                header::HeaderName::from_static("docker-upload-uuid"),
+                upload.to_string(),
+            ),
+        ],
+    )
+        .into_response())
+}
+
+#[derive(Deserialize)]
+struct FinishQuery {
+    digest: String,
+}
+
+async fn finish_upload(
+    State(state): State<AppState>,
+    UrlPath((name, upload)): UrlPath<(String, Uuid)>,
+    Query(query): Query<FinishQuery>,
+    headers: HeaderMap,
+    body: Body,
+) -> Result<Response, RegistryError> {
+    let repository = authorize(&state.project_context, &headers, Some(&name)).await?;
+    let digest = parse_digest(&query.digest)?;
+    append_body(&repository.upload_path(upload), body).await?;
+    commit_upload(&repository, upload, &digest).await
+}
+
+async fn cancel_upload(
+    State(state): State<AppState>,
+    UrlPath((name, upload)): UrlPath<(String, Uuid)>,
+    headers: HeaderMap,
+) -> Result<Response, RegistryError> {
+    let repository = authorize(&state.project_context, &headers, Some(&name)).await?;
+    tokio::fs::remove_file(repository.upload_path(upload))
+        .await
+        .map_err(|_| upload_unknown())?;
+    Ok(StatusCode::NO_CONTENT.into_response())
+}
+
+async fn get_manifest(
+    State(state): State<AppState>,
+    UrlPath((name, reference)): UrlPath<(String, String)>,
+    headers: HeaderMap,
+) -> Result<Response, RegistryError> {
+    let repository = authorize(&state.project_context, &headers, Some(&name)).await?;
+    let digest = repository.resolve(&reference).await?;
+    let (manifest, media_type) = repository.read_manifest(&digest).await?;
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, media_type),
+            (header::CONTENT_LENGTH, manifest.len().to_string()),
+            (
+                header::HeaderName::from_static("docker-content-digest"),
+                digest.to_string(),
+            ),
+        ],
+        manifest,
+    )
+        .into_response())
+}
+
+async fn put_manifest(
+    State(state): State<AppState>,
+    UrlPath((name, reference)): UrlPath<(String, String)>,
+    headers: HeaderMap,
+    body: axum::body::Bytes,
+) -> Result<Response, RegistryError> {
+    let repository = authorize(&state.project_context, &headers, Some(&name)).await?;
+    let digest = Digest::of(&body);
+    let tag = if reference.starts_with("sha256:") {
+        if parse_digest(&reference)? != digest {
+            return Err(RegistryError::new(
+                StatusCode::BAD_REQUEST,
+                "DIGEST_INVALID",
+                "manifest does not match the digest",
+            ));
+        }
+        None
+    } else {
+        Some(parse_tag(&reference)?)
+    };
+
+    let manifest = serde_json::from_slice::<serde_json::Value>(&body).map_err(|err| {
+        RegistryError::new(StatusCode::BAD_REQUEST, "MANIFEST_INVALID", err.to_string())
+    })?;
+    let media_type = headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|t| t.to_str().ok())
+        .filter(|t| MANIFEST_TYPES.contains(t))
+        .or(manifest["mediaType"].as_str())
+        .unwrap_or(MANIFEST_TYPES[0])
+        .to_string();
+
+    // Everything a manifest points to has to be pushed before it
+    for reference in registry_store::referenced_digests(&manifest) {
+        let reference = parse_digest(&reference)?;
+        if !tokio::fs::try_exists(repository.blob_path(&reference)).await? {
+            return Err(RegistryError::new(
+                StatusCode::BAD_REQUEST,
+                "MANIFEST_BLOB_UNKNOWN",
+                format!("blob {reference} is not pushed"),
+            ));
+        }
+    }
+
+    let blob_path = repository.blob_path(&digest);
+    tokio::fs::create_dir_all(blob_path.parent().expect("blob has a parent")).await?;
+    tokio::fs::write(&blob_path, &body).await?;
+    let type_path = repository.manifest_type_path(&digest);
+    tokio::fs::create_dir_all(type_path.parent().expect("manifest has a parent")).await?;
+    tokio::fs::write(&type_path, &media_type).await?;
+
+    if let Some(tag) = tag {
+        let tag_path = repository.tag_path(tag);
+        tokio::fs::create_dir_all(tag_path.parent().expect("tag has a parent")).await?;
+        tokio::fs::write(&tag_path, digest.to_string()).await?;
+        info!("Pushed {}:{tag} as {digest}", repository.project_id);
+
+        let mut context = state.project_context.clone();
+        let digest = digest.clone();
+        tokio::spawn(async move {
+            if let Err(err) = deploy_manifest(&mut context, &repository, &digest).await {
+                warn!("Cannot deploy pushed image {digest} {err:?}");
+            }
+        });
+    }
+
+    Ok((
+        StatusCode::CREATED,
+        [
+            (header::LOCATION, format!("/v2/{name}/manifests/{digest}")),
+            (
+                header::HeaderName::from_static("docker-content-digest"),
+                digest.to_string(),
+            ),
+        ],
+    )
+        .into_response())
+}
+
+/// Architecture name docker uses for the machine self cloud runs on
+fn docker_architecture() -> &'static str {
+    match std::env::consts::ARCH {
+        "x86_64" => "amd64",
+        "aarch64" => "arm64",
+        "arm" => "arm",
+        other => other,
+    }
+}
+
+/// Loads the pushed image into docker and redeploys the primary container
+async fn deploy_manifest(
+    context: &mut ProjectContext,
+    repository: &Repository,
+    digest: &Digest,
+) -> anyhow::Result<()> {
+    let deployed_path = repository.root.join("deployed");
+    if tokio::fs::read_to_string(&deployed_path)
+        .await
+        .ok()
+        .as_deref()
+        == Some(digest.to_string().as_str())
+    {
+        info!("{digest} is already deployed");
+        return Ok(());
+    }
+
+    let invalid = |digest: &str| anyhow::anyhow!("Invalid digest {digest}");
+    let manifest = tokio::fs::read(repository.blob_path(digest)).await?;
+    let mut manifest = serde_json::from_slice::<serde_json::Value>(&manifest)?;
+
+    // Multi platform pushes, pick the image for this machine
+    if let Some(manifests) = manifest["manifests"].as_array() {
+        let platform = manifests
+            .iter()
+            .find(|entry| {
+                entry["platform"]["os"] == "linux"
+                    && entry["platform"]["architecture"] == docker_architecture()
+            })
+            .and_then(|entry| entry["digest"].as_str())
+            .ok_or(anyhow::anyhow!(
+                "No linux/{} image in the pushed index",
+                docker_architecture()
+            ))?;
+        let platform = Digest::parse(platform).ok_or(invalid(platform))?;
+        let platform_manifest = tokio::fs::read(repository.blob_path(&platform)).await?;
+        manifest = serde_json::from_slice(&platform_manifest)?;
+    }
+
+    let config = manifest["config"]["digest"]
+        .as_str()
+        .ok_or(anyhow::anyhow!("Manifest has no config"))?;
+    let config = Digest::parse(config).ok_or(invalid(config))?;
+    let layers = manifest["layers"]
+        .as_array()
+        .map(Vec::as_slice)
+        .unwrap_or_default()
+        .iter()
+        .filter_map(|layer| layer["digest"].as_str())
+        .map(|layer| Digest::parse(layer).ok_or(invalid(layer)))
+        .collect::<anyhow::Result<Vec<_>>>()?;
+
+    let archive = build_archive(repository, config, layers).await?;
+    let image = load_image_archive(archive.path()).await;
+    drop(archive);
+    let image = image?;
+    let before = context.get_project(repository.project_id).await;
+
+    webhooks::emit(
+        WebhookEventKind::ImagePushed,
+        Some(repository.project_id),
+        serde_json::json!({
+            "image": image,
+            "digest": digest.to_string(),
+            "source": format!("Registry push with {}", repository.token_description),
+        }),
+    );
+    deploy_loaded_image(
+        context,
+        repository.project_id,
+        image,
+        format!("Registry push with {}", repository.token_description),
+        None,
+    )
+    .await?;
+    audit::record_change(
+        AuditActor::Token {
+            project_id: repository.project_id,
+            description: repository.token_description.clone(),
+        },
+        "image.push",
+        context,
+        repository.project_id,
+        before.as_deref(),
+        Some(digest.to_string()),
+    )
+    .await;
+    tokio::fs::write(&deployed_path, digest.to_string()).await?;
+    Ok(())
+}
+
+/// Writes the blobs in the layout `docker load` understands
+async fn build_archive(
+    repository: &Repository,
+    config: Digest,
+    layers: Vec<Digest>,
+) -> anyhow::Result<tempfile::NamedTempFile> {
+    let archive = tempfile::NamedTempFile::new()?;
+    let file = archive.reopen()?;
+    let blob_name = |digest: &Digest| format!("blobs/sha256/{}", digest.0);
+    let index = serde_json::to_vec(&serde_json::json!([{
+        "Config": blob_name(&config),
+        "RepoTags": [format!("selfcloud_image_{}:latest", repository.project_id)],
+        "Layers": layers.iter().map(blob_name).collect::<Vec<_>>(),
+    }]))?;
+    let blobs = std::iter::once(config)
+        .chain(layers)
+        .collect::<HashSet<_>>()
+        .into_iter()
+        .map(|digest| (repository.blob_path(&digest), blob_name(&digest)))
+        .collect::<Vec<_>>();
+
+    tokio::task::spawn_blocking(move || -> anyhow::Result<()> {
+        let mut builder = tar::Builder::new(file);
+        for (path, name) in blobs {
+            builder.append_path_with_name(path, name)?;
+        }
+        let mut header = tar::Header::new_gnu();
+        header.set_size(index.len() as u64);
+        header.set_mode(0o644);
+        header.set_cksum();
+        builder.append_data(&mut header, "manifest.json", index.as_slice())?;
+        builder.finish()?;
+        Ok(())
+    })
+    .await??;
+
+    Ok(archive)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HELLO: &str = "sha256:b94d27b9934d3e08a52e52d7da7dabfac484efe37a5380ee9088f7ace2efcde9";
+
+    fn repository() -> Repository {
+        static HOME: std::sync::OnceLock<PathBuf> = std::sync::OnceLock::new();
+        HOME.get_or_init(|| {
+            let home = std::env::temp_dir().join(format!("selfcloud-test-{}", Uuid::new_v4()));
+            std::fs::create_dir_all(&home).unwrap();
+            std::env::set_var("SELF_CLOUD_HOME", &home);
+            home
+        });
+        Repository::new(Uuid::new_v4(), "test".to_string())
+    }
+
+    async fn start(repository: &Repository) -> Uuid {
+        let upload = Uuid::new_v4();
+        let path = repository.upload_path(upload);
+        tokio::fs::create_dir_all(path.parent().unwrap())
+            .await
+            .unwrap();
+        tokio::fs::File::create(path).await.unwrap();
+        upload
+    }
+
+    #[test]
+    fn digests_are_lowercase_sha256() {
+        assert!(Digest::parse(HELLO) == Some(Digest::of(b"hello world")));
+        assert_eq!(Digest::of(b"hello world").to_string(), HELLO);
+        for invalid in [
+            &HELLO[7..],
+            &HELLO[..70],
+            HELLO.to_uppercase().as_str(),
+            HELLO.replace("sha256", "sha512").as_str(),
+            "sha256:../../../../etc/passwd",
+        ] {
+            assert!(Digest::parse(invalid).is_none(), "{invalid} accepted");
+            assert_eq!(parse_digest(invalid).err().unwrap().code, "DIGEST_INVALID");
+        }
+    }
+
+    #[test]
+    fn tags_follow_the_distribution_grammar() {
+        for valid in [
+            "latest",
+            "v1.2.3",
+            "main_2024-01-01",
+            "a".repeat(128).as_str(),
+        ] {
+            assert_eq!(parse_tag(valid).ok(), Some(valid));
+        }
+        for invalid in [
+            "",
+            ".hidden",
+            "-flag",
+            "a/b",
+            "..",
+            "tag name",
+            "a".repeat(129).as_str(),
+        ] {
+            assert_eq!(parse_tag(invalid).err().unwrap().code, "TAG_INVALID");
+        }
+    }
+
+    #[tokio::test]
+    async fn chunks_are_assembled_into_the_blob() {
+        let repository = repository();
+        let upload = start(&repository).await;
+        let path = repository.upload_path(upload);
+        assert_eq!(append_body(&path, Body::from("hello ")).await.ok(), Some(6));
+        assert_eq!(append_body(&path, Body::from("world")).await.ok(), Some(11));
+
+        let digest = parse_digest(HELLO).ok().unwrap();
+        let response = commit_upload(&repository, upload, &digest)
+            .await
+            .ok()
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::CREATED);
+        assert_eq!(
+            response.headers()["docker-content-digest"]
+                .to_str()
+                .unwrap(),
+            HELLO
+        );
+        let blob = tokio::fs::read(repository.blob_path(&digest))
+            .await
+            .unwrap();
+        assert_eq!(blob, b"hello world");
+        assert!(!path.exists());
+        registry_store::remove_project_registry(repository.project_id).await;
+    }
+
+    #[tokio::test]
+    async fn uploads_not_matching_their_digest_are_dropped() {
+        let repository = repository();
+        let upload = start(&repository).await;
+        let path = repository.upload_path(upload);
+        append_body(&path, Body::from("hello")).await.ok().unwrap();
+
+        let digest = parse_digest(HELLO).ok().unwrap();
+        let err = commit_upload(&repository, upload, &digest)
+            .await
+            .err()
+            .unwrap();
+        assert_eq!(err.code, "DIGEST_INVALID");
+        assert!(!path.exists());
+        assert!(!repository.blob_path(&digest).exists());
+        // Chunks of an upload that does not exist are refused
+        let err = append_body(&path, Body::from("world")).await.err().unwrap();
+        assert_eq!(err.code, "BLOB_UPLOAD_UNKNOWN");
+        registry_store::remove_project_registry(repository.project_id).await;
+    }
+}
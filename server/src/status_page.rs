@@ -0,0 +1,160 @@
+//! Public status pages, rendered by the gateway from the summaries of [`app::uptime`].
+
+use app::{
+    common::{DomainUptime, StatusPage},
+    uptime,
+};
+use axum::body::Bytes;
+use pingora::{http::ResponseHeader, proxy::Session};
+use tracing::warn;
+use uuid::Uuid;
+
+/// Answers the request with the status page of the project
+pub async fn serve(session: &mut Session, project_id: Uuid, page: &StatusPage) {
+    let body = render(page, &uptime::project_uptime(project_id));
+    let mut response = match ResponseHeader::build(http::StatusCode::OK, Some(4)) {
+        Ok(response) => response,
+        Err(err) => {
+            warn!("Cant create response {err:?}");
+            return;
+        }
+    };
+    for (name, value) in [
+        ("Content-Type", "text/html; charset=utf-8".to_string()),
+        ("Content-Length", body.len().to_string()),
+        ("Cache-Control", "public, max-age=30".to_string()),
+    ] {
+        if let Err(err) = response.append_header(name, value) {
+            warn!("Cant append header {err:?}")
+        }
+    }
+    if let Err(err) = session
+        .write_response_header(Box::new(response), false)
+        .await
+    {
+        warn!("Cant write response header {err:?}")
+    }
+    if let Err(err) = session
+        .write_response_body(Some(Bytes::from(body)), true)
+        .await
+    {
+        warn!("Cant write response body {err:?}")
+    }
+    if let Err(err) = session.finish_body().await {
+        warn!("Cant finish body {err:?}")
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn percent(uptime: Option<f64>) -> String {
+    uptime
+        .map(|uptime| format!("{uptime:.2}%"))
+        .unwrap_or("-".to_string())
+}
+
+fn render(page: &StatusPage, domains: &[DomainUptime]) -> String {
+    let down = domains
+        .iter()
+        .filter(|domain| domain.last.as_ref().is_some_and(|last| !last.is_up()))
+        .count();
+    let overall = match (domains.is_empty(), down) {
+        (true, _) => ("unknown", "No checks yet".to_string()),
+        (false, 0) => ("up", "All systems operational".to_string()),
+        (false, down) => ("down", format!("{down} of {} down", domains.len())),
+    };
+
+    let mut rows = String::new();
+    for domain in domains {
+        let state = match &domain.last {
+            Some(last) if last.is_up() => "up",
+            Some(_) => "down",
+            None => "unknown",
+        };
+        rows.push_str(&format!(
+            "<tr><td><span class=\"dot {state}\"></span>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>",
+            escape(&domain.domain),
+            percent(domain.uptime_day),
+            percent(domain.uptime_week),
+            percent(domain.uptime_month),
+            domain
+                .latency_ms_day
+                .map(|latency| format!("{latency} ms"))
+                .unwrap_or("-".to_string()),
+        ));
+    }
+
+    let mut incidents = domains
+        .iter()
+        .flat_map(|domain| {
+            domain
+                .incidents
+                .iter()
+                .map(move |incident| (&domain.domain, incident))
+        })
+        .collect::<Vec<_>>();
+    incidents.sort_by(|a, b| b.1.started.cmp(&a.1.started));
+    let mut history = String::new();
+    for (domain, incident) in incidents.iter().take(50) {
+        let ended = match incident.ended {
+            Some(ended) => {
+                let minutes = (ended - incident.started).num_minutes().max(1);
+                format!("resolved after {minutes} min")
+            }
+            None => "ongoing".to_string(),
+        };
+        history.push_str(&format!(
+            "<li><b>{}</b> {} &middot; {} &middot; {}</li>",
+            escape(domain),
+            incident.started.format("%Y-%m-%d %H:%M UTC"),
+            escape(&incident.reason),
+            ended,
+        ));
+    }
+    if history.is_empty() {
+        history.push_str("<li>No incidents in the last 30 days</li>");
+    }
+
+    let title = escape(&page.title);
+    format!(
+        r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<meta name="viewport" content="width=device-width, initial-scale=1">
+<meta http-equiv="refresh" content="60">
+<title>{title} Status</title>
+<style>
+body {{ font-family: system-ui, sans-serif; max-width: 52rem; margin: 2rem auto; padding: 0 1rem; color: #18181b; }}
+.banner {{ padding: 1rem; border-radius: .5rem; color: #fff; font-weight: 600; }}
+.banner.up {{ background: #16a34a; }} .banner.down {{ background: #dc2626; }} .banner.unknown {{ background: #71717a; }}
+table {{ width: 100%; border-collapse: collapse; margin: 1.5rem 0; }}
+th, td {{ text-align: left; padding: .5rem; border-bottom: 1px solid #e4e4e7; }}
+.dot {{ display: inline-block; width: .6rem; height: .6rem; border-radius: 50%; margin-right: .5rem; }}
+.dot.up {{ background: #16a34a; }} .dot.down {{ background: #dc2626; }} .dot.unknown {{ background: #a1a1aa; }}
+li {{ margin: .4rem 0; }} footer {{ color: #71717a; font-size: .85rem; margin-top: 2rem; }}
+</style>
+</head>
+<body>
+<h1>{title}</h1>
+<div class="banner {}">{}</div>
+<table>
+<tr><th>Domain</th><th>24h</th><th>7d</th><th>30d</th><th>Latency</th></tr>
+{rows}
+</table>
+<h2>Incidents</h2>
+<ul>{history}</ul>
+<footer>Checked every minute, updated {}</footer>
+</body>
+</html>
+"#,
+        overall.0,
+        overall.1,
+        chrono::Utc::now().format("%Y-%m-%d %H:%M UTC"),
+    )
+}
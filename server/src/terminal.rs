@@ -1,4 +1,7 @@
-use app::common::{AttachParams, TtyChunk};
+use app::{
+    audit,
+    common::{AttachParams, AuditActor, TtyChunk},
+};
 use axum::{
     extract::{
         ws::{Message, WebSocket},
@@ -20,8 +23,16 @@ pub async fn terminal_ws(
     Query(params): Query<AttachParams>,
     ws: WebSocketUpgrade,
 ) -> Result<Response, (axum::http::StatusCode, String)> {
-    ensure_authorized_user(jar)?;
-    Ok(ws.on_upgrade(move |socket| handle_terminal_socket(socket, params)))
+    let user = ensure_authorized_user(jar)?;
+    Ok(ws.on_upgrade(move |socket| {
+        audit::record_session(
+            AuditActor::from(&user),
+            "terminal",
+            None,
+            None,
+            handle_terminal_socket(socket, params),
+        )
+    }))
 }
 
 async fn handle_terminal_socket(socket: WebSocket, params: AttachParams) {
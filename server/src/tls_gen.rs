@@ -4,8 +4,9 @@ use std::{
 };
 
 use app::{
-    common::{get_home_path, SSLProvisioning},
+    common::{get_home_path, SSLProvisioning, WebhookEventKind},
     context::ProjectContext,
+    webhooks,
 };
 use axum::{
     extract::{Path, State},
@@ -28,14 +29,49 @@ use crate::leptos_service::AppState;
 
 pub type TLSState = Arc<RwLock<HashMap<String, KeyAuthorization>>>;
 
+/// Domains whose last certificate order failed
+pub type CertFailures = Arc<RwLock<HashMap<UniCase<String>, CertFailure>>>;
+
+/// First retry of a failed order, doubled with every further failure
+const CERT_RETRY_BASE_SECS: i64 = 10 * 60;
+const CERT_RETRY_MAX_SECS: i64 = 24 * 60 * 60;
+
+#[derive(Clone, Debug)]
+pub struct CertFailure {
+    pub attempts: u32,
+    pub error: String,
+    pub failed_at: chrono::DateTime<chrono::Utc>,
+    pub retry_at: chrono::DateTime<chrono::Utc>,
+}
+
 pub struct TLSGenService {
     state: TLSState,
     context: ProjectContext,
+    failures: CertFailures,
 }
 
 impl TLSGenService {
-    pub fn to_service(state: TLSState, context: ProjectContext) -> GenBackgroundService<Self> {
-        background_service("tls generator", Self { state, context })
+    pub fn to_service(
+        state: TLSState,
+        context: ProjectContext,
+        failures: CertFailures,
+    ) -> GenBackgroundService<Self> {
+        background_service(
+            "tls generator",
+            Self {
+                state,
+                context,
+                failures,
+            },
+        )
+    }
+
+    fn backing_off(&self, domain: &UniCase<String>) -> bool {
+        self.failures
+            .read()
+            .expect("cert failures lock poisoned")
+            .get(domain)
+            .is_some_and(|failure| failure.retry_at > chrono::Utc::now())
     }
 }
 
@@ -118,10 +154,14 @@ impl BackgroundService for TLSGenService {
                     let domain = 'ba: {
                         let mut peers = self.context.get_all_domains().await;
                         for (domain, peer) in peers.iter_mut() {
-                             if peer.ssl_provision.is_not_provisioned() {
+                            if self.backing_off(domain) {
+                                continue;
+                            }
+                            let previous = peer.ssl_provision.clone();
+                            if peer.ssl_provision.is_not_provisioned() {
                                 peer.ssl_provision = SSLProvisioning::Provisioning;
                                 project_context.update_domain(domain.clone(), peer.clone()).await;
-                                break 'ba Some(domain.clone());
+                                break 'ba Some((domain.clone(), previous));
                             } else if let SSLProvisioning::Provisioned(data) = &peer.ssl_provision {
                                 // Check expiry
                                 if let Some(first_cert) = data.cert.first() {
@@ -133,7 +173,7 @@ impl BackgroundService for TLSGenService {
                                        if days_left < 30 {                    tracing::info!("Certificate for {} expires in {} days. Renewing...", domain, days_left);
                                             peer.ssl_provision = SSLProvisioning::Provisioning;
                                             project_context.update_domain(domain.clone(), peer.clone()).await;
-                                            break 'ba Some(domain.clone());
+                                            break 'ba Some((domain.clone(), previous));
                                        }
 
                                 }
@@ -144,9 +184,11 @@ impl BackgroundService for TLSGenService {
                     let account = account.clone();
                     let acme = self.state.clone();
 
-                    if let Some(domain) = domain {
+                    if let Some((domain, previous)) = domain {
+                        let failures = self.failures.clone();
                         tokio::spawn(async move {
-                            generate_certificate(domain, account, acme, project_context).await;
+                            let result = generate_certificate(domain.clone(), account, acme, project_context.clone()).await;
+                            record_outcome(&failures, &mut project_context, domain, previous, result).await;
                         });
                     }
                 }
@@ -167,19 +209,87 @@ pub async fn acme_handler(
     }
 }
 
+/// Remembers failed orders so the domain is retried with backoff instead of on every tick,
+/// and puts the domain back into its state from before the order
+async fn record_outcome(
+    failures: &CertFailures,
+    project_context: &mut ProjectContext,
+    domain: UniCase<String>,
+    previous: SSLProvisioning,
+    result: anyhow::Result<()>,
+) {
+    let project_id = project_context
+        .get_domain(&domain)
+        .await
+        .and_then(|status| status.project.upgrade())
+        .map(|project| project.id);
+    let err = match result {
+        Ok(()) => {
+            failures
+                .write()
+                .expect("cert failures lock poisoned")
+                .remove(&domain);
+            let event = if previous.is_provisioned() {
+                WebhookEventKind::CertificateRenewed
+            } else {
+                WebhookEventKind::CertificateIssued
+            };
+            webhooks::emit(
+                event,
+                project_id,
+                serde_json::json!({ "domain": domain.to_lowercase() }),
+            );
+            return;
+        }
+        Err(err) => err,
+    };
+    let (attempts, retry_at) = {
+        let mut failures = failures.write().expect("cert failures lock poisoned");
+        let failure = failures.entry(domain.clone()).or_insert(CertFailure {
+            attempts: 0,
+            error: String::new(),
+            failed_at: chrono::Utc::now(),
+            retry_at: chrono::Utc::now(),
+        });
+        failure.attempts += 1;
+        failure.error = format!("{err:#}");
+        failure.failed_at = chrono::Utc::now();
+        let backoff = CERT_RETRY_BASE_SECS
+            .saturating_mul(1 << (failure.attempts - 1).min(16))
+            .min(CERT_RETRY_MAX_SECS);
+        failure.retry_at = failure.failed_at + chrono::Duration::seconds(backoff);
+        (failure.attempts, failure.retry_at)
+    };
+    tracing::error!("Certificate order for {domain} failed, retrying at {retry_at} {err:#}");
+    webhooks::emit(
+        WebhookEventKind::CertificateFailed,
+        project_id,
+        serde_json::json!({
+            "domain": domain.to_lowercase(),
+            "error": format!("{err:#}"),
+            "attempts": attempts,
+            "retry_at": retry_at,
+        }),
+    );
+
+    if let Some(mut peer) = project_context.get_domain(&domain).await {
+        peer.ssl_provision = previous;
+        project_context.update_domain(domain, peer).await;
+    }
+}
+
 async fn generate_certificate(
     domain: UniCase<String>,
     account: Account,
     acme: TLSState,
     mut project_context: ProjectContext,
-) {
+) -> anyhow::Result<()> {
     let identifier = Identifier::Dns(domain.to_lowercase());
     let mut order = account
         .new_order(&NewOrder {
             identifiers: &[identifier],
         })
-        .await
-        .unwrap();
+        .await?;
 
     let state = order.state();
     info!("order state: {:#?}", state);
@@ -187,26 +297,19 @@ async fn generate_certificate(
     // Pick the desired challenge type and prepare the response.
 
     if state.status == OrderStatus::Pending {
-        let authorizations = order.authorizations().await.unwrap();
+        let authorizations = order.authorizations().await?;
         let mut challenges = Vec::with_capacity(authorizations.len());
         for authz in &authorizations {
             match authz.status {
                 AuthorizationStatus::Pending => {}
                 AuthorizationStatus::Valid => continue,
-                _ => todo!(),
+                status => anyhow::bail!("Authorization is {status:?}"),
             }
             let challenge = authz
                 .challenges
                 .iter()
                 .find(|c| c.r#type == ChallengeType::Http01)
-                .ok_or_else(|| anyhow::anyhow!("no http01 challenge found"));
-            let challenge = match challenge {
-                Ok(c) => c,
-                Err(e) => {
-                    tracing::error!("{e:#?}");
-                    return;
-                }
-            };
+                .ok_or_else(|| anyhow::anyhow!("no http01 challenge found"))?;
 
             info!("Found challenge {challenge:#?}");
 
@@ -223,7 +326,7 @@ async fn generate_certificate(
         // Let the server know we're ready to accept the challenges.
 
         for (_, url) in &challenges {
-            order.set_challenge_ready(url).await.unwrap();
+            order.set_challenge_ready(url).await?;
         }
     }
 
@@ -233,7 +336,7 @@ async fn generate_certificate(
     let mut delay = std::time::Duration::from_millis(250);
     loop {
         tokio::time::sleep(delay).await;
-        let state = order.refresh().await.unwrap();
+        let state = order.refresh().await?;
         if let OrderStatus::Ready | OrderStatus::Invalid = state.status {
             info!("order state: {:#?}", state);
             break;
@@ -243,17 +346,13 @@ async fn generate_certificate(
         tries += 1;
         match tries < 20 {
             true => info!(?state, tries, "order is not ready, waiting {delay:?}"),
-            false => {
-                tracing::error!(tries, "order is not ready: {state:#?}");
-                return;
-            }
+            false => anyhow::bail!("Order is not ready after {tries} tries"),
         }
     }
 
     let state = order.state();
     if state.status != OrderStatus::Ready {
-        tracing::error!("unexpected order status: {:?}", state.status);
-        return;
+        anyhow::bail!("Unexpected order status {:?}", state.status);
     }
 
     let names = vec![domain.to_lowercase()];
@@ -261,17 +360,17 @@ async fn generate_certificate(
     // If the order is ready, we can provision the certificate.
     // Use the rcgen library to create a Certificate Signing Request.
 
-    let mut params = CertificateParams::new(names.clone()).unwrap();
+    let mut params = CertificateParams::new(names.clone())?;
     params.distinguished_name = DistinguishedName::new();
-    let kp = KeyPair::generate().unwrap();
-    let cert = params.serialize_request(&kp).unwrap();
+    let kp = KeyPair::generate()?;
+    let cert = params.serialize_request(&kp)?;
     let csr = cert.der();
 
     // Finalize the order and print certificate chain, private key and account credentials.
 
-    order.finalize(&csr).await.unwrap();
+    order.finalize(&csr).await?;
     let cert_chain_pem = loop {
-        match order.certificate().await.unwrap() {
+        match order.certificate().await? {
             Some(cert_chain_pem) => break cert_chain_pem,
             None => tokio::time::sleep(std::time::Duration::from_secs(1)).await,
         }
@@ -280,39 +379,20 @@ async fn generate_certificate(
     // info!("certficate chain:\n\n{}", cert_chain_pem);
     // info!("private key:\n\n{}", kp.serialize_pem());
 
-    tokio::fs::create_dir_all(get_home_path().join("certificates").join(domain.as_str()))
-        .await
-        .unwrap();
-    tokio::fs::write(
-        get_home_path()
-            .join("certificates")
-            .join(domain.as_str())
-            .join("cert.pem"),
-        cert_chain_pem.clone(),
-    )
-    .await
-    .expect("cant write cert");
-    tokio::fs::write(
-        get_home_path()
-            .join("certificates")
-            .join(domain.as_str())
-            .join("key.pem"),
-        kp.serialize_pem(),
-    )
-    .await
-    .expect("cant write key");
+    let cert_dir = get_home_path().join("certificates").join(domain.as_str());
+    tokio::fs::create_dir_all(&cert_dir).await?;
+    tokio::fs::write(cert_dir.join("cert.pem"), cert_chain_pem.clone()).await?;
+    tokio::fs::write(cert_dir.join("key.pem"), kp.serialize_pem()).await?;
 
+    let cert = pingora::tls::x509::X509::stack_from_pem(cert_chain_pem.as_bytes())?;
+    let key = pingora::tls::pkey::PKey::private_key_from_pem(kp.serialize_pem().as_bytes())?;
     if let Some(mut peer) = project_context.get_domain(&domain).await {
-        let cert = pingora::tls::x509::X509::stack_from_pem(cert_chain_pem.as_bytes());
-        let key = pingora::tls::pkey::PKey::private_key_from_pem(kp.serialize_pem().as_bytes());
-
-        if let (Ok(cert), Ok(key)) = (cert, key) {
-            peer.ssl_provision = SSLProvisioning::Provisioned(app::common::SSlData {
-                cert,
-                key,
-                is_active: true,
-            });
-        }
+        peer.ssl_provision = SSLProvisioning::Provisioned(app::common::SSlData {
+            cert,
+            key,
+            is_active: true,
+        });
         project_context.update_domain(domain, peer).await
     };
+    Ok(())
 }
@@ -0,0 +1,203 @@
+//! Probes the domains of every project over HTTP(S) and publishes their uptime to
+//! [`app::uptime`], for the panel and the public status pages served by the gateway.
+
+use std::{
+    collections::{HashMap, VecDeque},
+    time::{Duration, Instant},
+};
+
+use app::{
+    common::{DomainStatus, SSLProvisioning, SSlData, UptimeCheck},
+    context::ProjectContext,
+    uptime,
+};
+use chrono::Utc;
+use openssl::asn1::Asn1Time;
+use pingora::{
+    server::ShutdownWatch,
+    services::background::{background_service, BackgroundService, GenBackgroundService},
+};
+use tracing::{info, warn};
+use unicase::UniCase;
+use uuid::Uuid;
+
+const PROBE_INTERVAL: Duration = Duration::from_secs(60);
+const PROBE_TIMEOUT: Duration = Duration::from_secs(10);
+/// Expired checks are dropped from memory and disk every this many rounds
+const COMPACT_TICKS: u64 = 60;
+
+pub struct UptimeMonitor {
+    project_context: ProjectContext,
+}
+
+impl UptimeMonitor {
+    pub fn to_service(project_context: ProjectContext) -> GenBackgroundService<Self> {
+        background_service("uptime_monitor", Self { project_context })
+    }
+
+    /// Domains attached to a live project, without the ones serving its status page
+    async fn targets(&self) -> Vec<(Uuid, UniCase<String>, DomainStatus)> {
+        let mut targets = vec![];
+        for (domain, status) in self.project_context.get_all_domains().await {
+            let Some(project) = status.project.upgrade() else {
+                continue;
+            };
+            if project
+                .status_page
+                .as_ref()
+                .is_some_and(|page| page.domain == domain)
+            {
+                continue;
+            }
+            targets.push((project.id, domain, status));
+        }
+        targets
+    }
+}
+
+#[async_trait::async_trait]
+impl BackgroundService for UptimeMonitor {
+    async fn start(&self, mut shutdown: ShutdownWatch) {
+        let client = match reqwest::Client::builder()
+            .redirect(reqwest::redirect::Policy::none())
+            .timeout(PROBE_TIMEOUT)
+            .build()
+        {
+            Ok(client) => client,
+            Err(err) => {
+                warn!("Cannot create uptime client {err:?}");
+                return;
+            }
+        };
+        let mut period = tokio::time::interval(PROBE_INTERVAL);
+        let mut history = HashMap::<(Uuid, UniCase<String>), VecDeque<UptimeCheck>>::new();
+        let mut ticks = 0u64;
+
+        loop {
+            tokio::select! {
+                _ = shutdown.changed() => {
+                    info!("Shutdown received");
+                    break;
+                }
+                _ = period.tick() => {
+                    let targets = self.targets().await;
+                    let checks = futures::future::join_all(
+                        targets
+                            .iter()
+                            .map(|(_, domain, status)| probe(&client, domain, status)),
+                    )
+                    .await;
+
+                    let mut current = HashMap::new();
+                    for ((project_id, domain), check) in targets
+                        .into_iter()
+                        .map(|(project_id, domain, _)| (project_id, domain))
+                        .zip(checks)
+                    {
+                        let key = (project_id, domain);
+                        let mut checks = match history.remove(&key) {
+                            Some(checks) => checks,
+                            None => uptime::load_checks(project_id, &key.1).await.into(),
+                        };
+                        if let Err(err) = uptime::append_check(project_id, &key.1, &check).await {
+                            warn!("Cannot store uptime check of {} {err:?}", key.1);
+                        }
+                        checks.push_back(check);
+                        current.insert(key, checks);
+                    }
+                    // Domains that were removed are forgotten
+                    history = current;
+
+                    if ticks % COMPACT_TICKS == 0 {
+                        let since = Utc::now() - uptime::RETENTION;
+                        for ((project_id, domain), checks) in history.iter_mut() {
+                            let expired = checks.iter().take_while(|check| check.ts < since).count();
+                            if expired == 0 {
+                                continue;
+                            }
+                            checks.drain(..expired);
+                            if let Err(err) = uptime::rewrite_checks(*project_id, domain, checks.iter()).await {
+                                warn!("Cannot compact uptime checks of {domain} {err:?}");
+                            }
+                        }
+                    }
+                    ticks += 1;
+
+                    let mut summaries = HashMap::<Uuid, Vec<_>>::new();
+                    for ((project_id, domain), checks) in history.iter() {
+                        summaries
+                            .entry(*project_id)
+                            .or_default()
+                            .push(uptime::summarize(&domain.to_lowercase(), checks.iter()));
+                    }
+                    for domains in summaries.values_mut() {
+                        domains.sort_by(|a, b| a.domain.cmp(&b.domain));
+                    }
+                    uptime::publish(summaries);
+                }
+            }
+        }
+    }
+}
+
+/// Requests the domain's root, over HTTPS once it has a certificate. Redirects are not
+/// followed, they count as an answer
+async fn probe(client: &reqwest::Client, domain: &str, status: &DomainStatus) -> UptimeCheck {
+    let (https, tls_expires_in_days) = match &status.ssl_provision {
+        SSLProvisioning::Provisioned(data) => (true, days_left(data)),
+        _ => (false, None),
+    };
+    let url = if https {
+        format!("https://{domain}/")
+    } else {
+        format!("http://{domain}/")
+    };
+    let started = Instant::now();
+    let response = client.get(&url).send().await;
+    let latency_ms = started.elapsed().as_millis() as u64;
+
+    match response {
+        Ok(response) => UptimeCheck {
+            ts: Utc::now(),
+            status: Some(response.status().as_u16()),
+            latency_ms: Some(latency_ms),
+            tls_valid: https.then_some(true),
+            tls_expires_in_days,
+            error: None,
+        },
+        Err(err) => {
+            let error = error_chain(&err);
+            // Verification failures only show up in the connect error's sources
+            let tls_invalid = https && err.is_connect() && error.contains("certificate");
+            UptimeCheck {
+                ts: Utc::now(),
+                status: None,
+                latency_ms: None,
+                tls_valid: tls_invalid.then_some(false),
+                tls_expires_in_days,
+                error: Some(if err.is_timeout() {
+                    "Timed out".to_string()
+                } else {
+                    error
+                }),
+            }
+        }
+    }
+}
+
+fn days_left(data: &SSlData) -> Option<i64> {
+    let cert = data.cert.first()?;
+    let diff = Asn1Time::days_from_now(0)
+        .ok()?
+        .diff(cert.not_after())
+        .ok()?;
+    Some(i64::from(diff.days))
+}
+
+/// The error and its sources, reqwest's own message rarely says what failed
+fn error_chain(err: &reqwest::Error) -> String {
+    std::iter::successors(Some(err as &dyn std::error::Error), |err| err.source())
+        .map(|err| err.to_string())
+        .collect::<Vec<_>>()
+        .join(": ")
+}
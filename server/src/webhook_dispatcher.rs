@@ -0,0 +1,147 @@
+//! Delivers the events queued by [`app::webhooks`] to the subscribed webhooks.
+//!
+//! Every event and subscription pair is delivered by its own task, so a slow endpoint
+//! never holds up the others. Failed attempts are retried with growing delays as long as
+//! the subscription stays enabled, and every attempt is written to the delivery log.
+
+use std::{sync::Arc, time::Duration};
+
+use app::{
+    context::ProjectContext,
+    webhooks::{self, WebhookEvent},
+};
+use chrono::Utc;
+use pingora::{
+    server::ShutdownWatch,
+    services::background::{background_service, BackgroundService, GenBackgroundService},
+};
+use tokio::sync::{mpsc, Mutex};
+use tracing::{info, warn};
+use uuid::Uuid;
+
+/// Waits before the attempts following the first, the event is given up on after the last
+const RETRY_DELAYS: [Duration; 5] = [
+    Duration::from_secs(10),
+    Duration::from_secs(60),
+    Duration::from_secs(300),
+    Duration::from_secs(1800),
+    Duration::from_secs(7200),
+];
+const COMPACT_INTERVAL: Duration = Duration::from_secs(3600);
+
+pub struct WebhookDispatcher {
+    project_context: ProjectContext,
+    receiver: Mutex<Option<mpsc::Receiver<WebhookEvent>>>,
+}
+
+impl WebhookDispatcher {
+    pub fn to_service(project_context: ProjectContext) -> GenBackgroundService<Self> {
+        background_service(
+            "webhook_dispatcher",
+            Self {
+                project_context,
+                receiver: Mutex::new(webhooks::take_events()),
+            },
+        )
+    }
+
+    async fn dispatch(&self, client: &reqwest::Client, event: WebhookEvent) {
+        let subscriptions = webhooks::load_subscriptions().await;
+        let subscribed = subscriptions
+            .iter()
+            .filter(|subscription| subscription.wants(event.event, event.project_id))
+            .map(|subscription| subscription.id)
+            .collect::<Vec<_>>();
+        if subscribed.is_empty() {
+            return;
+        }
+
+        let project_name = match event.project_id {
+            Some(project_id) => self
+                .project_context
+                .get_project(project_id)
+                .await
+                .map(|project| project.name.clone()),
+            None => None,
+        };
+        let body = Arc::new(webhooks::payload(&event, project_name));
+        let event = Arc::new(event);
+        for subscription_id in subscribed {
+            tokio::spawn(deliver_with_retries(
+                client.clone(),
+                subscription_id,
+                event.clone(),
+                body.clone(),
+            ));
+        }
+    }
+}
+
+async fn deliver_with_retries(
+    client: reqwest::Client,
+    subscription_id: Uuid,
+    event: Arc<WebhookEvent>,
+    body: Arc<Vec<u8>>,
+) {
+    for attempt in 1..=RETRY_DELAYS.len() as u32 + 1 {
+        // Looked up again for every attempt, it may have been changed or removed meanwhile
+        let Some(subscription) = webhooks::load_subscriptions()
+            .await
+            .into_iter()
+            .find(|subscription| subscription.id == subscription_id && subscription.enabled)
+        else {
+            return;
+        };
+        let mut delivery = webhooks::deliver(&client, &subscription, &event, &body, attempt).await;
+        let retry_in = if delivery.is_success() {
+            None
+        } else {
+            RETRY_DELAYS.get(attempt as usize - 1).copied()
+        };
+        delivery.next_attempt_at = retry_in
+            .and_then(|delay| chrono::Duration::from_std(delay).ok())
+            .map(|delay| Utc::now() + delay);
+        if let Some(error) = &delivery.error {
+            warn!(
+                "Webhook {} of {} failed on attempt {attempt} {error}",
+                event.event, subscription.url
+            );
+        }
+        if let Err(err) = webhooks::log_delivery(&delivery).await {
+            warn!("Cannot log webhook delivery {err:?}");
+        }
+        match retry_in {
+            Some(delay) => tokio::time::sleep(delay).await,
+            None => return,
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl BackgroundService for WebhookDispatcher {
+    async fn start(&self, mut shutdown: ShutdownWatch) {
+        let Some(mut receiver) = self.receiver.lock().await.take() else {
+            warn!("Webhook events are already taken");
+            return;
+        };
+        let client = reqwest::Client::new();
+        let mut compact = tokio::time::interval(COMPACT_INTERVAL);
+
+        loop {
+            tokio::select! {
+                _ = shutdown.changed() => {
+                    info!("Shutdown received");
+                    break;
+                }
+                Some(event) = receiver.recv() => {
+                    self.dispatch(&client, event).await;
+                }
+                _ = compact.tick() => {
+                    if let Err(err) = webhooks::compact_deliveries().await {
+                        warn!("Cannot compact webhook deliveries {err:?}");
+                    }
+                }
+            }
+        }
+    }
+}